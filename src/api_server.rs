@@ -0,0 +1,337 @@
+use gtk4 as gtk;
+use gtk::glib;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use ollama_rs::generation::images::Image;
+use serde_json::{json, Value};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::context;
+use crate::controller::{Command, Commands};
+use crate::error::{AppResult, Context};
+use crate::provider::{stream_reply, Backend};
+use crate::state::{Agent, AppState, ChatEvent};
+
+/// Largest request body accepted; images come inline as base64.
+const MAX_REQUEST_BYTES: usize = 32 * 1024 * 1024;
+/// Largest request line and headers accepted.
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+/// How long a client may take to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A random key for clients to send, set when the server is first enabled.
+pub fn new_api_key() -> String {
+    format!("sk-local-{}", glib::uuid_string_random().replace('-', ""))
+}
+
+/// What a request needs from the app, built by the controller on the main
+/// thread (`Command::DescribeApi`) so connections never lock the state.
+pub struct ApiSnapshot {
+    /// Key clients must send; empty while none is set
+    pub api_key: String,
+    /// The agents offered as models, each with the backend it talks to and
+    /// its model's context window if known
+    pub agents: Vec<(Agent, Backend, Option<u64>)>,
+}
+
+impl ApiSnapshot {
+    pub fn of(state: &AppState) -> Self {
+        Self {
+            api_key: state.settings.api_server.api_key.clone(),
+            agents: state.settings.agents.iter()
+                .map(|agent| (agent.clone(), state.backend_for(agent), state.known_context_length(agent)))
+                .collect(),
+        }
+    }
+}
+
+/// A running local server offering the configured agents as models on an
+/// OpenAI-compatible API; it stops when dropped.
+pub struct ApiServer {
+    pub port: u16,
+    task: tokio::task::AbortHandle,
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl ApiServer {
+    /// Listens on `127.0.0.1:port`. Requests must carry `Authorization:
+    /// Bearer <key>` with the configured API key, and are all refused while
+    /// none is set; it is read for every request, so changing it needs no
+    /// restart. No CORS headers are sent, so web pages can't read replies.
+    pub fn start(commands: Commands, port: u16) -> AppResult<Self> {
+        let listener = std::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .and_then(TcpListener::from_std)
+            .context(&format!("Could not start the API server on port {}", port))?;
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        tracing::warn!("API server failed to accept a connection: {}", e);
+                        continue;
+                    }
+                };
+                let commands = commands.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &commands).await {
+                        tracing::debug!("API request ended early: {}", e);
+                    }
+                });
+            }
+        });
+        tracing::info!("API server listening on http://127.0.0.1:{}/v1", port);
+        Ok(Self { port, task: task.abort_handle() })
+    }
+}
+
+/// The parts of an HTTP request the API looks at.
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Reads one request; connections are closed after the response.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Request> {
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(invalid("request headers too large"));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(invalid("connection closed before the request was complete"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().split('?').next().unwrap_or_default().to_string();
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut content_type = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().map_err(|_| invalid("invalid Content-Length"))?,
+            "authorization" => authorization = Some(value.trim().to_string()),
+            "content-type" => content_type = Some(value.trim().to_ascii_lowercase()),
+            _ => {}
+        }
+    }
+    if content_length > MAX_REQUEST_BYTES {
+        return Err(invalid("request body too large"));
+    }
+
+    let mut body = buffer.split_off(header_end + 4);
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(invalid("connection closed before the body was complete"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    Ok(Request { method, path, authorization, content_type, body })
+}
+
+async fn handle_connection(mut stream: TcpStream, commands: &Commands) -> std::io::Result<()> {
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => return send_error(&mut stream, 400, &e.to_string()).await,
+        Err(_) => return send_error(&mut stream, 408, "Timed out reading the request").await,
+    };
+    let Some(snapshot) = commands.query(Command::DescribeApi).await else {
+        return send_error(&mut stream, 503, "Arch-LLM is shutting down").await;
+    };
+    let api_key = &snapshot.api_key;
+    if api_key.is_empty() {
+        return send_error(&mut stream, 401, "No API key is set; set one in Arch-LLM's settings").await;
+    }
+    let authorized = request.authorization.as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token.trim() == api_key.as_str());
+    if !authorized {
+        return send_error(&mut stream, 401, "Invalid API key").await;
+    }
+    // Browsers can only send other types without a preflight this server doesn't answer
+    if request.method == "POST" && !request.content_type.as_deref().is_some_and(|t| t.starts_with("application/json")) {
+        return send_error(&mut stream, 415, "Content-Type must be application/json").await;
+    }
+    match (request.method.as_str(), request.path.trim_end_matches('/')) {
+        ("GET", "/v1/models") => {
+            let models: Vec<Value> = snapshot.agents.iter().map(|(agent, _, _)| json!({
+                "id": agent.name,
+                "object": "model",
+                "created": 0,
+                "owned_by": "arch-llm",
+            })).collect();
+            let body = json!({ "object": "list", "data": models });
+            send_response(&mut stream, 200, "application/json", body.to_string().as_bytes()).await
+        }
+        ("POST", "/v1/chat/completions") => match serde_json::from_slice::<Value>(&request.body) {
+            Ok(body) => chat_completion(&mut stream, snapshot, commands, &body).await,
+            Err(e) => send_error(&mut stream, 400, &format!("Invalid JSON: {}", e)).await,
+        },
+        _ => send_error(&mut stream, 404, &format!("No route for {} {}", request.method, request.path)).await,
+    }
+}
+
+/// Answers a chat completion with the agent named by `model`: its system
+/// prompt goes first, and its generation options apply unless the request
+/// overrides them. Tools are not offered.
+async fn chat_completion(stream: &mut TcpStream, snapshot: ApiSnapshot, commands: &Commands, body: &Value) -> std::io::Result<()> {
+    let name = body["model"].as_str().unwrap_or_default();
+    let found = snapshot.agents.into_iter().find(|(agent, _, _)| agent.name == name);
+    let Some((agent, backend, known_context)) = found else {
+        return send_error(stream, 404, &format!("No agent named '{}'", name)).await;
+    };
+
+    let mut messages = Vec::new();
+    if !agent.system_prompt.trim().is_empty() {
+        messages.push(ChatMessage::system(agent.system_prompt.clone()));
+    }
+    messages.extend(body["messages"].as_array().into_iter().flatten().filter_map(request_message));
+    let mut options = context::generation_options(&agent, &backend, known_context, commands).await;
+    match &body["stop"] {
+        Value::String(stop) => options.stop = vec![stop.clone()],
+        Value::Array(stops) => options.stop = stops.iter().filter_map(|s| s.as_str().map(str::to_string)).collect(),
+        _ => {}
+    }
+    if let Some(max_tokens) = body["max_completion_tokens"].as_i64().or_else(|| body["max_tokens"].as_i64()) {
+        options.num_predict = Some(max_tokens as i32);
+    }
+    if let Some(seed) = body["seed"].as_i64() {
+        options.seed = Some(seed as i32);
+    }
+    let streaming = body["stream"].as_bool().unwrap_or(false);
+
+    let (sender, receiver) = async_channel::unbounded();
+    let task = tokio::spawn(async move {
+        stream_reply(&backend, &agent.model, &messages, &[], agent.raw_template(), &options, &sender).await
+    });
+
+    let id = format!("chatcmpl-{}", glib::uuid_string_random());
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    if !streaming {
+        // Chunks are collected by `stream_reply` itself
+        while receiver.recv().await.is_ok() {}
+        return match task.await {
+            Ok(Ok(reply)) => {
+                let body = json!({
+                    "id": id,
+                    "object": "chat.completion",
+                    "created": created,
+                    "model": name,
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": reply.text },
+                        "finish_reason": "stop",
+                    }],
+                });
+                send_response(stream, 200, "application/json", body.to_string().as_bytes()).await
+            }
+            Ok(Err(e)) => send_error(stream, 502, &e.message).await,
+            Err(e) => send_error(stream, 500, &e.to_string()).await,
+        };
+    }
+
+    let chunk = |delta: Value, finish_reason: Value| format!("data: {}\n\n", json!({
+        "id": id,
+        "object": "chat.completion.chunk",
+        "created": created,
+        "model": name,
+        "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+    }));
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    let written = async {
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(chunk(json!({ "role": "assistant", "content": "" }), Value::Null).as_bytes()).await?;
+        while let Ok(event) = receiver.recv().await {
+            if let ChatEvent::Chunk(text) = event {
+                stream.write_all(chunk(json!({ "content": text }), Value::Null).as_bytes()).await?;
+            }
+        }
+        Ok::<_, std::io::Error>(())
+    }.await;
+    if let Err(e) = written {
+        // The client went away; don't keep generating for nobody
+        task.abort();
+        return Err(e);
+    }
+    match task.await {
+        Ok(Ok(_)) => stream.write_all(chunk(json!({}), json!("stop")).as_bytes()).await?,
+        Ok(Err(e)) => stream.write_all(format!("data: {}\n\n", json!({ "error": { "message": e.message, "type": "server_error" } })).as_bytes()).await?,
+        Err(e) => tracing::warn!("API reply task failed: {}", e),
+    }
+    stream.write_all(b"data: [DONE]\n\n").await?;
+    stream.shutdown().await
+}
+
+/// Converts an OpenAI message; content given as parts keeps its text and
+/// base64 `data:` images.
+fn request_message(message: &Value) -> Option<ChatMessage> {
+    let (content, images) = match &message["content"] {
+        Value::String(text) => (text.clone(), Vec::new()),
+        Value::Array(parts) => {
+            let text = parts.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n");
+            let images = parts.iter()
+                .filter_map(|p| p["image_url"]["url"].as_str().or_else(|| p["image_url"].as_str()))
+                .filter_map(|url| url.strip_prefix("data:")?.split_once(";base64,").map(|(_, data)| Image::from_base64(data)))
+                .collect();
+            (text, images)
+        }
+        _ => (String::new(), Vec::new()),
+    };
+    let message = match message["role"].as_str()? {
+        "system" | "developer" => ChatMessage::system(content),
+        "user" => ChatMessage::user(content),
+        "assistant" => ChatMessage::assistant(content),
+        _ => return None,
+    };
+    Some(if images.is_empty() || message.role != MessageRole::User { message } else { message.with_images(images) })
+}
+
+async fn send_error(stream: &mut TcpStream, status: u16, message: &str) -> std::io::Result<()> {
+    let body = json!({ "error": { "message": message, "type": if status == 401 { "authentication_error" } else { "invalid_request_error" } } });
+    send_response(stream, status, "application/json", body.to_string().as_bytes()).await
+}
+
+async fn send_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        415 => "Unsupported Media Type",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, reason, content_type, body.len(),
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
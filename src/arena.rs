@@ -0,0 +1,77 @@
+use gtk4::glib;
+
+use crate::state::{ArenaOutcome, ArenaVote};
+
+/// Rating every model starts the leaderboard with.
+const INITIAL_RATING: f64 = 1000.0;
+/// How far one vote moves a rating.
+const K_FACTOR: f64 = 32.0;
+
+/// A model's place on the arena leaderboard.
+pub struct Standing {
+    pub model: String,
+    pub rating: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+}
+
+/// Two different models picked at random, in random order, or `None` with
+/// fewer than two installed.
+pub fn pick_pair(models: &[String]) -> Option<(String, String)> {
+    if models.len() < 2 {
+        return None;
+    }
+    let first = glib::random_int_range(0, models.len() as i32) as usize;
+    // Drawn from the others, so it can't be the same model
+    let mut second = glib::random_int_range(0, models.len() as i32 - 1) as usize;
+    if second >= first {
+        second += 1;
+    }
+    Some((models[first].clone(), models[second].clone()))
+}
+
+/// Elo ratings from the votes in the order they were cast, best first. A tie
+/// and "both bad" count as a draw.
+pub fn leaderboard(votes: &[ArenaVote]) -> Vec<Standing> {
+    fn index(standings: &mut Vec<Standing>, model: &str) -> usize {
+        match standings.iter().position(|s| s.model == model) {
+            Some(i) => i,
+            None => {
+                standings.push(Standing { model: model.to_string(), rating: INITIAL_RATING, wins: 0, losses: 0, ties: 0 });
+                standings.len() - 1
+            }
+        }
+    }
+
+    let mut standings: Vec<Standing> = Vec::new();
+    for vote in votes {
+        let a = index(&mut standings, &vote.model_a);
+        let b = index(&mut standings, &vote.model_b);
+        let score_a = match vote.outcome {
+            ArenaOutcome::AWins => 1.0,
+            ArenaOutcome::BWins => 0.0,
+            ArenaOutcome::Tie | ArenaOutcome::BothBad => 0.5,
+        };
+        let expected_a = 1.0 / (1.0 + 10f64.powf((standings[b].rating - standings[a].rating) / 400.0));
+        let change = K_FACTOR * (score_a - expected_a);
+        standings[a].rating += change;
+        standings[b].rating -= change;
+        match vote.outcome {
+            ArenaOutcome::AWins => {
+                standings[a].wins += 1;
+                standings[b].losses += 1;
+            }
+            ArenaOutcome::BWins => {
+                standings[b].wins += 1;
+                standings[a].losses += 1;
+            }
+            ArenaOutcome::Tie | ArenaOutcome::BothBad => {
+                standings[a].ties += 1;
+                standings[b].ties += 1;
+            }
+        }
+    }
+    standings.sort_by(|x, y| y.rating.total_cmp(&x.rating));
+    standings
+}
@@ -0,0 +1,201 @@
+use gtk4::gio;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult, Context};
+use crate::portal::read_chosen_file;
+use crate::secrets;
+
+/// Text files larger than this are refused rather than sent whole.
+const MAX_TEXT_BYTES: usize = 512 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+/// Pastes longer than this become an attachment instead of filling the input.
+const PASTE_MAX_LINES: usize = 200;
+const PASTE_MAX_BYTES: usize = 16 * 1024;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+/// Something queued to go out with the next message.
+#[derive(Clone, Debug)]
+pub enum Attachment {
+    /// Base64 image data, sent as a message image
+    Image(String),
+    /// A text file, sent as part of the message
+    File { name: String, content: String },
+    /// A web page's text, sent as part of the message
+    Page { url: String, content: String },
+    /// A long paste, sent as part of the message
+    Pasted(String),
+}
+
+impl Attachment {
+    /// What the chip and the sent message call it.
+    pub fn name(&self) -> &str {
+        match self {
+            Attachment::Image(_) => "Image",
+            Attachment::File { name, .. } => name,
+            Attachment::Page { url, .. } => url,
+            Attachment::Pasted(_) => "Pasted text",
+        }
+    }
+
+    /// The text that goes into the message, `None` for images.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Attachment::Image(_) => None,
+            Attachment::File { content, .. } | Attachment::Page { content, .. } | Attachment::Pasted(content) => Some(content),
+        }
+    }
+
+    /// A copy with the secrets in its text replaced by markers.
+    pub fn redacted(&self) -> Self {
+        match self {
+            Attachment::Image(data) => Attachment::Image(data.clone()),
+            Attachment::File { name, content } => Attachment::File { name: name.clone(), content: secrets::redact(content) },
+            Attachment::Page { url, content } => Attachment::Page { url: url.clone(), content: secrets::redact(content) },
+            Attachment::Pasted(content) => Attachment::Pasted(secrets::redact(content)),
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Attachment::Image(_) => "image-x-generic-symbolic",
+            Attachment::File { .. } => "text-x-generic-symbolic",
+            Attachment::Page { .. } => "web-browser-symbolic",
+            Attachment::Pasted(_) => "edit-paste-symbolic",
+        }
+    }
+}
+
+/// Whether pasted `text` is long enough to attach rather than insert.
+pub fn is_huge_paste(text: &str) -> bool {
+    text.len() > PASTE_MAX_BYTES || text.lines().count() > PASTE_MAX_LINES
+}
+
+/// Reads a file to attach: images by extension, anything else as UTF-8 text.
+pub fn read_file(file: &gio::File) -> AppResult<Attachment> {
+    let (name, bytes) = read_chosen_file(file)?;
+    let extension = Path::new(&name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(Attachment::Image(gtk4::glib::base64_encode(&bytes).to_string()));
+    }
+    if bytes.len() > MAX_TEXT_BYTES {
+        return Err(AppError::new(format!("{} is too large to attach", name), format!("{} KB, the limit is {} KB", bytes.len() / 1024, MAX_TEXT_BYTES / 1024)));
+    }
+    let content = String::from_utf8(bytes).map_err(|_| AppError::new(format!("Can't attach {}", name), "Only text files and images can be attached"))?;
+    Ok(Attachment::File { name, content })
+}
+
+/// Downloads a page and keeps its readable text.
+pub async fn fetch_page(url: &str) -> AppResult<Attachment> {
+    let url = url.trim();
+    let url = if url.contains("://") { url.to_string() } else { format!("https://{}", url) };
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build().context("Failed to fetch page")?;
+    let res = client.get(&url).send().await
+        .and_then(|r| r.error_for_status())
+        .context(&format!("Failed to fetch {}", url))?;
+    let is_html = res.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+    let body = res.text().await.context(&format!("Failed to fetch {}", url))?;
+    let mut content = if is_html { html_to_text(&body) } else { body };
+    if content.len() > MAX_TEXT_BYTES {
+        let mut end = MAX_TEXT_BYTES;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        content.truncate(end);
+    }
+    Ok(Attachment::Page { url, content })
+}
+
+/// Drops tags, scripts and styles and collapses the whitespace left behind.
+/// Crude, but enough for a model to read an article.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let lower = rest.get(..8).unwrap_or(rest).to_ascii_lowercase();
+        let skip_to = ["script", "style"].iter()
+            .find(|tag| lower[1..].starts_with(*tag))
+            .map(|tag| format!("</{}", tag));
+        if let Some(close) = skip_to {
+            match rest.to_ascii_lowercase().find(&close) {
+                Some(end) => rest = &rest[end + close.len()..],
+                None => break,
+            }
+        }
+        match rest.find('>') {
+            Some(end) => {
+                // Block-level tags end a line
+                let tag = rest[1..end].trim_start_matches('/').split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+                if matches!(tag.as_str(), "p" | "br" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6") {
+                    text.push('\n');
+                }
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+    text.push_str(rest);
+    let text = text.replace("&nbsp;", " ").replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&");
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Builds what is sent for `text` with `attachments`: text attachments are
+/// appended in `<attachment>` blocks, images are returned for the message.
+pub fn compose(text: &str, attachments: &[Attachment]) -> (String, Vec<String>) {
+    let mut content = text.to_string();
+    let mut images = Vec::new();
+    for attachment in attachments {
+        match attachment.text() {
+            Some(body) => {
+                if !content.is_empty() {
+                    content.push_str("\n\n");
+                }
+                content.push_str(&format!("<attachment name=\"{}\">\n{}\n</attachment>", attachment.name().replace('"', "'"), body.trim_end()));
+            }
+            None => {
+                if let Attachment::Image(base64) = attachment {
+                    images.push(base64.clone());
+                }
+            }
+        }
+    }
+    (content, images)
+}
+
+/// Splits a sent message back into the typed text and its text attachments
+/// as (name, content), so the chat can show them collapsed.
+pub fn split(content: &str) -> (String, Vec<(String, String)>) {
+    const OPEN: &str = "<attachment name=\"";
+    const CLOSE: &str = "\n</attachment>";
+    let mut text = String::new();
+    let mut attached = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(OPEN) {
+        let block = &rest[start + OPEN.len()..];
+        let Some((name, body)) = block.split_once("\">\n") else { break };
+        let Some(end) = body.find(CLOSE) else { break };
+        text.push_str(&rest[..start]);
+        attached.push((name.to_string(), body[..end].to_string()));
+        rest = &body[end + CLOSE.len()..];
+    }
+    text.push_str(rest);
+    (text.trim().to_string(), attached)
+}
+
+/// Chip label for an attachment's text, e.g. "notes.md · 14 KB".
+pub fn describe(name: &str, content: &str) -> String {
+    let bytes = content.len();
+    if bytes < 1024 {
+        format!("{} · {} B", name, bytes)
+    } else {
+        format!("{} · {} KB", name, bytes.div_ceil(1024))
+    }
+}
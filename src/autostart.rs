@@ -0,0 +1,69 @@
+use gtk4 as gtk;
+use gtk::glib;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{AppResult, Context};
+use crate::paths;
+use crate::utils::write_atomic;
+
+/// Command line option the autostart entry passes, so a launch at login can
+/// start hidden.
+pub const AUTOSTART_OPTION: &str = "autostart";
+
+/// The XDG autostart entry started with the desktop session.
+fn entry_path() -> PathBuf {
+    glib::user_config_dir().join("autostart").join("org.archllm.ollama_chat.desktop")
+}
+
+/// Whether the app starts after login.
+pub fn is_enabled() -> bool {
+    entry_path().exists()
+}
+
+/// Installs or removes the autostart entry. It runs the current executable,
+/// with the custom settings directory of this instance if there is one.
+pub fn set_enabled(enabled: bool) -> AppResult<()> {
+    let path = entry_path();
+    if !enabled {
+        if path.exists() {
+            fs::remove_file(&path).context("Could not remove the autostart entry")?;
+        }
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe().context("Could not find the app's executable")?;
+    let mut exec = vec![exe.to_string_lossy().to_string(), format!("--{}", AUTOSTART_OPTION)];
+    if let Some(dirs) = paths::dirs().filter(|dirs| dirs.custom) {
+        exec.push("--config-dir".to_string());
+        exec.push(dirs.config.to_string_lossy().to_string());
+    }
+    let exec: Vec<String> = exec.iter().map(|arg| quote_exec_arg(arg)).collect();
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Arch LLM\nComment=Chat with local language models through Ollama\nExec={}\nIcon=internet-chat\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        exec.join(" "),
+    );
+    fs::create_dir_all(path.parent().unwrap_or(&path)).context("Could not create the autostart directory")?;
+    write_atomic(&path, entry).context("Could not write the autostart entry")
+}
+
+/// Quotes an `Exec` argument as the desktop entry spec asks when it has
+/// reserved characters.
+fn quote_exec_arg(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "/-_.=+,:@".contains(c)) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::migrations;
+use crate::state::{AppState, Bookmark, ChatHistory, Settings};
+
+/// Bumped when the archive layout changes incompatibly.
+const FORMAT: u32 = 1;
+
+/// Everything the app stores, in one JSON file: settings, history, drafts,
+/// bookmarks, long-term memories (by file name) and the custom stylesheet.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub format: u32,
+    pub created: String,
+    pub settings: Settings,
+    pub history: Vec<ChatHistory>,
+    #[serde(default)]
+    pub drafts: HashMap<String, String>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub memories: HashMap<String, String>,
+    #[serde(default)]
+    pub style_css: Option<String>,
+}
+
+/// What to do when the backup and the current data both have an item.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RestoreMode {
+    /// Drop the current data and use the backup as is.
+    Replace,
+    /// Merge, keeping the current version of conflicting items.
+    KeepCurrent,
+    /// Merge, taking the backup's version of conflicting items.
+    PreferBackup,
+}
+
+pub fn create(state: &AppState, style_path: &Path) -> Backup {
+    let mut memories = HashMap::new();
+    if let Ok(entries) = fs::read_dir(&state.memory_path) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "txt") {
+                if let (Some(name), Some(text)) = (path.file_name(), state.storage.read_private_string(&path)) {
+                    memories.insert(name.to_string_lossy().to_string(), text);
+                }
+            }
+        }
+    }
+    Backup {
+        format: FORMAT,
+        created: gtk4::glib::DateTime::now_local()
+            .and_then(|now| now.format_iso8601())
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        settings: state.settings.clone(),
+        history: state.history.clone(),
+        drafts: state.drafts.clone(),
+        bookmarks: state.bookmarks.clone(),
+        memories,
+        style_css: fs::read_to_string(style_path).ok(),
+    }
+}
+
+pub fn parse(data: &str) -> Result<Backup, String> {
+    let mut value: serde_json::Value = serde_json::from_str(data).map_err(|e| format!("Not an Arch LLM backup: {}", e))?;
+    // Backups can be older than the running version, just like settings.json
+    if let Some(settings) = value.get_mut("settings") {
+        migrations::upgrade(settings)?;
+    }
+    let backup: Backup = serde_json::from_value(value).map_err(|e| format!("Not an Arch LLM backup: {}", e))?;
+    if backup.format > FORMAT {
+        return Err("The backup was made by a newer version of Arch LLM".to_string());
+    }
+    Ok(backup)
+}
+
+/// Applies a backup to the state and writes every file. Returns how many
+/// conversations were added or replaced.
+pub fn restore(state: &mut AppState, backup: Backup, mode: RestoreMode, style_path: &Path) -> usize {
+    let prefer_backup = mode != RestoreMode::KeepCurrent;
+    let mut restored = 0;
+
+    if mode == RestoreMode::Replace {
+        restored = backup.history.len();
+        state.settings = backup.settings;
+        state.history = backup.history;
+        state.drafts = backup.drafts;
+        state.bookmarks = backup.bookmarks;
+        if let Ok(entries) = fs::read_dir(&state.memory_path) {
+            for entry in entries.flatten() {
+                if entry.path().extension().is_some_and(|ext| ext == "txt") {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    } else {
+        let current = std::mem::take(&mut state.settings);
+        let (mut settings, other) = if prefer_backup { (backup.settings, current) } else { (current, backup.settings) };
+        // Lists are merged: items only the other side has are added
+        for agent in other.agents {
+            if !settings.agents.iter().any(|a| a.name == agent.name) {
+                settings.agents.push(agent);
+            }
+        }
+        for provider in other.providers {
+            if !settings.providers.iter().any(|p| p.id == provider.id) {
+                settings.providers.push(provider);
+            }
+        }
+        for template in other.templates {
+            if !settings.templates.iter().any(|t| t.id == template.id) {
+                settings.templates.push(template);
+            }
+        }
+        for profile in other.profiles {
+            if !settings.profiles.iter().any(|p| p.id == profile.id) {
+                settings.profiles.push(profile);
+            }
+        }
+        state.settings = settings;
+
+        for item in backup.history {
+            match state.history.iter_mut().find(|h| h.id == item.id) {
+                Some(existing) => {
+                    if prefer_backup {
+                        *existing = item;
+                        restored += 1;
+                    }
+                }
+                None => {
+                    state.history.push(item);
+                    restored += 1;
+                }
+            }
+        }
+        for (key, draft) in backup.drafts {
+            if prefer_backup || !state.drafts.contains_key(&key) {
+                state.drafts.insert(key, draft);
+            }
+        }
+        for bookmark in backup.bookmarks {
+            match state.bookmarks.iter_mut().find(|b| b.id == bookmark.id) {
+                Some(existing) => {
+                    if prefer_backup {
+                        *existing = bookmark;
+                    }
+                }
+                None => state.bookmarks.push(bookmark),
+            }
+        }
+    }
+
+    for (name, text) in &backup.memories {
+        // Only plain file names; a crafted backup must not write elsewhere
+        if name.contains('/') || name.starts_with('.') {
+            continue;
+        }
+        let path = state.memory_path.join(name);
+        if prefer_backup || !path.exists() {
+            state.storage.save_private(&path, text.as_str());
+        }
+    }
+    if let Some(css) = &backup.style_css {
+        if prefer_backup || !style_path.exists() {
+            state.storage.save(style_path, css.as_str());
+        }
+    }
+
+    state.save_settings();
+    state.save_history();
+    state.save_drafts();
+    state.save_bookmarks();
+    // Written right away: the stylesheet is reloaded as soon as this returns
+    state.storage.flush();
+    restored
+}
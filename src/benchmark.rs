@@ -0,0 +1,53 @@
+use ollama_rs::generation::completion::request::GenerationRequest;
+use ollama_rs::generation::parameters::KeepAlive;
+use ollama_rs::models::ModelOptions;
+use ollama_rs::Ollama;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::state::BenchmarkRun;
+
+/// The same prompts for every model, mixing a long prompt (prompt
+/// evaluation) with open-ended answers (generation).
+const PROMPTS: &[&str] = &[
+    "Explain in detail how a package manager resolves dependencies, covering version constraints, conflicts and the order packages are installed in.",
+    "Write a Python function that parses an INI file into a dictionary of sections, with comments, and explain how it handles edge cases.",
+    "Summarize the following text in three sentences:\n\nThe Linux kernel is a free and open-source, monolithic, modular, multitasking, Unix-like operating system kernel. It was originally written in 1991 by Linus Torvalds for his i386-based PC, and it was soon adopted as the kernel for the GNU operating system, which was written to be a free replacement for Unix. Since the late 1990s, it has been included as part of a large number of operating system distributions, many of which are commonly also called Linux. The kernel supports a wide range of hardware architectures, from embedded devices to supercomputers, and it is developed by thousands of contributors from companies and the community, coordinated through mailing lists and maintainers responsible for each subsystem.",
+];
+/// Tokens generated per prompt, so runs are comparable and bounded.
+const MAX_TOKENS: i32 = 256;
+
+/// Runs the prompt set on `model` and measures it from the timings Ollama
+/// reports. The model is unloaded first, so the load time is a cold load.
+/// `progress` gets a line of status per step.
+pub async fn run(ollama: &Ollama, model: &str, mut progress: impl FnMut(String)) -> Result<BenchmarkRun, String> {
+    progress("Unloading the model".to_string());
+    // An empty prompt with keep_alive 0 only unloads
+    let _ = ollama.generate(GenerationRequest::new(model.to_string(), String::new()).keep_alive(KeepAlive::UnloadOnCompletion)).await;
+
+    let options = ModelOptions::default().temperature(0.0).seed(42).num_predict(MAX_TOKENS);
+    let mut load_ns = 0;
+    let (mut prompt_tokens, mut prompt_ns, mut generated_tokens, mut generation_ns) = (0, 0, 0, 0);
+    for (i, prompt) in PROMPTS.iter().enumerate() {
+        progress(format!("Prompt {} of {}", i + 1, PROMPTS.len()));
+        let request = GenerationRequest::new(model.to_string(), prompt.to_string()).options(options.clone());
+        let response = ollama.generate(request).await.map_err(|e| e.to_string())?;
+        if i == 0 {
+            load_ns = response.load_duration.unwrap_or(0);
+        }
+        prompt_tokens += response.prompt_eval_count.unwrap_or(0);
+        prompt_ns += response.prompt_eval_duration.unwrap_or(0);
+        generated_tokens += response.eval_count.unwrap_or(0);
+        generation_ns += response.eval_duration.unwrap_or(0);
+    }
+
+    let per_second = |tokens: u64, ns: u64| if ns > 0 { tokens as f64 / (ns as f64 / 1e9) } else { 0.0 };
+    Ok(BenchmarkRun {
+        model: model.to_string(),
+        run_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default(),
+        load_secs: load_ns as f64 / 1e9,
+        prompt_tokens_per_sec: per_second(prompt_tokens, prompt_ns),
+        generation_tokens_per_sec: per_second(generated_tokens, generation_ns),
+        prompt_tokens,
+        generated_tokens,
+    })
+}
@@ -0,0 +1,42 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use adw::ToastOverlay;
+
+use crate::ui::toasts::toast;
+
+/// Undo toasts stay up longer than plain ones so there is time to react.
+const UNDO_SECONDS: u32 = 8;
+
+/// Asks before a destructive action. `on_confirm` runs only if the user picks
+/// `action`; closing the dialog or pressing Escape cancels.
+pub fn confirm(
+    parent: &impl IsA<gtk::Widget>,
+    message: &str,
+    detail: &str,
+    action: &str,
+    on_confirm: impl FnOnce() + 'static,
+) {
+    let dialog = gtk::AlertDialog::builder()
+        .modal(true)
+        .message(message)
+        .detail(detail)
+        .buttons(["Cancel", action])
+        .cancel_button(0)
+        .default_button(0)
+        .build();
+    let window = parent.root().and_downcast::<gtk::Window>();
+    dialog.choose(window.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+        if res == Ok(1) {
+            on_confirm();
+        }
+    });
+}
+
+/// Reports a finished destructive action with an Undo button.
+pub fn undo_toast(toasts: &ToastOverlay, title: &str, undo: impl Fn() + 'static) {
+    let toast = toast(title);
+    toast.set_button_label(Some("Undo"));
+    toast.set_timeout(UNDO_SECONDS);
+    toast.connect_button_clicked(move |_| undo());
+    toasts.add_toast(toast);
+}
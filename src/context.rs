@@ -0,0 +1,108 @@
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+
+use crate::attachments::Attachment;
+use crate::controller::{Command, Commands};
+use crate::provider::{context_length, Backend, GenerationOptions, Provider};
+use crate::state::{Agent, StoredMessage};
+use crate::utils::{estimate_tokens, IMAGE_TOKEN_ESTIMATE};
+
+/// Latest messages kept word for word when the ones before are summarized.
+const KEEP_RECENT: usize = 2;
+
+/// Rough token count of a message as sent: its text plus its images.
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    estimate_tokens(&message.content) + message.images.as_ref().map_or(0, |images| images.len() * IMAGE_TOKEN_ESTIMATE)
+}
+
+/// Rough token count of a conversation as sent.
+pub fn prompt_tokens(messages: &[StoredMessage]) -> usize {
+    messages.iter().map(|m| message_tokens(&m.message)).sum()
+}
+
+/// Rough token count of a message about to be sent with `attachments`.
+pub fn draft_tokens(text: &str, attachments: &[Attachment]) -> usize {
+    estimate_tokens(text) + attachments.iter().map(|a| a.text().map_or(IMAGE_TOKEN_ESTIMATE, estimate_tokens)).sum::<usize>()
+}
+
+/// Index of the first message after the system prompt, if there is one.
+fn history_start(messages: &[StoredMessage]) -> usize {
+    usize::from(messages.first().is_some_and(|m| m.message.role == MessageRole::System))
+}
+
+/// Messages that dropping or summarizing could take out.
+pub fn history_len(messages: &[StoredMessage]) -> usize {
+    messages.len() - history_start(messages)
+}
+
+/// Drops the oldest messages after the system prompt until the conversation
+/// and `new_tokens` more fit in three quarters of `context`, leaving the rest
+/// for the reply. Tool results aren't left without the call they answer.
+/// Returns how many messages were dropped.
+pub fn truncate(messages: &mut Vec<StoredMessage>, new_tokens: usize, context: u64) -> usize {
+    let budget = (context as usize * 3 / 4).saturating_sub(new_tokens);
+    let start = history_start(messages);
+    let mut end = start;
+    let mut tokens = prompt_tokens(messages);
+    while end < messages.len() && (tokens > budget || messages[end].message.role == MessageRole::Tool) {
+        tokens -= message_tokens(&messages[end].message);
+        end += 1;
+    }
+    messages.drain(start..end);
+    end - start
+}
+
+/// The conversation with everything but the system prompt and the latest
+/// messages replaced by a summary from `model`, kept as a system message.
+pub async fn summarize(backend: &Backend, model: String, messages: &[StoredMessage]) -> Result<Vec<StoredMessage>, String> {
+    let start = history_start(messages);
+    let mut end = messages.len().saturating_sub(KEEP_RECENT).max(start);
+    // Keep a tool call together with its results
+    while end > start && end < messages.len() && messages[end].message.role == MessageRole::Tool {
+        end -= 1;
+    }
+    if end == start {
+        return Err("The conversation is too short to summarize".to_string());
+    }
+
+    let transcript: Vec<String> = messages[start..end].iter().map(|m| {
+        let role = match m.message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+            MessageRole::Tool => "Tool result",
+            MessageRole::System => "System",
+        };
+        format!("{}: {}", role, m.message.content.trim())
+    }).collect();
+    let prompt = format!(
+        "Summarize the conversation below so it can be continued without it. Keep facts, decisions, \
+        commands, file names, versions and open questions; leave out pleasantries. Output only the summary.\n\n{}",
+        transcript.join("\n\n"),
+    );
+    let summary = backend.chat(model, vec![ChatMessage::user(prompt)]).await?;
+
+    let mut summarized = messages[..start].to_vec();
+    summarized.push(StoredMessage::new(ChatMessage::system(format!("Summary of the earlier conversation:\n{}", summary.trim()))));
+    summarized.extend_from_slice(&messages[end..]);
+    Ok(summarized)
+}
+
+/// The agent's generation options with `num_ctx` set to its model's context
+/// window: `known`, read from `AppState::known_context_length` before the
+/// task started, or else looked up (`provider::context_length`) and handed to
+/// the controller to keep. Ollama's default window, 2048 tokens on older
+/// servers, would otherwise silently cut long prompts.
+pub async fn generation_options(agent: &Agent, backend: &Backend, known: Option<u64>, commands: &Commands) -> GenerationOptions {
+    let mut options = agent.generation_options();
+    options.num_ctx = match (known, backend) {
+        (_, Backend::OpenAi(_)) => None,
+        (Some(context), _) => Some(context),
+        (None, Backend::Ollama(server)) => {
+            let context = context_length(&server.client, &agent.model).await;
+            if let Some(context) = context {
+                commands.send(Command::CacheContextLength { endpoint: server.endpoint.clone(), model: agent.model.clone(), context });
+            }
+            context
+        }
+    };
+    options
+}
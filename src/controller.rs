@@ -0,0 +1,408 @@
+use gtk4 as gtk;
+use gtk::glib;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::api_server::ApiSnapshot;
+use crate::error::LockExt;
+use crate::state::{AppState, Bookmark, ChatHistory, MessageMeta, TrashedConversation};
+
+/// A change to the app's data. Widgets and tokio tasks send these instead of
+/// editing `AppState` themselves; the controller applies them one at a time
+/// on the main thread.
+pub enum Command {
+    /// Store a conversation after a turn: a new one is added, a known one
+    /// gets the new messages and moves to the top
+    SaveConversation(ChatHistory),
+    RenameConversation { id: String, title: String },
+    /// Replace the text of message `index` in the conversation open in tab
+    /// `tab_id` and in its history entry `history_id`, if it has been saved
+    EditMessage { tab_id: u64, history_id: Option<String>, index: usize, content: String },
+    /// Replace the notes of conversation `id`
+    SetNotes { id: String, notes: String },
+    /// Add `tag` to the conversations, or remove it when `tagged` is false
+    SetTag { ids: Vec<String>, tag: String, tagged: bool },
+    /// Add conversations from another client; ones imported before are skipped
+    ImportConversations(Vec<ChatHistory>),
+    /// Move conversations to the Trash
+    DeleteConversations(Vec<String>),
+    /// Move conversations back from the Trash
+    RestoreConversations(Vec<String>),
+    /// Remove a conversation from the Trash for good
+    PurgeConversation(String),
+    EmptyTrash,
+    /// Delete every conversation, including the Trash, for good
+    DeleteAllConversations,
+    AddBookmark(Bookmark),
+    RemoveBookmark(String),
+    /// Keep the context window looked up for `model` on the Ollama `endpoint`
+    CacheContextLength { endpoint: String, model: String, context: u64 },
+    /// Answer with what the local API server needs for a request
+    DescribeApi(tokio::sync::oneshot::Sender<ApiSnapshot>),
+}
+
+/// What a command changed, for the parts of the window that show it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppEvent {
+    HistoryChanged,
+    BookmarksChanged,
+}
+
+/// The sending half of the command channel. It is `Send`, so tokio tasks can
+/// hold one where they used to lock the state.
+#[derive(Clone)]
+pub struct Commands(async_channel::Sender<Command>);
+
+impl Commands {
+    pub fn send(&self, command: Command) {
+        let _ = self.0.try_send(command);
+    }
+
+    /// Sends a command that answers through the channel it is given, and
+    /// waits for the answer; `None` once the controller is gone.
+    pub async fn query<T>(&self, command: impl FnOnce(tokio::sync::oneshot::Sender<T>) -> Command) -> Option<T> {
+        let (reply, answer) = tokio::sync::oneshot::channel();
+        self.send(command(reply));
+        answer.await.ok()
+    }
+}
+
+/// Owns the state transitions for commands and tells listeners about them.
+pub struct Controller {
+    listeners: RefCell<Vec<Box<dyn Fn(AppEvent)>>>,
+}
+
+impl Controller {
+    /// Starts applying commands to `state` on the main context.
+    pub fn new(state: Arc<Mutex<AppState>>) -> (Rc<Self>, Commands) {
+        let (sender, receiver) = async_channel::unbounded();
+        let controller = Rc::new(Self {
+            listeners: RefCell::new(Vec::new()),
+        });
+
+        let controller_weak = Rc::downgrade(&controller);
+        glib::MainContext::default().spawn_local(async move {
+            while let Ok(command) = receiver.recv().await {
+                let event = apply(&mut state.locked(), command);
+                let Some(controller) = controller_weak.upgrade() else { break };
+                if let Some(event) = event {
+                    for listener in controller.listeners.borrow().iter() {
+                        listener(event);
+                    }
+                }
+            }
+        });
+
+        (controller, Commands(sender))
+    }
+
+    pub fn connect_event(&self, f: impl Fn(AppEvent) + 'static) {
+        self.listeners.borrow_mut().push(Box::new(f));
+    }
+}
+
+/// Applies one command and saves what it touched. Returns `None` when
+/// nothing the window shows changed, e.g. renaming a conversation that was
+/// deleted meanwhile, or for commands that only answer or fill caches.
+fn apply(state: &mut AppState, command: Command) -> Option<AppEvent> {
+    match command {
+        Command::SaveConversation(mut conversation) => {
+            if let Some(idx) = state.history.iter().position(|h| h.id == conversation.id) {
+                // Keep the generated or user-given title, the tags, the notes and when it started
+                let existing = state.history.remove(idx);
+                conversation.title = existing.title;
+                conversation.tags = existing.tags;
+                conversation.notes = existing.notes;
+                conversation.created_at = existing.created_at.or(conversation.created_at);
+            }
+            state.history.push(conversation);
+        }
+        Command::RenameConversation { id, title } => {
+            let conversation = state.history.iter_mut().find(|h| h.id == id)?;
+            conversation.title = title;
+        }
+        Command::EditMessage { tab_id, history_id, index, content } => {
+            let open = state.chat_in_tab(tab_id).filter(|chat| chat.history_id == history_id);
+            if let Some(message) = open.and_then(|chat| chat.messages.get_mut(index)) {
+                message.set_content(content.clone());
+            }
+            let conversation = state.history.iter_mut().find(|h| Some(&h.id) == history_id.as_ref())?;
+            conversation.messages.get_mut(index)?.set_content(content);
+        }
+        Command::SetNotes { id, notes } => {
+            let conversation = state.history.iter_mut().find(|h| h.id == id)?;
+            if conversation.notes == notes {
+                return None;
+            }
+            conversation.notes = notes;
+        }
+        Command::SetTag { ids, tag, tagged } => {
+            let mut changed = false;
+            for conversation in state.history.iter_mut().filter(|h| ids.contains(&h.id)) {
+                let has_tag = conversation.tags.contains(&tag);
+                if tagged && !has_tag {
+                    conversation.tags.push(tag.clone());
+                    changed = true;
+                } else if !tagged && has_tag {
+                    conversation.tags.retain(|t| t != &tag);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return None;
+            }
+        }
+        Command::ImportConversations(mut conversations) => {
+            conversations.retain(|c| !state.history.iter().any(|h| h.id == c.id));
+            if conversations.is_empty() {
+                return None;
+            }
+            conversations.sort_by_key(|c| c.last_activity());
+            state.history.extend(conversations);
+        }
+        Command::DeleteConversations(ids) => {
+            let deleted_at = MessageMeta::now().timestamp;
+            let (deleted, kept): (Vec<ChatHistory>, _) = std::mem::take(&mut state.history).into_iter().partition(|h| ids.contains(&h.id));
+            state.history = kept;
+            if deleted.is_empty() {
+                return None;
+            }
+            state.purge_trash();
+            state.trash.extend(deleted.into_iter().map(|conversation| TrashedConversation { conversation, deleted_at }));
+            state.save_trash();
+            let drafts = state.drafts.len();
+            state.drafts.retain(|id, _| !ids.contains(id));
+            if state.drafts.len() != drafts {
+                state.save_drafts();
+            }
+        }
+        Command::RestoreConversations(ids) => {
+            let (restored, kept): (Vec<TrashedConversation>, _) = std::mem::take(&mut state.trash).into_iter().partition(|t| ids.contains(&t.conversation.id));
+            state.trash = kept;
+            if restored.is_empty() {
+                return None;
+            }
+            state.history.extend(restored.into_iter().map(|t| t.conversation));
+            state.save_trash();
+        }
+        Command::PurgeConversation(id) => {
+            let idx = state.trash.iter().position(|t| t.conversation.id == id)?;
+            state.trash.remove(idx);
+            state.save_trash();
+            return Some(AppEvent::HistoryChanged);
+        }
+        Command::EmptyTrash => {
+            state.trash.clear();
+            state.storage.remove(&state.trash_path);
+            return Some(AppEvent::HistoryChanged);
+        }
+        Command::DeleteAllConversations => {
+            state.history.clear();
+            state.trash.clear();
+            state.history_store.remove_all(&state.storage);
+            state.storage.remove(&state.trash_path);
+            return Some(AppEvent::HistoryChanged);
+        }
+        Command::AddBookmark(bookmark) => {
+            state.bookmarks.push(bookmark);
+            state.save_bookmarks();
+            return Some(AppEvent::BookmarksChanged);
+        }
+        Command::RemoveBookmark(id) => {
+            let count = state.bookmarks.len();
+            state.bookmarks.retain(|b| b.id != id);
+            if state.bookmarks.len() == count {
+                return None;
+            }
+            state.save_bookmarks();
+            return Some(AppEvent::BookmarksChanged);
+        }
+        Command::CacheContextLength { endpoint, model, context } => {
+            state.context_lengths.insert((endpoint, model), Some(context));
+            return None;
+        }
+        Command::DescribeApi(reply) => {
+            let _ = reply.send(ApiSnapshot::of(state));
+            return None;
+        }
+    }
+    state.save_history();
+    Some(AppEvent::HistoryChanged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history_store::HistoryStore;
+    use crate::state::{OpenChat, Settings, StoredMessage};
+    use crate::storage::Storage;
+    use ollama_rs::generation::chat::ChatMessage;
+    use ollama_rs::Ollama;
+    use std::collections::HashMap;
+
+    /// A state with one open chat whose files go to a fresh temporary directory.
+    fn state() -> AppState {
+        let dir = std::env::temp_dir().join(format!("arch-llm-test-{}", glib::uuid_string_random()));
+        let (storage, _) = Storage::new();
+        let chat = OpenChat::new(0);
+        AppState {
+            ollama: Ollama::default(),
+            active_tab: chat.tab_id,
+            chats: vec![chat],
+            history: Vec::new(),
+            drafts: HashMap::new(),
+            bookmarks: Vec::new(),
+            benchmarks: Vec::new(),
+            arena_votes: Vec::new(),
+            trash: Vec::new(),
+            settings: Settings::default(),
+            config_path: dir.join("settings.json"),
+            history_store: HistoryStore::new(dir.join("history")),
+            memory_path: dir.join("memory"),
+            drafts_path: dir.join("drafts.json"),
+            bookmarks_path: dir.join("bookmarks.json"),
+            benchmarks_path: dir.join("benchmarks.json"),
+            arena_path: dir.join("arena.json"),
+            trash_path: dir.join("trash.json"),
+            recovery_path: dir.join("recovery.json"),
+            available_models: Vec::new(),
+            context_lengths: HashMap::new(),
+            model_capabilities: HashMap::new(),
+            server_version: None,
+            storage,
+        }
+    }
+
+    fn conversation(id: &str, messages: &[&str]) -> ChatHistory {
+        ChatHistory {
+            id: id.to_string(),
+            title: format!("Chat {}", id),
+            messages: messages.iter().map(|m| StoredMessage::new(ChatMessage::user(m.to_string()))).collect(),
+            agent: None,
+            model: None,
+            tags: Vec::new(),
+            notes: String::new(),
+            created_at: Some(100),
+            updated_at: Some(100),
+        }
+    }
+
+    fn ids(history: &[ChatHistory]) -> Vec<&str> {
+        history.iter().map(|h| h.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn saving_a_known_conversation_keeps_its_title_and_tags_and_moves_it_up() {
+        let mut state = state();
+        apply(&mut state, Command::SaveConversation(conversation("a", &["hi"])));
+        apply(&mut state, Command::SaveConversation(conversation("b", &["hello"])));
+        apply(&mut state, Command::SetTag { ids: vec!["a".into()], tag: "work".into(), tagged: true });
+        apply(&mut state, Command::RenameConversation { id: "a".into(), title: "Renamed".into() });
+
+        let mut update = conversation("a", &["hi", "again"]);
+        update.created_at = Some(200);
+        let event = apply(&mut state, Command::SaveConversation(update));
+
+        assert_eq!(event, Some(AppEvent::HistoryChanged));
+        assert_eq!(ids(&state.history), ["b", "a"]);
+        let saved = &state.history[1];
+        assert_eq!(saved.title, "Renamed");
+        assert_eq!(saved.tags, ["work"]);
+        assert_eq!(saved.created_at, Some(100));
+        assert_eq!(saved.messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn changes_to_missing_conversations_are_ignored() {
+        let mut state = state();
+        assert_eq!(apply(&mut state, Command::RenameConversation { id: "gone".into(), title: "x".into() }), None);
+        assert_eq!(apply(&mut state, Command::SetNotes { id: "gone".into(), notes: "x".into() }), None);
+        assert_eq!(apply(&mut state, Command::DeleteConversations(vec!["gone".into()])), None);
+        assert_eq!(apply(&mut state, Command::RestoreConversations(vec!["gone".into()])), None);
+        assert_eq!(apply(&mut state, Command::PurgeConversation("gone".into())), None);
+    }
+
+    #[tokio::test]
+    async fn tagging_twice_or_untagging_an_untagged_conversation_changes_nothing() {
+        let mut state = state();
+        apply(&mut state, Command::SaveConversation(conversation("a", &["hi"])));
+        let tag = |tagged| Command::SetTag { ids: vec!["a".into()], tag: "work".into(), tagged };
+
+        assert_eq!(apply(&mut state, tag(true)), Some(AppEvent::HistoryChanged));
+        assert_eq!(apply(&mut state, tag(true)), None);
+        assert_eq!(state.history[0].tags, ["work"]);
+        assert_eq!(apply(&mut state, tag(false)), Some(AppEvent::HistoryChanged));
+        assert_eq!(apply(&mut state, tag(false)), None);
+        assert!(state.history[0].tags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deleting_moves_conversations_to_the_trash_and_restoring_brings_them_back() {
+        let mut state = state();
+        apply(&mut state, Command::SaveConversation(conversation("a", &["hi"])));
+        apply(&mut state, Command::SaveConversation(conversation("b", &["hello"])));
+        state.drafts.insert("a".into(), "unsent".into());
+
+        apply(&mut state, Command::DeleteConversations(vec!["a".into()]));
+        assert_eq!(ids(&state.history), ["b"]);
+        assert_eq!(state.trash.len(), 1);
+        assert!(!state.drafts.contains_key("a"));
+
+        apply(&mut state, Command::RestoreConversations(vec!["a".into()]));
+        assert_eq!(ids(&state.history), ["b", "a"]);
+        assert!(state.trash.is_empty());
+    }
+
+    #[tokio::test]
+    async fn import_skips_conversations_that_are_already_there() {
+        let mut state = state();
+        apply(&mut state, Command::SaveConversation(conversation("a", &["hi"])));
+
+        let mut older = conversation("b", &["old"]);
+        older.updated_at = Some(50);
+        let event = apply(&mut state, Command::ImportConversations(vec![conversation("c", &["new"]), conversation("a", &["other"]), older]));
+        assert_eq!(event, Some(AppEvent::HistoryChanged));
+        assert_eq!(ids(&state.history), ["a", "b", "c"]);
+        assert_eq!(state.history[0].messages.len(), 1);
+
+        assert_eq!(apply(&mut state, Command::ImportConversations(vec![conversation("a", &["hi"])])), None);
+    }
+
+    #[tokio::test]
+    async fn editing_a_message_changes_the_open_chat_and_its_history_entry() {
+        let mut state = state();
+        let tab_id = state.active_tab;
+        let saved = conversation("a", &["hi"]);
+        state.chat_mut().messages = saved.messages.clone();
+        state.chat_mut().history_id = Some("a".into());
+        apply(&mut state, Command::SaveConversation(saved));
+
+        apply(&mut state, Command::EditMessage { tab_id, history_id: Some("a".into()), index: 0, content: "edited".into() });
+        assert_eq!(state.chat().messages[0].message.content, "edited");
+        assert_eq!(state.history[0].messages[0].message.content, "edited");
+    }
+
+    #[tokio::test]
+    async fn bookmarks_are_added_and_removed() {
+        let mut state = state();
+        let bookmark = Bookmark { id: "m".into(), content: "text".into(), conversation: String::new(), conversation_id: None, meta: None, saved_at: 0 };
+        assert_eq!(apply(&mut state, Command::AddBookmark(bookmark)), Some(AppEvent::BookmarksChanged));
+        assert_eq!(state.bookmarks.len(), 1);
+        assert_eq!(apply(&mut state, Command::RemoveBookmark("m".into())), Some(AppEvent::BookmarksChanged));
+        assert_eq!(apply(&mut state, Command::RemoveBookmark("m".into())), None);
+        assert!(state.bookmarks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn context_lengths_are_cached_and_api_queries_answered_without_an_event() {
+        let mut state = state();
+        let key = ("http://localhost:11434".to_string(), "llama3".to_string());
+        assert_eq!(apply(&mut state, Command::CacheContextLength { endpoint: key.0.clone(), model: key.1.clone(), context: 8192 }), None);
+        assert_eq!(state.context_lengths.get(&key), Some(&Some(8192)));
+
+        let (reply, answer) = tokio::sync::oneshot::channel();
+        assert_eq!(apply(&mut state, Command::DescribeApi(reply)), None);
+        assert!(answer.await.is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use gtk4 as gtk;
+use gtk::glib;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::error::{AppError, AppResult, Context};
+
+/// Starts every encrypted file, so plain files written before encryption was
+/// turned on (or after it was turned off) still load.
+const MAGIC: &[u8] = b"ARCHLLM-ENC1\n";
+const NONCE_LEN: usize = 12;
+/// Attributes the data key is stored under in the Secret Service keyring.
+const KEY_ATTRIBUTES: [&str; 4] = ["application", "arch-llm", "purpose", "data-key"];
+
+/// Encrypts data files with ChaCha20-Poly1305 under a random key kept in the
+/// desktop keyring (through `secret-tool`), so the files are unreadable
+/// without the user's login keyring.
+#[derive(Clone)]
+pub struct Cipher(ChaCha20Poly1305);
+
+impl Cipher {
+    /// The key stored in the keyring, or `None` if there is none or the
+    /// keyring can't be reached.
+    pub fn from_keyring() -> Option<Self> {
+        let output = Command::new("secret-tool")
+            .arg("lookup")
+            .args(KEY_ATTRIBUTES)
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let key = glib::base64_decode(String::from_utf8_lossy(&output.stdout).trim());
+        if key.len() != 32 {
+            tracing::warn!("Ignoring malformed data key in the keyring");
+            return None;
+        }
+        Some(Self(ChaCha20Poly1305::new(Key::from_slice(&key))))
+    }
+
+    /// The stored key, or a new one that is saved to the keyring first.
+    pub fn from_keyring_or_create() -> AppResult<Self> {
+        if let Some(cipher) = Self::from_keyring() {
+            return Ok(cipher);
+        }
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let mut child = Command::new("secret-tool")
+            .args(["store", "--label=Arch LLM data encryption key"])
+            .args(KEY_ATTRIBUTES)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Could not store the encryption key; is libsecret (secret-tool) installed?")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(glib::base64_encode(&key).as_bytes()).context("Could not store the encryption key")?;
+        }
+        let output = child.wait_with_output().context("Could not store the encryption key")?;
+        if !output.status.success() {
+            return Err(AppError::new("Could not store the encryption key in the keyring", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        // Read back, so a keyring that silently dropped it isn't trusted with the data
+        Self::from_keyring().ok_or_else(|| AppError::new("Could not store the encryption key in the keyring", "The key could not be read back"))
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        // Only fails for plaintexts beyond the cipher's 256 GiB limit
+        let ciphertext = self.0.encrypt(&nonce, plaintext).expect("plaintext within ChaCha20-Poly1305 limits");
+        let mut data = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        data
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let body = data.strip_prefix(MAGIC).ok_or("not an encrypted file")?;
+        if body.len() < NONCE_LEN {
+            return Err("encrypted file is truncated".to_string());
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        self.0.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| "wrong key or damaged file".to_string())
+    }
+}
+
+/// Whether `data` was written by `Cipher::encrypt`.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// An error the user should hear about: a one-line summary for the toast and
+/// the underlying cause, shown when they ask for details.
+#[derive(Debug, Clone)]
+pub struct AppError {
+    pub summary: String,
+    pub details: String,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(summary: impl Into<String>, details: impl fmt::Display) -> Self {
+        Self {
+            summary: summary.into(),
+            details: details.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.details.is_empty() {
+            write!(f, "{}", self.summary)
+        } else {
+            write!(f, "{}: {}", self.summary, self.details)
+        }
+    }
+}
+
+/// Attaches a user-facing summary to any error.
+pub trait Context<T> {
+    fn context(self, summary: &str) -> AppResult<T>;
+}
+
+impl<T, E: fmt::Display> Context<T> for Result<T, E> {
+    fn context(self, summary: &str) -> AppResult<T> {
+        self.map_err(|e| AppError::new(summary, e))
+    }
+}
+
+/// Locking that keeps going after another thread panicked while holding the
+/// lock; the state is still usable and losing the whole app is worse.
+pub trait LockExt<T> {
+    fn locked(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn locked(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
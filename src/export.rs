@@ -0,0 +1,270 @@
+use ollama_rs::generation::chat::MessageRole;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde_json::json;
+
+use crate::state::{ChatHistory, StoredMessage};
+use crate::utils::{format_timestamp, is_openable_link};
+
+/// Follows the system light or dark preference; everything is inline so the
+/// page works offline and can be attached to a bug report as is.
+const STYLE: &str = r#"
+:root { color-scheme: light dark; --bg: #ffffff; --fg: #1f1f1f; --muted: #5f6368; --user: #e9eef6; --code: #f6f8fa; --border: #dde3ea;
+  --kw: #8839ef; --str: #40a02b; --com: #8c8fa1; --num: #fe640b; }
+@media (prefers-color-scheme: dark) {
+  :root { --bg: #131314; --fg: #e3e3e3; --muted: #9aa0a6; --user: #282a2c; --code: #1e1f20; --border: #333537;
+    --kw: #cba6f7; --str: #a6e3a1; --com: #7f849c; --num: #fab387; }
+}
+body { background: var(--bg); color: var(--fg); font-family: system-ui, sans-serif; line-height: 1.55; max-width: 820px; margin: 40px auto; padding: 0 20px; }
+header { border-bottom: 1px solid var(--border); margin-bottom: 24px; }
+header p, .meta { color: var(--muted); font-size: 0.85em; }
+.message { margin: 18px 0; }
+.message h2 { font-size: 0.8em; text-transform: uppercase; letter-spacing: 0.05em; color: var(--muted); margin: 0 0 4px; }
+.user .body { background: var(--user); border-radius: 16px; padding: 2px 16px; }
+pre { background: var(--code); border: 1px solid var(--border); border-radius: 8px; padding: 12px; overflow-x: auto; }
+code { font-family: ui-monospace, monospace; font-size: 0.9em; }
+details { border: 1px solid var(--border); border-radius: 8px; padding: 6px 12px; margin: 6px 0; }
+details pre { border: none; margin: 6px 0 0; }
+.tool { color: var(--muted); font-family: ui-monospace, monospace; font-size: 0.85em; }
+.kw { color: var(--kw); } .str { color: var(--str); } .com { color: var(--com); font-style: italic; } .num { color: var(--num); }
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "def", "default", "do", "done", "elif",
+    "else", "enum", "esac", "export", "extends", "false", "fi", "fn", "for", "from", "func", "function", "if", "impl",
+    "import", "in", "interface", "let", "local", "loop", "match", "mod", "mut", "new", "nil", "None", "null", "pub",
+    "return", "self", "static", "struct", "switch", "then", "this", "throw", "trait", "true", "True", "False", "try",
+    "type", "use", "var", "void", "where", "while", "with", "yield", "SELECT", "FROM", "WHERE", "INSERT", "UPDATE",
+    "DELETE", "JOIN", "ORDER", "GROUP", "BY",
+];
+
+/// A standalone HTML page of a conversation, with highlighted code blocks.
+/// Like the markdown export it leaves out the system prompt and images.
+pub fn history_to_html(item: &ChatHistory) -> String {
+    let mut details = Vec::new();
+    if let Some(agent) = &item.agent {
+        details.push(escape(agent));
+    }
+    if let Some(model) = &item.model {
+        details.push(escape(model));
+    }
+    if let Some(created_at) = item.created_at {
+        details.push(format_timestamp(created_at));
+    }
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<header>\n<h1>{title}</h1>\n<p>{}</p>\n</header>\n",
+        details.join(" · "),
+        title = escape(&item.title),
+    );
+    for stored in &item.messages {
+        push_message(&mut out, stored);
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// One chat-format fine-tuning example, `{"messages": [...]}`, with the
+/// system, user and assistant messages of a conversation. Tool calls and
+/// their results are dropped; `None` when there is no reply to learn from.
+pub fn history_to_training_example(item: &ChatHistory) -> Option<String> {
+    let messages: Vec<serde_json::Value> = item.messages.iter()
+        .map(|stored| &stored.message)
+        .filter(|msg| msg.role != MessageRole::Tool && !msg.content.trim().is_empty())
+        .map(|msg| json!({
+            "role": match msg.role {
+                MessageRole::System => "system",
+                MessageRole::User => "user",
+                _ => "assistant",
+            },
+            "content": msg.content.trim(),
+        }))
+        .collect();
+    if !messages.iter().any(|m| m["role"] == "assistant") {
+        return None;
+    }
+    Some(json!({ "messages": messages }).to_string())
+}
+
+/// A file name (without extension) for an exported conversation titled `title`.
+pub fn file_stem(title: &str) -> String {
+    let stem: String = title.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let stem = stem.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-");
+    if stem.is_empty() { "chat".to_string() } else { stem }
+}
+
+fn push_message(out: &mut String, stored: &StoredMessage) {
+    let msg = &stored.message;
+    match msg.role {
+        MessageRole::System => return,
+        MessageRole::Tool => {
+            out.push_str(&format!("<details><summary>Tool result</summary><pre><code>{}</code></pre></details>\n", escape(msg.content.trim())));
+            return;
+        }
+        _ => {}
+    }
+    for call in &msg.tool_calls {
+        out.push_str(&format!("<p class=\"tool\">🔧 {}({})</p>\n", escape(&call.function.name), escape(&call.function.arguments.to_string())));
+    }
+    let (text, attached) = stored.display_parts();
+    if text.trim().is_empty() && attached.is_empty() {
+        return;
+    }
+    let (class, who) = if msg.role == MessageRole::User { ("user", "You") } else { ("assistant", "Assistant") };
+    out.push_str(&format!("<section class=\"message {}\">\n<h2>{}</h2>\n", class, who));
+    for (name, content) in &attached {
+        out.push_str(&format!("<details><summary>{}</summary><pre><code>{}</code></pre></details>\n", escape(name), escape(content)));
+    }
+    if !text.trim().is_empty() {
+        out.push_str("<div class=\"body\">\n");
+        out.push_str(&markdown_to_html(&text));
+        out.push_str("</div>\n");
+    }
+    if msg.images.as_ref().is_some_and(|images| !images.is_empty()) {
+        out.push_str("<p class=\"meta\"><em>(image attached)</em></p>\n");
+    }
+    if let Some(meta) = &stored.meta {
+        let mut line = format_timestamp(meta.timestamp);
+        if let Some(model) = &meta.model {
+            line.push_str(&format!(" · {}", escape(model)));
+        }
+        if meta.edited_at.is_some() {
+            line.push_str(" · edited");
+        }
+        out.push_str(&format!("<p class=\"meta\">{}</p>\n", line));
+    }
+    out.push_str("</section>\n");
+}
+
+/// Renders markdown with code blocks highlighted. Raw HTML in a message is
+/// shown as text, and links and images other than web and mail ones
+/// (`is_openable_link`) as their text, so a reply can't add scripts to the page.
+fn markdown_to_html(markdown: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    // Whether each open link or image was kept
+    let mut links: Vec<bool> = Vec::new();
+    let mut images: Vec<bool> = Vec::new();
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, body)) = code.as_mut() {
+                    body.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, body)) = code.take() {
+                    let class = if lang.is_empty() { String::new() } else { format!(" class=\"language-{}\"", escape(&lang)) };
+                    events.push(Event::Html(format!("<pre><code{}>{}</code></pre>\n", class, highlight(&lang, &body)).into()));
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            Event::Start(Tag::Link { ref dest_url, .. }) => {
+                let keep = is_openable_link(dest_url);
+                links.push(keep);
+                if keep {
+                    events.push(event);
+                }
+            }
+            Event::End(TagEnd::Link) => {
+                if links.pop().unwrap_or(true) {
+                    events.push(event);
+                }
+            }
+            Event::Start(Tag::Image { ref dest_url, .. }) => {
+                let keep = is_openable_link(dest_url);
+                images.push(keep);
+                if keep {
+                    events.push(event);
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                if images.pop().unwrap_or(true) {
+                    events.push(event);
+                }
+            }
+            event => events.push(event),
+        }
+    }
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+    out
+}
+
+/// A small keyword, string, number and comment highlighter, enough for
+/// the languages that usually come up; unknown languages are left plain.
+fn highlight(lang: &str, code: &str) -> String {
+    let lang = lang.to_ascii_lowercase();
+    let hash_comments = matches!(lang.as_str(), "python" | "py" | "sh" | "bash" | "zsh" | "fish" | "shell" | "console" | "ruby" | "rb" | "perl" | "yaml" | "yml" | "toml" | "ini" | "conf" | "dockerfile" | "makefile" | "r");
+    let dash_comments = matches!(lang.as_str(), "sql" | "lua" | "haskell" | "hs");
+    let c_like = matches!(lang.as_str(), "rust" | "rs" | "c" | "h" | "cpp" | "c++" | "cc" | "hpp" | "java" | "kotlin" | "kt" | "go" | "js" | "javascript" | "jsx" | "ts" | "typescript" | "tsx" | "cs" | "csharp" | "swift" | "php" | "scala" | "dart" | "zig" | "json" | "jsonc" | "css" | "scss");
+    if !hash_comments && !dash_comments && !c_like {
+        return escape(code);
+    }
+    let line_comment = if hash_comments {
+        Some("#")
+    } else if dash_comments {
+        Some("--")
+    } else if matches!(lang.as_str(), "css" | "scss") {
+        None
+    } else {
+        Some("//")
+    };
+    // Rust lifetimes look like an opening quote
+    let short_single_quotes = matches!(lang.as_str(), "rust" | "rs");
+
+    let mut out = String::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (class, len) = if line_comment.is_some_and(|prefix| rest.starts_with(prefix)) {
+            (Some("com"), rest.find('\n').unwrap_or(rest.len()))
+        } else if c_like && rest.starts_with("/*") {
+            (Some("com"), rest[2..].find("*/").map(|end| end + 4).unwrap_or(rest.len()))
+        } else if matches!(c, '"' | '\'' | '`') {
+            match string_len(rest, c) {
+                Some(len) if !(c == '\'' && short_single_quotes && len > 4) => (Some("str"), len),
+                _ => (None, 1),
+            }
+        } else if c.is_ascii_digit() {
+            (Some("num"), rest.find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_')).unwrap_or(rest.len()))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+            (KEYWORDS.contains(&&rest[..len]).then_some("kw"), len)
+        } else {
+            (None, c.len_utf8())
+        };
+        let token = escape(&rest[..len]);
+        match class {
+            Some(class) => out.push_str(&format!("<span class=\"{}\">{}</span>", class, token)),
+            None => out.push_str(&token),
+        }
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// Length of the string literal `text` starts with, quotes included. Only
+/// backtick strings span lines; `None` when there is no closing quote.
+fn string_len(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '\n' if quote != '`' => return None,
+            c if c == quote && !escaped => return Some(i + c.len_utf8()),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,215 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::crypto::Cipher;
+use crate::error::LockExt;
+use crate::state::ChatHistory;
+use crate::storage::{read_private, Storage};
+use crate::utils::{read_json, write_atomic};
+
+/// Conversation history as one JSON file per conversation in a directory,
+/// so a data directory synced between machines (Syncthing, Nextcloud) only
+/// ever transfers, and conflicts over, the conversations that changed.
+pub struct HistoryStore {
+    pub dir: PathBuf,
+    /// Hash of each conversation's contents as last written, so saving only
+    /// touches files whose conversation changed
+    written: Mutex<HashMap<String, u64>>,
+}
+
+impl HistoryStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, written: Mutex::new(HashMap::new()) }
+    }
+
+    /// The file a conversation is kept in.
+    pub fn file_path(&self, id: &str) -> PathBuf {
+        let name: String = id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Reads every conversation, oldest activity first, and remembers them as
+    /// written. Copies left by a sync conflict are resolved: the newest copy
+    /// of a conversation wins, and an older copy with messages the winner
+    /// lacks is kept as a separate "(conflicted copy)" conversation.
+    pub fn load(&self, storage: &Storage) -> Vec<ChatHistory> {
+        let mut copies: HashMap<String, Vec<(PathBuf, ChatHistory)>> = HashMap::new();
+        for path in json_files(&self.dir) {
+            match storage.read_private_json::<ChatHistory>(&path) {
+                Some(conversation) => copies.entry(conversation.id.clone()).or_default().push((path, conversation)),
+                None => tracing::warn!("Skipping unreadable conversation {}", path.display()),
+            }
+        }
+
+        // Each conversation with the file it was read from
+        let mut loaded = Vec::new();
+        let mut stale = Vec::new();
+        for (id, mut versions) in copies {
+            versions.sort_by_key(|(_, c)| std::cmp::Reverse(c.last_activity()));
+            let mut versions = versions.into_iter();
+            let Some(newest) = versions.next() else { continue };
+            for (n, (path, copy)) in versions.enumerate() {
+                if !is_prefix(&copy, &newest.1) {
+                    tracing::warn!("Keeping a conflicting copy of conversation {} from {}", id, path.display());
+                    loaded.push((None, ChatHistory {
+                        id: format!("{}-conflict-{}", id, n + 1),
+                        title: format!("{} (conflicted copy)", copy.title),
+                        ..copy
+                    }));
+                }
+                stale.push(path);
+            }
+            loaded.push((Some(newest.0), newest.1));
+        }
+        loaded.sort_by(|(_, a), (_, b)| a.last_activity().cmp(&b.last_activity()).then_with(|| a.id.cmp(&b.id)));
+
+        let mut written = self.written.locked();
+        for (path, conversation) in &loaded {
+            let Ok(json) = serde_json::to_string(conversation) else { continue };
+            let hash = content_hash(&json);
+            // A winner read from a conflict copy, or a kept copy, gets its own file
+            if path.as_ref() != Some(&self.file_path(&conversation.id)) {
+                storage.save_private(&self.file_path(&conversation.id), json);
+            }
+            written.insert(conversation.id.clone(), hash);
+        }
+        drop(written);
+        let history: Vec<ChatHistory> = loaded.into_iter().map(|(_, conversation)| conversation).collect();
+        for path in stale {
+            if !history.iter().any(|c| self.file_path(&c.id) == path) {
+                storage.remove(&path);
+            }
+        }
+        history
+    }
+
+    /// Queues writing the conversations that changed since they were last
+    /// written and deleting the files of those no longer in `history`.
+    /// Files added meanwhile by another machine are left alone.
+    pub fn save(&self, storage: &Storage, history: &[ChatHistory]) {
+        let mut written = self.written.locked();
+        for conversation in history {
+            let json = match serde_json::to_string(conversation) {
+                Ok(json) => json,
+                Err(e) => {
+                    tracing::error!("Failed to serialize conversation {}: {}", conversation.id, e);
+                    continue;
+                }
+            };
+            let hash = content_hash(&json);
+            if written.get(&conversation.id) != Some(&hash) {
+                storage.save_private(&self.file_path(&conversation.id), json);
+                written.insert(conversation.id.clone(), hash);
+            }
+        }
+        written.retain(|id, _| {
+            let kept = history.iter().any(|c| &c.id == id);
+            if !kept {
+                storage.remove(&self.file_path(id));
+            }
+            kept
+        });
+    }
+
+    /// Queues writing every conversation again, e.g. after encryption was
+    /// turned on or off.
+    pub fn rewrite_all(&self, storage: &Storage, history: &[ChatHistory]) {
+        self.written.locked().clear();
+        self.save(storage, history);
+    }
+
+    /// Queues deleting what `migrate` left of the old `history.json`: the
+    /// renamed file and the original's backup, both unencrypted.
+    pub fn remove_legacy(&self, storage: &Storage) {
+        let legacy = self.dir.with_file_name("history.json");
+        let mut migrated = legacy.as_os_str().to_owned();
+        migrated.push(".migrated");
+        storage.remove(&PathBuf::from(migrated));
+        storage.remove(&legacy);
+    }
+
+    /// Queues deleting every conversation file, including ones this instance
+    /// never loaded.
+    pub fn remove_all(&self, storage: &Storage) {
+        self.written.locked().clear();
+        for path in json_files(&self.dir) {
+            storage.remove(&path);
+        }
+    }
+}
+
+/// Reads the conversations in `dir` without keeping track of them, for
+/// readers outside the app's state such as the search provider. Of
+/// conflicting copies only the newest is returned.
+pub fn read_all(dir: &Path, cipher: Option<&Cipher>) -> Vec<ChatHistory> {
+    let mut newest: HashMap<String, ChatHistory> = HashMap::new();
+    let conversations = json_files(dir).into_iter()
+        .filter_map(|path| read_private(&path, cipher))
+        .filter_map(|data| serde_json::from_slice::<ChatHistory>(&data).ok());
+    for conversation in conversations {
+        match newest.get(&conversation.id) {
+            Some(existing) if existing.last_activity() >= conversation.last_activity() => {}
+            _ => {
+                newest.insert(conversation.id.clone(), conversation);
+            }
+        }
+    }
+    let mut history: Vec<ChatHistory> = newest.into_values().collect();
+    history.sort_by(|a, b| a.last_activity().cmp(&b.last_activity()).then_with(|| a.id.cmp(&b.id)));
+    history
+}
+
+/// Splits a `history.json` from before per-conversation files into `dir`,
+/// then renames it to `history.json.migrated`. Does nothing once `dir`
+/// exists.
+pub fn migrate(legacy: &Path, dir: &Path) -> std::io::Result<()> {
+    if dir.exists() {
+        return Ok(());
+    }
+    let conversations = read_json::<Vec<ChatHistory>>(legacy).unwrap_or_default();
+    // Written to a temporary directory first so an interrupted migration is redone
+    let tmp = dir.with_extension("tmp");
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(&tmp)?;
+    let store = HistoryStore::new(tmp.clone());
+    for conversation in &conversations {
+        let json = serde_json::to_string(conversation).map_err(std::io::Error::other)?;
+        write_atomic(&store.file_path(&conversation.id), json)?;
+    }
+    fs::rename(&tmp, dir)?;
+    if legacy.exists() {
+        let mut migrated = legacy.as_os_str().to_owned();
+        migrated.push(".migrated");
+        fs::rename(legacy, PathBuf::from(migrated))?;
+        tracing::info!("Moved {} conversations from {} into {}", conversations.len(), legacy.display(), dir.display());
+    }
+    Ok(())
+}
+
+/// The conversation files in `dir`, including sync conflict copies such as
+/// `<id>.sync-conflict-….json`, but not backups or temporary files.
+fn json_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect()
+}
+
+fn content_hash(json: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether `older` is an earlier state of `newer`: the same messages, with
+/// `newer` possibly continuing it.
+fn is_prefix(older: &ChatHistory, newer: &ChatHistory) -> bool {
+    older.messages.len() <= newer.messages.len()
+        && older.messages.iter().zip(&newer.messages).all(|(a, b)| a.message.role == b.message.role && a.message.content == b.message.content)
+}
@@ -0,0 +1,192 @@
+use gtk4 as gtk;
+use gtk::{gio, glib};
+use ollama_rs::generation::chat::ChatMessage;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{AppError, AppResult, Context};
+use crate::portal::read_chosen_file;
+use crate::state::{ChatHistory, MessageMeta, StoredMessage};
+use crate::utils::find_program;
+
+/// Reads the conversations in another client's export: a ChatGPT data
+/// export (the ZIP, or the `conversations.json` inside it) or an Open WebUI
+/// chat export. Ids are prefixed with the source, so importing the same
+/// export twice doesn't duplicate anything.
+pub fn read_file(file: &gio::File) -> AppResult<Vec<ChatHistory>> {
+    let (name, bytes) = read_chosen_file(file)?;
+    let is_zip = Path::new(&name).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let data = if is_zip {
+        // The tools need a real file, which a file from the portal or a remote location may not be
+        match file.path() {
+            Some(path) => read_zip_entry(&path, "conversations.json")?,
+            None => {
+                let path = glib::tmp_dir().join(format!("arch-llm-import-{}.zip", glib::uuid_string_random()));
+                std::fs::write(&path, &bytes).context("Failed to read the export")?;
+                let data = read_zip_entry(&path, "conversations.json");
+                let _ = std::fs::remove_file(&path);
+                data?
+            }
+        }
+    } else {
+        String::from_utf8(bytes).map_err(|_| AppError::new("Failed to read the export", format!("{} isn't UTF-8", name)))?
+    };
+    let json: Value = serde_json::from_str(&data).context("The export isn't valid JSON")?;
+    let items = match json {
+        Value::Array(items) => items,
+        item => vec![item],
+    };
+    let conversations: Vec<ChatHistory> = items.iter()
+        .filter_map(|item| if item.get("mapping").is_some() { from_chatgpt(item) } else { from_open_webui(item) })
+        .collect();
+    if conversations.is_empty() {
+        return Err(AppError::new("No conversations found", "Expected a ChatGPT data export or an Open WebUI chat export"));
+    }
+    Ok(conversations)
+}
+
+/// Extracts one file from a ZIP with `bsdtar` (part of libarchive, which
+/// pacman needs) or `unzip`.
+fn read_zip_entry(path: &Path, entry: &str) -> AppResult<String> {
+    let output = if find_program("bsdtar").is_some() {
+        Command::new("bsdtar").arg("-xOf").arg(path).arg(entry).output()
+    } else if find_program("unzip").is_some() {
+        Command::new("unzip").arg("-p").arg(path).arg(entry).output()
+    } else {
+        return Err(AppError::new("Can't open ZIP files", "Install libarchive (bsdtar) or unzip, or import conversations.json from the ZIP"));
+    };
+    let output = output.context("Failed to read the export")?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(AppError::new(format!("No {} in the export", entry), String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    String::from_utf8(output.stdout).map_err(|_| AppError::new("Failed to read the export", format!("{} isn't UTF-8", entry)))
+}
+
+/// ChatGPT keeps every edit and regeneration as a tree in `mapping`; the
+/// shown conversation is the branch ending at `current_node`.
+fn from_chatgpt(item: &Value) -> Option<ChatHistory> {
+    let mapping = item.get("mapping")?.as_object()?;
+    let mut node_id = item.get("current_node").and_then(Value::as_str)
+        // Older exports lack it; the newest leaf ends the latest branch
+        .or_else(|| mapping.iter()
+            .filter(|(_, node)| node["children"].as_array().is_none_or(|c| c.is_empty()))
+            .max_by_key(|(_, node)| unix_seconds(&node["message"]["create_time"]))
+            .map(|(id, _)| id.as_str()));
+    let mut branch = Vec::new();
+    while let Some(node) = node_id.and_then(|id| mapping.get(id)) {
+        branch.push(node);
+        node_id = node["parent"].as_str();
+    }
+    branch.reverse();
+
+    let mut model = None;
+    let messages: Vec<StoredMessage> = branch.iter().filter_map(|node| {
+        let message = node.get("message").filter(|m| !m.is_null())?;
+        if message["metadata"]["is_visually_hidden_from_conversation"].as_bool() == Some(true) {
+            return None;
+        }
+        // Only text; images, code output and browsing results are left out
+        let text = message["content"]["parts"].as_array()?.iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let slug = message["metadata"]["model_slug"].as_str().map(str::to_string);
+        if slug.is_some() {
+            model = slug.clone();
+        }
+        stored_message(message["author"]["role"].as_str()?, text, unix_seconds(&message["create_time"]), slug)
+    }).collect();
+    if messages.is_empty() {
+        return None;
+    }
+    let id = item.get("conversation_id").or_else(|| item.get("id")).and_then(Value::as_str)?;
+    Some(ChatHistory {
+        id: format!("chatgpt-{}", id),
+        title: title(item),
+        messages,
+        agent: None,
+        model,
+        tags: vec!["ChatGPT".to_string()],
+        notes: String::new(),
+        created_at: unix_seconds(&item["create_time"]),
+        updated_at: unix_seconds(&item["update_time"]),
+    })
+}
+
+/// Open WebUI exports `{id, title, chat: {history: {messages, currentId}},
+/// created_at, updated_at}`; like ChatGPT, `history` is a tree of branches.
+fn from_open_webui(item: &Value) -> Option<ChatHistory> {
+    let chat = item.get("chat")?;
+    let tree = chat["history"]["messages"].as_object();
+    let branch: Vec<&Value> = match (tree, chat["history"]["currentId"].as_str()) {
+        (Some(tree), Some(current)) => {
+            let mut branch = Vec::new();
+            let mut id = Some(current);
+            while let Some(message) = id.and_then(|id| tree.get(id)) {
+                branch.push(message);
+                id = message["parentId"].as_str();
+            }
+            branch.reverse();
+            branch
+        }
+        _ => chat["messages"].as_array()?.iter().collect(),
+    };
+
+    let mut model = chat["models"].as_array().and_then(|models| models.first()).and_then(Value::as_str).map(str::to_string);
+    let messages: Vec<StoredMessage> = branch.iter().filter_map(|message| {
+        let slug = message["model"].as_str().map(str::to_string);
+        if slug.is_some() {
+            model = slug.clone();
+        }
+        stored_message(message["role"].as_str()?, message["content"].as_str()?.to_string(), unix_seconds(&message["timestamp"]), slug)
+    }).collect();
+    if messages.is_empty() {
+        return None;
+    }
+    let id = item.get("id").or_else(|| chat.get("id")).and_then(Value::as_str)?;
+    let created_at = unix_seconds(&item["created_at"]).or_else(|| unix_seconds(&chat["timestamp"]));
+    Some(ChatHistory {
+        id: format!("openwebui-{}", id),
+        title: if item["title"].is_string() { title(item) } else { title(chat) },
+        messages,
+        agent: None,
+        model,
+        tags: vec!["Open WebUI".to_string()],
+        notes: String::new(),
+        created_at,
+        updated_at: unix_seconds(&item["updated_at"]).or(created_at),
+    })
+}
+
+/// A user or assistant message; other roles (system, tool) and empty
+/// messages are dropped.
+fn stored_message(role: &str, text: String, timestamp: Option<i64>, model: Option<String>) -> Option<StoredMessage> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    let (message, model) = match role {
+        "user" => (ChatMessage::user(text), None),
+        "assistant" => (ChatMessage::assistant(text), model),
+        _ => return None,
+    };
+    let mut stored = StoredMessage::new(message);
+    // Without a time there is nothing true to show under the message
+    stored.meta = timestamp.map(|timestamp| MessageMeta {
+        timestamp,
+        model,
+        ..Default::default()
+    });
+    Some(stored)
+}
+
+fn title(item: &Value) -> String {
+    item["title"].as_str().map(str::trim).filter(|t| !t.is_empty()).unwrap_or("Imported chat").to_string()
+}
+
+/// A timestamp in seconds, milliseconds or nanoseconds, as float or integer.
+fn unix_seconds(value: &Value) -> Option<i64> {
+    let time = value.as_f64()?;
+    let seconds = if time > 1e14 { time / 1e9 } else if time > 1e11 { time / 1e3 } else { time };
+    Some(seconds as i64)
+}
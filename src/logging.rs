@@ -0,0 +1,156 @@
+use gtk4 as gtk;
+use gtk::glib;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::error::LockExt;
+use crate::state::LogLevel;
+
+/// The log is rotated once it grows past this size.
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// Rotated files kept next to the current one (`arch-llm.log.1` …).
+const KEPT_LOGS: u32 = 3;
+const LOG_NAME: &str = "arch-llm.log";
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Writes events to stderr and to a size-rotated file in the data directory,
+/// so launches from a .desktop file still leave a trace.
+struct FileLogger {
+    file: Mutex<Option<(File, u64)>>,
+    path: PathBuf,
+    next_span: AtomicU64,
+}
+
+/// Installs the logger; `dir` is created if needed. Call once, before the
+/// first event.
+pub fn init(dir: &Path) {
+    let _ = fs::create_dir_all(dir);
+    let _ = LOG_DIR.set(dir.to_path_buf());
+    let path = dir.join(LOG_NAME);
+    let logger = FileLogger {
+        file: Mutex::new(open(&path)),
+        path,
+        next_span: AtomicU64::new(1),
+    };
+    if tracing::subscriber::set_global_default(logger).is_err() {
+        return;
+    }
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        default_hook(info);
+    }));
+}
+
+/// Changes the verbosity of everything logged from now on.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Directory holding the current and rotated log files.
+pub fn log_dir() -> Option<&'static Path> {
+    LOG_DIR.get().map(PathBuf::as_path)
+}
+
+fn open(path: &Path) -> Option<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path).ok()?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    Some((file, size))
+}
+
+fn level_rank(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => LogLevel::Error as u8,
+        Level::WARN => LogLevel::Warn as u8,
+        Level::INFO => LogLevel::Info as u8,
+        Level::DEBUG => LogLevel::Debug as u8,
+        Level::TRACE => LogLevel::Trace as u8,
+    }
+}
+
+impl FileLogger {
+    fn rotate(&self) -> Option<(File, u64)> {
+        let rotated = |n: u32| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..KEPT_LOGS).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        let _ = fs::rename(&self.path, rotated(1));
+        open(&self.path)
+    }
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            let _ = write!(self.0, " {}", value);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, " {:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+impl Subscriber for FileLogger {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // The level can change at runtime, so never cache the decision
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        level_rank(metadata.level()) <= LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let time = glib::DateTime::now_local()
+            .and_then(|now| now.format("%Y-%m-%d %H:%M:%S"))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        let mut line = format!("{} {:5} {}:", time, metadata.level(), metadata.target());
+        event.record(&mut LineVisitor(&mut line));
+        line.push('\n');
+
+        eprint!("{}", line);
+        let mut file = self.file.locked();
+        if file.as_ref().is_some_and(|(_, size)| *size > MAX_LOG_BYTES) {
+            *file = self.rotate();
+        }
+        if let Some((file, size)) = file.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                *size += line.len() as u64;
+            }
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
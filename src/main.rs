@@ -2,105 +2,344 @@ use gtk4 as gtk;
 use gtk::glib;
 use gtk::prelude::*;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use gtk::{
-    Application, ApplicationWindow, Box, Orientation, Label, Entry, Button,
-    ScrolledWindow, ListBox, DropDown, StringList, Stack, StackSidebar,
-    Popover, GestureClick, EventControllerKey, Spinner, TextView
+    Box, Orientation, Label, Entry, Button,
+    ScrolledWindow, Stack,
+    EventControllerKey, Spinner
 };
+use adw::{Application, ApplicationWindow, ToastOverlay};
 use std::sync::{Arc, Mutex};
-use serde_json;
 use std::fs;
-use ollama_rs::generation::chat::request::ChatMessageRequest;
-use ollama_rs::generation::chat::ChatMessage;
-use ollama_rs::Ollama;
-use futures_util::StreamExt;
-use directories::ProjectDirs;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+mod api_server;
+mod arena;
+mod attachments;
+mod autostart;
+mod backup;
+mod benchmark;
+mod confirm;
+mod context;
+mod controller;
+mod crypto;
+mod error;
+mod export;
+mod history_store;
+mod import;
+mod logging;
+mod migrations;
+mod paths;
+mod portal;
+mod provider;
+mod quick_ask;
+mod recovery;
+mod remote;
+mod search_provider;
+mod secrets;
+mod service;
+mod share;
+mod speech;
 mod state;
+mod storage;
+mod terminal;
+mod theme;
+mod tools;
+mod tray;
+mod ui;
 mod utils;
 
-use state::{AppState, Agent, Profile, Settings, ChatHistory, ChatEvent};
-use utils::{normalize_url, parse_markdown, markdown_to_pango, MarkdownBlock};
-
-fn get_config_files() -> (PathBuf, PathBuf, PathBuf) {
-    let dirs = ProjectDirs::from("org", "archllm", "arch-llm").expect("Could not determine project directories");
-    
-    let config_dir = dirs.config_dir();
-    let data_dir = dirs.data_dir();
+use api_server::{new_api_key, ApiServer};
+use attachments::Attachment;
+use controller::{AppEvent, Controller};
+use crypto::Cipher;
+use error::{AppError, AppResult, Context, LockExt};
+use history_store::HistoryStore;
+use provider::{missing_features, ollama_client, ollama_version};
+use quick_ask::QuickAsk;
+use remote::RemoteHandlers;
+use speech::{Speaker, SpeechState};
+use storage::Storage;
+use theme::ThemeManager;
+use tray::StatusIcon;
+use ui::agent_picker::AgentPicker;
+use ui::arena::ArenaView;
+use ui::chat::CHAT_MIN_MARGIN;
+use ui::composer::{AttachmentBar, MarkdownPreview, TokenCounter};
+use ui::diagnose;
+use ui::inspector::Inspector;
+use ui::monitor::ResourceMonitor;
+use ui::notes::NotesPanel;
+use ui::saved::SavedView;
+use ui::send::ChatSender;
+use ui::server_log::ServerLog;
+use ui::settings::{SettingsCallbacks, SettingsWindow};
+use ui::sidebar::Sidebar;
+use ui::split::SplitChat;
+use ui::tabs::ChatTabs;
+use ui::templates;
+use ui::toasts::{toast, Report};
+use ui::vision_offer::VisionOffer;
+use state::{AppState, OpenChat, Settings, WindowGeometry, StoredMessage};
+use utils::{transcript_to_markdown, read_json};
+
+fn get_config_files() -> AppResult<(PathBuf, PathBuf, PathBuf)> {
+    let dirs = paths::dirs()
+        .ok_or_else(|| AppError::new("Could not determine project directories", "No home directory was found"))?;
+
+    let config_dir = &dirs.config;
+    let data_dir = &dirs.data;
     let memory_dir = data_dir.join("memories");
+    let history_dir = data_dir.join("history");
 
-    fs::create_dir_all(config_dir).expect("Could not create config directory");
-    fs::create_dir_all(data_dir).expect("Could not create data directory");
-    fs::create_dir_all(&memory_dir).expect("Could not create memory directory");
+    fs::create_dir_all(config_dir).context("Could not create config directory")?;
+    fs::create_dir_all(data_dir).context("Could not create data directory")?;
+    fs::create_dir_all(&memory_dir).context("Could not create memory directory")?;
+    history_store::migrate(&data_dir.join("history.json"), &history_dir).context("Could not move chat history into per-conversation files")?;
 
-    (
+    Ok((
         config_dir.join("settings.json"),
-        data_dir.join("history.json"),
+        history_dir,
         memory_dir
-    )
+    ))
+}
+
+/// Stand-in main window for errors the app can't start without.
+fn show_startup_error(app: &Application, error: &AppError) {
+    tracing::error!("{}", error);
+    let content = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .valign(gtk::Align::Center)
+        .margin_start(40)
+        .margin_end(40)
+        .build();
+    content.append(&Label::builder().label("Arch LLM could not start").css_classes(["title-2"]).build());
+    content.append(&Label::builder().label(&error.summary).wrap(true).build());
+    let details = Label::builder().label(&error.details).wrap(true).selectable(true).css_classes(["monospace"]).build();
+    content.append(&gtk::Expander::builder().label("Details").child(&details).build());
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&adw::HeaderBar::new());
+    toolbar_view.set_content(Some(&content));
+    ApplicationWindow::builder()
+        .application(app)
+        .title("Arch LLM")
+        .default_width(480)
+        .default_height(240)
+        .content(&toolbar_view)
+        .build()
+        .present();
 }
 
+/// Window width below which the sidebar is folded away.
+const NARROW_LAYOUT_WIDTH: i32 = 760;
+
+/// Wait after a network change before checking the connection again, as
+/// changes tend to come in bursts while an interface comes up.
+const NETWORK_SETTLE_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[tokio::main]
 async fn main() -> glib::ExitCode {
-    println!("Arch-LLM v0.2 Started");
+    let dirs = paths::init(std::env::args_os());
+    if let Some(dirs) = dirs {
+        logging::init(&dirs.data.join("logs"));
+    }
+    tracing::info!("Arch-LLM v0.2 started");
+    let mut flags = gtk::gio::ApplicationFlags::HANDLES_COMMAND_LINE;
+    // Another profile runs in its own process instead of handing over to the default one
+    if let Some(dirs) = dirs.filter(|dirs| dirs.custom) {
+        tracing::info!("Keeping settings and data in {}", dirs.data.display());
+        flags |= gtk::gio::ApplicationFlags::NON_UNIQUE;
+    }
     let app = Application::builder()
         .application_id("org.archllm.ollama_chat")
+        .flags(flags)
         .build();
-
-    app.connect_activate(build_ui);
+    // Already read by `paths::init`; declared so they aren't taken as prompt text
+    app.add_main_option("config-dir", glib::Char::from(0u8), glib::OptionFlags::NONE, glib::OptionArg::Filename, "Keep settings and data in DIR", Some("DIR"));
+    app.add_main_option("portable", glib::Char::from(0u8), glib::OptionFlags::NONE, glib::OptionArg::None, "Keep settings and data in arch-llm-data next to the executable", None);
+    app.add_main_option(autostart::AUTOSTART_OPTION, glib::Char::from(0u8), glib::OptionFlags::HIDDEN, glib::OptionArg::None, "Started with the desktop session", None);
+
+    // Registered at startup so it also answers when the shell starts us as a D-Bus service
+    app.connect_startup(|app| {
+        let Some(connection) = app.dbus_connection() else { return };
+        let Ok((_, history_dir, _)) = get_config_files() else { return };
+        let app_activate = app.downgrade();
+        let app_launch = app.downgrade();
+        let result = search_provider::export(
+            &connection,
+            history_dir,
+            move |id| {
+                let Some(app) = app_activate.upgrade() else { return };
+                app.activate();
+                app.activate_action("open-conversation", Some(&id.to_variant()));
+            },
+            move |terms| {
+                let Some(app) = app_launch.upgrade() else { return };
+                app.activate();
+                app.activate_action("prefill", Some(&terms.to_variant()));
+            },
+        );
+        if let Err(e) = result {
+            tracing::error!("Failed to export search provider: {}", e);
+        }
+    });
+    app.connect_activate(|app| {
+        // Relaunching while running in the background re-presents the existing window
+        match app.windows().first() {
+            Some(window) => window.present(),
+            None => build_ui(app, false),
+        }
+    });
+    // Runs in the primary instance, also for arguments forwarded from a second launch
+    app.connect_command_line(|app, command_line| {
+        if command_line.options_dict().contains(autostart::AUTOSTART_OPTION) && app.windows().is_empty() {
+            build_ui(app, true);
+            return glib::ExitCode::SUCCESS;
+        }
+        let prompt = prompt_from_arguments(command_line);
+        app.activate();
+        if !prompt.is_empty() {
+            app.activate_action("prefill", Some(&prompt.to_variant()));
+        }
+        glib::ExitCode::SUCCESS
+    });
     app.run()
 }
 
-fn build_ui(app: &Application) {
-    let (settings_path, history_path, memory_path) = get_config_files();
-
-    let history_data = fs::read_to_string(&history_path)
-        .ok()
-        .and_then(|s| serde_json::from_str::<Vec<ChatHistory>>(&s).ok())
-        .unwrap_or_default();
-
-    let mut settings_data = fs::read_to_string(&settings_path)
-        .ok()
-        .and_then(|s| serde_json::from_str::<Settings>(&s).ok())
-        .unwrap_or_else(|| Settings::default());
-
-    // Ensure all profiles have IDs
-    let mut modified = false;
-    for profile in &mut settings_data.profiles {
-        if profile.id.is_empty() {
-            profile.id = glib::uuid_string_random().to_string();
-            modified = true;
+/// Turns `arch-llm "summarize" notes.txt` into a prompt: plain arguments are
+/// joined as text and arguments naming files (relative to the caller's working
+/// directory) are appended as fenced blocks.
+fn prompt_from_arguments(command_line: &gtk::gio::ApplicationCommandLine) -> String {
+    let mut text = Vec::new();
+    let mut files = Vec::new();
+    for arg in command_line.arguments().iter().skip(1) {
+        let file = command_line.create_file_for_arg(arg);
+        match file.path().filter(|path| path.is_file()) {
+            Some(path) => match fs::read(&path) {
+                Ok(bytes) => {
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    files.push(format!("{}:\n```\n{}\n```", name, String::from_utf8_lossy(&bytes).trim_end()));
+                }
+                Err(e) => tracing::error!("Failed to read {}: {}", path.display(), e),
+            },
+            None => text.push(arg.to_string_lossy().to_string()),
+        }
+    }
+    let mut prompt = text.join(" ");
+    for file in files {
+        if !prompt.is_empty() {
+            prompt.push_str("\n\n");
         }
+        prompt.push_str(&file);
     }
-    if modified {
-        let _ = fs::write(&settings_path, serde_json::to_string(&settings_data).unwrap());
+    prompt
+}
+
+/// `at_login` is set when the session's autostart launched the app, which
+/// then stays hidden behind its status icon if the user asked for that.
+fn build_ui(app: &Application, at_login: bool) {
+    let (settings_path, history_dir, memory_path) = match get_config_files() {
+        Ok(paths) => paths,
+        Err(e) => return show_startup_error(app, &e),
+    };
+    let user_css_path = settings_path.with_file_name("style.css");
+    let drafts_path = history_dir.with_file_name("drafts.json");
+    let bookmarks_path = history_dir.with_file_name("bookmarks.json");
+    let benchmarks_path = history_dir.with_file_name("benchmarks.json");
+    let arena_path = history_dir.with_file_name("arena.json");
+    let trash_path = history_dir.with_file_name("trash.json");
+    let recovery_path = history_dir.with_file_name("recovery.json");
+
+    let benchmarks_data = read_json(&benchmarks_path).unwrap_or_default();
+    let arena_data = read_json(&arena_path).unwrap_or_default();
+
+    let (settings_data, settings_warning) = migrations::load(&settings_path);
+    logging::set_level(settings_data.log_level);
+    let (storage, storage_errors) = Storage::new();
+    let mut encryption_warning = None;
+    if settings_data.encrypt_data {
+        let cipher = Cipher::from_keyring();
+        if cipher.is_none() {
+            encryption_warning = Some(AppError::new(
+                "Encrypted chats and memories can't be read or saved encrypted",
+                "The encryption key was not found in the keyring (secret-tool lookup application arch-llm purpose data-key)",
+            ));
+        }
+        storage.set_encryption(cipher, true);
     }
+    let recovered_session = recovery::load(&storage, &recovery_path);
+    let trash_data = storage.read_private_json(&trash_path).unwrap_or_default();
+    let drafts_data = storage.read_private_json(&drafts_path).unwrap_or_default();
+    let bookmarks_data = storage.read_private_json(&bookmarks_path).unwrap_or_default();
+    let history_store = HistoryStore::new(history_dir);
+    let history_data = history_store.load(&storage);
 
-    let ollama_url = normalize_url(&settings_data.ollama_endpoint);
-    let ollama = Ollama::from_url(
-        url::Url::parse(&ollama_url).unwrap_or_else(|_| url::Url::parse("http://localhost:11434").unwrap())
-    );
+    let ollama = ollama_client(&settings_data.ollama_endpoint, &settings_data.ollama_headers, &settings_data.ollama_tls, &settings_data.timeouts).unwrap_or_default();
 
+    let first_chat = OpenChat::new(settings_data.default_agent_index());
     let state = Arc::new(Mutex::new(AppState {
         ollama,
-        current_agent_idx: 0,
-        messages: Vec::new(),
+        active_tab: first_chat.tab_id,
+        chats: vec![first_chat],
         history: history_data,
+        drafts: drafts_data,
+        bookmarks: bookmarks_data,
+        benchmarks: benchmarks_data,
+        arena_votes: arena_data,
+        trash: trash_data,
         settings: settings_data.clone(),
         config_path: settings_path,
-        history_path,
+        history_store,
         memory_path,
-        current_task: None,
+        drafts_path,
+        bookmarks_path,
+        benchmarks_path,
+        arena_path,
+        trash_path,
+        recovery_path,
         available_models: Vec::new(),
+        context_lengths: HashMap::new(),
+        model_capabilities: HashMap::new(),
+        server_version: None,
+        storage,
     }));
+    {
+        let mut s = state.locked();
+        if s.purge_trash() {
+            s.save_trash();
+        }
+    }
+
+    let theme = ThemeManager::new(settings_data.appearance, user_css_path);
+    theme.set_fonts(settings_data.chat_font_size, &settings_data.monospace_font);
 
     // --- Root Stack (Loading -> Error -> Main) ---
     let root_stack = Stack::builder()
         .transition_type(gtk::StackTransitionType::Crossfade)
         .build();
 
+    let toasts = ToastOverlay::new();
+    for warning in [settings_warning, encryption_warning].into_iter().flatten() {
+        toasts.report(&warning);
+    }
+    glib::MainContext::default().spawn_local({
+        let toasts = toasts.clone();
+        async move {
+            while let Ok(error) = storage_errors.recv().await {
+                toasts.report(&error);
+            }
+        }
+    });
+    toasts.set_child(Some(&root_stack));
+
+    if let Err(e) = theme.reload_user_css() {
+        toasts.report(&AppError::new("Custom stylesheet has errors", e));
+    }
+
+    let speaker = Speaker::new();
+
     // Loading Page
     let loading_box = Box::builder()
         .orientation(Orientation::Vertical)
@@ -135,11 +374,17 @@ fn build_ui(app: &Application) {
     
     let retry_btn = Button::with_label("Connect");
     retry_btn.add_css_class("suggested-action");
+    let start_ollama_btn = Button::builder()
+        .label("Start Ollama")
+        .tooltip_text("Start the local Ollama server and connect")
+        .visible(service::can_start(&settings_data.ollama_endpoint))
+        .build();
     
     error_box.append(&error_icon);
     error_box.append(&error_label);
     error_box.append(&endpoint_entry_setup);
     error_box.append(&retry_btn);
+    error_box.append(&start_ollama_btn);
     root_stack.add_named(&error_box, Some("error"));
 
     let main_stack = Stack::builder()
@@ -151,42 +396,20 @@ fn build_ui(app: &Application) {
     let chat_box_container = Box::builder()
         .orientation(Orientation::Horizontal)
         .build();
-
-    // --- Sidebar ---
-    let sidebar = Box::builder()
-        .orientation(Orientation::Vertical)
-        .css_name("sidebar")
-        .build();
-    
-    let sidebar_top = Box::builder()
-        .orientation(Orientation::Vertical)
-        .vexpand(true)
-        .build();
-    sidebar.append(&sidebar_top);
-
-    let new_chat_btn = Button::with_label("New chat");
-    new_chat_btn.set_margin_start(10);
-    new_chat_btn.set_margin_end(10);
-    new_chat_btn.set_margin_top(10);
-    new_chat_btn.set_margin_bottom(10);
-    sidebar_top.append(&new_chat_btn);
-    
-    let history_list = ListBox::builder()
-        .margin_top(20)
-        .css_classes(["history-list"])
-        .build();
-    let history_scrolled = ScrolledWindow::builder()
-        .child(&history_list)
-        .hscrollbar_policy(gtk::PolicyType::Never)
-        .vexpand(true)
-        .build();
-    sidebar_top.append(&history_scrolled);
-
-    let settings_btn = Button::with_label("Settings");
-    settings_btn.set_margin_start(10);
-    settings_btn.set_margin_end(10);
-    settings_btn.set_margin_bottom(20);
-    sidebar.append(&settings_btn);
+    let inspector = Inspector::new();
+    let server_log = ServerLog::new();
+
+    let (controller, commands) = Controller::new(state.clone());
+    let notes_panel = NotesPanel::new(&state, &commands);
+    let sidebar = Sidebar::new(&state, &toasts, &commands);
+    controller.connect_event({
+        let sidebar = sidebar.clone();
+        move |event| {
+            if event == AppEvent::HistoryChanged {
+                sidebar.refresh_history();
+            }
+        }
+    });
 
     // --- Main Content Area ---
     let content_area = Box::builder()
@@ -202,164 +425,246 @@ fn build_ui(app: &Application) {
         .margin_bottom(20)
         .build();
     
-    let agent_names_list = StringList::new(&[]);
-    let agent_dropdown = DropDown::builder()
-        .model(&agent_names_list)
-        .build();
+    let agent_picker = AgentPicker::new(&state);
+    let agent_dropdown = agent_picker.dropdown.clone();
     header.append(&agent_dropdown);
 
-    let refresh_agent_dropdown_func = |state: Arc<Mutex<AppState>>, agent_names_list: StringList| {
-        let names: Vec<String> = {
-            let s = state.lock().expect("Failed to lock state for agent dropdown refresh");
-            s.settings.agents.iter().map(|a| a.name.clone()).collect()
-        };
-        let name_refs: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
-        agent_names_list.splice(0, agent_names_list.n_items(), &name_refs);
-    };
+    let copy_transcript_btn = Button::builder()
+        .icon_name("edit-copy-symbolic")
+        .css_classes(["flat"])
+        .tooltip_text("Copy Transcript (Ctrl+Shift+C)")
+        .hexpand(true)
+        .halign(gtk::Align::End)
+        .build();
+    copy_transcript_btn.connect_clicked({
+        let state = state.clone();
+        let toasts = toasts.clone();
+        move |_| {
+            let transcript = {
+                let s = state.locked();
+                let title = s.chat().history_id.as_ref()
+                    .and_then(|id| s.history.iter().find(|h| &h.id == id))
+                    .map(|h| h.title.clone());
+                transcript_to_markdown(title.as_deref(), &s.chat().messages)
+            };
+            if transcript.trim().is_empty() {
+                toasts.add_toast(toast("Nothing to copy yet"));
+                return;
+            }
+            if let Some(display) = gtk::gdk::Display::default() {
+                display.clipboard().set(&transcript.trim_start());
+                toasts.add_toast(toast("Transcript copied"));
+            }
+        }
+    });
+    header.append(&copy_transcript_btn);
 
-    refresh_agent_dropdown_func(state.clone(), agent_names_list.clone());
-    content_area.append(&header);
+    let refresh_agent_names: Rc<dyn Fn()> = Rc::new(move || agent_picker.refresh());
+    agent_dropdown.set_selected(settings_data.default_agent_index() as u32);
 
-    // Chat display
-    let scrolled_window = ScrolledWindow::builder()
-        .vexpand(true)
+    // Connection-lost banner, shown while reconnecting with backoff
+    let connection_banner = gtk::Revealer::builder()
+        .transition_type(gtk::RevealerTransitionType::SlideDown)
         .build();
-    let chat_box = Box::builder()
-        .orientation(Orientation::Vertical)
+    let banner_box = Box::builder()
+        .orientation(Orientation::Horizontal)
         .spacing(10)
-        .margin_start(100)
-        .margin_end(100)
-        .margin_top(20)
-        .margin_bottom(20)
+        .css_classes(["connection-banner"])
         .build();
-    scrolled_window.set_child(Some(&chat_box));
-    content_area.append(&scrolled_window);
-
-    let scroll_to_bottom = {
-        let scrolled_window = scrolled_window.clone();
-        move || {
-            let vadj = scrolled_window.vadjustment();
-            vadj.set_value(vadj.upper() - vadj.page_size());
+    let banner_label = Label::builder().xalign(0.0).hexpand(true).build();
+    let banner_retry_btn = Button::with_label("Retry now");
+    let banner_start_btn = Button::with_label("Start Ollama");
+    banner_box.append(&banner_label);
+    banner_box.append(&banner_start_btn);
+    banner_box.append(&banner_retry_btn);
+    connection_banner.set_child(Some(&banner_box));
+    content_area.append(&connection_banner);
+
+    let (reconnect_wake_tx, reconnect_wake_rx) = async_channel::unbounded::<()>();
+    {
+        let reconnect_wake_tx = reconnect_wake_tx.clone();
+        banner_retry_btn.connect_clicked(move |_| {
+            let _ = reconnect_wake_tx.try_send(());
+        });
+    }
+    // Starts the local server, then runs `on_ready`; failures are shown as a toast
+    let start_ollama = {
+        let state = state.clone();
+        let toasts = toasts.clone();
+        move |btn: &Button, on_ready: std::boxed::Box<dyn Fn()>| {
+            btn.set_sensitive(false);
+            btn.set_label("Starting Ollama…");
+            let btn = btn.clone();
+            let ollama = state.locked().ollama.clone();
+            let toasts = toasts.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let result = service::start(&ollama).await;
+                btn.set_sensitive(true);
+                btn.set_label("Start Ollama");
+                match result {
+                    Ok(()) => on_ready(),
+                    Err(e) => toasts.report(&AppError::new("Failed to start Ollama", e)),
+                }
+            });
         }
     };
+    {
+        let start_ollama = start_ollama.clone();
+        let reconnect_wake_tx = reconnect_wake_tx.clone();
+        banner_start_btn.connect_clicked(move |btn| {
+            let reconnect_wake_tx = reconnect_wake_tx.clone();
+            start_ollama(btn, std::boxed::Box::new(move || {
+                let _ = reconnect_wake_tx.try_send(());
+            }));
+        });
+    }
 
-    let render_chat = {
-        let chat_box = chat_box.clone();
-        let scroll_to_bottom = scroll_to_bottom.clone();
-        move |messages: &Vec<ChatMessage>| {
-            while let Some(child) = chat_box.first_child() {
-                chat_box.remove(&child);
-            }
-            
-            if messages.is_empty() {
-                let welcome = Box::builder()
-                    .orientation(Orientation::Vertical)
-                    .valign(gtk::Align::Center)
-                    .halign(gtk::Align::Center)
-                    .spacing(20)
-                    .margin_top(50)
-                    .build();
-                let icon = Label::builder().label("🤖").css_classes(["welcome-icon"]).build();
-                let text = Label::builder().label("Select an agent or start typing...").css_classes(["welcome-text"]).build();
-                welcome.append(&icon);
-                welcome.append(&text);
-                chat_box.append(&welcome);
-            } else {
-                for msg in messages {
-                    if msg.role == ollama_rs::generation::chat::MessageRole::System { continue; }
-                    let is_user = msg.role == ollama_rs::generation::chat::MessageRole::User;
-                    
-                    let msg_container = Box::builder()
-                        .orientation(Orientation::Vertical)
-                        .spacing(5)
-                        .margin_bottom(10)
-                        .build();
-                    
-                    if is_user {
-                        msg_container.set_halign(gtk::Align::End);
-                    } else {
-                        msg_container.set_halign(gtk::Align::Start);
-                        let header_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
-                        let header = Label::builder()
-                            .label("Ollama")
-                            .css_classes(["msg-header"])
-                            .halign(gtk::Align::Start)
-                            .hexpand(true)
-                            .build();
-                        header_box.append(&header);
-                        
-                        let copy_btn = Button::builder()
-                            .icon_name("edit-copy-symbolic")
-                            .css_classes(["flat"])
-                            .valign(gtk::Align::Center)
-                            .tooltip_text("Copy Response")
-                            .build();
-                        
-                        let content = msg.content.clone();
-                        copy_btn.connect_clicked(move |_| {
-                            if let Some(display) = gtk::gdk::Display::default() {
-                                display.clipboard().set(&content);
-                            }
-                        });
-                        header_box.append(&copy_btn);
-                        
-                        msg_container.append(&header_box);
+    let reconnecting = Rc::new(Cell::new(false));
+    // Run once the connection is back, set when the queued messages can be sent
+    let on_reconnected: Rc<RefCell<Option<Rc<dyn Fn()>>>> = Rc::new(RefCell::new(None));
+    let start_reconnect: Rc<dyn Fn()> = {
+        let state = state.clone();
+        let reconnecting = reconnecting.clone();
+        let on_reconnected = on_reconnected.clone();
+        let connection_banner = connection_banner.clone();
+        let banner_label = banner_label.clone();
+        let banner_start_btn = banner_start_btn.clone();
+        let sidebar = sidebar.clone();
+        Rc::new(move || {
+            if reconnecting.replace(true) { return; }
+            banner_start_btn.set_visible(service::can_start(&state.locked().settings.ollama_endpoint));
+            let state = state.clone();
+            let connection_banner = connection_banner.clone();
+            let banner_label = banner_label.clone();
+            let sidebar = sidebar.clone();
+            let reconnecting = reconnecting.clone();
+            let on_reconnected = on_reconnected.clone();
+            let wake_rx = reconnect_wake_rx.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let mut delay = 1;
+                loop {
+                    let ollama = state.locked().ollama.clone();
+                    match ollama.list_local_models().await {
+                        Ok(models) => {
+                            state.locked().available_models = models.into_iter().map(|m| m.name).collect();
+                            connection_banner.set_reveal_child(false);
+                            sidebar.set_connection_status(true, "Connected", "Reconnected to Ollama");
+                            break;
+                        }
+                        Err(_) => {
+                            sidebar.set_connection_status(false, "Disconnected", "Ollama is unreachable");
+                            banner_label.set_label(&format!("Lost connection to Ollama. Retrying in {}s…", delay));
+                            connection_banner.set_reveal_child(true);
+                            while wake_rx.try_recv().is_ok() {}
+                            futures_util::future::select(
+                                std::boxed::Box::pin(glib::timeout_future_seconds(delay)),
+                                std::boxed::Box::pin(wake_rx.recv()),
+                            ).await;
+                            banner_label.set_label("Reconnecting…");
+                            delay = (delay * 2).min(60);
+                        }
                     }
+                }
+                reconnecting.set(false);
+                let on_reconnected = on_reconnected.borrow().clone();
+                if let Some(f) = on_reconnected {
+                    f();
+                }
+            });
+        })
+    };
 
-                    let blocks = parse_markdown(&msg.content);
-                    for block in blocks {
-                        match block {
-                            MarkdownBlock::Text(text) => {
-                                let label = Label::builder()
-                                    .xalign(0.0)
-                                    .wrap(true)
-                                    .css_classes([if is_user { "user-message" } else { "bot-message" }])
-                                    .build();
-                                label.set_markup(&text);
-                                if is_user {
-                                    label.set_halign(gtk::Align::End);
-                                } else {
-                                    label.set_halign(gtk::Align::Start);
-                                }
-                                msg_container.append(&label);
-                            }
-                            MarkdownBlock::Code(_lang, code) => {
-                                let buffer = gtk::TextBuffer::builder().text(&code).build();
-                                let view = gtk::TextView::builder()
-                                    .buffer(&buffer)
-                                    .editable(false)
-                                    .monospace(true)
-                                    .wrap_mode(gtk::WrapMode::WordChar)
-                                    .bottom_margin(10)
-                                    .top_margin(10)
-                                    .left_margin(10)
-                                    .right_margin(10)
-                                    .css_classes(["code-view"])
-                                    .build();
-                                
-                                let frame = gtk::Frame::builder()
-                                    .child(&view)
-                                    .css_classes(["code-frame"])
-                                    .build();
-                                msg_container.append(&frame);
+    // Periodic connection check feeding the sidebar status indicator
+    let check_connection = {
+        let state = state.clone();
+        let root_stack = root_stack.clone();
+        let sidebar = sidebar.clone();
+        let start_reconnect = start_reconnect.clone();
+        let toasts = toasts.clone();
+        Rc::new(move || {
+            let state = state.clone();
+            let root_stack = root_stack.clone();
+            let sidebar = sidebar.clone();
+            let start_reconnect = start_reconnect.clone();
+            let toasts = toasts.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let (endpoint, headers, tls) = {
+                    let s = state.locked();
+                    (s.settings.ollama_endpoint.clone(), s.settings.ollama_headers.clone(), s.settings.ollama_tls.clone())
+                };
+                match ollama_version(&endpoint, &headers, &tls).await {
+                    Ok((version, latency)) => {
+                        sidebar.set_connection_status(
+                            true,
+                            &format!("Connected · {} ms", latency.as_millis()),
+                            &format!("Ollama {} at {}", version, endpoint),
+                        );
+                        let previous = state.locked().server_version.replace(version.clone());
+                        // Warn once per server, not on every check
+                        if previous.as_ref() != Some(&version) {
+                            let missing = missing_features(&version);
+                            if !missing.is_empty() {
+                                let message = format!("Ollama {} is too old for {}. Update it to use them.", version, missing.join(", "));
+                                tracing::warn!("{}", message);
+                                let toast = toast(&message);
+                                toast.set_timeout(10);
+                                toasts.add_toast(toast);
                             }
                         }
                     }
-                    chat_box.append(&msg_container);
+                    Err(e) => {
+                        sidebar.set_connection_status(false, "Disconnected", &format!("Could not reach {}: {}", endpoint, e));
+                        // The setup page handles the initial connection; only reconnect mid-session
+                        if root_stack.visible_child_name().as_deref() == Some("main") {
+                            start_reconnect();
+                        }
+                    }
                 }
-                scroll_to_bottom();
-            }
-        }
+            });
+        })
     };
+    let check_connection_tick = check_connection.clone();
+    glib::timeout_add_seconds_local(15, move || {
+        check_connection_tick();
+        glib::ControlFlow::Continue
+    });
+
+    // A VPN coming up or another Wi-Fi network can make Ollama reachable or not
+    // before the next check; look again once the changes have settled
+    {
+        let check_connection = check_connection.clone();
+        let pending_check: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+        gtk::gio::NetworkMonitor::default().connect_network_changed(move |_, available| {
+            tracing::debug!("Network changed (available: {})", available);
+            let check_connection = check_connection.clone();
+            let reconnect_wake_tx = reconnect_wake_tx.clone();
+            let pending = pending_check.clone();
+            let source = glib::timeout_add_local_once(NETWORK_SETTLE_DELAY, move || {
+                pending.borrow_mut().take();
+                check_connection();
+                // Retry right away instead of waiting out the reconnect backoff
+                let _ = reconnect_wake_tx.try_send(());
+            });
+            if let Some(previous) = pending_check.replace(Some(source)) {
+                previous.remove();
+            }
+        });
+    }
 
-    render_chat(&state.lock().unwrap().messages);
+    content_area.append(&header);
+
+    // Chat display: a tab per open conversation
+    let chat_tabs = ChatTabs::new(&state, &speaker, &toasts, &commands);
+    content_area.append(&chat_tabs.notebook);
+    let first_tab = state.locked().active_tab;
+    chat_tabs.add(first_tab).render(&[]);
 
     // Input area
     let input_container = Box::builder()
         .orientation(Orientation::Vertical)
         .margin_bottom(30)
-        .margin_start(100)
-        .margin_end(100)
+        .margin_start(CHAT_MIN_MARGIN)
+        .margin_end(CHAT_MIN_MARGIN)
         .build();
 
     let input_box = Box::builder()
@@ -379,1113 +684,1034 @@ fn build_ui(app: &Application) {
         .hexpand(true)
         .css_classes(["chat-input"])
         .build();
-    input_scroll.set_child(Some(&text_view));
-
-    let send_btn = Button::with_label("Send");
-    send_btn.set_valign(gtk::Align::End);
-    send_btn.add_css_class("send-btn");
-
-    input_box.append(&input_scroll);
-    input_box.append(&send_btn);
-    input_container.append(&input_box);
-    content_area.append(&input_container);
-
-    chat_box_container.append(&sidebar);
-    chat_box_container.append(&content_area);
-
-    // --- Settings View ---
-    let settings_view = Box::builder()
-        .orientation(Orientation::Vertical)
-        .build();
-
-    let settings_header = Box::builder()
-        .orientation(Orientation::Horizontal)
-        .margin_start(20)
-        .margin_end(20)
-        .margin_top(20)
-        .build();
-
-    let back_btn = Button::with_label("← Back to Chat");
-    settings_header.append(&back_btn);
-    settings_view.append(&settings_header);
-
-    let settings_content = Box::builder()
-        .orientation(Orientation::Horizontal)
-        .vexpand(true)
-        .build();
-    settings_view.append(&settings_content);
-
-    let settings_stack = Stack::builder()
-        .transition_type(gtk::StackTransitionType::SlideLeftRight)
-        .hexpand(true)
-        .build();
-    
-    let settings_stack_sidebar = StackSidebar::builder()
-        .stack(&settings_stack)
-        .build();
-
-    settings_content.append(&settings_stack_sidebar);
-    settings_content.append(&settings_stack);
+    // The input, or its markdown rendered in place while previewing
+    let markdown_preview = MarkdownPreview::new(&text_view);
+    input_scroll.set_child(Some(&markdown_preview.stack));
 
-    // --- General Settings ---
-    let general_box = Box::builder()
-        .orientation(Orientation::Vertical)
-        .margin_start(20)
-        .margin_end(20)
-        .margin_top(20)
-        .spacing(10)
-        .build();
-    
-    general_box.append(&Label::new(Some("Ollama Endpoint")));
-    let endpoint_entry = Entry::builder()
-        .text(&state.lock().unwrap().settings.ollama_endpoint)
-        .build();
-    general_box.append(&endpoint_entry);
-
-    let save_btn = Button::with_label("Save Settings");
-    let state_save = state.clone();
-    let endpoint_entry_clone = endpoint_entry.clone();
-    save_btn.connect_clicked(move |_| {
-        let endpoint = endpoint_entry_clone.text().to_string();
-        let mut s = state_save.lock().unwrap();
-        s.settings.ollama_endpoint = endpoint.clone();
-        
-        let final_url = normalize_url(&endpoint);
-        if let Ok(url) = url::Url::parse(&final_url) {
-            s.ollama = Ollama::from_url(url);
+    // Drafts: keep unsent input per conversation, written to disk shortly after typing stops
+    let drafts_generation = Rc::new(Cell::new(0u64));
+    let save_drafts = {
+        let state = state.clone();
+        move || {
+            let s = state.locked();
+            s.save_drafts();
         }
-        if let Err(e) = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap()) {
-            eprintln!("Failed to write settings.json: {}", e);
+    };
+    text_view.buffer().connect_changed({
+        let state = state.clone();
+        let drafts_generation = drafts_generation.clone();
+        let save_drafts = save_drafts.clone();
+        move |buffer| {
+            let (start, end) = buffer.bounds();
+            let text = buffer.text(&start, &end, false).to_string();
+            {
+                let mut s = state.locked();
+                let key = s.draft_key();
+                let unchanged = s.drafts.get(&key).map(String::as_str).unwrap_or_default() == text;
+                if unchanged {
+                    return;
+                }
+                if text.trim().is_empty() {
+                    s.drafts.remove(&key);
+                } else {
+                    s.drafts.insert(key, text);
+                }
+            }
+            let generation = drafts_generation.get() + 1;
+            drafts_generation.set(generation);
+            let drafts_generation = drafts_generation.clone();
+            let save_drafts = save_drafts.clone();
+            glib::timeout_add_seconds_local_once(1, move || {
+                if drafts_generation.get() == generation {
+                    save_drafts();
+                }
+            });
         }
     });
-    general_box.append(&save_btn);
-    settings_stack.add_titled(&general_box, Some("general"), "General");
-
-    // --- Agents Settings ---
-    let agents_box = Box::builder()
-        .orientation(Orientation::Vertical)
-        .margin_start(20)
-        .margin_end(20)
-        .margin_top(20)
-        .spacing(10)
-        .build();
-    
-    let agents_list = ListBox::builder().build();
-    let scrolled_agents = ScrolledWindow::builder()
-        .child(&agents_list)
-        .vexpand(true)
-        .build();
-    agents_box.append(&scrolled_agents);
+    let new_chat_draft = state.locked().drafts.get("").cloned().unwrap_or_default();
+    text_view.buffer().set_text(&new_chat_draft);
+
+    // Images, files and pages to send with the next message, shown as chips above the input
+    let attachments: Rc<RefCell<Vec<Attachment>>> = Rc::new(RefCell::new(Vec::new()));
+    // Sends the tabs' and the split pane's messages and streams the replies
+    let chat_sender = ChatSender::new(&state, &commands, &toasts, &speaker, &inspector, &reconnecting, &start_reconnect);
+
+    // Estimated size of the composed message, against the model's context window
+    let token_counter = TokenCounter::new(&state, &text_view, &attachments);
+    // An image for a model that can't see it: offer a vision model for this chat
+    let vision_offer = VisionOffer::new(&state, &toasts);
+    let attachment_bar = AttachmentBar::new(&attachments, &token_counter, vision_offer, &text_view);
+    attachment_bar.intercept_pastes(&toasts);
+
+    // Adds a tab with an empty chat for agent `agent_idx` and brings it on screen
+    let new_tab: Rc<dyn Fn(usize)> = {
+        let state = state.clone();
+        let chat_tabs = chat_tabs.clone();
+        Rc::new(move |agent_idx| {
+            let chat = OpenChat::new(agent_idx);
+            let tab_id = chat.tab_id;
+            state.locked().chats.push(chat);
+            let view = chat_tabs.add(tab_id);
+            chat_tabs.select(tab_id);
+            view.render(&[]);
+        })
+    };
 
-    let refresh_agents_list_func = {
+    // Opens a conversation (or a new chat for `None`) and restores its draft.
+    // One open in another tab or the split pane is shown there, and one
+    // replacing a tab that is still generating gets a new tab.
+    // While set, picking an agent doesn't start a new chat
+    let restoring_agent = Rc::new(Cell::new(false));
+    let switch_conversation: Rc<dyn Fn(Option<String>, Vec<StoredMessage>)> = {
         let state = state.clone();
-        let agents_list = agents_list.clone();
-        let agent_names_list = agent_names_list.clone();
-        
-        Rc::new(move || {
-            while let Some(child) = agents_list.first_child() {
-                agents_list.remove(&child);
-            }
-            refresh_agent_dropdown_func(state.clone(), agent_names_list.clone());
-            let (agents, available_models) = {
-                let s = state.lock().expect("Failed to lock state for agents list refresh");
-                (s.settings.agents.clone(), s.available_models.clone())
+        let chat_tabs = chat_tabs.clone();
+        let text_view = text_view.clone();
+        let agent_dropdown = agent_dropdown.clone();
+        let restoring_agent = restoring_agent.clone();
+        let new_tab = new_tab.clone();
+        let notes_panel = notes_panel.clone();
+        let chat_sender = chat_sender.clone();
+        let toasts = toasts.clone();
+        Rc::new(move |history_id, messages| {
+            let (open_tab, busy_agent) = {
+                let s = state.locked();
+                let open_tab = history_id.as_ref()
+                    .and_then(|id| s.chats.iter().find(|c| c.history_id.as_ref() == Some(id)))
+                    .map(|c| c.tab_id);
+                (open_tab, s.chat().task.is_some().then_some(s.chat().agent_idx))
             };
-            for (idx, agent) in agents.into_iter().enumerate() {
-                let row = Box::builder()
-                    .orientation(Orientation::Vertical)
-                    .spacing(5)
-                    .margin_top(10)
-                    .margin_bottom(10)
-                    .build();
-
-                row.append(&Label::builder().label("Agent Name").xalign(0.0).css_classes(["settings-label"]).build());
-                let name_entry = Entry::builder().text(&agent.name).placeholder_text("Name").build();
-                row.append(&name_entry);
-
-                row.append(&Label::builder().label("Description").xalign(0.0).css_classes(["settings-label"]).build());
-                let desc_entry = Entry::builder().text(&agent.description).placeholder_text("Description").build();
-                row.append(&desc_entry);
-
-                row.append(&Label::builder().label("Model").xalign(0.0).css_classes(["settings-label"]).build());
-                
-                let model_list = StringList::new(&[]);
-                let model_refs: Vec<&str> = available_models.iter().map(|s| s.as_str()).collect();
-                model_list.splice(0, 0, &model_refs);
-                
-                // If current model is not in list (or list empty), add it so user can see/save it
-                let mut selected_idx = 0;
-                let mut found = false;
-                for (i, m) in available_models.iter().enumerate() {
-                    if m == &agent.model {
-                        selected_idx = i;
-                        found = true;
-                        break;
-                    }
-                }
-                if !found && !agent.model.is_empty() {
-                    model_list.append(&agent.model);
-                    selected_idx = available_models.len();
+            if let Some(tab_id) = open_tab {
+                chat_tabs.select(tab_id);
+                return;
+            }
+            if let Some(agent_idx) = busy_agent {
+                new_tab(agent_idx);
+            }
+            // Queued messages were written for the conversation leaving the tab
+            let discarded = chat_sender.discard_queued(state.locked().active_tab);
+            if discarded > 0 {
+                toasts.add_toast(toast(&format!("Discarded {} queued message{}", discarded, if discarded == 1 { "" } else { "s" })));
+            }
+            chat_tabs.current().render(&messages);
+            let (draft, agent_idx) = {
+                let mut s = state.locked();
+                // Saved conversations continue with the agent they were held with
+                let agent_idx = history_id.as_ref()
+                    .and_then(|id| s.history.iter().find(|h| &h.id == id))
+                    .and_then(|h| h.agent_index(&s.settings.agents));
+                let chat = s.chat_mut();
+                if let Some(idx) = agent_idx {
+                    chat.agent_idx = idx;
                 }
-
-                let model_dropdown = DropDown::builder()
-                    .model(&model_list)
-                    .selected(selected_idx as u32)
-                    .build();
-                row.append(&model_dropdown);
-
-                row.append(&Label::builder().label("System Prompt").xalign(0.0).css_classes(["settings-label"]).build());
-                let prompt_entry = Entry::builder().text(&agent.system_prompt).placeholder_text("System Prompt").build();
-                row.append(&prompt_entry);
-
-                let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
-                let save_btn = Button::with_label("Save");
-                let delete_btn = Button::with_label("Delete");
-                actions_box.append(&save_btn);
-                actions_box.append(&delete_btn);
-                row.append(&actions_box);
-                row.append(&gtk::Separator::new(Orientation::Horizontal));
-
-                let state_c = state.clone();
-                let name_c = name_entry.clone();
-                let desc_c = desc_entry.clone();
-                let model_c = model_dropdown.clone();
-                let prompt_c = prompt_entry.clone();
-                let agent_names_list_c = agent_names_list.clone();
-                save_btn.connect_clicked(move |_| {
-                    let name = name_c.text().to_string();
-                    let desc = desc_c.text().to_string();
-                    let model = if let Some(item) = model_c.selected_item() {
-                        item.downcast::<gtk::StringObject>().unwrap().string().to_string()
-                    } else {
-                        "".to_string()
-                    };
-                    let prompt = prompt_c.text().to_string();
-                    
-                    {
-                        let mut s = state_c.lock().expect("Failed to lock state for saving agent");
-                        if let Some(a) = s.settings.agents.get_mut(idx) {
-                            a.name = name;
-                            a.description = desc;
-                            a.model = model;
-                            a.system_prompt = prompt;
-                            if let Err(e) = fs::write(&s.config_path, serde_json::to_string(&s.settings).expect("Failed to serialize settings")) {
-                                eprintln!("Failed to write settings.json: {}", e);
-                            }
-                        }
-                    }
-                    refresh_agent_dropdown_func(state_c.clone(), agent_names_list_c.clone());
-                });
-
-                let state_d = state.clone();
-                let agent_name_clone = agent.name.clone();
-                let agents_list_clone = agents_list.clone();
-                let row_clone = row.clone();
-                let agent_names_list_d = agent_names_list.clone();
-                delete_btn.connect_clicked(move |_| {
-                    let mut s = state_d.lock().expect("Failed to lock state for deleting agent");
-                    s.settings.agents.retain(|a| a.name != agent_name_clone);
-                    if let Err(e) = fs::write(&s.config_path, serde_json::to_string(&s.settings).expect("Failed to serialize settings")) {
-                        eprintln!("Failed to write settings.json: {}", e);
-                    }
-                    drop(s);
-                    agents_list_clone.remove(&row_clone);
-                    refresh_agent_dropdown_func(state_d.clone(), agent_names_list_d.clone());
-                });
-                agents_list.append(&row);
+                chat.history_id = history_id;
+                chat.messages = messages;
+                chat.model_override = None;
+                (s.drafts.get(&s.draft_key()).cloned().unwrap_or_default(), agent_idx)
+            };
+            if let Some(idx) = agent_idx {
+                restoring_agent.set(true);
+                agent_dropdown.set_selected(idx as u32);
+                restoring_agent.set(false);
             }
+            text_view.buffer().set_text(&draft);
+            chat_tabs.refresh_titles();
+            notes_panel.sync();
         })
     };
 
-    refresh_agents_list_func();
+    let send_btn = Button::with_label("Send");
+    send_btn.set_valign(gtk::Align::End);
+    send_btn.add_css_class("send-btn");
+    // Send turns into Stop while the tab on screen is generating
+    let update_send_btn: Rc<dyn Fn()> = {
+        let state = state.clone();
+        let send_btn = send_btn.clone();
+        Rc::new(move || {
+            if state.locked().chat().task.is_some() {
+                send_btn.set_label("Stop");
+                send_btn.remove_css_class("send-btn");
+                send_btn.add_css_class("stop-btn");
+            } else {
+                send_btn.set_label("Send");
+                send_btn.remove_css_class("stop-btn");
+                send_btn.add_css_class("send-btn");
+            }
+        })
+    };
 
-    let settings_stack_c = settings_stack.clone();
-    let refresh_agents = refresh_agents_list_func.clone();
-    settings_stack_c.connect_visible_child_name_notify(move |stack| {
-        if stack.visible_child_name().as_deref() == Some("agents") {
-            (refresh_agents)();
+    chat_sender.connect_busy({
+        let chat_tabs = chat_tabs.clone();
+        let update_send_btn = update_send_btn.clone();
+        move |tab_id, busy| {
+            chat_tabs.set_busy(tab_id, busy);
+            update_send_btn();
         }
     });
-
-    let add_agent_btn = Button::with_label("Add Agent");
-    let state_add = state.clone();
-    let refresh_agents_add = refresh_agents_list_func.clone();
-    add_agent_btn.connect_clicked(move |_| {
-        let mut s = state_add.lock().expect("Failed to lock state for adding agent");
-        s.settings.agents.push(Agent {
-            name: "New Agent".to_string(),
-            model: "llama3".to_string(),
-            system_prompt: "You are a helpful assistant.".to_string(),
-            description: "Personal Assistant".to_string(),
-        });
-        if let Err(e) = fs::write(&s.config_path, serde_json::to_string(&s.settings).expect("Failed to serialize settings")) {
-            eprintln!("Failed to write settings.json: {}", e);
-        }
-        drop(s);
-        refresh_agents_add();
+    *on_reconnected.borrow_mut() = Some({
+        let chat_sender = chat_sender.clone();
+        Rc::new(move || chat_sender.send_all_queued())
     });
 
-    let delete_chat_history_btn = Button::with_label("Delete Chat History");
-    let state_delete_history = state.clone();
-    delete_chat_history_btn.connect_clicked(move |_| {
-        let mut s = state_delete_history.lock().unwrap();
-        s.history.clear();
-        if let Err(e) = fs::remove_file(&s.history_path) {
-            eprintln!("Failed to remove history.json: {}", e);
+    let send_template = {
+        let text_view = text_view.clone();
+        let send_btn = send_btn.clone();
+        move |text: String| {
+            text_view.buffer().set_text(&text);
+            // While a response is streaming the button means Stop; leave the prompt in the input
+            if send_btn.label().map(|l| l.as_str() != "Stop").unwrap_or(true) {
+                send_btn.emit_clicked();
+            }
         }
+    };
+
+    chat_tabs.connect_suggestion(send_template.clone());
+
+    // Prompt templates: pick one, fill in its {{variables}}, then send
+    let templates_btn = templates::picker(&state, send_template.clone());
+
+    // Diagnose: pull recent journal entries, let the user review them, then ask the agent
+    let diagnose_btn = Button::builder()
+        .icon_name("dialog-warning-symbolic")
+        .tooltip_text("Diagnose system logs")
+        .valign(gtk::Align::End)
+        .build();
+    diagnose_btn.connect_clicked({
+        let send_template = send_template.clone();
+        let toasts = toasts.clone();
+        move |btn| diagnose::open(btn, &toasts, send_template.clone())
     });
-    general_box.append(&delete_chat_history_btn);
-    agents_box.append(&add_agent_btn);
-    settings_stack.add_titled(&agents_box, Some("agents"), "Agents");
 
-    // --- Models Settings ---
-    let models_box = Box::builder()
+    // Files and web pages to send along, next to the screenshot button
+    let attach_popover = gtk::Popover::new();
+    let attach_btn = gtk::MenuButton::builder()
+        .icon_name("mail-attachment-symbolic")
+        .tooltip_text("Attach file or web page")
+        .valign(gtk::Align::End)
+        .popover(&attach_popover)
+        .build();
+    let attach_menu = Box::builder()
         .orientation(Orientation::Vertical)
-        .margin_start(20)
-        .margin_end(20)
-        .margin_top(20)
-        .spacing(10)
+        .spacing(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
         .build();
-
-    models_box.append(&Label::builder().label("Pull Model").xalign(0.0).css_classes(["settings-title"]).build());
-    
-    let pull_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
-    let pull_entry = Entry::builder().placeholder_text("Model name (e.g. llama3)").hexpand(true).build();
-    let pull_btn = Button::with_label("Pull");
-    pull_box.append(&pull_entry);
-    pull_box.append(&pull_btn);
-    models_box.append(&pull_box);
-
-    let progress_label = Label::new(None);
-    progress_label.set_visible(false);
-    models_box.append(&progress_label);
-
-    models_box.append(&gtk::Separator::new(Orientation::Horizontal));
-    models_box.append(&Label::builder().label("Installed Models").xalign(0.0).css_classes(["settings-title"]).build());
-
-    let models_list = ListBox::builder().build();
-    let models_scrolled = ScrolledWindow::builder().child(&models_list).vexpand(true).build();
-    models_box.append(&models_scrolled);
-
-    let refresh_models_list = {
-        let models_list = models_list.clone();
-        let state = state.clone();
-        Rc::new(move || {
-            let models_list = models_list.clone();
-            let state = state.clone();
-            glib::MainContext::default().spawn_local(async move {
-                let ollama = state.lock().unwrap().ollama.clone();
-                if let Ok(models) = ollama.list_local_models().await {
-                    {
-                        let mut s = state.lock().unwrap();
-                        s.available_models = models.iter().map(|m| m.name.clone()).collect();
+    let attach_file_btn = Button::builder().label("File…").css_classes(["flat"]).build();
+    let url_row = Box::builder().orientation(Orientation::Horizontal).spacing(6).build();
+    let url_entry = Entry::builder().placeholder_text("https://…").width_chars(28).build();
+    let fetch_btn = Button::builder().label("Fetch").build();
+    url_row.append(&url_entry);
+    url_row.append(&fetch_btn);
+    attach_menu.append(&attach_file_btn);
+    attach_menu.append(&url_row);
+    attach_popover.set_child(Some(&attach_menu));
+    attach_file_btn.connect_clicked({
+        let attach_popover = attach_popover.clone();
+        let attachment_bar = attachment_bar.clone();
+        let toasts = toasts.clone();
+        move |btn| {
+            attach_popover.popdown();
+            let dialog = gtk::FileDialog::builder().title("Attach Files").build();
+            let parent = btn.root().and_downcast::<gtk::Window>();
+            let attachment_bar = attachment_bar.clone();
+            let toasts = toasts.clone();
+            dialog.open_multiple(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                let Ok(files) = res else { return };
+                for i in 0..files.n_items() {
+                    let Some(file) = files.item(i).and_downcast::<gtk::gio::File>() else { continue };
+                    match attachments::read_file(&file) {
+                        Ok(attachment) => attachment_bar.add(attachment),
+                        Err(e) => toasts.report(&e),
                     }
-                    while let Some(child) = models_list.first_child() {
-                        models_list.remove(&child);
-                    }
-                    for model in models {
-                        let row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
-                        let label = Label::builder().label(&model.name).xalign(0.0).hexpand(true).margin_start(10).margin_top(5).margin_bottom(5).build();
-                        row.append(&label);
-                        
-                        let size_gb = model.size as f64 / 1024.0 / 1024.0 / 1024.0;
-                        let size_label = Label::new(Some(&format!("{:.1} GB", size_gb)));
-                        row.append(&size_label);
-                        
-                        models_list.append(&row);
+                }
+            });
+        }
+    });
+    let fetch_url = {
+        let url_entry = url_entry.clone();
+        let fetch_btn = fetch_btn.clone();
+        let attach_popover = attach_popover.clone();
+        let attachment_bar = attachment_bar.clone();
+        let toasts = toasts.clone();
+        move || {
+            let url = url_entry.text().trim().to_string();
+            if url.is_empty() {
+                return;
+            }
+            fetch_btn.set_sensitive(false);
+            let url_entry = url_entry.clone();
+            let fetch_btn = fetch_btn.clone();
+            let attach_popover = attach_popover.clone();
+            let attachment_bar = attachment_bar.clone();
+            let toasts = toasts.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let result = attachments::fetch_page(&url).await;
+                fetch_btn.set_sensitive(true);
+                match result {
+                    Ok(attachment) => {
+                        url_entry.set_text("");
+                        attach_popover.popdown();
+                        attachment_bar.add(attachment);
                     }
+                    Err(e) => toasts.report(&e),
                 }
             });
-        })
+        }
     };
-    refresh_models_list();
-
-    let state_pull = state.clone();
-    let pull_entry_c = pull_entry.clone();
-    let progress_label_c = progress_label.clone();
-    let refresh_models_c = refresh_models_list.clone();
-    pull_btn.connect_clicked(move |btn| {
-        let model_name = pull_entry_c.text().to_string();
-        if model_name.is_empty() { return; }
-        
-        btn.set_sensitive(false);
-        progress_label_c.set_visible(true);
-        progress_label_c.set_label(&format!("Pulling {}... this may take a while.", model_name));
-        
-        let state = state_pull.clone();
-        let btn = btn.clone();
-        let progress_label = progress_label_c.clone();
-        let refresh = refresh_models_c.clone();
-        
-        glib::MainContext::default().spawn_local(async move {
-            let ollama = state.lock().unwrap().ollama.clone();
-            // Use simple pull for now
-            let res = ollama.pull_model(model_name.clone(), false).await;
-            
-            btn.set_sensitive(true);
-            match res {
-                Ok(_) => {
-                    progress_label.set_label(&format!("Successfully pulled {}", model_name));
-                    refresh();
-                }
-                Err(e) => {
-                    progress_label.set_label(&format!("Error: {}", e));
-                }
-            }
-        });
+    fetch_btn.connect_clicked({
+        let fetch_url = fetch_url.clone();
+        move |_| fetch_url()
     });
+    url_entry.connect_activate(move |_| fetch_url());
 
-    settings_stack.add_titled(&models_box, Some("models"), "Models");
-
-    // --- Personalization Settings ---
-    let personalization_box = Box::builder()
-        .orientation(Orientation::Vertical)
-        .margin_start(20)
-        .margin_end(20)
-        .margin_top(20)
-        .spacing(10)
-        .build();
-    
-    personalization_box.append(&Label::builder()
-        .label("Personalization")
-        .xalign(0.0)
-        .css_classes(["settings-title"])
-        .build());
-
-    personalization_box.append(&Label::builder()
-        .label("Profiles")
-        .xalign(0.0)
-        .css_classes(["settings-title"])
-        .build());
-
-    let profiles_scrolled_content = Box::builder()
-        .orientation(Orientation::Horizontal)
-        .spacing(15)
-        .build();
-    let scrolled_profiles = ScrolledWindow::builder()
-        .child(&profiles_scrolled_content)
-        .vexpand(false)
-        .min_content_height(120)
+    let screenshot_btn = Button::builder()
+        .icon_name("camera-photo-symbolic")
+        .tooltip_text("Capture screenshot")
+        .valign(gtk::Align::End)
         .build();
-    scrolled_profiles.add_css_class("profile-scrolled-window");
-    personalization_box.append(&scrolled_profiles);
-
-    personalization_box.append(&gtk::Separator::new(Orientation::Horizontal));
+    screenshot_btn.connect_clicked({
+        let app = app.clone();
+        let attachment_bar = attachment_bar.clone();
+        let toasts = toasts.clone();
+        let text_view = text_view.clone();
+        move |btn| {
+            let Some(bus) = app.dbus_connection() else {
+                toasts.add_toast(toast("Screenshots need a session bus connection"));
+                return;
+            };
+            btn.set_sensitive(false);
+            let btn = btn.clone();
+            let attachment_bar = attachment_bar.clone();
+            let toasts = toasts.clone();
+            let text_view = text_view.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let result = match portal::screenshot(&bus).await {
+                    Ok(Some(path)) => fs::read(&path).map(Some).map_err(|e| e.to_string()),
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(e),
+                };
+                btn.set_sensitive(true);
+                match result {
+                    Ok(Some(bytes)) => {
+                        attachment_bar.add(Attachment::Image(glib::base64_encode(&bytes).to_string()));
+                        text_view.grab_focus();
+                    }
+                    Ok(None) => {}
+                    Err(e) => toasts.report(&AppError::new("Failed to capture screenshot", e)),
+                }
+            });
+        }
+    });
 
-    // Editor Stack
-    let editor_stack = Stack::builder()
-        .transition_type(gtk::StackTransitionType::Crossfade)
-        .vexpand(true)
-        .build();
-    
-    let empty_page = Box::builder()
-        .orientation(Orientation::Vertical)
-        .valign(gtk::Align::Center)
+    input_box.append(&templates_btn);
+    input_box.append(&diagnose_btn);
+    input_box.append(&attach_btn);
+    input_box.append(&screenshot_btn);
+    input_box.append(&markdown_preview.button);
+    input_box.append(&input_scroll);
+    input_box.append(&send_btn);
+    // Shown while a reply is being read aloud
+    let speech_bar = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
         .halign(gtk::Align::Center)
-        .spacing(10)
+        .margin_bottom(8)
+        .visible(false)
+        .css_classes(["speech-bar"])
         .build();
-    empty_page.append(&Label::new(Some("Select a profile above to edit or activate.")));
-    editor_stack.add_named(&empty_page, Some("empty"));
-
-    let editor_page = Box::builder()
-        .orientation(Orientation::Vertical)
-        .spacing(10)
-        .margin_top(10)
+    let speech_label = Label::builder().label("Reading aloud…").build();
+    let speech_pause_btn = Button::builder()
+        .icon_name("media-playback-pause-symbolic")
+        .css_classes(["flat", "circular"])
+        .tooltip_text("Pause")
         .build();
-    
-    let edit_name = Entry::builder().placeholder_text("Profile Name").build();
-    editor_page.append(&Label::builder().label("Profile Name").xalign(0.0).css_classes(["settings-label"]).build());
-    editor_page.append(&edit_name);
-
-    let edit_grid = gtk::Grid::builder().column_spacing(10).row_spacing(5).build();
-    let edit_fname = Entry::builder().placeholder_text("First Name").hexpand(true).build();
-    let edit_lname = Entry::builder().placeholder_text("Last Name").hexpand(true).build();
-    let edit_email = Entry::builder().placeholder_text("Email").hexpand(true).build();
-    let edit_phone = Entry::builder().placeholder_text("Phone").hexpand(true).build();
-    
-    edit_grid.attach(&edit_fname, 0, 0, 1, 1);
-    edit_grid.attach(&edit_lname, 1, 0, 1, 1);
-    edit_grid.attach(&edit_email, 0, 1, 1, 1);
-    edit_grid.attach(&edit_phone, 1, 1, 1, 1);
-    editor_page.append(&edit_grid);
-
-    editor_page.append(&Label::builder().label("Location").xalign(0.0).css_classes(["settings-label"]).build());
-    let edit_location = Entry::builder().placeholder_text("City, Country").build();
-    editor_page.append(&edit_location);
-
-    editor_page.append(&Label::builder().label("Bio / Context").xalign(0.0).css_classes(["settings-label"]).build());
-    let edit_bio = Entry::builder().placeholder_text("Short bio").build();
-    editor_page.append(&edit_bio);
-
-    let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(10).build();
-    let activate_btn = Button::with_label("Use This Profile");
-    let save_btn = Button::with_label("Save Changes");
-    let delete_btn = Button::with_label("Delete Profile");
-    let clear_mem_btn = Button::with_label("Clear Memory");
-    
-    delete_btn.add_css_class("destructive-action");
-    clear_mem_btn.add_css_class("destructive-action");
-    
-    actions_box.append(&activate_btn);
-    actions_box.append(&save_btn);
-    actions_box.append(&delete_btn);
-    actions_box.append(&clear_mem_btn);
-    editor_page.append(&actions_box);
-
-    editor_page.append(&Label::builder().label("Long-term Memory").xalign(0.0).css_classes(["settings-label"]).build());
-    let memory_view = TextView::builder()
-        .editable(false)
-        .wrap_mode(gtk::WrapMode::WordChar)
-        .height_request(150)
+    let speech_stop_btn = Button::builder()
+        .icon_name("media-playback-stop-symbolic")
+        .css_classes(["flat", "circular"])
+        .tooltip_text("Stop Reading")
         .build();
-    let memory_scroll = ScrolledWindow::builder().child(&memory_view).vexpand(true).build();
-    editor_page.append(&memory_scroll);
-
-    editor_stack.add_named(&editor_page, Some("editor"));
-    personalization_box.append(&editor_stack);
+    speech_bar.append(&speech_label);
+    speech_bar.append(&speech_pause_btn);
+    speech_bar.append(&speech_stop_btn);
+    speech_pause_btn.connect_clicked({
+        let speaker = speaker.clone();
+        move |_| speaker.toggle_pause()
+    });
+    speech_stop_btn.connect_clicked({
+        let speaker = speaker.clone();
+        move |_| speaker.stop()
+    });
+    speaker.connect_state_changed({
+        let speech_bar = speech_bar.clone();
+        move |speech_state| {
+            speech_bar.set_visible(speech_state != SpeechState::Idle);
+            let paused = speech_state == SpeechState::Paused;
+            speech_label.set_label(if paused { "Paused" } else { "Reading aloud…" });
+            speech_pause_btn.set_icon_name(if paused { "media-playback-start-symbolic" } else { "media-playback-pause-symbolic" });
+            speech_pause_btn.set_tooltip_text(Some(if paused { "Resume" } else { "Pause" }));
+        }
+    });
 
-    let selected_profile_idx: Rc<RefCell<Option<usize>>> = Rc::new(RefCell::new(None));
+    input_container.append(&speech_bar);
+    input_container.append(&attachment_bar.widget);
+    input_container.append(&input_box);
+    input_container.append(&token_counter.label);
+    content_area.append(&input_container);
 
-    let refresh_profiles_ui = {
-        let state = state.clone();
-        let profiles_list = profiles_scrolled_content.clone();
-        let selected_idx = selected_profile_idx.clone();
-        let editor_stack = editor_stack.clone();
-        
-        let edit_name = edit_name.clone();
-        let edit_fname = edit_fname.clone();
-        let edit_lname = edit_lname.clone();
-        let edit_email = edit_email.clone();
-        let edit_phone = edit_phone.clone();
-        let edit_location = edit_location.clone();
-        let edit_bio = edit_bio.clone();
-        let activate_btn = activate_btn.clone();
-        let memory_view = memory_view.clone();
-
-        let refresh_ref: Rc<RefCell<Option<std::boxed::Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
-        let refresh_ref_weak = refresh_ref.clone();
-
-        let logic = move || {
-            while let Some(child) = profiles_list.first_child() {
-                profiles_list.remove(&child);
-            }
-            
-            let (profiles, active_profile, memory_path) = {
-                let s = state.lock().unwrap();
-                (s.settings.profiles.clone(), s.settings.active_profile.clone(), s.memory_path.clone())
-            };
+    // A second conversation beside the tabs, when split view is on
+    let split_chat = SplitChat::new(&state, &speaker, &toasts, &commands, &chat_sender);
+    let chat_paned = gtk::Paned::builder()
+        .orientation(Orientation::Horizontal)
+        .start_child(&content_area)
+        .end_child(&split_chat.widget)
+        .shrink_start_child(false)
+        .shrink_end_child(false)
+        .hexpand(true)
+        .build();
 
-            let current_sel = *selected_idx.borrow();
-
-            for (idx, profile) in profiles.iter().enumerate() {
-                let circle_btn = Button::builder()
-                    .css_classes(["profile-circle"])
-                    .width_request(80)
-                    .height_request(80)
-                    .build();
-                
-                if let Some(active) = &active_profile {
-                    if active == &profile.name {
-                        circle_btn.add_css_class("active-profile");
-                    }
-                }
-                
-                if Some(idx) == current_sel {
-                     circle_btn.add_css_class("selected-editing");
-                }
+    chat_box_container.append(&sidebar.widget);
+    chat_box_container.append(&chat_paned);
+    chat_box_container.append(&notes_panel.widget);
+    chat_box_container.append(&inspector.widget);
+    chat_box_container.append(&server_log.widget);
+
+    // --- Settings Window ---
+    let settings_window = SettingsWindow::new(&state, &theme, &toasts, &commands, SettingsCallbacks {
+        agents_changed: {
+            let refresh_agent_names = refresh_agent_names.clone();
+            let split_chat = split_chat.clone();
+            Rc::new(move || {
+                refresh_agent_names();
+                split_chat.refresh_agents();
+            })
+        },
+        start_reconnect: start_reconnect.clone(),
+        restored: {
+            let sidebar = sidebar.clone();
+            Rc::new(move || sidebar.refresh_history())
+        },
+    });
 
-                let icon_label = Label::new(Some(&profile.name.chars().next().unwrap_or('?').to_string().to_uppercase()));
-                circle_btn.set_child(Some(&icon_label));
-                
-                let container = Box::builder().orientation(Orientation::Vertical).spacing(5).build();
-                container.append(&circle_btn);
-                container.append(&Label::builder().label(&profile.name).css_classes(["profile-mini-name"]).build());
-                profiles_list.append(&container);
-
-                let sel_idx = selected_idx.clone();
-                let refresh = refresh_ref_weak.clone();
-                circle_btn.connect_clicked(move |_| {
-                    *sel_idx.borrow_mut() = Some(idx);
-                    if let Some(f) = &*refresh.borrow() { f(); }
-                });
-            }
+    main_stack.add_titled(&chat_box_container, Some("chat"), "Chat");
 
-            // Add Profile Button
-            let add_btn = Button::builder().label("+").css_classes(["profile-circle"]).width_request(80).height_request(80).build();
-            let state_add = state.clone();
-            let refresh_add = refresh_ref_weak.clone();
-            let sel_add = selected_idx.clone();
-            add_btn.connect_clicked(move |_| {
-                {
-                    let mut s = state_add.lock().unwrap();
-                    s.settings.profiles.push(Profile {
-                        id: glib::uuid_string_random().to_string(),
-                        name: "New Profile".to_string(),
-                        first_name: "".to_string(),
-                        last_name: "".to_string(),
-                        email: "".to_string(),
-                        phone: "".to_string(),
-                        location: "".to_string(),
-                        bio: "".to_string(),
-                        image_path: None,
-                    });
-                    let _ = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap());
-                    *sel_add.borrow_mut() = Some(s.settings.profiles.len() - 1);
-                }
-                if let Some(f) = &*refresh_add.borrow() { f(); }
-            });
-            let container = Box::builder().orientation(Orientation::Vertical).spacing(5).build();
-            container.append(&add_btn);
-            container.append(&Label::new(Some("Add")));
-            profiles_list.append(&container);
-
-            if let Some(idx) = current_sel {
-                if let Some(profile) = profiles.get(idx) {
-                    editor_stack.set_visible_child_name("editor");
-                    edit_name.set_text(&profile.name);
-                    edit_fname.set_text(&profile.first_name);
-                    edit_lname.set_text(&profile.last_name);
-                    edit_email.set_text(&profile.email);
-                    edit_phone.set_text(&profile.phone);
-                    edit_location.set_text(&profile.location);
-                    edit_bio.set_text(&profile.bio);
-
-                    // Load Memory
-                    let mem_file = memory_path.join(format!("{}.txt", profile.id));
-                    let memory = fs::read_to_string(mem_file).unwrap_or_default();
-                    memory_view.buffer().set_text(&memory);
-                    
-                    if let Some(active) = &active_profile {
-                        if active == &profile.name {
-                            activate_btn.set_label("Current Profile");
-                            activate_btn.set_sensitive(false);
-                        } else {
-                            activate_btn.set_label("Use This Profile");
-                            activate_btn.set_sensitive(true);
-                        }
-                    } else {
-                        activate_btn.set_label("Use This Profile");
-                        activate_btn.set_sensitive(true);
-                    }
-                } else {
-                    *selected_idx.borrow_mut() = None;
-                    editor_stack.set_visible_child_name("empty");
+    let saved_view = SavedView::new(&state, &toasts, &commands);
+    main_stack.add_titled(&saved_view.widget, Some("saved"), "Saved");
+    let arena_view = ArenaView::new(&state, &toasts);
+    main_stack.add_titled(&arena_view.widget, Some("arena"), "Arena");
+    controller.connect_event({
+        let saved_view = saved_view.clone();
+        let chat_tabs = chat_tabs.clone();
+        let split_chat = split_chat.clone();
+        let notes_panel = notes_panel.clone();
+        move |event| match event {
+            AppEvent::BookmarksChanged => {
+                saved_view.refresh();
+                for view in chat_tabs.views() {
+                    view.refresh_bookmarks();
                 }
-            } else {
-                editor_stack.set_visible_child_name("empty");
+                split_chat.refresh_bookmarks();
             }
-        };
-        
-        *refresh_ref.borrow_mut() = Some(std::boxed::Box::new(logic));
-        refresh_ref
-    };
+            AppEvent::HistoryChanged => {
+                chat_tabs.refresh_titles();
+                // A new chat's first reply gives it a history entry to hold notes
+                notes_panel.sync();
+            }
+        }
+    });
 
-    let call_refresh = {
-        let refresh = refresh_profiles_ui.clone();
-        move || { if let Some(f) = &*refresh.borrow() { f(); } }
-    };
-    call_refresh();
-
-    let state_save = state.clone();
-    let sel_save = selected_profile_idx.clone();
-    let refresh_save = call_refresh.clone();
-    let name_s = edit_name.clone();
-    let fname_s = edit_fname.clone();
-    let lname_s = edit_lname.clone();
-    let email_s = edit_email.clone();
-    let phone_s = edit_phone.clone();
-    let loc_s = edit_location.clone();
-    let bio_s = edit_bio.clone();
-
-    save_btn.connect_clicked(move |_| {
-        if let Some(idx) = *sel_save.borrow() {
-            let mut s = state_save.lock().unwrap();
-            if let Some(p) = s.settings.profiles.get_mut(idx) {
-                p.name = name_s.text().to_string();
-                p.first_name = fname_s.text().to_string();
-                p.last_name = lname_s.text().to_string();
-                p.email = email_s.text().to_string();
-                p.phone = phone_s.text().to_string();
-                p.location = loc_s.text().to_string();
-                p.bio = bio_s.text().to_string();
-                let _ = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap());
+    let header_bar = adw::HeaderBar::new();
+    let sidebar_visible = state.locked().settings.sidebar_visible;
+    let sidebar_toggle = gtk::ToggleButton::builder()
+        .icon_name("sidebar-show-symbolic")
+        .tooltip_text("Toggle sidebar (Ctrl+B)")
+        .active(sidebar_visible)
+        .build();
+    header_bar.pack_start(&sidebar_toggle);
+    let new_tab_btn = Button::builder()
+        .icon_name("tab-new-symbolic")
+        .tooltip_text("New tab (Ctrl+T)")
+        .build();
+    header_bar.pack_start(&new_tab_btn);
+    let split_view = state.locked().settings.split_view;
+    let split_toggle = gtk::ToggleButton::builder()
+        .icon_name("view-dual-symbolic")
+        .tooltip_text("Split view: a second chat side by side")
+        .active(split_view)
+        .build();
+    header_bar.pack_start(&split_toggle);
+    let inspector_toggle = gtk::ToggleButton::builder()
+        .icon_name("system-search-symbolic")
+        .tooltip_text("Show the last request sent to the model")
+        .build();
+    inspector_toggle.bind_property("active", &inspector.widget, "reveal-child").build();
+    header_bar.pack_end(&inspector_toggle);
+    let notes_toggle = gtk::ToggleButton::builder()
+        .icon_name("accessories-text-editor-symbolic")
+        .tooltip_text("Your notes on this chat")
+        .build();
+    notes_toggle.bind_property("active", &notes_panel.widget, "reveal-child").build();
+    header_bar.pack_end(&notes_toggle);
+    let server_log_toggle = gtk::ToggleButton::builder()
+        .icon_name("utilities-terminal-symbolic")
+        .tooltip_text("Follow the Ollama server's log")
+        .build();
+    server_log_toggle.bind_property("active", &server_log.widget, "reveal-child").build();
+    header_bar.pack_end(&server_log_toggle);
+    let monitor = ResourceMonitor::new(&state, &toasts);
+    header_bar.pack_end(&monitor.button);
+    sidebar.widget.set_visible(sidebar_visible);
+    split_chat.set_open(split_view);
+    split_toggle.connect_toggled({
+        let state = state.clone();
+        let split_chat = split_chat.clone();
+        let chat_paned = chat_paned.clone();
+        move |btn| {
+            let open = btn.is_active();
+            split_chat.set_open(open);
+            // Start with the two chats equally wide
+            if open && chat_paned.width() > 0 {
+                chat_paned.set_position(chat_paned.width() / 2);
             }
+            let mut s = state.locked();
+            s.settings.split_view = open;
+            s.save_settings();
         }
-        refresh_save();
     });
 
-    let state_act = state.clone();
-    let sel_act = selected_profile_idx.clone();
-    let refresh_act = call_refresh.clone();
-    activate_btn.connect_clicked(move |_| {
-        if let Some(idx) = *sel_act.borrow() {
-            let mut s = state_act.lock().unwrap();
-            if let Some(p) = s.settings.profiles.get(idx) {
-                s.settings.active_profile = Some(p.name.clone());
-                let _ = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap());
-            }
+    // Whether the window is too narrow for the sidebar; it then only opens on demand
+    let narrow = Rc::new(Cell::new(false));
+    let sidebar_c = sidebar.widget.clone();
+    let state_sidebar = state.clone();
+    let narrow_c = narrow.clone();
+    sidebar_toggle.connect_toggled(move |btn| {
+        let visible = btn.is_active();
+        sidebar_c.set_visible(visible);
+        if narrow_c.get() {
+            return;
+        }
+        let mut s = state_sidebar.locked();
+        if s.settings.sidebar_visible != visible {
+            s.settings.sidebar_visible = visible;
+            s.save_settings();
         }
-        refresh_act();
     });
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&header_bar);
+    toolbar_view.set_content(Some(&toasts));
+    let geometry = state.locked().settings.window.clone();
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("Arch LLM")
+        .default_width(geometry.width)
+        .default_height(geometry.height)
+        .maximized(geometry.maximized)
+        .content(&toolbar_view)
+        .build();
 
-    let state_del = state.clone();
-    let sel_del = selected_profile_idx.clone();
-    let refresh_del = call_refresh.clone();
-    delete_btn.connect_clicked(move |_| {
-        if let Some(idx) = *sel_del.borrow() {
-            let mut s = state_del.lock().unwrap();
-            if idx < s.settings.profiles.len() {
-                let name = s.settings.profiles[idx].name.clone();
-                s.settings.profiles.remove(idx);
-                if s.settings.active_profile.as_ref() == Some(&name) {
-                    s.settings.active_profile = None;
-                }
-                let _ = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap());
+    let save_geometry = {
+        let state = state.clone();
+        let window = window.clone();
+        move || {
+            // default_size() is the unmaximized size, so restoring un-maximizes to it
+            let (width, height) = window.default_size();
+            let mut s = state.locked();
+            s.settings.window = WindowGeometry {
+                width,
+                height,
+                maximized: window.is_maximized(),
+            };
+            s.save_settings();
+        }
+    };
+
+    let status_icon = app.dbus_connection().and_then(|connection| {
+        let window = window.clone();
+        let visible = state.locked().settings.run_in_background;
+        StatusIcon::new(&connection, visible, move || {
+            if window.is_visible() {
+                window.set_visible(false);
+            } else {
+                window.present();
             }
+        })
+        .map_err(|e| tracing::error!("Failed to export status icon: {}", e))
+        .ok()
+    });
+    let status_icon = Rc::new(status_icon);
+
+    let state_close = state.clone();
+    let save_geometry_close = save_geometry.clone();
+    let save_drafts_close = save_drafts.clone();
+    window.connect_close_request(move |window| {
+        save_geometry_close();
+        save_drafts_close();
+        if state_close.locked().settings.run_in_background {
+            // The hidden window keeps the application (and any generation) alive
+            window.set_visible(false);
+            return glib::Propagation::Stop;
         }
-        *sel_del.borrow_mut() = None;
-        refresh_del();
+        glib::Propagation::Proceed
     });
 
-    let state_clr = state.clone();
-    let sel_clr = selected_profile_idx.clone();
-    let refresh_clr = call_refresh.clone();
-    clear_mem_btn.connect_clicked(move |_| {
-        if let Some(idx) = *sel_clr.borrow() {
-            let s = state_clr.lock().unwrap();
-            if let Some(p) = s.settings.profiles.get(idx) {
-                let mem_file = s.memory_path.join(format!("{}.txt", p.id));
-                let _ = fs::remove_file(mem_file);
-            }
+    let status_icon_save = status_icon.clone();
+    let background_switch_c = settings_window.general.background_switch.clone();
+    settings_window.general.save_btn.connect_clicked(move |_| {
+        if let Some(icon) = status_icon_save.as_ref() {
+            icon.set_visible(background_switch_c.is_active());
         }
-        refresh_clr();
     });
 
-    let personalization_scrolled = ScrolledWindow::builder()
-        .child(&personalization_box)
-        .vexpand(true)
-        .build();
+    // Responsive layout: clamp the message column to the configured width and
+    // fold the sidebar behind the header bar toggle on narrow windows.
+    let update_layout: Rc<dyn Fn()> = {
+        let window = window.clone();
+        let state = state.clone();
+        let chat_tabs = chat_tabs.clone();
+        let input_container = input_container.clone();
+        let sidebar = sidebar.widget.clone();
+        let sidebar_toggle = sidebar_toggle.clone();
+        let narrow = narrow.clone();
+        Rc::new(move || {
+            let width = window.width();
+            if width <= 0 {
+                return;
+            }
+            // Hysteresis keeps the sidebar from flapping around the threshold
+            let is_narrow = if narrow.get() { width < NARROW_LAYOUT_WIDTH + 60 } else { width < NARROW_LAYOUT_WIDTH };
+            if is_narrow != narrow.get() {
+                narrow.set(is_narrow);
+                let visible = !is_narrow && state.locked().settings.sidebar_visible;
+                sidebar_toggle.set_active(visible);
+                sidebar.set_visible(visible);
+            }
 
-    settings_stack.add_titled(&personalization_scrolled, Some("personalization"), "Personalization");
+            let max_width = state.locked().settings.chat_max_width as i32;
+            let viewport = chat_tabs.current().scrolled_window.hadjustment().page_size() as i32;
+            let margin = ((viewport - max_width) / 2).max(CHAT_MIN_MARGIN);
+            // Every tab, so switching doesn't jump
+            for view in chat_tabs.views() {
+                view.chat_box.set_margin_start(margin);
+                view.chat_box.set_margin_end(margin);
+            }
+            if input_container.margin_start() != margin {
+                input_container.set_margin_start(margin);
+                input_container.set_margin_end(margin);
+            }
+        })
+    };
+    let update_layout_resize = update_layout.clone();
+    chat_tabs.connect_resized(move || {
+        // Runs during size allocation; defer the margin changes to the next idle
+        let update_layout = update_layout_resize.clone();
+        glib::idle_add_local_once(move || update_layout());
+    });
+    let state_width = state.clone();
+    let update_layout_width = update_layout.clone();
+    settings_window.general.chat_width_spin.connect_value_changed(move |spin| {
+        state_width.locked().settings.chat_max_width = spin.value_as_int() as u32;
+        update_layout_width();
+    });
 
-    scrolled_profiles.add_css_class("profile-scrolled-window");
+    settings_window.window.set_transient_for(Some(&window));
+    sidebar.settings_btn.connect_clicked({
+        let settings_window = settings_window.window.clone();
+        move |_| settings_window.present()
+    });
 
-    main_stack.add_titled(&chat_box_container, Some("chat"), "Chat");
-    main_stack.add_titled(&settings_view, Some("settings"), "Settings");
+    let main_stack_clone = main_stack.clone();
+    let saved_view_clone = saved_view.clone();
+    sidebar.saved_btn.connect_clicked(move |_| {
+        saved_view_clone.refresh();
+        main_stack_clone.set_visible_child_name("saved");
+    });
 
-    let window = ApplicationWindow::builder()
-        .application(app)
-        .title("Arch LLM")
-        .default_width(1200)
-        .default_height(800)
-        .child(&root_stack)
-        .build();
+    let main_stack_clone = main_stack.clone();
+    saved_view.back_btn.connect_clicked(move |_| {
+        main_stack_clone.set_visible_child_name("chat");
+    });
 
     let main_stack_clone = main_stack.clone();
-    settings_btn.connect_clicked(move |_| {
-        main_stack_clone.set_visible_child_name("settings");
+    sidebar.arena_btn.connect_clicked(move |_| {
+        main_stack_clone.set_visible_child_name("arena");
     });
 
     let main_stack_clone = main_stack.clone();
-    back_btn.connect_clicked(move |_| {
+    arena_view.back_btn.connect_clicked(move |_| {
         main_stack_clone.set_visible_child_name("chat");
     });
 
-    // --- History Helper ---
-    let refresh_history: Rc<RefCell<Option<std::boxed::Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
-    
-    let refresh_history_impl = {
+    sidebar.connect_open_conversation({
         let state = state.clone();
-        let history_list = history_list.clone();
-        let render_chat = render_chat.clone();
-        let refresh_history_ref = refresh_history.clone();
-        move || {
-            while let Some(child) = history_list.first_child() {
-                history_list.remove(&child);
-            }
-            let history = {
-                let s = state.lock().unwrap();
-                s.history.clone()
+        let new_tab = new_tab.clone();
+        let switch_conversation = switch_conversation.clone();
+        move |history_id, messages, in_new_tab| {
+            let open = {
+                let s = state.locked();
+                s.chats.iter().any(|c| c.history_id.is_some() && c.history_id == history_id)
             };
-            for item in history.into_iter().rev() {
-                let row_btn = Button::builder()
-                    .label(&item.title)
-                    .css_classes(["history-item"])
-                    .build();
-                
-                let state_h = state.clone();
-                let render_chat = render_chat.clone();
-                let item_messages = item.messages.clone();
-                row_btn.connect_clicked(move |_| {
-                    let mut s = state_h.lock().unwrap();
-                    s.messages = item_messages.clone();
-                    render_chat(&s.messages);
-                });
-
-                // Context Menu
-                let popover = Popover::new();
-                let menu_box = Box::builder().orientation(Orientation::Vertical).spacing(5).margin_top(10).margin_bottom(10).margin_start(10).margin_end(10).build();
-                
-                let rename_box = Box::builder().orientation(Orientation::Horizontal).spacing(5).build();
-                let rename_entry = Entry::builder().text(&item.title).hexpand(true).build();
-                let rename_confirm_btn = Button::with_label("Save");
-                rename_box.append(&rename_entry);
-                rename_box.append(&rename_confirm_btn);
-                menu_box.append(&rename_box);
-
-                let delete_btn = Button::with_label("Delete Chat");
-                delete_btn.add_css_class("destructive-action"); // Will add CSS later
-                menu_box.append(&delete_btn);
-                
-                popover.set_child(Some(&menu_box));
-                popover.set_parent(&row_btn);
-                popover.set_has_arrow(false);
-
-                let gesture = GestureClick::new();
-                gesture.set_button(3); // Right click
-                gesture.connect_pressed(glib::clone!(#[weak] popover, #[weak] row_btn, move |_, _, _, _| {
-                     let allocation = row_btn.allocation();
-                     popover.set_pointing_to(Some(&allocation));
-                     popover.popup();
-                }));
-                row_btn.add_controller(gesture);
-
-                // Handlers
-                let state_r = state.clone();
-                let item_id = item.id.clone();
-                let refresh_r = refresh_history_ref.clone();
-                let rename_entry_c = rename_entry.clone();
-                let popover_r = popover.clone();
-                
-                rename_confirm_btn.connect_clicked(move |_| {
-                    let new_title = rename_entry_c.text().to_string();
-                    if new_title.is_empty() { return; }
-                    {
-                        let mut s = state_r.lock().unwrap();
-                        if let Some(h) = s.history.iter_mut().find(|x| x.id == item_id) {
-                            h.title = new_title;
-                            if let Err(e) = fs::write(&s.history_path, serde_json::to_string(&s.history).unwrap()) {
-                                eprintln!("Failed to save history: {}", e);
-                            }
-                        }
-                    }
-                    popover_r.popdown();
-                    if let Some(f) = &*refresh_r.borrow() { f(); }
-                });
-
-                let state_d = state.clone();
-                let item_id_d = item.id.clone();
-                let refresh_d = refresh_history_ref.clone();
-                let popover_d = popover.clone();
-                
-                delete_btn.connect_clicked(move |_| {
-                    {
-                        let mut s = state_d.lock().unwrap();
-                        s.history.retain(|x| x.id != item_id_d);
-                        if let Err(e) = fs::write(&s.history_path, serde_json::to_string(&s.history).unwrap()) {
-                            eprintln!("Failed to save history: {}", e);
-                        }
-                        // If deleted chat was active, clear it? Maybe not necessary for UX flow
-                    }
-                    popover_d.popdown();
-                    if let Some(f) = &*refresh_d.borrow() { f(); }
-                });
-
-                history_list.append(&row_btn);
+            if in_new_tab && !open {
+                let agent_idx = state.locked().chat().agent_idx;
+                new_tab(agent_idx);
             }
+            switch_conversation(history_id, messages);
         }
-    };
-    *refresh_history.borrow_mut() = Some(std::boxed::Box::new(refresh_history_impl));
-    if let Some(f) = &*refresh_history.borrow() { f(); }
+    });
+    sidebar.refresh_history();
 
-    new_chat_btn.connect_clicked({
+    // New chats start with the default agent
+    let start_new_chat = {
         let state = state.clone();
-        let render_chat = render_chat.clone();
-        move |_| {
-            let mut s = state.lock().unwrap();
-            s.messages.clear();
-            render_chat(&s.messages);
+        let agent_dropdown = agent_dropdown.clone();
+        let restoring_agent = restoring_agent.clone();
+        let switch_conversation = switch_conversation.clone();
+        let new_tab = new_tab.clone();
+        move || {
+            let (idx, busy) = {
+                let s = state.locked();
+                (s.settings.default_agent_index(), s.chat().task.is_some())
+            };
+            // The agent of a tab that is generating stays as it is
+            if busy {
+                new_tab(idx);
+            }
+            restoring_agent.set(true);
+            agent_dropdown.set_selected(idx as u32);
+            restoring_agent.set(false);
+            switch_conversation(None, Vec::new());
         }
+    };
+    sidebar.new_chat_btn.connect_clicked({
+        let start_new_chat = start_new_chat.clone();
+        move |_| start_new_chat()
     });
 
-    // --- Event Handlers ---
-    let state_clone = state.clone();
-    let render_chat_clone = render_chat.clone();
-    agent_dropdown.connect_selected_notify(move |dd| {
-        let mut s = state_clone.lock().unwrap();
-        s.current_agent_idx = dd.selected() as usize;
-        s.messages.clear();
-        render_chat_clone(&s.messages);
+    // `app.prefill`: start a new chat with the given text in the input (used by the command line)
+    let prefill_action = gtk::gio::SimpleAction::new("prefill", Some(glib::VariantTy::STRING));
+    prefill_action.connect_activate({
+        let text_view = text_view.clone();
+        move |_, parameter| {
+            let Some(text) = parameter.and_then(|p| p.get::<String>()) else { return };
+            start_new_chat();
+            text_view.buffer().set_text(&text);
+            text_view.grab_focus();
+        }
     });
+    app.add_action(&prefill_action);
 
-    let state_clone = state.clone();
-    let chat_box_clone = chat_box.clone();
-    let refresh_history_clone = refresh_history.clone();
-    let send_btn_clone = send_btn.clone();
-    let text_view_clone = text_view.clone();
-    let scroll_to_bottom_clone = scroll_to_bottom.clone();
-
-    // Logic to handle Send / Stop
-    let handle_send_or_stop = move || {
-        let is_sending = send_btn_clone.label().map(|l| l.as_str() == "Stop").unwrap_or(false);
-
-        if is_sending {
-            // STOP Logic
-            let mut s = state_clone.lock().unwrap();
-            if let Some(handle) = s.current_task.take() {
-                handle.abort();
+    // `app.open-conversation`: show a saved conversation by id (used by the shell search provider)
+    let open_conversation_action = gtk::gio::SimpleAction::new("open-conversation", Some(glib::VariantTy::STRING));
+    open_conversation_action.connect_activate({
+        let state = state.clone();
+        let switch_conversation = switch_conversation.clone();
+        move |_, parameter| {
+            let Some(id) = parameter.and_then(|p| p.get::<String>()) else { return };
+            let messages = state.locked().history.iter().find(|h| h.id == id).map(|h| h.messages.clone());
+            if let Some(messages) = messages {
+                switch_conversation(Some(id), messages);
             }
-            send_btn_clone.set_label("Send");
-            send_btn_clone.remove_css_class("stop-btn");
-            send_btn_clone.add_css_class("send-btn");
-            return;
         }
+    });
+    app.add_action(&open_conversation_action);
 
-        // SEND Logic
-        let buffer = text_view_clone.buffer();
-        let (start, end) = buffer.bounds();
-        let text = buffer.text(&start, &end, false).to_string();
-        
-        if text.trim().is_empty() { return; }
-        buffer.set_text("");
-
-        send_btn_clone.set_label("Stop");
-        send_btn_clone.remove_css_class("send-btn");
-        send_btn_clone.add_css_class("stop-btn");
-
-        // Add user message to UI
-        let user_label = Label::builder()
-            .xalign(0.0)
-            .wrap(true)
-            .css_classes(["user-message"])
-            .halign(gtk::Align::End)
-            .build();
-        user_label.set_markup(&glib::markup_escape_text(&text));
-        chat_box_clone.append(&user_label);
-        scroll_to_bottom_clone();
-
-        // Response container
-        let bot_msg_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
-        let bot_spinner = Spinner::builder().spinning(true).build();
-        let bot_label = Label::builder()
-            .label("Thinking...")
-            .xalign(0.0)
-            .wrap(true)
-            .css_classes(["bot-message"])
-            .hexpand(true)
-            .build();
-        bot_msg_box.append(&bot_spinner);
-        bot_msg_box.append(&bot_label);
-        chat_box_clone.append(&bot_msg_box);
-        scroll_to_bottom_clone();
-
-        let (sender, receiver) = async_channel::unbounded();
-        
-        // Receiver (Main Thread)
-        let mut full_response_acc = String::new();
-        let bot_label_c = bot_label.clone();
-        let bot_spinner_c = bot_spinner.clone();
-        let scroll_to_bottom_c = scroll_to_bottom_clone.clone();
-        let send_btn_c = send_btn_clone.clone();
-        let state_c = state_clone.clone();
-        let text_c = text.clone();
-        let refresh_history_c = refresh_history_clone.clone();
-        let sender_for_title = sender.clone();
+    let present_main_window = {
+        let app_weak = app.downgrade();
+        move || {
+            if let Some(window) = app_weak.upgrade().and_then(|app| app.windows().first().cloned()) {
+                window.present();
+            }
+        }
+    };
 
-        glib::MainContext::default().spawn_local(async move {
-            while let Ok(event) = receiver.recv().await {
-                match event {
-                    ChatEvent::Chunk(chunk) => {
-                        bot_spinner_c.set_spinning(false);
-                        bot_spinner_c.set_visible(false);
-                        full_response_acc.push_str(&chunk);
-                        bot_label_c.set_markup(&markdown_to_pango(&full_response_acc));
-                        scroll_to_bottom_c();
-                    }
-                    ChatEvent::Error(err) => {
-                        bot_label_c.set_label(&format!("Error: {}", err));
-                        send_btn_c.set_label("Send");
-                        send_btn_c.remove_css_class("stop-btn");
-                        send_btn_c.add_css_class("send-btn");
-                        
-                        let mut s = state_c.lock().unwrap();
-                        s.current_task = None;
-                        break;
-                    }
-                    ChatEvent::RefreshHistory => {
-                        if let Some(f) = &*refresh_history_c.borrow() { f(); }
-                    }
-                    ChatEvent::Done(full_text) => {
-                        // Save history
-                        let is_first_message;
-                        let history_id = glib::uuid_string_random().to_string();
-                        let (history_path, ollama_clone, model_clone) = {
-                            let mut s = state_c.lock().unwrap();
-                            s.messages.push(ChatMessage::assistant(full_text));
-                            is_first_message = s.messages.len() <= 3;
-                            s.current_task = None;
-                            
-                            let history_item = ChatHistory {
-                                id: history_id.clone(),
-                                title: text_c.chars().take(20).collect(),
-                                messages: s.messages.clone(),
-                            };
-                            s.history.push(history_item);
-                            if let Err(e) = fs::write(&s.history_path, serde_json::to_string(&s.history).unwrap()) {
-                                eprintln!("Failed to write history.json: {}", e);
-                            }
-                            
-                            // Need copies for async title gen
-                            let agent = s.settings.agents.get(s.current_agent_idx).cloned().unwrap_or_else(|| s.settings.agents[0].clone());
-                            (s.history_path.clone(), s.ollama.clone(), agent.model.clone())
-                        };
-
-                        // Reset UI
-                        send_btn_c.set_label("Send");
-                        send_btn_c.remove_css_class("stop-btn");
-                        send_btn_c.add_css_class("send-btn");
-                        if let Some(f) = &*refresh_history_c.borrow() { f(); }
-
-                        // Generate Title Async
-                        if is_first_message {
-                            let state_title = state_c.clone();
-                            let user_text_title = text_c.clone();
-                            let sender_title = sender_for_title.clone();
-                            
-                            tokio::spawn(async move {
-                                let title_prompt = format!(
-                                    "Generate a very short, creative 2-4 word title for a chat that starts with: \"{}\". Output ONLY the title, no quotes or punctuation.",
-                                    user_text_title
-                                );
-                                let req = ChatMessageRequest::new(
-                                    model_clone,
-                                    vec![ChatMessage::user(title_prompt)]
-                                );
-                                
-                                if let Ok(res) = ollama_clone.send_chat_messages(req).await {
-                                    let new_title = res.message.content.trim().trim_matches('"').trim_matches('.').to_string();
-                                    if !new_title.is_empty() {
-                                        let mut s = state_title.lock().unwrap();
-                                        if let Some(hist) = s.history.iter_mut().find(|h| h.id == history_id) {
-                                            hist.title = new_title;
-                                            if let Err(e) = fs::write(&history_path, serde_json::to_string(&s.history).unwrap()) {
-                                                eprintln!("Failed to write history.json: {}", e);
-                                            }
-                                        }
-                                    }
-                                    let _ = sender_title.send(ChatEvent::RefreshHistory).await;
-                                }
-                            });
-                        }
-                        // Do NOT break here, as we might receive RefreshHistory later
+    // Quick Ask: a one-off prompt window, opened by a global shortcut or over D-Bus
+    let quick_ask = QuickAsk::new(state.clone(), commands.clone(), {
+        let switch_conversation = switch_conversation.clone();
+        let present_main_window = present_main_window.clone();
+        move |messages| {
+            switch_conversation(None, messages.into_iter().map(StoredMessage::new).collect());
+            present_main_window();
+        }
+    });
+    let quick_ask_shortcut: Rc<RefCell<Option<gtk::gio::SignalSubscription>>> = Rc::new(RefCell::new(None));
+    let bind_quick_ask_shortcut = {
+        let app = app.clone();
+        let quick_ask = quick_ask.clone();
+        let quick_ask_shortcut = quick_ask_shortcut.clone();
+        let toasts = toasts.clone();
+        move || {
+            let Some(bus) = app.dbus_connection() else { return };
+            if quick_ask_shortcut.borrow().is_some() {
+                return;
+            }
+            let quick_ask = quick_ask.clone();
+            let quick_ask_shortcut = quick_ask_shortcut.clone();
+            let toasts = toasts.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let quick_ask_weak = Rc::downgrade(&quick_ask);
+                let result = portal::bind_global_shortcut(&bus, "quick-ask", "Open Quick Ask", "<Control><Alt>space", move || {
+                    if let Some(quick_ask) = quick_ask_weak.upgrade() {
+                        quick_ask.show();
                     }
+                }).await;
+                match result {
+                    Ok(subscription) => *quick_ask_shortcut.borrow_mut() = Some(subscription),
+                    Err(e) => toasts.report(&AppError::new("Could not register the Quick Ask shortcut", e)),
+                }
+            });
+        }
+    };
+    if state.locked().settings.quick_ask_shortcut {
+        bind_quick_ask_shortcut();
+    }
+
+    // The last session ended before its conversation reached history
+    if let Some(session) = recovered_session {
+        let restore = {
+            let state = state.clone();
+            let switch_conversation = switch_conversation.clone();
+            let agent_dropdown = agent_dropdown.clone();
+            let session = RefCell::new(Some(session));
+            move || {
+                let Some(session) = session.borrow_mut().take() else { return };
+                if session.agent_idx < state.locked().settings.agents.len() {
+                    agent_dropdown.set_selected(session.agent_idx as u32);
                 }
+                switch_conversation(session.history_id.clone(), session.into_messages());
             }
-        });
+        };
+        let toast = toast("Arch LLM didn't close cleanly last time");
+        toast.set_button_label(Some("Restore Last Session"));
+        toast.set_timeout(20);
+        toast.connect_button_clicked(move |_| restore());
+        toasts.add_toast(toast);
+    }
+    // Local OpenAI-compatible API, restarted when its settings change
+    let api_server: Rc<RefCell<Option<ApiServer>>> = Rc::new(RefCell::new(None));
+    let apply_api_server = {
+        let state = state.clone();
+        let commands = commands.clone();
+        let toasts = toasts.clone();
+        move || {
+            let (enabled, port) = {
+                let mut s = state.locked();
+                // Settings from before a key was required
+                if s.settings.api_server.enabled && s.settings.api_server.api_key.is_empty() {
+                    s.settings.api_server.api_key = new_api_key();
+                    s.save_settings();
+                }
+                (s.settings.api_server.enabled, s.settings.api_server.port)
+            };
+            let mut running = api_server.borrow_mut();
+            if enabled && running.as_ref().is_some_and(|server| server.port == port) {
+                return;
+            }
+            running.take();
+            if !enabled {
+                return;
+            }
+            match ApiServer::start(commands.clone(), port) {
+                Ok(server) => *running = Some(server),
+                Err(e) => toasts.report(&e),
+            }
+        }
+    };
+    apply_api_server();
+    settings_window.general.save_btn.connect_clicked({
+        let state = state.clone();
+        move |_| {
+            if state.locked().settings.quick_ask_shortcut {
+                bind_quick_ask_shortcut();
+            }
+            apply_api_server();
+        }
+    });
+    app.connect_shutdown({
+        let quick_ask_shortcut = quick_ask_shortcut.clone();
+        let state = state.clone();
+        move |_| {
+            quick_ask_shortcut.borrow_mut().take();
+            let s = state.locked();
+            // Quitting mid-reply keeps the snapshot so it can be picked up again
+            if s.chats.iter().all(|c| c.task.is_none()) {
+                recovery::clear(&s);
+            }
+            s.storage.flush();
+        }
+    });
 
-        // Task (Tokio Thread)
-        let state = state_clone.clone();
-        let text_task = text.clone();
-        
-        let task = tokio::spawn(async move {
-            let (ollama, model, messages, profile_id, memory_path) = {
-                let mut s = state.lock().unwrap();
-                let agent = s.settings.agents.get(s.current_agent_idx).cloned().unwrap_or_else(|| s.settings.agents[0].clone());
-                
-                let mut profile_info = None;
-                if let Some(active_name) = &s.settings.active_profile {
-                    if let Some(profile) = s.settings.profiles.iter().find(|p| &p.name == active_name) {
-                        profile_info = Some((profile.id.clone(), profile.first_name.clone(), profile.last_name.clone(), profile.location.clone(), profile.bio.clone()));
+    // Scripting interface on the session bus
+    if let Some(connection) = app.dbus_connection() {
+        let present = present_main_window.clone();
+        let handlers = RemoteHandlers {
+            ask: std::boxed::Box::new({
+                let present = present.clone();
+                let send_template = send_template.clone();
+                move |text| {
+                    present();
+                    send_template(text);
+                }
+            }),
+            new_chat: std::boxed::Box::new({
+                let present = present.clone();
+                let new_chat_btn = sidebar.new_chat_btn.clone();
+                move || {
+                    present();
+                    new_chat_btn.emit_clicked();
+                }
+            }),
+            set_agent: std::boxed::Box::new({
+                let state = state.clone();
+                let agent_dropdown = agent_dropdown.clone();
+                move |name| {
+                    let index = state.locked().settings.agents.iter().position(|a| a.name == name);
+                    match index {
+                        Some(index) => {
+                            agent_dropdown.set_selected(index as u32);
+                            true
+                        }
+                        None => false,
                     }
                 }
+            }),
+            list_agents: std::boxed::Box::new({
+                let state = state.clone();
+                move || state.locked().settings.agents.iter().map(|a| a.name.clone()).collect()
+            }),
+            show: std::boxed::Box::new(present),
+            quick_ask: std::boxed::Box::new({
+                let quick_ask = quick_ask.clone();
+                move || quick_ask.show()
+            }),
+        };
+        if let Err(e) = remote::export(&connection, handlers) {
+            tracing::error!("Failed to export D-Bus interface: {}", e);
+        }
+    }
 
-                if s.messages.is_empty() {
-                    let mut system_prompt = agent.system_prompt.clone();
-                    
-                    if let Some((id, fname, lname, loc, bio)) = &profile_info {
-                        system_prompt.push_str("\n\n---\nUser Profile:\n");
-                        if !fname.is_empty() || !lname.is_empty() {
-                            system_prompt.push_str(&format!("Name: {} {}\n", fname, lname));
-                        }
-                        if !loc.is_empty() {
-                            system_prompt.push_str(&format!("Location: {}\n", loc));
-                        }
-                        if !bio.is_empty() {
-                            system_prompt.push_str(&format!("Bio: {}\n", bio));
-                        }
+    // --- Event Handlers ---
+    let state_clone = state.clone();
+    let switch_conversation_agent = switch_conversation.clone();
+    let token_counter_agent = token_counter.clone();
+    let new_tab_agent = new_tab.clone();
+    let restoring_agent_c = restoring_agent.clone();
+    agent_dropdown.connect_selected_notify(move |dd| {
+        let selected = dd.selected() as usize;
+        // A new chat with another agent leaves a tab that is generating alone
+        if !restoring_agent_c.get() && state_clone.locked().chat().task.is_some() {
+            new_tab_agent(selected);
+        }
+        state_clone.locked().chat_mut().agent_idx = selected;
+        if !restoring_agent_c.get() {
+            switch_conversation_agent(None, Vec::new());
+        }
+        token_counter_agent.update();
+    });
 
-                        // Load Long-term Memory
-                        let mem_file = s.memory_path.join(format!("{}.txt", id));
-                        if let Ok(memory) = fs::read_to_string(&mem_file) {
-                            if !memory.trim().is_empty() {
-                                system_prompt.push_str("\nLong-term Memory of User:\n");
-                                system_prompt.push_str(&memory);
-                            }
-                        }
-                    }
-                    s.messages.push(ChatMessage::system(system_prompt));
+    // Brings the input, agent and Send button in line with the tab on screen
+    chat_tabs.connect_switched({
+        let state = state.clone();
+        let agent_dropdown = agent_dropdown.clone();
+        let text_view = text_view.clone();
+        let update_send_btn = update_send_btn.clone();
+        let token_counter = token_counter.clone();
+        let notes_panel = notes_panel.clone();
+        move |tab_id| {
+            let (agent_idx, draft) = {
+                let mut s = state.locked();
+                if s.active_tab == tab_id {
+                    return;
                 }
-                
-                s.messages.push(ChatMessage::user(text_task.clone()));
-                (s.ollama.clone(), agent.model.clone(), s.messages.clone(), profile_info.map(|p| p.0), s.memory_path.clone())
+                s.active_tab = tab_id;
+                (s.chat().agent_idx, s.drafts.get(&s.draft_key()).cloned().unwrap_or_default())
             };
+            restoring_agent.set(true);
+            agent_dropdown.set_selected(agent_idx as u32);
+            restoring_agent.set(false);
+            text_view.buffer().set_text(&draft);
+            update_send_btn();
+            token_counter.update();
+            notes_panel.sync();
+        }
+    });
 
-            match ollama.send_chat_messages_stream(
-                ChatMessageRequest::new(model.clone(), messages.clone())
-            ).await {
-                Ok(mut stream) => {
-                    let mut full_response = String::new();
-                    while let Some(res) = stream.next().await {
-                        if let Ok(res) = res {
-                            let msg = res.message;
-                            full_response.push_str(&msg.content);
-                            if sender.send(ChatEvent::Chunk(msg.content)).await.is_err() { break; }
-                        }
-                    }
-                    
-                    // Update Memory if profile is active
-                    if let Some(id) = profile_id {
-                        let ollama_mem = ollama.clone();
-                        let model_mem = model.clone();
-                        let mut messages_mem = messages.clone();
-                        messages_mem.push(ChatMessage::assistant(full_response.clone()));
-                        let memory_path_mem = memory_path.clone();
-
-                        tokio::spawn(async move {
-                            let mem_file = memory_path_mem.join(format!("{}.txt", id));
-                            let existing_memory = fs::read_to_string(&mem_file).unwrap_or_default();
-                            
-                            let memory_prompt = format!(
-                                "You are a memory module. Based on the recent conversation above and the existing knowledge about the user, update the Long-term Memory. \
-                                Existing Knowledge:\n{}\n\n\
-                                Requirements:\n\
-                                1. Output a concise, bulleted list of facts, preferences, and important context about the user.\n\
-                                2. Include new info from this chat.\n\
-                                3. Keep it brief and relevant for future assistance.\n\
-                                4. Output ONLY the list, no headers or conversational text.",
-                                existing_memory
-                            );
-                            
-                            messages_mem.push(ChatMessage::user(memory_prompt));
-                            if let Ok(res) = ollama_mem.send_chat_messages(ChatMessageRequest::new(model_mem, messages_mem)).await {
-                                let new_memory = res.message.content.trim().to_string();
-                                if !new_memory.is_empty() {
-                                    let _ = fs::write(mem_file, new_memory);
-                                }
-                            }
-                        });
-                    }
+    // Closing a tab stops its reply; the last one makes way for a new chat
+    let close_tab: Rc<dyn Fn(u64)> = {
+        let state = state.clone();
+        let chat_tabs = chat_tabs.clone();
+        let new_tab = new_tab.clone();
+        let chat_sender = chat_sender.clone();
+        Rc::new(move |tab_id| {
+            chat_sender.discard_queued(tab_id);
+            // The split pane's chat doesn't count, it isn't a tab
+            let last = chat_tabs.views().len() == 1;
+            let default_agent = state.locked().settings.default_agent_index();
+            if last {
+                new_tab(default_agent);
+            }
+            // Another tab is on screen before this one leaves the state
+            chat_tabs.remove(tab_id);
+            let mut s = state.locked();
+            if let Some(idx) = s.chats.iter().position(|c| c.tab_id == tab_id) {
+                s.chats.remove(idx).stop_tasks();
+            }
+        })
+    };
+    chat_tabs.connect_close({
+        let close_tab = close_tab.clone();
+        move |tab_id| close_tab(tab_id)
+    });
+    new_tab_btn.connect_clicked({
+        let state = state.clone();
+        let new_tab = new_tab.clone();
+        move |_| {
+            let idx = state.locked().settings.default_agent_index();
+            new_tab(idx);
+        }
+    });
 
-                    let _ = sender.send(ChatEvent::Done(full_response)).await;
-                }
-                Err(e) => {
-                    let _ = sender.send(ChatEvent::Error(format!("{:?}", e))).await;
-                }
+    // Logic to handle Send / Stop
+    let handle_send_or_stop = {
+        let state = state.clone();
+        let chat_tabs = chat_tabs.clone();
+        let chat_sender = chat_sender.clone();
+        let text_view = text_view.clone();
+        let attachments = attachments.clone();
+        let attachment_bar = attachment_bar.clone();
+        move || {
+            let (tab_id, busy) = {
+                let s = state.locked();
+                (s.active_tab, s.chat().task.is_some())
+            };
+            if busy {
+                chat_sender.stop(tab_id);
+                return;
             }
-        });
-        
-        let mut s = state_clone.lock().unwrap();
-        s.current_task = Some(task.abort_handle());
+
+            let buffer = text_view.buffer();
+            let (start, end) = buffer.bounds();
+            let text = buffer.text(&start, &end, false).to_string();
+            if text.trim().is_empty() && attachments.borrow().is_empty() { return; }
+            buffer.set_text("");
+            let attached: Vec<Attachment> = attachments.borrow_mut().drain(..).collect();
+            attachment_bar.refresh();
+
+            // The reply goes to this tab, even once another one is on screen
+            let view = chat_tabs.current();
+            let state = state.clone();
+            let chat_sender = chat_sender.clone();
+            let attachments = attachments.clone();
+            let attachment_bar = attachment_bar.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let Some((text, attached)) = chat_sender.submit(tab_id, view, text, attached).await else { return };
+                // Cancelled at a check: back into the input, unless it moved on meanwhile
+                if state.locked().active_tab != tab_id || buffer.char_count() > 0 || !attachments.borrow().is_empty() {
+                    return;
+                }
+                buffer.set_text(&text);
+                attachments.borrow_mut().extend(attached);
+                attachment_bar.refresh();
+            });
+        }
     };
 
     let handle_send_clone = handle_send_or_stop.clone();
@@ -1506,9 +1732,26 @@ fn build_ui(app: &Application) {
 
     // Global Shortcuts
     let controller = EventControllerKey::new();
-    let new_chat_btn_c = new_chat_btn.clone();
-    let settings_btn_c = settings_btn.clone();
+    let new_chat_btn_c = sidebar.new_chat_btn.clone();
+    let settings_btn_c = sidebar.settings_btn.clone();
     let app_c = app.clone();
+    let save_geometry_quit = save_geometry.clone();
+    let save_drafts_quit = save_drafts.clone();
+    let font_size_spin_c = settings_window.general.font_size_spin.clone();
+    let sidebar_toggle_c = sidebar_toggle.clone();
+    let copy_transcript_btn_c = copy_transcript_btn.clone();
+    let new_tab_btn_c = new_tab_btn.clone();
+    let state_tabs = state.clone();
+    let state_zoom = state.clone();
+    // `None` resets to the default size
+    let zoom = move |step: Option<f64>| {
+        match step {
+            Some(step) => font_size_spin_c.set_value(font_size_spin_c.value() + step),
+            None => font_size_spin_c.set_value(Settings::default().chat_font_size as f64),
+        }
+        let s = state_zoom.locked();
+        s.save_settings();
+    };
     
     controller.connect_key_pressed(move |_, key, _, modifiers| {
         if modifiers.contains(gtk::gdk::ModifierType::CONTROL_MASK) {
@@ -1517,14 +1760,46 @@ fn build_ui(app: &Application) {
                     new_chat_btn_c.emit_clicked();
                     return glib::Propagation::Stop;
                 }
+                gtk::gdk::Key::t => {
+                    new_tab_btn_c.emit_clicked();
+                    return glib::Propagation::Stop;
+                }
+                gtk::gdk::Key::w => {
+                    let tab_id = state_tabs.locked().active_tab;
+                    close_tab(tab_id);
+                    return glib::Propagation::Stop;
+                }
                 gtk::gdk::Key::comma => {
                     settings_btn_c.emit_clicked();
                     return glib::Propagation::Stop;
                 }
                 gtk::gdk::Key::q => {
+                    // Quit even in background mode; close-request is skipped, so save here
+                    save_geometry_quit();
+                    save_drafts_quit();
                     app_c.quit();
                     return glib::Propagation::Stop;
                 }
+                gtk::gdk::Key::b => {
+                    sidebar_toggle_c.set_active(!sidebar_toggle_c.is_active());
+                    return glib::Propagation::Stop;
+                }
+                gtk::gdk::Key::C if modifiers.contains(gtk::gdk::ModifierType::SHIFT_MASK) => {
+                    copy_transcript_btn_c.emit_clicked();
+                    return glib::Propagation::Stop;
+                }
+                gtk::gdk::Key::equal | gtk::gdk::Key::plus | gtk::gdk::Key::KP_Add => {
+                    zoom(Some(1.0));
+                    return glib::Propagation::Stop;
+                }
+                gtk::gdk::Key::minus | gtk::gdk::Key::KP_Subtract => {
+                    zoom(Some(-1.0));
+                    return glib::Propagation::Stop;
+                }
+                gtk::gdk::Key::_0 | gtk::gdk::Key::KP_0 => {
+                    zoom(None);
+                    return glib::Propagation::Stop;
+                }
                 _ => {}
             }
         }
@@ -1541,11 +1816,123 @@ fn build_ui(app: &Application) {
             color: #aaa;
             margin-bottom: 2px;
         }
+        .message-meta {
+            font-size: 11px;
+            color: #777;
+        }
         .code-frame {
             background-color: #1e1f20;
             border-radius: 8px;
             border: 1px solid #333;
         }
+        .headers-view {
+            background-color: #1e1f20;
+            border: 1px solid #444;
+            border-radius: 8px;
+            padding: 8px;
+        }
+        .status-dot {
+            font-size: 10px;
+            color: #888;
+        }
+        .status-dot.status-connected { color: #4caf50; }
+        .status-dot.status-disconnected { color: #e53935; }
+        .status-text {
+            font-size: 12px;
+            color: #aaa;
+        }
+        headerbar {
+            background-color: #1e1f20;
+            color: #e3e3e3;
+            box-shadow: none;
+            border-bottom: 1px solid #2b2d30;
+        }
+        .tool-chip {
+            background-color: #1e1f20;
+            border: 1px solid #333;
+            border-radius: 12px;
+            padding: 4px 12px;
+            font-size: 13px;
+            color: #aaa;
+        }
+        .tool-chip-details {
+            font-family: monospace;
+            font-size: 12px;
+            margin-top: 6px;
+        }
+        .execution-result {
+            background-color: #1e1f20;
+            border: 1px solid #333;
+            border-left: 3px solid #0b93f6;
+            border-radius: 8px;
+            padding: 8px 12px;
+            color: #ccc;
+        }
+        .jump-pill {
+            background-color: #2b2d30;
+            color: #e3e3e3;
+            border-radius: 16px;
+            padding: 4px 14px;
+            box-shadow: 0 2px 8px rgba(0, 0, 0, 0.4);
+        }
+        .notice-card {
+            background-color: #1e1f20;
+            border: 1px solid #333;
+            border-left: 3px solid #f9a825;
+            border-radius: 8px;
+            padding: 8px 12px;
+            color: #ccc;
+        }
+        .execution-result-title {
+            font-size: 13px;
+            font-weight: bold;
+        }
+        .attachment-chip {
+            border-radius: 8px;
+        }
+        .attachment-thumb {
+            border-radius: 8px;
+            border: 1px solid #333;
+        }
+        .attachment-remove {
+            min-width: 20px;
+            min-height: 20px;
+            padding: 0;
+            margin: 2px;
+            background-color: rgba(0, 0, 0, 0.6);
+            color: #fff;
+        }
+        .translation {
+            border-left: 3px solid #4a8cff;
+            padding-left: 10px;
+            color: #ccc;
+        }
+        .attachment-pill {
+            background-color: #1e1f20;
+            border: 1px solid #333;
+            border-radius: 12px;
+            padding: 2px 4px 2px 10px;
+            font-size: 13px;
+            color: #ccc;
+        }
+        .quick-ask {
+            border-radius: 14px;
+        }
+        .quick-ask-entry {
+            font-size: 18px;
+            min-height: 40px;
+        }
+        .speech-bar {
+            background-color: #2b2d30;
+            border-radius: 16px;
+            padding: 2px 6px 2px 14px;
+            font-size: 13px;
+        }
+        .connection-banner {
+            background-color: #5c3b00;
+            color: #ffd28a;
+            padding: 8px 20px;
+        }
         .code-view {
             font-family: monospace;
             padding: 10px;
@@ -1558,7 +1945,7 @@ fn build_ui(app: &Application) {
         }
 
         window { background-color: #131314; color: #e3e3e3; font-family: sans-serif; }
-        .sidebar { background-color: #1e1f20; }
+        .sidebar, .inspector { background-color: #1e1f20; }
         .sidebar button {
             background: none;
             border: none;
@@ -1575,8 +1962,9 @@ fn build_ui(app: &Application) {
             border-radius: 10px;
             font-size: 14px;
         }
+        .history-group { font-size: 12px; font-weight: bold; }
         
-        textview.chat-input {
+        textview.chat-input, .input-preview {
             background-color: #1e1f20;
             border-radius: 15px;
             color: white;
@@ -1695,12 +2083,29 @@ fn build_ui(app: &Application) {
             color: #888;
             font-weight: bold;
         }
+        .suggestion-chip {
+            background-color: #1e1f20;
+            border: 1px solid #444;
+            border-radius: 12px;
+            padding: 10px 14px;
+            color: #e3e3e3;
+        }
+        .suggestion-chip:hover { background-color: #333537; }
+        .agent-subtitle { font-size: 12px; font-weight: normal; }
+        .capability-badge {
+            background-color: #2b2d30;
+            color: #aaa;
+            border-radius: 8px;
+            padding: 0 6px;
+            font-size: 11px;
+        }
+        .queued-message { opacity: 0.6; }
+        .token-count { font-size: 12px; color: #888; }
+        .token-warning { color: #ff5555; }
     "#);
-    gtk::style_context_add_provider_for_display(
-        &gtk::gdk::Display::default().expect("Could not connect to a display."),
-        &provider,
-        gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+    if let Some(display) = gtk::gdk::Display::default() {
+        gtk::style_context_add_provider_for_display(&display, &provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+    }
 
     // Connection Check
     let root_stack_c = root_stack.clone();
@@ -1711,38 +2116,50 @@ fn build_ui(app: &Application) {
     
     // Retry / Setup handler
     let endpoint_entry_setup_c = endpoint_entry_setup.clone();
-    let endpoint_entry_general_c = endpoint_entry.clone();
+    let endpoint_entry_general_c = settings_window.general.endpoint_entry.clone();
+    let check_connection_retry = check_connection.clone();
+    let start_ollama_btn_c = start_ollama_btn.clone();
+    start_ollama_btn.connect_clicked({
+        let retry_btn = retry_btn.clone();
+        move |btn| {
+            let retry_btn = retry_btn.clone();
+            start_ollama(btn, std::boxed::Box::new(move || retry_btn.emit_clicked()));
+        }
+    });
     retry_btn.connect_clicked(glib::clone!(#[weak] root_stack_c, #[weak] state_conn, move |_| {
         let new_endpoint = endpoint_entry_setup_c.text().to_string();
         
         {
-            let mut s = state_conn.lock().unwrap();
+            let mut s = state_conn.locked();
+            if s.settings.ollama_endpoint != new_endpoint {
+                s.context_lengths.clear();
+            }
             s.settings.ollama_endpoint = new_endpoint.clone();
-            let final_url = normalize_url(&new_endpoint);
-            if let Ok(url) = url::Url::parse(&final_url) {
-                s.ollama = Ollama::from_url(url);
+            if let Some(ollama) = ollama_client(&new_endpoint, &s.settings.ollama_headers, &s.settings.ollama_tls, &s.settings.timeouts) {
+                s.ollama = ollama;
             }
             // Update general settings entry too
             endpoint_entry_general_c.set_text(&new_endpoint);
             
             // Save settings
-            if let Err(e) = fs::write(&s.config_path, serde_json::to_string(&s.settings).unwrap()) {
-                eprintln!("Failed to write settings.json: {}", e);
-            }
+            s.save_settings();
         }
 
+        start_ollama_btn_c.set_visible(service::can_start(&new_endpoint));
         root_stack_c.set_visible_child_name("loading");
         let root_stack_c = root_stack_c.clone();
         let state = state_conn.clone();
+        let check_connection = check_connection_retry.clone();
         glib::MainContext::default().spawn_local(async move {
-            let ollama = state.lock().unwrap().ollama.clone();
+            let ollama = state.locked().ollama.clone();
             match ollama.list_local_models().await {
                 Ok(models) => {
                     {
-                        let mut s = state.lock().unwrap();
+                        let mut s = state.locked();
                         s.available_models = models.into_iter().map(|m| m.name).collect();
                     }
                     root_stack_c.set_visible_child_name("main");
+                    check_connection();
                 }
                 Err(_) => {
                     root_stack_c.set_visible_child_name("error");
@@ -1753,14 +2170,15 @@ fn build_ui(app: &Application) {
 
     // Trigger check
     glib::MainContext::default().spawn_local(async move {
-        let ollama = state_conn.lock().unwrap().ollama.clone();
+        let ollama = state_conn.locked().ollama.clone();
         match ollama.list_local_models().await {
             Ok(models) => {
                 {
-                    let mut s = state_conn.lock().unwrap();
+                    let mut s = state_conn.locked();
                     s.available_models = models.into_iter().map(|m| m.name).collect();
                 }
                 root_stack_c.set_visible_child_name("main");
+                check_connection();
             }
             Err(_) => {
                 root_stack_c.set_visible_child_name("error");
@@ -1768,5 +2186,11 @@ fn build_ui(app: &Application) {
         }
     });
 
-    window.present();
+    let start_hidden = {
+        let s = state.locked();
+        at_login && s.settings.run_in_background && s.settings.start_hidden
+    };
+    if !start_hidden {
+        window.present();
+    }
 }
@@ -0,0 +1,125 @@
+use gtk4 as gtk;
+use gtk::glib;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{AppError, Context};
+use crate::state::Settings;
+use crate::utils::{backup_path, write_atomic};
+
+/// Schema version written to `settings.json`. Bump it together with a new
+/// entry in `MIGRATIONS` whenever a change can't be covered by `#[serde(default)]`.
+pub const SETTINGS_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a version `n` file to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[v0_to_v1];
+
+/// Files written before versioning: fill the fields that used to be
+/// optional in practice but are required by the structs.
+fn v0_to_v1(settings: &mut Map<String, Value>) {
+    fill(settings, "ollama_endpoint", Value::from("http://localhost:11434"));
+    if !settings.get("agents").is_some_and(Value::is_array) {
+        if let Ok(agents) = serde_json::to_value(Settings::default().agents) {
+            settings.insert("agents".to_string(), agents);
+        }
+    }
+    for agent in array_objects(settings, "agents") {
+        for key in ["name", "model", "system_prompt", "description"] {
+            fill(agent, key, Value::from(""));
+        }
+    }
+    for profile in array_objects(settings, "profiles") {
+        for key in ["name", "first_name", "last_name", "email", "phone", "location", "bio"] {
+            fill(profile, key, Value::from(""));
+        }
+        if profile.get("id").and_then(Value::as_str).is_none_or(str::is_empty) {
+            profile.insert("id".to_string(), Value::from(glib::uuid_string_random().as_str()));
+        }
+    }
+}
+
+fn fill(object: &mut Map<String, Value>, key: &str, value: Value) {
+    if object.get(key).is_none_or(Value::is_null) {
+        object.insert(key.to_string(), value);
+    }
+}
+
+fn array_objects<'a>(object: &'a mut Map<String, Value>, key: &str) -> impl Iterator<Item = &'a mut Map<String, Value>> {
+    object
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Brings a settings object of any older version up to `SETTINGS_VERSION`.
+/// Returns the version it started from.
+pub fn upgrade(value: &mut Value) -> Result<u32, String> {
+    let settings = value.as_object_mut().ok_or("settings are not a JSON object")?;
+    let version = settings.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(settings);
+    }
+    if version < SETTINGS_VERSION {
+        settings.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    Ok(version)
+}
+
+/// Loads `settings.json`, upgrading older files in place. A file that can't
+/// be read is kept next to the original instead of being overwritten later,
+/// and the returned error tells the user what happened.
+pub fn load(path: &Path) -> (Settings, Option<AppError>) {
+    if !path.exists() {
+        return (Settings::default(), None);
+    }
+
+    let (settings, version) = match read(path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            let notice = set_aside(path, &e);
+            return match read(&backup_path(path)) {
+                Ok((settings, _)) => (settings, Some(AppError::new("Settings could not be read and were restored from the last backup", e))),
+                Err(_) => (Settings::default(), Some(notice)),
+            };
+        }
+    };
+
+    if version < SETTINGS_VERSION {
+        // Keep the pre-migration file around in case the upgrade lost something
+        let _ = fs::copy(path, path.with_extension(format!("v{}.json", version)));
+        let written = serde_json::to_string(&settings).context("Failed to save upgraded settings")
+            .and_then(|json| write_atomic(path, json).context("Failed to save upgraded settings"));
+        return (settings, written.err());
+    }
+    if version > SETTINGS_VERSION {
+        return (settings, Some(AppError::new(
+            "Settings were saved by a newer version of Arch LLM; options it added are ignored",
+            format!("settings.json has version {}, this version understands {}", version, SETTINGS_VERSION),
+        )));
+    }
+    (settings, None)
+}
+
+fn read(path: &Path) -> Result<(Settings, u32), String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut value = serde_json::from_str::<Value>(&data).map_err(|e| e.to_string())?;
+    let version = upgrade(&mut value)?;
+    let settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    Ok((settings, version))
+}
+
+fn set_aside(path: &Path, error: &str) -> AppError {
+    let backup = path.with_extension("json.broken");
+    match fs::copy(path, &backup) {
+        Ok(_) => AppError::new(
+            format!("Settings could not be read; defaults are in use and the old file was kept as {}", backup.display()),
+            error,
+        ),
+        Err(e) => AppError::new("Settings could not be read and the old file could not be kept", format!("{}\n{}", error, e)),
+    }
+}
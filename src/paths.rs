@@ -0,0 +1,64 @@
+use directories::ProjectDirs;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Environment variable naming a directory to keep everything in, like `--config-dir`.
+const DIR_ENV: &str = "ARCH_LLM_DIR";
+/// A directory with this name next to the executable turns on portable mode.
+const PORTABLE_DIR: &str = "arch-llm-data";
+
+static DIRS: OnceLock<Option<AppDirs>> = OnceLock::new();
+
+/// Where settings and data are kept.
+pub struct AppDirs {
+    pub config: PathBuf,
+    pub data: PathBuf,
+    /// Not the usual XDG directories, so this instance is a separate profile
+    pub custom: bool,
+}
+
+/// Picks the directories once, before anything reads or writes them: the
+/// first of `--config-dir DIR`, `$ARCH_LLM_DIR`, portable mode (`--portable`
+/// or an existing `arch-llm-data` directory beside the executable) and the
+/// XDG directories. A custom directory holds settings and data together.
+pub fn init(args: impl IntoIterator<Item = OsString>) -> Option<&'static AppDirs> {
+    DIRS.get_or_init(|| {
+        let mut args = args.into_iter().skip(1);
+        let mut flag_dir = None;
+        let mut portable = false;
+        while let Some(arg) = args.next() {
+            let arg = arg.to_string_lossy().to_string();
+            if arg == "--" {
+                break;
+            } else if arg == "--portable" {
+                portable = true;
+            } else if arg == "--config-dir" {
+                flag_dir = args.next().map(PathBuf::from);
+            } else if let Some(dir) = arg.strip_prefix("--config-dir=") {
+                flag_dir = Some(PathBuf::from(dir));
+            }
+        }
+        let env_dir = std::env::var_os(DIR_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from);
+        let portable_dir = std::env::current_exe().ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(PORTABLE_DIR)))
+            .filter(|dir| portable || dir.is_dir());
+
+        match flag_dir.or(env_dir).or(portable_dir) {
+            Some(dir) => {
+                let dir = std::path::absolute(&dir).unwrap_or(dir);
+                Some(AppDirs { config: dir.clone(), data: dir, custom: true })
+            }
+            None => ProjectDirs::from("org", "archllm", "arch-llm").map(|dirs| AppDirs {
+                config: dirs.config_dir().to_path_buf(),
+                data: dirs.data_dir().to_path_buf(),
+                custom: false,
+            }),
+        }
+    }).as_ref()
+}
+
+/// The directories chosen by `init`, `None` before it or without a home directory.
+pub fn dirs() -> Option<&'static AppDirs> {
+    DIRS.get().and_then(Option::as_ref)
+}
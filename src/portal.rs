@@ -0,0 +1,156 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::error::{AppResult, Context};
+
+pub const PORTAL_BUS: &str = "org.freedesktop.portal.Desktop";
+pub const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_REQUEST: &str = "org.freedesktop.portal.Request";
+const PORTAL_SCREENSHOT: &str = "org.freedesktop.portal.Screenshot";
+const PORTAL_GLOBAL_SHORTCUTS: &str = "org.freedesktop.portal.GlobalShortcuts";
+
+/// Asks the screenshot portal for an interactive capture, so the user can
+/// pick a region or window, and returns the path of the saved image, or
+/// `None` if they cancelled.
+pub async fn screenshot(bus: &gio::DBusConnection) -> Result<Option<PathBuf>, String> {
+    let token = new_token();
+    let mut options: HashMap<&str, glib::Variant> = HashMap::new();
+    options.insert("handle_token", token.to_variant());
+    options.insert("interactive", true.to_variant());
+    options.insert("modal", true.to_variant());
+    let Some(results) = request(bus, PORTAL_SCREENSHOT, "Screenshot", &token, ("", options).to_variant()).await? else {
+        return Ok(None);
+    };
+    let uri = results.get("uri")
+        .and_then(|uri| uri.get::<String>())
+        .ok_or("The portal returned no image")?;
+    gio::File::for_uri(&uri).path().map(Some).ok_or_else(|| format!("Screenshot is not a local file: {}", uri))
+}
+
+/// Registers a desktop-wide shortcut through the global shortcuts portal and
+/// calls `on_activated` whenever it is pressed. The desktop lets the user
+/// confirm or change `preferred_trigger` (e.g. `"<Super>space"`). Keep the
+/// returned subscription alive for as long as the shortcut should work.
+pub async fn bind_global_shortcut(
+    bus: &gio::DBusConnection,
+    id: &str,
+    description: &str,
+    preferred_trigger: &str,
+    on_activated: impl Fn() + 'static,
+) -> Result<gio::SignalSubscription, String> {
+    let token = new_token();
+    let mut options: HashMap<&str, glib::Variant> = HashMap::new();
+    options.insert("handle_token", token.to_variant());
+    options.insert("session_handle_token", new_token().to_variant());
+    let results = request(bus, PORTAL_GLOBAL_SHORTCUTS, "CreateSession", &token, (options,).to_variant())
+        .await?
+        .ok_or("Cancelled")?;
+    let session = results.get("session_handle")
+        .and_then(|handle| handle.str().map(str::to_string))
+        .ok_or("The portal returned no session")?;
+    let session_path = glib::variant::ObjectPath::try_from(session.clone()).map_err(|e| e.to_string())?;
+
+    let mut shortcut: HashMap<&str, glib::Variant> = HashMap::new();
+    shortcut.insert("description", description.to_variant());
+    shortcut.insert("preferred_trigger", preferred_trigger.to_variant());
+    let token = new_token();
+    let mut options: HashMap<&str, glib::Variant> = HashMap::new();
+    options.insert("handle_token", token.to_variant());
+    let parameters = (session_path, vec![(id, shortcut)], "", options).to_variant();
+    request(bus, PORTAL_GLOBAL_SHORTCUTS, "BindShortcuts", &token, parameters)
+        .await?
+        .ok_or("Cancelled")?;
+
+    let id = id.to_string();
+    Ok(bus.subscribe_to_signal(
+        Some(PORTAL_BUS),
+        Some(PORTAL_GLOBAL_SHORTCUTS),
+        Some("Activated"),
+        Some(PORTAL_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |signal| {
+            let handle = signal.parameters.child_value(0);
+            let shortcut = signal.parameters.child_value(1);
+            if handle.str() == Some(session.as_str()) && shortcut.str() == Some(id.as_str()) {
+                on_activated();
+            }
+        },
+    ))
+}
+
+/// Reads a file picked with `gtk::FileDialog`, returning its display name and
+/// contents. Sandboxed, the file chooser portal hands out document portal
+/// files, and files on phones or network shares have no local path at all,
+/// so this goes through GIO rather than `std::fs`.
+pub fn read_chosen_file(file: &gio::File) -> AppResult<(String, Vec<u8>)> {
+    let name = file.query_info("standard::display-name", gio::FileQueryInfoFlags::NONE, gio::Cancellable::NONE)
+        .map(|info| info.display_name().to_string())
+        .ok()
+        .or_else(|| file.basename().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| file.uri().to_string());
+    let (bytes, _) = file.load_contents(gio::Cancellable::NONE).context(&format!("Failed to read {}", name))?;
+    Ok((name, bytes.to_vec()))
+}
+
+/// Writes a file picked with `gtk::FileDialog`, see `read_chosen_file`.
+pub fn write_chosen_file(file: &gio::File, contents: impl AsRef<[u8]>, summary: &str) -> AppResult<()> {
+    file.replace_contents(contents.as_ref(), None, false, gio::FileCreateFlags::REPLACE_DESTINATION, gio::Cancellable::NONE)
+        .map(|_| ())
+        .context(summary)
+}
+
+fn new_token() -> String {
+    format!("arch_llm_{}", glib::random_int())
+}
+
+/// Calls a portal method following the Request pattern: the reply only
+/// carries a request handle and the results arrive in its `Response` signal.
+/// `token` is the `handle_token` the caller put into the method's options.
+/// Returns `None` when the user cancelled.
+async fn request(
+    bus: &gio::DBusConnection,
+    interface: &str,
+    method: &str,
+    token: &str,
+    parameters: glib::Variant,
+) -> Result<Option<HashMap<String, glib::Variant>>, String> {
+    // Subscribe before calling so a fast response can't be missed
+    let sender = bus.unique_name().ok_or("Not connected to the session bus")?;
+    let handle = format!("{}/request/{}/{}", PORTAL_PATH, sender.trim_start_matches(':').replace('.', "_"), token);
+    let (tx, rx) = async_channel::bounded(1);
+    let _subscription = bus.subscribe_to_signal(
+        Some(PORTAL_BUS),
+        Some(PORTAL_REQUEST),
+        Some("Response"),
+        Some(&handle),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |signal| {
+            let _ = tx.try_send(signal.parameters.get::<(u32, HashMap<String, glib::Variant>)>());
+        },
+    );
+
+    bus.call_future(
+        Some(PORTAL_BUS),
+        PORTAL_PATH,
+        interface,
+        method,
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match rx.recv().await.map_err(|e| e.to_string())? {
+        Some((0, results)) => Ok(Some(results)),
+        Some((1, _)) => Ok(None),
+        _ => Err("The portal request failed".to_string()),
+    }
+}
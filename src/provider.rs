@@ -0,0 +1,969 @@
+use futures_util::{Stream, StreamExt};
+use ollama_rs::error::OllamaError;
+use ollama_rs::generation::chat::request::ChatMessageRequest;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use ollama_rs::generation::completion::request::GenerationRequest;
+use ollama_rs::generation::parameters::{FormatType, JsonStructure};
+use ollama_rs::generation::tools::{ToolCall, ToolCallFunction, ToolInfo};
+use ollama_rs::models::ModelOptions;
+use ollama_rs::Ollama;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::json;
+use std::fs;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use crate::state::{ChatEvent, CustomHeader, ProviderConfig, ProviderKind, Timeouts, TlsOptions};
+use crate::utils::{image_data_uri, normalize_url, raw_prompt};
+
+/// Attempts made at a chat request before a transient failure is shown.
+pub const REQUEST_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for each one after.
+pub const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Converts configured headers into a `HeaderMap`, skipping invalid entries.
+pub fn header_map(headers: &[CustomHeader]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for header in headers {
+        match (HeaderName::from_bytes(header.name.trim().as_bytes()), HeaderValue::from_str(header.value.trim())) {
+            (Ok(name), Ok(value)) => {
+                map.append(name, value);
+            }
+            _ => tracing::warn!("Ignoring invalid header: {}", header.name),
+        }
+    }
+    map
+}
+
+/// Builds the HTTP client used for an endpoint, trusting the configured CA
+/// bundle and optionally accepting invalid (e.g. self-signed) certificates.
+/// The response timeout bounds the wait between reads, so long streamed
+/// replies are not cut off.
+pub fn http_client(tls: &TlsOptions, timeouts: &Timeouts) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    if timeouts.connect_secs > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(timeouts.connect_secs));
+    }
+    if timeouts.response_secs > 0 {
+        builder = builder.read_timeout(Duration::from_secs(timeouts.response_secs));
+    }
+    if tls.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(path) = tls.ca_cert_path.as_ref().filter(|p| !p.trim().is_empty()) {
+        match fs::read(path.trim()).map_err(|e| e.to_string())
+            .and_then(|pem| reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| e.to_string()))
+        {
+            Ok(certs) => {
+                for cert in certs {
+                    builder = builder.add_root_certificate(cert);
+                }
+            }
+            Err(e) => tracing::error!("Failed to load CA certificate {}: {}", path, e),
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        tracing::error!("Failed to build HTTP client: {}", e);
+        reqwest::Client::new()
+    })
+}
+
+/// Builds an Ollama client for `endpoint` that sends `headers` with every
+/// request. Returns `None` when the endpoint is not a valid URL.
+pub fn ollama_client(endpoint: &str, headers: &[CustomHeader], tls: &TlsOptions, timeouts: &Timeouts) -> Option<Ollama> {
+    let url = url::Url::parse(&normalize_url(endpoint)).ok()?;
+    let mut ollama = match url.port_or_known_default() {
+        Some(port) => Ollama::new_with_client(url, port, http_client(tls, timeouts)),
+        None => Ollama::from_url(url),
+    };
+    ollama.set_headers(Some(header_map(headers)));
+    Some(ollama)
+}
+
+/// A piece of a streamed response: text, and any tool calls the model made.
+#[derive(Default)]
+pub struct StreamChunk {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Limits an agent puts on its replies; unset fields keep the server's defaults.
+#[derive(Clone, Default, Debug)]
+pub struct GenerationOptions {
+    pub stop: Vec<String>,
+    /// Maximum tokens to generate
+    pub num_predict: Option<i32>,
+    /// Fixed sampling seed, for reproducible replies
+    pub seed: Option<i32>,
+    /// Layers offloaded to the GPU (Ollama only)
+    pub num_gpu: Option<u32>,
+    /// GPU holding what isn't split when the model spans several (Ollama
+    /// only; not in ollama-rs's `ModelOptions`, see `extra_options`)
+    pub main_gpu: Option<u32>,
+    /// Context window to load the model with (Ollama only), instead of the
+    /// server's default, which cuts longer prompts from the start
+    pub num_ctx: Option<u64>,
+    /// Structured output: `"json"` or a JSON schema
+    pub format: Option<serde_json::Value>,
+}
+
+impl GenerationOptions {
+    /// The options as Ollama takes them, `None` when nothing is set.
+    fn model_options(&self) -> Option<ModelOptions> {
+        if self.stop.is_empty() && self.num_predict.is_none() && self.seed.is_none() && self.num_gpu.is_none() && self.num_ctx.is_none() {
+            return None;
+        }
+        let mut options = ModelOptions::default();
+        if !self.stop.is_empty() {
+            options = options.stop(self.stop.clone());
+        }
+        if let Some(num_predict) = self.num_predict {
+            options = options.num_predict(num_predict);
+        }
+        if let Some(seed) = self.seed {
+            options = options.seed(seed);
+        }
+        if let Some(num_gpu) = self.num_gpu {
+            options = options.num_gpu(num_gpu);
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            options = options.num_ctx(num_ctx);
+        }
+        Some(options)
+    }
+
+    /// Options ollama-rs's `ModelOptions` has no field for, which
+    /// `OllamaServer` adds to the request's `options` by hand.
+    fn extra_options(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut options = serde_json::Map::new();
+        if let Some(main_gpu) = self.main_gpu {
+            options.insert("main_gpu".to_string(), json!(main_gpu));
+        }
+        options
+    }
+
+    fn format_type(&self) -> Option<FormatType> {
+        match self.format.as_ref()? {
+            serde_json::Value::String(_) => Some(FormatType::Json),
+            schema => match serde_json::from_value(schema.clone()) {
+                Ok(schema) => Some(FormatType::StructuredJson(Box::new(JsonStructure::new_for_schema(schema)))),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid JSON schema: {}", e);
+                    Some(FormatType::Json)
+                }
+            },
+        }
+    }
+}
+
+/// Stream of response chunks produced by a chat request.
+pub type ChunkStream = Pin<Box<dyn Stream<Item = Result<StreamChunk, String>> + Send>>;
+
+/// A chat backend that can list its models and answer conversations.
+pub trait Provider {
+    fn list_models(&self) -> impl Future<Output = Result<Vec<String>, String>> + Send;
+    fn chat(&self, model: String, messages: Vec<ChatMessage>) -> impl Future<Output = Result<String, String>> + Send;
+    /// Streams a reply; `tools` may be empty when the agent has tools disabled.
+    fn chat_stream(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: GenerationOptions) -> impl Future<Output = Result<ChunkStream, String>> + Send;
+}
+
+impl Provider for Ollama {
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        self.list_local_models()
+            .await
+            .map(|models| models.into_iter().map(|m| m.name).collect())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    async fn chat(&self, model: String, messages: Vec<ChatMessage>) -> Result<String, String> {
+        self.send_chat_messages(ChatMessageRequest::new(model, messages))
+            .await
+            .map(|res| res.message.content)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    async fn chat_stream(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: GenerationOptions) -> Result<ChunkStream, String> {
+        let stream = self.send_chat_messages_stream(chat_request(model, messages, tools, &options))
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(Box::pin(stream.map(|res| {
+            res.map(|r| StreamChunk { content: r.message.content, tool_calls: r.message.tool_calls })
+                .map_err(|_| "Failed to read response stream".to_string())
+        })))
+    }
+}
+
+fn chat_request(model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: &GenerationOptions) -> ChatMessageRequest {
+    let mut request = ChatMessageRequest::new(model, messages).tools(tools);
+    if let Some(format) = options.format_type() {
+        request = request.format(format);
+    }
+    if let Some(options) = options.model_options() {
+        request = request.options(options);
+    }
+    request
+}
+
+fn generation_request(model: String, prompt: String, options: &GenerationOptions) -> GenerationRequest<'static> {
+    let mut request = GenerationRequest::new(model, prompt).raw(true);
+    if let Some(format) = options.format_type() {
+        request = request.format(format);
+    }
+    if let Some(options) = options.model_options() {
+        request = request.options(options);
+    }
+    request
+}
+
+/// An Ollama server: the ollama-rs client, and the HTTP client and headers
+/// it was built with, for requests carrying options ollama-rs can't
+/// (`GenerationOptions::extra_options`).
+#[derive(Clone, Default)]
+pub struct OllamaServer {
+    /// As configured; with a model, the key of its `AppState::context_lengths`
+    pub endpoint: String,
+    pub client: Ollama,
+    http: reqwest::Client,
+    headers: HeaderMap,
+}
+
+impl OllamaServer {
+    pub fn new(endpoint: &str, headers: &[CustomHeader], tls: &TlsOptions, timeouts: &Timeouts) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            client: ollama_client(endpoint, headers, tls, timeouts).unwrap_or_default(),
+            http: http_client(tls, timeouts),
+            headers: header_map(headers),
+        }
+    }
+
+    /// Posts ollama-rs's `request` to `path` with `extra` added to its
+    /// options, and streams the reply one JSON line at a time.
+    async fn send_stream(&self, path: &str, request: &impl serde::Serialize, extra: serde_json::Map<String, serde_json::Value>) -> Result<ChunkStream, String> {
+        let mut body = serde_json::to_value(request).map_err(|e| e.to_string())?;
+        body["stream"] = json!(true);
+        match body["options"].as_object_mut() {
+            Some(options) => options.extend(extra),
+            None => body["options"] = serde_json::Value::Object(extra),
+        }
+        let res = self.http.post(format!("{}{}", self.client.url_str(), path))
+            .headers(self.headers.clone())
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            return Err(format!("{}: {}", status, text));
+        }
+        let lines = futures_util::stream::unfold((Box::pin(res.bytes_stream()), Vec::new()), |(mut bytes, mut buffer)| async move {
+            loop {
+                if let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=pos).collect();
+                    return Some((Ok(line), (bytes, buffer)));
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(_)) => return Some((Err("Failed to read response stream".to_string()), (bytes, buffer))),
+                    None if buffer.is_empty() => return None,
+                    None => return Some((Ok(std::mem::take(&mut buffer)), (bytes, buffer))),
+                }
+            }
+        });
+        Ok(Box::pin(lines.filter_map(|line| async move {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let line = String::from_utf8_lossy(&line);
+            (!line.trim().is_empty()).then(|| stream_line_chunk(&line))
+        })))
+    }
+
+    async fn chat_stream(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: GenerationOptions) -> Result<ChunkStream, String> {
+        let extra = options.extra_options();
+        if extra.is_empty() {
+            return self.client.chat_stream(model, messages, tools, options).await;
+        }
+        self.send_stream("api/chat", &chat_request(model, messages, tools, &options), extra).await
+    }
+
+    async fn generate_stream(&self, model: String, prompt: String, options: &GenerationOptions) -> Result<ChunkStream, String> {
+        let extra = options.extra_options();
+        let request = generation_request(model, prompt, options);
+        if !extra.is_empty() {
+            return self.send_stream("api/generate", &request, extra).await;
+        }
+        let stream = self.client.generate_stream(request)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(Box::pin(stream.map(|res| {
+            res.map(|responses| StreamChunk {
+                content: responses.into_iter().map(|r| r.response).collect(),
+                tool_calls: Vec::new(),
+            })
+            .map_err(|_| "Failed to read response stream".to_string())
+        })))
+    }
+}
+
+/// One line of an Ollama stream: a chat `message`, or a raw completion's
+/// `response`; an `error` line fails the reply.
+fn stream_line_chunk(line: &str) -> Result<StreamChunk, String> {
+    let value: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    if let Some(error) = value["error"].as_str() {
+        return Err(error.to_string());
+    }
+    let message = &value["message"];
+    Ok(StreamChunk {
+        content: message["content"].as_str().or(value["response"].as_str()).unwrap_or_default().to_string(),
+        tool_calls: serde_json::from_value(message["tool_calls"].clone()).unwrap_or_default(),
+    })
+}
+
+/// Pulls `model` with the streaming API, so progress keeps arriving and the
+/// response timeout isn't hit on large downloads. `progress` gets status
+/// lines such as "pulling 6a0746a1ec1a 42%".
+pub async fn pull_model(ollama: &Ollama, model: &str, mut progress: impl FnMut(String)) -> Result<(), String> {
+    let mut stream = ollama.pull_model_stream(model.to_string(), false)
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(status) = stream.next().await {
+        match status {
+            Ok(status) => match (status.completed, status.total) {
+                (Some(completed), Some(total)) if total > 0 => progress(format!("{} {}%", status.message, completed * 100 / total)),
+                _ => progress(status.message),
+            },
+            // A chunk can end mid-line; the next status line still parses
+            Err(OllamaError::JsonError(_)) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// Whether a reachable Ollama server lacks `model`, i.e. pulling it would
+/// fix a failed request. Names without a tag mean `latest`.
+pub async fn model_missing(ollama: &Ollama, model: &str) -> bool {
+    let Ok(models) = ollama.list_models().await else { return false };
+    let tagged = if model.contains(':') { model.to_string() } else { format!("{}:latest", model) };
+    !models.iter().any(|m| m == model || *m == tagged)
+}
+
+/// Largest context window asked for on a model's behalf. Models trained
+/// with 128K tokens or more would otherwise need far more memory for their
+/// KV cache than most GPUs have.
+const MAX_AUTO_CONTEXT: u64 = 32768;
+
+/// Context window to run an installed Ollama model with: the `num_ctx` its
+/// Modelfile sets, or else the length it was trained with, up to
+/// `MAX_AUTO_CONTEXT`. Chat requests pass it as `num_ctx`.
+pub async fn context_length(ollama: &Ollama, model: &str) -> Option<u64> {
+    let info = ollama.show_model_info(model.to_string()).await.ok()?;
+    let num_ctx = info.parameters.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        (parts.next() == Some("num_ctx")).then(|| parts.next()?.parse().ok()).flatten()
+    });
+    num_ctx.or_else(|| info.model_info.iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64())
+        .map(|trained| trained.min(MAX_AUTO_CONTEXT)))
+}
+
+/// Something an installed model can do, shown as a badge next to it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Capability {
+    Vision,
+    Tools,
+    Embedding,
+    Code,
+}
+
+impl Capability {
+    pub fn label(self) -> &'static str {
+        match self {
+            Capability::Vision => "Vision",
+            Capability::Tools => "Tools",
+            Capability::Embedding => "Embedding",
+            Capability::Code => "Code",
+        }
+    }
+}
+
+/// Name parts of code models. Ollama reports no code capability, only
+/// fill-in-the-middle ("insert"), which most code models but not all have.
+const CODE_MODEL_HINTS: &[&str] = &["code", "devstral"];
+
+/// What an installed Ollama model can do, from the capabilities the server
+/// reports (older servers report none) and its name.
+pub async fn model_capabilities(ollama: &Ollama, model: &str) -> Option<Vec<Capability>> {
+    let info = ollama.show_model_info(model.to_string()).await.ok()?;
+    let reported = |name: &str| info.capabilities.iter().any(|c| c == name);
+    let name = model.to_lowercase();
+    let mut capabilities = Vec::new();
+    if reported("vision") {
+        capabilities.push(Capability::Vision);
+    }
+    if reported("tools") {
+        capabilities.push(Capability::Tools);
+    }
+    if reported("embedding") {
+        capabilities.push(Capability::Embedding);
+    }
+    if reported("insert") || CODE_MODEL_HINTS.iter().any(|hint| name.contains(hint)) {
+        capabilities.push(Capability::Code);
+    }
+    Some(capabilities)
+}
+
+/// Server features the app relies on, with the Ollama release that added them.
+const VERSION_REQUIREMENTS: &[(&str, (u32, u32, u32))] = &[
+    ("loaded model status (/api/ps)", (0, 1, 38)),
+    ("tool calling", (0, 3, 0)),
+];
+
+/// Parses versions like "0.3.12" or "v0.4.0-rc1".
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let core = version.trim().trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next().flatten().unwrap_or(0), parts.next().flatten().unwrap_or(0)))
+}
+
+/// Features a server at `version` is too old for, e.g. "tool calling (needs
+/// 0.3.0)". Empty when the version can't be parsed, such as a dev build.
+pub fn missing_features(version: &str) -> Vec<String> {
+    let Some(version) = parse_version(version) else { return Vec::new() };
+    VERSION_REQUIREMENTS.iter()
+        .filter(|(_, required)| version < *required)
+        .map(|(feature, (major, minor, patch))| format!("{} (needs {}.{}.{})", feature, major, minor, patch))
+        .collect()
+}
+
+/// Queries the server version of an Ollama endpoint, returning it together
+/// with the round-trip latency of the request.
+pub async fn ollama_version(endpoint: &str, headers: &[CustomHeader], tls: &TlsOptions) -> Result<(String, Duration), String> {
+    let url = format!("{}/api/version", normalize_url(endpoint).trim_end_matches('/'));
+    let started = Instant::now();
+    let res = http_client(tls, &Timeouts::default())
+        .get(url)
+        .headers(header_map(headers))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
+    }
+    let latency = started.elapsed();
+    let value: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    Ok((value["version"].as_str().unwrap_or("unknown").to_string(), latency))
+}
+
+/// A model an Ollama server holds in memory, from `/api/ps`.
+#[derive(Debug, Clone)]
+pub struct LoadedModel {
+    pub name: String,
+    /// Bytes in memory altogether
+    pub size: u64,
+    /// Of those, the bytes on the GPU
+    pub size_vram: u64,
+    /// When the server unloads it if unused, as RFC 3339
+    pub expires_at: String,
+}
+
+/// The models an Ollama endpoint has loaded, and where their memory is.
+pub async fn loaded_models(endpoint: &str, headers: &[CustomHeader], tls: &TlsOptions) -> Result<Vec<LoadedModel>, String> {
+    let url = format!("{}/api/ps", normalize_url(endpoint).trim_end_matches('/'));
+    let res = http_client(tls, &Timeouts::default())
+        .get(url)
+        .headers(header_map(headers))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
+    }
+    let value: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    Ok(value["models"].as_array().into_iter().flatten().map(|model| LoadedModel {
+        name: model["name"].as_str().or_else(|| model["model"].as_str()).unwrap_or_default().to_string(),
+        size: model["size"].as_u64().unwrap_or(0),
+        size_vram: model["size_vram"].as_u64().unwrap_or(0),
+        expires_at: model["expires_at"].as_str().unwrap_or_default().to_string(),
+    }).collect())
+}
+
+/// Asks an Ollama endpoint to drop `model` from memory right away.
+pub async fn unload_model(endpoint: &str, headers: &[CustomHeader], tls: &TlsOptions, model: &str) -> Result<(), String> {
+    let url = format!("{}/api/generate", normalize_url(endpoint).trim_end_matches('/'));
+    let res = http_client(tls, &Timeouts::default())
+        .post(url)
+        .headers(header_map(headers))
+        .json(&json!({ "model": model, "keep_alive": 0 }))
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
+    }
+    Ok(())
+}
+
+/// Client for servers speaking the OpenAI chat completions API
+/// (llama.cpp server, LM Studio, vLLM, OpenRouter, ...).
+#[derive(Clone)]
+pub struct OpenAiClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    headers: HeaderMap,
+}
+
+impl OpenAiClient {
+    pub fn new(config: &ProviderConfig, timeouts: &Timeouts) -> Self {
+        Self {
+            client: http_client(&config.tls, timeouts),
+            base_url: config.base_url.trim().trim_end_matches('/').to_string(),
+            api_key: config.api_key.trim().to_string(),
+            headers: header_map(&config.headers),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, format!("{}/{}", self.base_url, path))
+            .headers(self.headers.clone());
+        if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn send_completion(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: &GenerationOptions, stream: bool) -> Result<reqwest::Response, String> {
+        let mut body = json!({
+            "model": model,
+            "messages": openai_messages(&messages),
+            "stream": stream,
+        });
+        if !options.stop.is_empty() {
+            body["stop"] = json!(options.stop);
+        }
+        if let Some(num_predict) = options.num_predict {
+            body["max_tokens"] = json!(num_predict);
+        }
+        if let Some(seed) = options.seed {
+            body["seed"] = json!(seed);
+        }
+        match &options.format {
+            Some(serde_json::Value::String(_)) => body["response_format"] = json!({ "type": "json_object" }),
+            Some(schema) => body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            }),
+            None => {}
+        }
+        if !tools.is_empty() {
+            body["tools"] = tools.iter().map(|t| json!({
+                "type": "function",
+                "function": t.function,
+            })).collect();
+        }
+        let res = self.request(reqwest::Method::POST, "chat/completions")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            return Err(format!("{}: {}", status, text));
+        }
+        Ok(res)
+    }
+}
+
+impl Provider for OpenAiClient {
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let res = self.request(reqwest::Method::GET, "models")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !res.status().is_success() {
+            return Err(res.status().to_string());
+        }
+        let value: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        Ok(value["data"]
+            .as_array()
+            .map(|models| models.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect())
+            .unwrap_or_default())
+    }
+
+    async fn chat(&self, model: String, messages: Vec<ChatMessage>) -> Result<String, String> {
+        let res = self.send_completion(model, messages, Vec::new(), &GenerationOptions::default(), false).await?;
+        let value: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        Ok(value["choices"][0]["message"]["content"].as_str().unwrap_or_default().to_string())
+    }
+
+    async fn chat_stream(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: GenerationOptions) -> Result<ChunkStream, String> {
+        let res = self.send_completion(model, messages, tools, &options, true).await?;
+        let bytes = Box::pin(res.bytes_stream());
+
+        // Server-sent events: one `data: {json}` line per delta, terminated by `data: [DONE]`.
+        // Tool calls arrive as fragments (name first, then pieces of the JSON arguments)
+        // and are handed out as one chunk once the stream ends.
+        let state = SseState { bytes, buffer: Vec::new(), pending_calls: Vec::new(), finished: false };
+        let stream = futures_util::stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            loop {
+                if let Some(pos) = state.buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = state.buffer.drain(..=pos).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    let Some(data) = line.trim().strip_prefix("data:") else { continue };
+                    let data = data.trim();
+                    if data == "[DONE]" {
+                        state.finished = true;
+                        let tool_calls = state.take_tool_calls();
+                        if tool_calls.is_empty() {
+                            return None;
+                        }
+                        return Some((Ok(StreamChunk { content: String::new(), tool_calls }), state));
+                    }
+                    match serde_json::from_str::<serde_json::Value>(data) {
+                        Ok(value) => {
+                            let delta = &value["choices"][0]["delta"];
+                            if let Some(calls) = delta["tool_calls"].as_array() {
+                                for call in calls {
+                                    let index = call["index"].as_u64().unwrap_or(0) as usize;
+                                    if state.pending_calls.len() <= index {
+                                        state.pending_calls.resize(index + 1, (String::new(), String::new()));
+                                    }
+                                    let (name, arguments) = &mut state.pending_calls[index];
+                                    name.push_str(call["function"]["name"].as_str().unwrap_or_default());
+                                    arguments.push_str(call["function"]["arguments"].as_str().unwrap_or_default());
+                                }
+                            }
+                            let content = delta["content"].as_str().unwrap_or_default();
+                            if content.is_empty() {
+                                continue;
+                            }
+                            return Some((Ok(StreamChunk { content: content.to_string(), tool_calls: Vec::new() }), state));
+                        }
+                        Err(e) => {
+                            state.finished = true;
+                            return Some((Err(e.to_string()), state));
+                        }
+                    }
+                }
+                match state.bytes.next().await {
+                    Some(Ok(chunk)) => state.buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => {
+                        state.finished = true;
+                        return Some((Err(e.to_string()), state));
+                    }
+                    None => {
+                        state.finished = true;
+                        let tool_calls = state.take_tool_calls();
+                        if tool_calls.is_empty() {
+                            return None;
+                        }
+                        return Some((Ok(StreamChunk { content: String::new(), tool_calls }), state));
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Parser state for an OpenAI-style SSE response over the byte stream `S`.
+struct SseState<S> {
+    bytes: S,
+    buffer: Vec<u8>,
+    /// Tool calls being assembled, as (name, JSON arguments) by index.
+    pending_calls: Vec<(String, String)>,
+    finished: bool,
+}
+
+impl<S> SseState<S> {
+    fn take_tool_calls(&mut self) -> Vec<ToolCall> {
+        self.pending_calls
+            .drain(..)
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, arguments)| ToolCall {
+                function: ToolCallFunction {
+                    name,
+                    arguments: serde_json::from_str(&arguments).unwrap_or(serde_json::Value::String(arguments)),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Converts a conversation to the OpenAI message format. Tool calls get
+/// synthetic ids, and each tool result is matched to the next unanswered call.
+fn openai_messages(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    let mut unanswered: std::collections::VecDeque<String> = std::collections::VecDeque::new();
+    messages.iter().enumerate().map(|(i, m)| {
+        let mut message = json!({
+            "role": role_name(&m.role),
+            "content": m.content,
+        });
+        if let Some(images) = m.images.as_ref().filter(|images| !images.is_empty()) {
+            let mut parts = vec![json!({ "type": "text", "text": m.content })];
+            parts.extend(images.iter().map(|image| json!({
+                "type": "image_url",
+                "image_url": { "url": image_data_uri(image.to_base64()) },
+            })));
+            message["content"] = json!(parts);
+        }
+        if !m.tool_calls.is_empty() {
+            message["tool_calls"] = m.tool_calls.iter().enumerate().map(|(j, call)| {
+                let id = format!("call_{}_{}", i, j);
+                unanswered.push_back(id.clone());
+                json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {
+                        "name": call.function.name,
+                        "arguments": call.function.arguments.to_string(),
+                    },
+                })
+            }).collect();
+        }
+        if m.role == MessageRole::Tool {
+            message["tool_call_id"] = json!(unanswered.pop_front().unwrap_or_default());
+        }
+        message
+    }).collect()
+}
+
+fn role_name(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        MessageRole::System => "system",
+        MessageRole::Tool => "tool",
+    }
+}
+
+/// Whether a request error looks like a dropped connection or a timeout,
+/// i.e. worth sending again rather than a problem with the request itself.
+pub fn is_transient(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["connection reset", "connectionreset", "connection closed", "connection aborted", "connectionaborted", "broken pipe", "timed out", "timeout", "incompletemessage", "unexpected eof", "failed to read response stream"]
+        .iter()
+        .any(|needle| error.contains(needle))
+}
+
+/// Whether a request error means the server couldn't be reached: refused,
+/// dropped or timed out, rather than answered with an error.
+pub fn is_connection_error(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    is_transient(error) || ["connection refused", "connectionrefused", "tcp connect error", "dns error"].iter().any(|needle| lower.contains(needle))
+}
+
+/// A chat request that failed for good.
+#[derive(Debug)]
+pub struct RequestError {
+    /// Readable text for the chat bubble (`describe_error`)
+    pub message: String,
+    /// The server couldn't be reached (`is_connection_error`)
+    pub connection: bool,
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A short, readable version of a request error for the chat bubble; the
+/// raw text is still logged.
+pub fn describe_error(error: &str) -> String {
+    let lower = error.to_lowercase();
+    if lower.contains("timed out") || lower.contains("timeout") {
+        "The server took too long to respond.".to_string()
+    } else if lower.contains("connection refused") || lower.contains("connectionrefused") || lower.contains("tcp connect error") {
+        "Could not connect to the server.".to_string()
+    } else if is_transient(error) {
+        "The connection to the server was lost.".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+/// A streamed reply, possibly cut off partway.
+pub struct Reply {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+    /// The stream broke off after some of the reply arrived, so `text` is
+    /// incomplete and not worth learning from or titling after
+    pub interrupted: bool,
+}
+
+/// What `stream_reply` sends for these arguments, as JSON for the inspector.
+/// Images are replaced by their count to keep it readable.
+pub fn describe_request(backend: &Backend, model: &str, messages: &[ChatMessage], tools: &[ToolInfo], raw_template: Option<&str>, options: &GenerationOptions) -> serde_json::Value {
+    let mut request = json!({
+        "provider": match backend {
+            Backend::Ollama(_) => "ollama",
+            Backend::OpenAi(_) => "openai",
+        },
+        "model": model,
+    });
+    match raw_template {
+        Some(template) => {
+            request["raw"] = json!(true);
+            request["prompt"] = json!(raw_prompt(template, messages));
+        }
+        None => {
+            request["messages"] = messages.iter().map(|m| {
+                let mut message = json!({
+                    "role": role_name(&m.role),
+                    "content": m.content,
+                });
+                if let Some(images) = m.images.as_ref().filter(|images| !images.is_empty()) {
+                    message["images"] = json!(images.len());
+                }
+                if !m.tool_calls.is_empty() {
+                    message["tool_calls"] = m.tool_calls.iter().map(|call| json!({
+                        "name": call.function.name,
+                        "arguments": call.function.arguments,
+                    })).collect();
+                }
+                message
+            }).collect();
+            if !tools.is_empty() {
+                request["tools"] = serde_json::to_value(tools).unwrap_or_default();
+            }
+        }
+    }
+    if let Some(model_options) = options.model_options() {
+        request["options"] = serde_json::to_value(model_options).unwrap_or_default();
+    }
+    if matches!(backend, Backend::Ollama(_)) {
+        for (name, value) in options.extra_options() {
+            request["options"][name] = value;
+        }
+    }
+    if let Some(format) = &options.format {
+        request["format"] = format.clone();
+    }
+    request
+}
+
+/// Streams one model reply into `sender`, returning its text and tool calls.
+/// Transient failures before anything arrived are retried with exponential
+/// backoff; once text has been shown the reply is kept as far as it got.
+pub async fn stream_reply(
+    backend: &Backend,
+    model: &str,
+    messages: &[ChatMessage],
+    tools: &[ToolInfo],
+    raw_template: Option<&str>,
+    options: &GenerationOptions,
+    sender: &async_channel::Sender<ChatEvent>,
+) -> Result<Reply, RequestError> {
+    let mut attempt = 1;
+    loop {
+        let stream = match raw_template {
+            Some(template) => backend.generate_stream(model.to_string(), raw_prompt(template, messages), options).await,
+            None => backend.chat_stream(model.to_string(), messages.to_vec(), tools.to_vec(), options.clone()).await,
+        };
+        let error = match stream {
+            Ok(mut stream) => {
+                let mut full_response = String::new();
+                let mut tool_calls = Vec::new();
+                let mut failed = None;
+                let mut interrupted = false;
+                while let Some(res) = stream.next().await {
+                    match res {
+                        Ok(chunk) => {
+                            tool_calls.extend(chunk.tool_calls);
+                            if chunk.content.is_empty() { continue; }
+                            full_response.push_str(&chunk.content);
+                            if sender.send(ChatEvent::Chunk(chunk.content)).await.is_err() {
+                                interrupted = true;
+                                break;
+                            }
+                        }
+                        Err(e) if full_response.is_empty() && tool_calls.is_empty() => {
+                            failed = Some(e);
+                            break;
+                        }
+                        Err(e) => {
+                            tracing::warn!("Response stream interrupted: {}", e);
+                            interrupted = true;
+                            break;
+                        }
+                    }
+                }
+                match failed {
+                    Some(e) => e,
+                    None => return Ok(Reply { text: full_response, tool_calls, interrupted }),
+                }
+            }
+            Err(e) => e,
+        };
+
+        tracing::warn!("Chat request failed (attempt {}/{}): {}", attempt, REQUEST_ATTEMPTS, error);
+        if attempt >= REQUEST_ATTEMPTS || !is_transient(&error) {
+            return Err(RequestError { message: describe_error(&error), connection: is_connection_error(&error) });
+        }
+        tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+        attempt += 1;
+        let _ = sender.send(ChatEvent::Retrying(attempt, REQUEST_ATTEMPTS)).await;
+    }
+}
+
+/// The backend an agent talks to, resolved from its provider setting.
+#[derive(Clone)]
+pub enum Backend {
+    Ollama(OllamaServer),
+    OpenAi(OpenAiClient),
+}
+
+impl Backend {
+    pub fn from_config(config: &ProviderConfig, timeouts: &Timeouts) -> Self {
+        match config.kind {
+            ProviderKind::OpenAi => Backend::OpenAi(OpenAiClient::new(config, timeouts)),
+            ProviderKind::Ollama => Backend::Ollama(OllamaServer::new(&config.base_url, &config.headers, &config.tls, timeouts)),
+        }
+    }
+
+    /// Streams a completion of `prompt` as is, without the model's chat template.
+    pub async fn generate_stream(&self, model: String, prompt: String, options: &GenerationOptions) -> Result<ChunkStream, String> {
+        let Backend::Ollama(server) = self else {
+            return Err("Raw completion mode needs an Ollama provider".to_string());
+        };
+        server.generate_stream(model, prompt, options).await
+    }
+}
+
+impl Provider for Backend {
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        match self {
+            Backend::Ollama(server) => server.client.list_models().await,
+            Backend::OpenAi(client) => client.list_models().await,
+        }
+    }
+
+    async fn chat(&self, model: String, messages: Vec<ChatMessage>) -> Result<String, String> {
+        match self {
+            Backend::Ollama(server) => server.client.chat(model, messages).await,
+            Backend::OpenAi(client) => client.chat(model, messages).await,
+        }
+    }
+
+    async fn chat_stream(&self, model: String, messages: Vec<ChatMessage>, tools: Vec<ToolInfo>, options: GenerationOptions) -> Result<ChunkStream, String> {
+        match self {
+            Backend::Ollama(server) => server.chat_stream(model, messages, tools, options).await,
+            Backend::OpenAi(client) => client.chat_stream(model, messages, tools, options).await,
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use ollama_rs::generation::chat::ChatMessage;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::context;
+use crate::controller::Commands;
+use crate::error::LockExt;
+use crate::provider::stream_reply;
+use crate::state::{Agent, AppState, ChatEvent};
+use crate::ui::secrets_prompt;
+use crate::utils::markdown_to_pango;
+
+/// A small Spotlight-style window for one-off questions to the current
+/// agent. The answer streams in below the entry; "Continue in main window"
+/// hands the exchange over as a new conversation. The window isn't added to
+/// the application so that `app.windows()` keeps meaning the main window.
+pub struct QuickAsk {
+    window: gtk::Window,
+    entry: gtk::Entry,
+    answer: gtk::Label,
+    answer_scroll: gtk::ScrolledWindow,
+    continue_btn: gtk::Button,
+    /// The messages sent (system prompt and question) and the answer so far
+    exchange: RefCell<Option<(Vec<ChatMessage>, String)>>,
+    task: RefCell<Option<tokio::task::AbortHandle>>,
+    commands: Commands,
+}
+
+impl QuickAsk {
+    pub fn new(state: Arc<Mutex<AppState>>, commands: Commands, on_continue: impl Fn(Vec<ChatMessage>) + 'static) -> Rc<Self> {
+        let window = gtk::Window::builder()
+            .title("Quick Ask")
+            .decorated(false)
+            .resizable(false)
+            .default_width(640)
+            .hide_on_close(true)
+            .css_classes(["quick-ask"])
+            .build();
+        let content = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(10)
+            .margin_top(14)
+            .margin_bottom(14)
+            .margin_start(14)
+            .margin_end(14)
+            .build();
+        let entry = gtk::Entry::builder()
+            .placeholder_text("Ask anything…")
+            .primary_icon_name("system-search-symbolic")
+            .css_classes(["quick-ask-entry"])
+            .build();
+        let answer = gtk::Label::builder()
+            .xalign(0.0)
+            .yalign(0.0)
+            .wrap(true)
+            .selectable(true)
+            .css_classes(["bot-message"])
+            .build();
+        let answer_scroll = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(420)
+            .child(&answer)
+            .visible(false)
+            .build();
+        let continue_btn = gtk::Button::builder()
+            .label("Continue in main window")
+            .halign(gtk::Align::End)
+            .visible(false)
+            .build();
+        content.append(&entry);
+        content.append(&answer_scroll);
+        content.append(&continue_btn);
+        window.set_child(Some(&content));
+
+        let this = Rc::new(Self {
+            window,
+            entry,
+            answer,
+            answer_scroll,
+            continue_btn,
+            exchange: RefCell::new(None),
+            task: RefCell::new(None),
+            commands,
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.entry.connect_activate(move |entry| {
+            let Some(this) = weak.upgrade() else { return };
+            let text = entry.text().trim().to_string();
+            if !text.is_empty() {
+                this.ask(&state, text);
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.continue_btn.connect_clicked(move |_| {
+            let Some(this) = weak.upgrade() else { return };
+            let Some((mut messages, answer)) = this.exchange.borrow_mut().take() else { return };
+            this.window.close();
+            messages.push(ChatMessage::assistant(answer));
+            on_continue(messages);
+        });
+
+        let keys = gtk::EventControllerKey::new();
+        let window = this.window.clone();
+        keys.connect_key_pressed(move |_, key, _, _| {
+            if key == gtk::gdk::Key::Escape {
+                window.close();
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        this.window.add_controller(keys);
+
+        let weak = Rc::downgrade(&this);
+        this.window.connect_hide(move |_| {
+            if let Some(this) = weak.upgrade() {
+                if let Some(task) = this.task.borrow_mut().take() {
+                    task.abort();
+                }
+            }
+        });
+
+        this
+    }
+
+    /// Opens the window with an empty prompt.
+    pub fn show(&self) {
+        if let Some(task) = self.task.borrow_mut().take() {
+            task.abort();
+        }
+        self.exchange.borrow_mut().take();
+        self.entry.set_text("");
+        self.answer.set_text("");
+        self.answer_scroll.set_visible(false);
+        self.continue_btn.set_visible(false);
+        self.window.present();
+        self.entry.grab_focus();
+    }
+
+    fn ask(self: &Rc<Self>, state: &Arc<Mutex<AppState>>, text: String) {
+        if let Some(task) = self.task.borrow_mut().take() {
+            task.abort();
+        }
+        let agent = match state.locked().current_agent() {
+            Ok(agent) => agent,
+            Err(e) => {
+                self.answer.set_text(&e.to_string());
+                self.answer_scroll.set_visible(true);
+                return;
+            }
+        };
+        let this = self.clone();
+        let state = state.clone();
+        glib::MainContext::default().spawn_local(async move {
+            if let Some((text, _)) = secrets_prompt::check(&this.window, &state, &agent, &text, &[]).await {
+                this.stream(&state, agent, text);
+            }
+        });
+    }
+
+    fn stream(self: &Rc<Self>, state: &Arc<Mutex<AppState>>, agent: Agent, text: String) {
+        let messages = vec![ChatMessage::system(agent.system_prompt.clone()), ChatMessage::user(text)];
+        let raw_template = agent.raw_template().map(str::to_string);
+        let (backend, known_context) = {
+            let s = state.locked();
+            (s.backend_for(&agent), s.known_context_length(&agent))
+        };
+        *self.exchange.borrow_mut() = Some((messages.clone(), String::new()));
+        self.answer.set_text("Thinking...");
+        self.answer_scroll.set_visible(true);
+        self.continue_btn.set_visible(false);
+
+        let (sender, receiver) = async_channel::unbounded();
+        let commands = self.commands.clone();
+        let task = tokio::spawn(async move {
+            let options = context::generation_options(&agent, &backend, known_context, &commands).await;
+            match stream_reply(&backend, &agent.model, &messages, &[], raw_template.as_deref(), &options, &sender).await {
+                Ok(_) => {
+                    let _ = sender.send(ChatEvent::Done(String::new())).await;
+                }
+                Err(e) => {
+                    let _ = sender.send(ChatEvent::Error(e.message)).await;
+                }
+            }
+        });
+        *self.task.borrow_mut() = Some(task.abort_handle());
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Ok(event) = receiver.recv().await {
+                match event {
+                    ChatEvent::Chunk(chunk) => {
+                        let mut exchange = this.exchange.borrow_mut();
+                        let Some((_, answer)) = exchange.as_mut() else { return };
+                        answer.push_str(&chunk);
+                        this.answer.set_markup(&markdown_to_pango(answer));
+                    }
+                    ChatEvent::Retrying(attempt, attempts) => {
+                        this.answer.set_text(&format!("Connection lost, retrying ({}/{})…", attempt, attempts));
+                    }
+                    ChatEvent::Done(_) | ChatEvent::Interrupted(_) => {
+                        this.task.borrow_mut().take();
+                        this.continue_btn.set_visible(this.exchange.borrow().is_some());
+                    }
+                    ChatEvent::Error(e) => {
+                        this.task.borrow_mut().take();
+                        this.exchange.borrow_mut().take();
+                        this.answer.set_text(&format!("Error: {}", e));
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,54 @@
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::state::{AppState, StoredMessage};
+use crate::storage::Storage;
+
+/// How often a streaming reply is snapshotted, in microseconds.
+pub const SAVE_INTERVAL: i64 = 2_000_000;
+
+/// A conversation while it has changes that aren't in history yet, the one
+/// that changed last when several tabs are generating. Written to
+/// `recovery.json` and removed once the turn completes, so finding one at
+/// startup means the last session didn't end cleanly.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub history_id: Option<String>,
+    pub agent_idx: usize,
+    pub messages: Vec<StoredMessage>,
+    /// What the model had streamed of its reply so far.
+    #[serde(default)]
+    pub partial: String,
+}
+
+impl Session {
+    /// Messages to show after restoring, with any partial reply as the last one.
+    pub fn into_messages(self) -> Vec<StoredMessage> {
+        let mut messages = self.messages;
+        if !self.partial.is_empty() {
+            messages.push(StoredMessage::new(ChatMessage::assistant(self.partial)));
+        }
+        messages
+    }
+}
+
+/// Snapshots the conversation in tab `tab_id`, if it is still open.
+pub fn save(state: &AppState, tab_id: u64, partial: &str) {
+    let Some(chat) = state.chats.iter().find(|c| c.tab_id == tab_id) else { return };
+    let session = Session {
+        history_id: chat.history_id.clone(),
+        agent_idx: chat.agent_idx,
+        messages: chat.messages.clone(),
+        partial: partial.to_string(),
+    };
+    state.storage.save_private_json(&state.recovery_path, &session);
+}
+
+pub fn clear(state: &AppState) {
+    state.storage.remove(&state.recovery_path);
+}
+
+pub fn load(storage: &Storage, path: &Path) -> Option<Session> {
+    storage.read_private_json::<Session>(path).filter(|session| session.messages.iter().any(|m| m.message.role != MessageRole::System))
+}
@@ -0,0 +1,87 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+
+const OBJECT_PATH: &str = "/org/archllm/Assistant";
+const INTERFACE: &str = "org.archllm.Assistant";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.archllm.Assistant">
+    <method name="Ask">
+      <arg name="text" type="s" direction="in"/>
+    </method>
+    <method name="NewChat"/>
+    <method name="SetAgent">
+      <arg name="name" type="s" direction="in"/>
+    </method>
+    <method name="ListAgents">
+      <arg name="names" type="as" direction="out"/>
+    </method>
+    <method name="Show"/>
+    <method name="QuickAsk"/>
+  </interface>
+</node>
+"#;
+
+/// What the D-Bus methods do, supplied by the UI.
+pub struct RemoteHandlers {
+    pub ask: Box<dyn Fn(String)>,
+    pub new_chat: Box<dyn Fn()>,
+    /// Returns `false` if no agent has that name.
+    pub set_agent: Box<dyn Fn(&str) -> bool>,
+    pub list_agents: Box<dyn Fn() -> Vec<String>>,
+    pub show: Box<dyn Fn()>,
+    pub quick_ask: Box<dyn Fn()>,
+}
+
+/// Exports `org.archllm.Assistant` at `/org/archllm/Assistant` on the
+/// application's bus name, so scripts can drive the app, e.g.
+/// `gdbus call --session --dest org.archllm.ollama_chat --object-path /org/archllm/Assistant --method org.archllm.Assistant.Ask "…"`.
+pub fn export(connection: &gio::DBusConnection, handlers: RemoteHandlers) -> Result<(), glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface = node.lookup_interface(INTERFACE).expect("Assistant interface in XML");
+
+    connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| match method {
+            "Ask" => {
+                let Some((text,)) = parameters.get::<(String,)>() else {
+                    invocation.return_error(gio::DBusError::InvalidArgs, "Expected a string");
+                    return;
+                };
+                (handlers.ask)(text);
+                invocation.return_value(None);
+            }
+            "NewChat" => {
+                (handlers.new_chat)();
+                invocation.return_value(None);
+            }
+            "SetAgent" => {
+                let Some((name,)) = parameters.get::<(String,)>() else {
+                    invocation.return_error(gio::DBusError::InvalidArgs, "Expected a string");
+                    return;
+                };
+                if (handlers.set_agent)(&name) {
+                    invocation.return_value(None);
+                } else {
+                    invocation.return_error(gio::DBusError::InvalidArgs, &format!("No agent named '{}'", name));
+                }
+            }
+            "ListAgents" => {
+                invocation.return_value(Some(&((handlers.list_agents)(),).to_variant()));
+            }
+            "Show" => {
+                (handlers.show)();
+                invocation.return_value(None);
+            }
+            "QuickAsk" => {
+                (handlers.quick_ask)();
+                invocation.return_value(None);
+            }
+            _ => invocation.return_error(gio::DBusError::UnknownMethod, &format!("Unknown method {}", method)),
+        })
+        .build()?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::crypto::Cipher;
+use crate::history_store;
+use crate::state::ChatHistory;
+
+const OBJECT_PATH: &str = "/org/archllm/SearchProvider";
+const INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+const MAX_RESULTS: usize = 20;
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in"/>
+      <arg type="aa{sv}" name="metas" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Exports a GNOME Shell search provider over the saved conversations. It
+/// reads the history directory on every query, so it also works while the app
+/// only runs as a D-Bus activated service without a window. `on_activate` gets the
+/// id of the chosen conversation, `on_launch` the search terms.
+pub fn export(
+    connection: &gio::DBusConnection,
+    history_dir: PathBuf,
+    on_activate: impl Fn(String) + 'static,
+    on_launch: impl Fn(String) + 'static,
+) -> Result<(), glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface = node.lookup_interface(INTERFACE).expect("SearchProvider2 interface in XML");
+    // Looked up on the first query, in case the history is encrypted
+    let cipher: OnceCell<Option<Cipher>> = OnceCell::new();
+    let load_history = move || history_store::read_all(&history_dir, cipher.get_or_init(Cipher::from_keyring).as_ref());
+
+    connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| match method {
+            "GetInitialResultSet" => {
+                let terms = parameters.child_value(0).get::<Vec<String>>().unwrap_or_default();
+                let ids: Vec<String> = search(&load_history(), &terms).into_iter().map(|h| h.id.clone()).collect();
+                invocation.return_value(Some(&(ids,).to_variant()));
+            }
+            "GetSubsearchResultSet" => {
+                let previous = parameters.child_value(0).get::<Vec<String>>().unwrap_or_default();
+                let terms = parameters.child_value(1).get::<Vec<String>>().unwrap_or_default();
+                let history = load_history();
+                let ids: Vec<String> = search(&history, &terms)
+                    .into_iter()
+                    .filter(|h| previous.contains(&h.id))
+                    .map(|h| h.id.clone())
+                    .collect();
+                invocation.return_value(Some(&(ids,).to_variant()));
+            }
+            "GetResultMetas" => {
+                let ids = parameters.child_value(0).get::<Vec<String>>().unwrap_or_default();
+                let history = load_history();
+                let metas: Vec<HashMap<String, glib::Variant>> = ids.iter()
+                    .filter_map(|id| history.iter().find(|h| &h.id == id))
+                    .map(|item| {
+                        let mut meta = HashMap::new();
+                        meta.insert("id".to_string(), item.id.to_variant());
+                        meta.insert("name".to_string(), item.title.to_variant());
+                        meta.insert("description".to_string(), preview(item).to_variant());
+                        meta.insert("gicon".to_string(), "internet-chat".to_variant());
+                        meta
+                    })
+                    .collect();
+                invocation.return_value(Some(&(metas,).to_variant()));
+            }
+            "ActivateResult" => {
+                if let Some(id) = parameters.child_value(0).get::<String>() {
+                    on_activate(id);
+                }
+                invocation.return_value(None);
+            }
+            "LaunchSearch" => {
+                let terms = parameters.child_value(0).get::<Vec<String>>().unwrap_or_default();
+                on_launch(terms.join(" "));
+                invocation.return_value(None);
+            }
+            _ => invocation.return_error(gio::DBusError::UnknownMethod, &format!("Unknown method {}", method)),
+        })
+        .build()?;
+    Ok(())
+}
+
+/// Conversations whose title, messages or notes contain every term, newest first.
+fn search<'a>(history: &'a [ChatHistory], terms: &[String]) -> Vec<&'a ChatHistory> {
+    let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    history.iter()
+        .rev()
+        .filter(|item| {
+            let text = format!(
+                "{}\n{}\n{}",
+                item.title,
+                item.messages.iter().map(|m| m.message.content.as_str()).collect::<Vec<_>>().join("\n"),
+                item.notes,
+            ).to_lowercase();
+            terms.iter().all(|term| text.contains(term))
+        })
+        .take(MAX_RESULTS)
+        .collect()
+}
+
+/// First line of the first user message, as the result's description.
+fn preview(item: &ChatHistory) -> String {
+    item.messages.iter()
+        .map(|m| &m.message)
+        .find(|m| m.role == ollama_rs::generation::chat::MessageRole::User)
+        .and_then(|m| m.content.lines().find(|line| !line.trim().is_empty()))
+        .map(|line| line.chars().take(80).collect())
+        .unwrap_or_default()
+}
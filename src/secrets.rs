@@ -0,0 +1,158 @@
+use std::ops::Range;
+
+/// Token prefixes of well-known services: (prefix, what it is, shortest length).
+const TOKEN_PREFIXES: &[(&str, &str, usize)] = &[
+    ("sk-", "API key", 24),
+    ("ghp_", "GitHub token", 30),
+    ("gho_", "GitHub token", 30),
+    ("ghu_", "GitHub token", 30),
+    ("ghs_", "GitHub token", 30),
+    ("ghr_", "GitHub token", 30),
+    ("github_pat_", "GitHub token", 40),
+    ("glpat-", "GitLab token", 20),
+    ("xoxb-", "Slack token", 20),
+    ("xoxp-", "Slack token", 20),
+    ("xoxa-", "Slack token", 20),
+    ("hf_", "Hugging Face token", 30),
+    ("AIza", "Google API key", 39),
+];
+
+/// Parts of a setting's name that mark its value as a secret, e.g.
+/// `DB_PASSWORD=…` or `"api_key": "…"`.
+const SECRET_KEY_WORDS: &[&str] = &["PASSWORD", "PASSWD", "SECRET", "TOKEN", "API_KEY", "APIKEY", "ACCESS_KEY", "PRIVATE_KEY", "CREDENTIAL", "AUTHORIZATION"];
+
+/// Something in a message that looks like it shouldn't leave the machine.
+#[derive(Debug, Clone)]
+pub struct Secret {
+    pub kind: &'static str,
+    /// Byte range in the scanned text
+    pub range: Range<usize>,
+}
+
+/// Finds private key blocks, service tokens and secret-looking values in
+/// `KEY=value` lines, in order and without overlaps. It errs towards obvious
+/// cases; it is a safety net, not a guarantee.
+pub fn find(text: &str) -> Vec<Secret> {
+    let mut found = private_keys(text);
+    found.extend(tokens(text));
+    found.extend(assignments(text));
+    found.sort_by_key(|s| (s.range.start, std::cmp::Reverse(s.range.end)));
+    let mut merged: Vec<Secret> = Vec::new();
+    for secret in found {
+        match merged.last() {
+            Some(last) if secret.range.start < last.range.end => {}
+            _ => merged.push(secret),
+        }
+    }
+    merged
+}
+
+/// `text` with every secret `find` reports replaced by a marker such as
+/// "[redacted API key]".
+pub fn redact(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut last = 0;
+    for secret in find(text) {
+        redacted.push_str(&text[last..secret.range.start]);
+        redacted.push_str(&format!("[redacted {}]", secret.kind));
+        last = secret.range.end;
+    }
+    redacted.push_str(&text[last..]);
+    redacted
+}
+
+/// The different kinds among `secrets`, e.g. "API key, private key".
+pub fn describe(secrets: &[Secret]) -> String {
+    let mut kinds: Vec<&str> = Vec::new();
+    for secret in secrets {
+        if !kinds.contains(&secret.kind) {
+            kinds.push(secret.kind);
+        }
+    }
+    kinds.join(", ")
+}
+
+fn private_keys(text: &str) -> Vec<Secret> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("-----BEGIN ").map(|i| i + offset) {
+        let Some(header_end) = text[start..].find('\n').map(|i| i + start) else { break };
+        if !text[start..header_end].contains("PRIVATE KEY") {
+            offset = header_end;
+            continue;
+        }
+        // An unterminated block is redacted to the end, it's still the key
+        let end = text[header_end..].find("-----END ")
+            .and_then(|i| text[header_end + i..].find('\n').map(|j| header_end + i + j).or(Some(text.len())))
+            .unwrap_or(text.len());
+        found.push(Secret { kind: "private key", range: start..end });
+        offset = end;
+    }
+    found
+}
+
+fn tokens(text: &str) -> Vec<Secret> {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    let mut found = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (start, is_token_char(c)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                let word = &text[s..i];
+                if let Some(kind) = token_kind(word) {
+                    found.push(Secret { kind, range: s..i });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn token_kind(word: &str) -> Option<&'static str> {
+    let known = TOKEN_PREFIXES.iter()
+        .find(|(prefix, _, min_len)| word.starts_with(prefix) && word.len() >= *min_len)
+        .map(|(_, kind, _)| *kind);
+    let aws = word.len() == 20
+        && (word.starts_with("AKIA") || word.starts_with("ASIA"))
+        && word.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    known.or(aws.then_some("AWS access key"))
+}
+
+fn assignments(text: &str) -> Vec<Secret> {
+    let mut found = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        if let Some(range) = assignment_value(line) {
+            found.push(Secret { kind: "password or secret", range: line_start + range.start..line_start + range.end });
+        }
+        line_start += line.len();
+    }
+    found
+}
+
+/// The value's range in `line` if it sets a secret-named key.
+fn assignment_value(line: &str) -> Option<Range<usize>> {
+    let separator = line.find(['=', ':'])?;
+    let key = line[..separator].trim().trim_start_matches("export ").trim().trim_matches(['"', '\'']);
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return None;
+    }
+    let key = key.to_ascii_uppercase().replace(['-', '.'], "_");
+    if !SECRET_KEY_WORDS.iter().any(|word| key.contains(word)) {
+        return None;
+    }
+
+    let raw = &line[separator + 1..];
+    let value = raw.trim_end().trim_end_matches(',').trim();
+    let value = value.strip_prefix(['"', '\'']).and_then(|v| v.strip_suffix(['"', '\''])).unwrap_or(value);
+    // Placeholders and references aren't secrets themselves
+    let placeholder = value.starts_with('$') || value.starts_with('<') || value.starts_with("[redacted") || value.chars().all(|c| c == '*' || c == 'x' || c == '.');
+    if value.len() < 4 || placeholder || (value.contains(' ') && !key.contains("AUTHORIZATION")) {
+        return None;
+    }
+    let start = separator + 1 + raw.find(value)?;
+    Some(start..start + value.len())
+}
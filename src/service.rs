@@ -0,0 +1,81 @@
+use ollama_rs::Ollama;
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use crate::error::LockExt;
+use crate::utils::{find_program, is_local_url};
+
+/// How long a freshly started server gets to answer before giving up.
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Lines of `ollama serve` output kept for the server log drawer.
+const SERVE_LOG_LINES: usize = 2000;
+
+/// Output of the `ollama serve` run by `start`, and how many lines it wrote
+/// in all; `None` unless the app started the server that way.
+static SERVE_LOG: Mutex<Option<(VecDeque<String>, u64)>> = Mutex::new(None);
+
+/// Whether the app can start Ollama for `endpoint`: it points at this
+/// machine and the `ollama` binary is installed.
+pub fn can_start(endpoint: &str) -> bool {
+    is_local_url(endpoint) && find_program("ollama").is_some()
+}
+
+/// Starts the local Ollama server through its systemd user unit, or else runs
+/// `ollama serve` in the background, then waits until `ollama` answers.
+pub async fn start(ollama: &Ollama) -> Result<(), String> {
+    let via_systemd = Command::new("systemctl")
+        .args(["--user", "start", "ollama"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success());
+    if !via_systemd {
+        tracing::info!("No ollama user service, running `ollama serve`");
+        let mut child = Command::new("ollama")
+            .arg("serve")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run `ollama serve`: {}", e))?;
+        // Ollama logs to stderr; keep it for the log drawer
+        if let Some(stderr) = child.stderr.take() {
+            *SERVE_LOG.locked() = Some((VecDeque::new(), 0));
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some((log, total)) = SERVE_LOG.locked().as_mut() {
+                        if log.len() == SERVE_LOG_LINES {
+                            log.pop_front();
+                        }
+                        log.push_back(line);
+                        *total += 1;
+                    }
+                }
+            });
+        }
+        // Reap it if it exits, e.g. because the port is taken
+        tokio::spawn(async move { child.wait().await });
+    }
+
+    let deadline = tokio::time::Instant::now() + START_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if ollama.list_local_models().await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Err(format!("Ollama did not respond within {} seconds", START_TIMEOUT.as_secs()))
+}
+
+/// The latest output of the `ollama serve` the app started, with the number
+/// of lines written so far, or `None` if it didn't start one.
+pub fn serve_log() -> Option<(Vec<String>, u64)> {
+    SERVE_LOG.locked().as_ref().map(|(log, total)| (log.iter().cloned().collect(), *total))
+}
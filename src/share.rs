@@ -0,0 +1,67 @@
+use serde_json::json;
+use std::time::Duration;
+
+use crate::state::{ShareService, ShareSettings};
+
+const USER_AGENT: &str = concat!("arch-llm/", env!("CARGO_PKG_VERSION"));
+
+/// Uploads a markdown transcript to the configured service and returns its URL.
+pub async fn upload(settings: &ShareSettings, title: &str, markdown: &str) -> Result<String, String> {
+    match settings.service {
+        ShareService::Paste => upload_paste(&settings.paste_url, markdown).await,
+        ShareService::Gist => upload_gist(&settings.github_token, title, markdown).await,
+    }
+}
+
+/// 0x0.st and compatible services take a multipart `file` field and answer
+/// with the URL as plain text.
+async fn upload_paste(url: &str, markdown: &str) -> Result<String, String> {
+    let boundary = format!("arch-llm-{}", gtk4::glib::uuid_string_random());
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"chat.md\"\r\nContent-Type: text/markdown; charset=utf-8\r\n\r\n{}\r\n--{b}--\r\n",
+        markdown,
+        b = boundary,
+    );
+    let res = reqwest::Client::new()
+        .post(url.trim())
+        .header("User-Agent", USER_AGENT)
+        .header("Content-Type", format!("multipart/form-data; boundary={}", boundary))
+        .timeout(Duration::from_secs(30))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = res.status();
+    let text = res.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("{}: {}", status, text.trim()));
+    }
+    Ok(text.trim().to_string())
+}
+
+/// Creates a secret gist with the transcript.
+async fn upload_gist(token: &str, title: &str, markdown: &str) -> Result<String, String> {
+    if token.trim().is_empty() {
+        return Err("Set a GitHub token in General settings to share as a gist".to_string());
+    }
+    let res = reqwest::Client::new()
+        .post("https://api.github.com/gists")
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/vnd.github+json")
+        .bearer_auth(token.trim())
+        .timeout(Duration::from_secs(30))
+        .json(&json!({
+            "description": title,
+            "public": false,
+            "files": { "chat.md": { "content": markdown } },
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = res.status();
+    let value: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("{}: {}", status, value["message"].as_str().unwrap_or("request failed")));
+    }
+    value["html_url"].as_str().map(str::to_string).ok_or_else(|| "GitHub returned no URL".to_string())
+}
@@ -0,0 +1,150 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use std::cell::{Cell, RefCell};
+use std::ffi::OsStr;
+use std::fs;
+use std::rc::Rc;
+
+use crate::state::SpeechSettings;
+use crate::utils::{find_program, markdown_to_speech};
+
+const SIGCONT: i32 = 18;
+const SIGSTOP: i32 = 19;
+
+/// Piper's usual output rate, used when the voice has no config next to it.
+const DEFAULT_SAMPLE_RATE: u64 = 22050;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SpeechState {
+    Idle,
+    Speaking,
+    Paused,
+}
+
+/// Reads replies aloud by piping piper's raw audio into a player
+/// (`aplay`, or `paplay` when ALSA utils are missing). Speaking again replaces
+/// the current utterance.
+pub struct Speaker {
+    processes: RefCell<Option<(gio::Subprocess, gio::Subprocess)>>,
+    state: Cell<SpeechState>,
+    generation: Cell<u64>,
+    on_state_changed: RefCell<Option<Box<dyn Fn(SpeechState)>>>,
+}
+
+impl Speaker {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            processes: RefCell::new(None),
+            state: Cell::new(SpeechState::Idle),
+            generation: Cell::new(0),
+            on_state_changed: RefCell::new(None),
+        })
+    }
+
+    pub fn connect_state_changed(&self, callback: impl Fn(SpeechState) + 'static) {
+        *self.on_state_changed.borrow_mut() = Some(Box::new(callback));
+    }
+
+    pub fn speak(self: &Rc<Self>, settings: &SpeechSettings, markdown: &str) -> Result<(), String> {
+        self.stop();
+        let text = markdown_to_speech(markdown);
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        if settings.voice_model.trim().is_empty() {
+            return Err("No piper voice model set in General settings".to_string());
+        }
+
+        let piper_argv = [settings.command.as_str(), "--model", settings.voice_model.as_str(), "--output-raw"];
+        let rate = sample_rate(&settings.voice_model).to_string();
+        let player_argv: Vec<String> = if let Some(aplay) = find_program("aplay") {
+            vec![aplay.to_string_lossy().to_string(), "-q".into(), "-r".into(), rate, "-f".into(), "S16_LE".into(), "-t".into(), "raw".into(), "-c".into(), "1".into(), "-".into()]
+        } else if let Some(paplay) = find_program("paplay") {
+            vec![paplay.to_string_lossy().to_string(), "--raw".into(), format!("--rate={}", rate), "--format=s16le".into(), "--channels=1".into()]
+        } else {
+            return Err("No audio player found; install alsa-utils or pulseaudio-utils".to_string());
+        };
+
+        let (reader, writer) = std::io::pipe().map_err(|e| e.to_string())?;
+        let piper_launcher = gio::SubprocessLauncher::new(gio::SubprocessFlags::STDIN_PIPE | gio::SubprocessFlags::STDERR_SILENCE);
+        piper_launcher.take_stdout_fd(Some(writer.into()));
+        let piper = piper_launcher
+            .spawn(&piper_argv.map(OsStr::new))
+            .map_err(|e| format!("Failed to start {}: {}", settings.command, e))?;
+        let player_launcher = gio::SubprocessLauncher::new(gio::SubprocessFlags::NONE);
+        player_launcher.take_stdin_fd(Some(reader.into()));
+        let player_args: Vec<&OsStr> = player_argv.iter().map(OsStr::new).collect();
+        let player = match player_launcher.spawn(&player_args) {
+            Ok(player) => player,
+            Err(e) => {
+                piper.force_exit();
+                return Err(format!("Failed to start the audio player: {}", e));
+            }
+        };
+        // The children hold their ends of the pipe now; drop ours so EOF reaches the player
+        drop(piper_launcher);
+        drop(player_launcher);
+
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+        *self.processes.borrow_mut() = Some((piper.clone(), player.clone()));
+        self.set_state(SpeechState::Speaking);
+
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            if let Err(e) = piper.communicate_utf8_future(Some(text)).await {
+                tracing::error!("piper failed: {}", e);
+            }
+            let _ = player.wait_future().await;
+            if this.generation.get() == generation {
+                this.processes.borrow_mut().take();
+                this.set_state(SpeechState::Idle);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn toggle_pause(&self) {
+        let Some((_, player)) = &*self.processes.borrow() else { return };
+        match self.state.get() {
+            SpeechState::Speaking => {
+                player.send_signal(SIGSTOP);
+                self.set_state(SpeechState::Paused);
+            }
+            SpeechState::Paused => {
+                player.send_signal(SIGCONT);
+                self.set_state(SpeechState::Speaking);
+            }
+            SpeechState::Idle => {}
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some((piper, player)) = self.processes.borrow_mut().take() {
+            piper.force_exit();
+            // A stopped process only dies once it is continued
+            player.send_signal(SIGCONT);
+            player.force_exit();
+        }
+        self.generation.set(self.generation.get() + 1);
+        self.set_state(SpeechState::Idle);
+    }
+
+    fn set_state(&self, state: SpeechState) {
+        if self.state.replace(state) != state {
+            if let Some(callback) = &*self.on_state_changed.borrow() {
+                callback(state);
+            }
+        }
+    }
+}
+
+/// Piper voices ship a `<model>.json` config holding their sample rate.
+fn sample_rate(model: &str) -> u64 {
+    fs::read_to_string(format!("{}.json", model))
+        .ok()
+        .and_then(|config| serde_json::from_str::<serde_json::Value>(&config).ok())
+        .and_then(|config| config["audio"]["sample_rate"].as_u64())
+        .unwrap_or(DEFAULT_SAMPLE_RATE)
+}
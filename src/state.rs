@@ -1,7 +1,20 @@
 use serde::{Serialize, Deserialize};
-use ollama_rs::generation::chat::ChatMessage;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
 use ollama_rs::Ollama;
+use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crypto::Cipher;
+use crate::error::{AppError, AppResult};
+use crate::history_store::HistoryStore;
+use crate::migrations::SETTINGS_VERSION;
+use crate::provider::{Backend, Capability, GenerationOptions, OllamaServer};
+use crate::storage::Storage;
+use crate::utils::{parse_markdown, pretty_json, MarkdownBlock};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Agent {
@@ -9,6 +22,182 @@ pub struct Agent {
     pub model: String,
     pub system_prompt: String,
     pub description: String,
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    /// Offer the built-in tools to the model (it must support tool calling).
+    #[serde(default)]
+    pub tools_enabled: bool,
+    /// Shown on the welcome screen of an empty chat
+    #[serde(default)]
+    pub greeting: String,
+    /// Prompts offered as chips on the welcome screen, sent when clicked
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    /// Send `raw_template` to the generate endpoint instead of chatting, for
+    /// base models and prompt experiments (Ollama only, no tools)
+    #[serde(default)]
+    pub raw_mode: bool,
+    #[serde(default = "default_raw_template")]
+    pub raw_template: String,
+    /// Generation stops before any of these is produced
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Reply length limit in tokens; unset keeps the server's default
+    #[serde(default)]
+    pub num_predict: Option<i32>,
+    /// Fixed seed for reproducible replies
+    #[serde(default)]
+    pub seed: Option<i32>,
+    /// Layers Ollama offloads to the GPU, the rest run on the CPU; 0 runs
+    /// the model on the CPU only, unset lets Ollama fit as many as VRAM allows
+    #[serde(default)]
+    pub num_gpu: Option<u32>,
+    /// GPU Ollama keeps the model's unsplit parts on when it spans several;
+    /// unset leaves it to Ollama
+    #[serde(default)]
+    pub main_gpu: Option<u32>,
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    /// JSON schema the reply must follow when `response_format` is `Schema`
+    #[serde(default)]
+    pub json_schema: String,
+}
+
+/// What shape an agent's replies take.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    #[default]
+    Text,
+    /// Any valid JSON
+    Json,
+    /// JSON following `Agent::json_schema` (Ollama 0.5 or later)
+    Schema,
+}
+
+impl Agent {
+    /// The prompt template to complete when raw mode is on.
+    pub fn raw_template(&self) -> Option<&str> {
+        self.raw_mode.then_some(self.raw_template.as_str())
+    }
+
+    pub fn generation_options(&self) -> GenerationOptions {
+        GenerationOptions {
+            stop: self.stop.clone(),
+            num_predict: self.num_predict,
+            seed: self.seed,
+            num_gpu: self.num_gpu,
+            main_gpu: self.main_gpu,
+            num_ctx: None,
+            format: self.json_format(),
+        }
+    }
+
+    /// The structured output asked for: `"json"`, or the schema. A schema
+    /// that doesn't parse falls back to plain JSON.
+    pub fn json_format(&self) -> Option<serde_json::Value> {
+        match self.response_format {
+            ResponseFormat::Text => None,
+            ResponseFormat::Json => Some(serde_json::Value::from("json")),
+            ResponseFormat::Schema => Some(serde_json::from_str(&self.json_schema)
+                .unwrap_or_else(|_| serde_json::Value::from("json"))),
+        }
+    }
+}
+
+/// Raw prompt layout: `{{system}}` is the system prompt, `{{history}}` the
+/// earlier turns as "User:"/"Assistant:" lines, `{{prompt}}` the new message.
+pub fn default_raw_template() -> String {
+    "{{system}}\n\n{{history}}User: {{prompt}}\nAssistant:".to_string()
+}
+
+/// Most suggestion chips an agent shows.
+pub const MAX_SUGGESTIONS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CustomHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TlsOptions {
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    OpenAi,
+    Ollama,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProviderConfig {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub kind: ProviderKind,
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub headers: Vec<CustomHeader>,
+    #[serde(default)]
+    pub tls: TlsOptions,
+}
+
+/// A reusable prompt; `{{name}}` placeholders are filled in before sending.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Appearance {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Verbosity of the log file, least to most verbose.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WindowGeometry {
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            maximized: false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -23,59 +212,828 @@ pub struct Profile {
     pub location: String,
     pub bio: String,
     pub image_path: Option<String>,
+    #[serde(default = "default_true")]
+    pub memory_enabled: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Settings {
+    /// Schema version, see `migrations::SETTINGS_VERSION`.
+    #[serde(default)]
+    pub version: u32,
     pub ollama_endpoint: String,
+    #[serde(default)]
+    pub ollama_headers: Vec<CustomHeader>,
+    #[serde(default)]
+    pub ollama_tls: TlsOptions,
     pub agents: Vec<Agent>,
     #[serde(default)]
+    pub providers: Vec<ProviderConfig>,
+    #[serde(default)]
+    pub templates: Vec<PromptTemplate>,
+    #[serde(default)]
     pub profiles: Vec<Profile>,
     #[serde(default)]
     pub active_profile: Option<String>,
+    #[serde(default = "default_true")]
+    pub memory_enabled: bool,
+    #[serde(default)]
+    pub appearance: Appearance,
+    #[serde(default = "default_chat_font_size")]
+    pub chat_font_size: u32,
+    #[serde(default = "default_monospace_font")]
+    pub monospace_font: String,
+    /// Widest the message column grows before the remaining space becomes margin.
+    #[serde(default = "default_chat_max_width")]
+    pub chat_max_width: u32,
+    #[serde(default = "default_true")]
+    pub sidebar_visible: bool,
+    /// Show a second conversation beside the tabs.
+    #[serde(default)]
+    pub split_view: bool,
+    #[serde(default)]
+    pub window: WindowGeometry,
+    /// Hide the window on close and keep running behind a status icon.
+    #[serde(default)]
+    pub run_in_background: bool,
+    /// When launched at login, stay hidden behind the status icon.
+    #[serde(default)]
+    pub start_hidden: bool,
+    /// Let tools read hardware and OS details (CPU, memory, GPU, disks).
+    #[serde(default)]
+    pub share_system_info: bool,
+    /// Offer the sandboxed Python/Bash tool to agents with tools enabled.
+    #[serde(default)]
+    pub code_execution_enabled: bool,
+    #[serde(default)]
+    pub speech: SpeechSettings,
+    /// Ask the desktop for a global shortcut that opens Quick Ask.
+    #[serde(default)]
+    pub quick_ask_shortcut: bool,
+    #[serde(default)]
+    pub share: ShareSettings,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub titles: TitleSettings,
+    /// Small local model for background work (titles, memory updates) so the
+    /// conversation model stays loaded; empty uses the agent's model.
+    #[serde(default)]
+    pub utility_model: String,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    /// Days deleted conversations stay in the Trash; 0 keeps them until emptied.
+    #[serde(default = "default_trash_days")]
+    pub trash_days: u32,
+    /// Name of the agent new chats and launches start with; unset or unknown
+    /// uses the first agent.
+    #[serde(default)]
+    pub default_agent: Option<String>,
+    /// Last language picked in a message's "Translate to…" menu.
+    #[serde(default = "default_translate_language")]
+    pub translate_language: String,
+    #[serde(default)]
+    pub api_server: ApiServerSettings,
+    /// Encrypt conversations, the Trash, crash recovery snapshots and
+    /// memories on disk with a key kept in the keyring.
+    #[serde(default)]
+    pub encrypt_data: bool,
+    /// Look for API keys, private keys and passwords in messages before they
+    /// go to a server on another machine, and offer to redact them.
+    #[serde(default = "default_true")]
+    pub check_secrets: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub const MIN_CHAT_FONT_SIZE: u32 = 10;
+pub const MAX_CHAT_FONT_SIZE: u32 = 32;
+
+fn default_chat_font_size() -> u32 {
+    16
+}
+
+fn default_monospace_font() -> String {
+    "monospace".to_string()
+}
+
+fn default_chat_max_width() -> u32 {
+    900
+}
+
+fn default_trash_days() -> u32 {
+    30
+}
+
+fn default_translate_language() -> String {
+    "English".to_string()
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
+            version: SETTINGS_VERSION,
             ollama_endpoint: "http://localhost:11434".to_string(),
+            ollama_headers: Vec::new(),
+            ollama_tls: TlsOptions::default(),
             agents: vec![
                 Agent {
                     name: "Default Assistant".to_string(),
                     model: "llama3".to_string(),
                     system_prompt: "You are a helpful assistant.".to_string(),
                     description: "Standard personal assistant".to_string(),
+                    provider_id: None,
+                    tools_enabled: false,
+                    greeting: "Hi! How can I help you today?".to_string(),
+                    suggestions: vec![
+                        "Explain a concept in simple terms".to_string(),
+                        "Help me write an email".to_string(),
+                        "Summarize a piece of text".to_string(),
+                    ],
+                    raw_mode: false,
+                    raw_template: default_raw_template(),
+                    stop: Vec::new(),
+                    num_predict: None,
+                    seed: None,
+                    num_gpu: None,
+                    main_gpu: None,
+                    response_format: ResponseFormat::Text,
+                    json_schema: String::new(),
                 }
             ],
+            providers: Vec::new(),
+            templates: Vec::new(),
             profiles: Vec::new(),
             active_profile: None,
+            memory_enabled: true,
+            appearance: Appearance::System,
+            chat_font_size: default_chat_font_size(),
+            monospace_font: default_monospace_font(),
+            chat_max_width: default_chat_max_width(),
+            sidebar_visible: true,
+            split_view: false,
+            window: WindowGeometry::default(),
+            run_in_background: false,
+            start_hidden: false,
+            share_system_info: false,
+            code_execution_enabled: false,
+            speech: SpeechSettings::default(),
+            quick_ask_shortcut: false,
+            share: ShareSettings::default(),
+            log_level: LogLevel::Info,
+            titles: TitleSettings::default(),
+            utility_model: String::new(),
+            timeouts: Timeouts::default(),
+            trash_days: default_trash_days(),
+            default_agent: None,
+            translate_language: default_translate_language(),
+            api_server: ApiServerSettings::default(),
+            encrypt_data: false,
+            check_secrets: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Index of the agent new chats start with.
+    pub fn default_agent_index(&self) -> usize {
+        self.default_agent.as_ref()
+            .and_then(|name| self.agents.iter().position(|a| &a.name == name))
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareService {
+    /// 0x0.st or a compatible paste service
+    #[default]
+    Paste,
+    Gist,
+}
+
+/// Where "Share…" uploads conversation transcripts.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ShareSettings {
+    #[serde(default)]
+    pub service: ShareService,
+    #[serde(default = "default_paste_url")]
+    pub paste_url: String,
+    /// GitHub token with the `gist` scope.
+    #[serde(default)]
+    pub github_token: String,
+}
+
+fn default_paste_url() -> String {
+    "https://0x0.st".to_string()
+}
+
+impl Default for ShareSettings {
+    fn default() -> Self {
+        Self {
+            service: ShareService::Paste,
+            paste_url: default_paste_url(),
+            github_token: String::new(),
         }
     }
 }
 
+/// Text-to-speech through a local piper voice.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpeechSettings {
+    #[serde(default = "default_piper_command")]
+    pub command: String,
+    /// Path to the voice's `.onnx` file.
+    #[serde(default)]
+    pub voice_model: String,
+    /// Read each reply aloud as soon as it is complete.
+    #[serde(default)]
+    pub auto_read: bool,
+}
+
+fn default_piper_command() -> String {
+    "piper".to_string()
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self {
+            command: default_piper_command(),
+            voice_model: String::new(),
+            auto_read: false,
+        }
+    }
+}
+
+/// How new conversations get their titles.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TitleSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Local Ollama model to name conversations with; empty uses the utility model.
+    #[serde(default)]
+    pub model: String,
+    /// `{{message}}` is replaced with the conversation's first message.
+    #[serde(default = "default_title_prompt")]
+    pub prompt: String,
+}
+
+pub fn default_title_prompt() -> String {
+    "Generate a very short, creative 2-4 word title for a chat that starts with: \"{{message}}\". Output ONLY the title, no quotes or punctuation.".to_string()
+}
+
+impl Default for TitleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model: String::new(),
+            prompt: default_title_prompt(),
+        }
+    }
+}
+
+/// Limits for requests to model servers, in seconds; 0 waits forever.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    #[serde(default = "default_connect_timeout")]
+    pub connect_secs: u64,
+    /// Longest silence while waiting for a reply, including loading the model
+    #[serde(default = "default_response_timeout")]
+    pub response_secs: u64,
+}
+
+fn default_connect_timeout() -> u64 { 10 }
+fn default_response_timeout() -> u64 { 300 }
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect_secs: default_connect_timeout(),
+            response_secs: default_response_timeout(),
+        }
+    }
+}
+
+/// The local OpenAI-compatible server offering agents as models to editors
+/// and other tools.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiServerSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Port on 127.0.0.1
+    #[serde(default = "default_api_server_port")]
+    pub port: u16,
+    /// Bearer token clients must send; requests are refused while it is empty
+    #[serde(default)]
+    pub api_key: String,
+}
+
+fn default_api_server_port() -> u16 { 11435 }
+
+impl Default for ApiServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_api_server_port(),
+            api_key: String::new(),
+        }
+    }
+}
+
+/// When a message was written and, for replies, what produced it.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct MessageMeta {
+    /// Unix time in seconds
+    pub timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// From sending the request to the end of the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// When the user last changed the text, in Unix seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<i64>,
+}
+
+/// A chat message as kept in the conversation and in history. Messages
+/// saved before metadata existed load without it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredMessage {
+    #[serde(flatten)]
+    pub message: ChatMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MessageMeta>,
+    /// The shown text parsed into blocks, filled on first render and carried
+    /// along with clones of the message so re-renders skip parsing. Not saved;
+    /// `set_content` clears it.
+    #[serde(skip)]
+    blocks: OnceLock<Arc<Vec<MarkdownBlock>>>,
+}
+
+impl MessageMeta {
+    /// Metadata for a message written now.
+    pub fn now() -> Self {
+        Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+impl StoredMessage {
+    /// Wraps `message`, stamped with the current time.
+    pub fn new(message: ChatMessage) -> Self {
+        Self {
+            message,
+            meta: Some(MessageMeta::now()),
+            blocks: OnceLock::new(),
+        }
+    }
+
+    /// What the chat shows: the text, and for a user message its attached
+    /// files separately as (name, content).
+    pub fn display_parts(&self) -> (String, Vec<(String, String)>) {
+        if self.message.role == MessageRole::User {
+            crate::attachments::split(&self.message.content)
+        } else {
+            (self.message.content.clone(), Vec::new())
+        }
+    }
+
+    /// The shown text as markdown blocks, parsed once per content. A reply
+    /// that is all JSON shows as one pretty-printed code block.
+    pub fn blocks(&self) -> Arc<Vec<MarkdownBlock>> {
+        self.blocks.get_or_init(|| {
+            if self.message.role == MessageRole::Assistant {
+                if let Some(json) = pretty_json(&self.message.content) {
+                    return Arc::new(vec![MarkdownBlock::Code("json".to_string(), json)]);
+                }
+            }
+            Arc::new(parse_markdown(&self.display_parts().0))
+        }).clone()
+    }
+
+    /// Replaces the text after the user edited it and marks it as edited.
+    pub fn set_content(&mut self, content: String) {
+        self.message.content = content;
+        self.blocks = OnceLock::new();
+        self.meta.get_or_insert_with(MessageMeta::now).edited_at = Some(MessageMeta::now().timestamp);
+    }
+
+    /// Records the model that wrote a reply and how long it took.
+    pub fn with_generation(mut self, model: &str, latency_ms: u64) -> Self {
+        let meta = self.meta.get_or_insert_with(MessageMeta::default);
+        meta.model = Some(model.to_string());
+        meta.latency_ms = Some(latency_ms);
+        self
+    }
+}
+
+/// An assistant message the user starred. It keeps its own copy of the text,
+/// so it outlives the conversation it came from.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    pub id: String,
+    pub content: String,
+    /// Title of the conversation at the time it was saved
+    #[serde(default)]
+    pub conversation: String,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub meta: Option<MessageMeta>,
+    /// Unix time in seconds
+    pub saved_at: i64,
+}
+
+/// Speed of a model measured by `benchmark::run`, kept to compare models
+/// and quantizations over time.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BenchmarkRun {
+    pub model: String,
+    /// Unix time in seconds
+    pub run_at: i64,
+    /// Cold load time of the model
+    pub load_secs: f64,
+    pub prompt_tokens_per_sec: f64,
+    pub generation_tokens_per_sec: f64,
+    pub prompt_tokens: u64,
+    pub generated_tokens: u64,
+}
+
+/// The user's verdict on a blind arena round.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ArenaOutcome {
+    AWins,
+    BWins,
+    Tie,
+    BothBad,
+}
+
+/// One vote in the blind arena.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArenaVote {
+    pub model_a: String,
+    pub model_b: String,
+    pub outcome: ArenaOutcome,
+    /// Unix time in seconds
+    pub voted_at: i64,
+}
+
+/// The bare messages, as sent to a model.
+pub fn chat_messages(messages: &[StoredMessage]) -> Vec<ChatMessage> {
+    messages.iter().map(|m| m.message.clone()).collect()
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ChatHistory {
     pub id: String,
     pub title: String,
-    pub messages: Vec<ChatMessage>,
+    pub messages: Vec<StoredMessage>,
+    /// Name of the agent the conversation was held with
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Model that agent used at the time
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Labels added from the sidebar's selection mode
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The user's own annotations from the notes panel; never sent to the model
+    #[serde(default)]
+    pub notes: String,
+    /// Unix times in seconds; missing in conversations saved before they were recorded
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    #[serde(default)]
+    pub updated_at: Option<i64>,
+}
+
+impl ChatHistory {
+    /// When the conversation last changed, falling back to its newest
+    /// timestamped message for older entries.
+    pub fn last_activity(&self) -> Option<i64> {
+        self.updated_at.or_else(|| self.messages.iter().rev().find_map(|m| m.meta.as_ref()).map(|meta| meta.timestamp))
+    }
+
+    /// The agent to continue this conversation with: the same one by name,
+    /// or else one using the same model.
+    pub fn agent_index(&self, agents: &[Agent]) -> Option<usize> {
+        self.agent.as_ref()
+            .and_then(|name| agents.iter().position(|a| &a.name == name))
+            .or_else(|| self.model.as_ref().and_then(|model| agents.iter().position(|a| &a.model == model)))
+    }
+}
+
+/// A deleted conversation, kept so it can be restored.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashedConversation {
+    #[serde(flatten)]
+    pub conversation: ChatHistory,
+    /// Unix time in seconds
+    pub deleted_at: i64,
 }
 
 pub enum ChatEvent {
+    /// A request is about to be sent, as shown in the inspector
+    Request(serde_json::Value),
     Chunk(String),
     Done(String),
+    /// The reply broke off partway; it is kept, but no title or memory
+    /// update is made from it
+    Interrupted(String),
     Error(String),
-    RefreshHistory,
+    /// Like `Error`, but Ollama couldn't be reached, so the app should
+    /// start reconnecting
+    ConnectionLost(String),
+    /// The model called a tool: (name, JSON arguments)
+    ToolCall(String, String),
+    /// Output of the most recent tool call: (name, output)
+    ToolResult(String, String),
+    /// The request failed transiently and is being sent again: (attempt, attempts)
+    Retrying(u32, u32),
+    /// The agent's model isn't installed on this Ollama server: (server, model)
+    ModelMissing(Ollama, String),
+    /// A round of tool calls finished: the reply that made them and their
+    /// results, to add to the chat before the model continues
+    ToolRound(Vec<ChatMessage>),
+}
+
+/// A conversation open in a tab or the split pane. Each streams its own
+/// replies, so several conversations can generate at once.
+pub struct OpenChat {
+    /// Identifies the tab while it is open, also before the chat is saved
+    pub tab_id: u64,
+    pub agent_idx: usize,
+    pub messages: Vec<StoredMessage>,
+    /// History entry the conversation belongs to; `None` for a new chat.
+    pub history_id: Option<String>,
+    /// Model used instead of the agent's, e.g. a vision model switched to
+    /// for an attached image; dropped when another conversation is opened
+    pub model_override: Option<String>,
+    pub task: Option<tokio::task::AbortHandle>,
+    /// Title and memory requests started by earlier replies; Stop cancels them too
+    pub background_tasks: Vec<tokio::task::AbortHandle>,
+}
+
+impl OpenChat {
+    /// An empty chat with the given agent, in a tab of its own.
+    pub fn new(agent_idx: usize) -> Self {
+        // Never reused, so a reply finishing after its tab closed finds nothing
+        static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            tab_id: NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed),
+            agent_idx,
+            messages: Vec::new(),
+            history_id: None,
+            model_override: None,
+            task: None,
+            background_tasks: Vec::new(),
+        }
+    }
+
+    /// Keeps `task` so it can be cancelled with the reply, forgetting finished ones.
+    pub fn track_background(&mut self, task: tokio::task::AbortHandle) {
+        self.background_tasks.retain(|t| !t.is_finished());
+        self.background_tasks.push(task);
+    }
+
+    /// Aborts the streaming reply and every background request.
+    pub fn stop_tasks(&mut self) {
+        if let Some(handle) = self.task.take() {
+            handle.abort();
+        }
+        for task in self.background_tasks.drain(..) {
+            task.abort();
+        }
+    }
 }
 
 pub struct AppState {
     pub ollama: Ollama,
-    pub current_agent_idx: usize,
-    pub messages: Vec<ChatMessage>,
+    /// Conversations open in tabs, plus the split pane's while it is shown;
+    /// never empty
+    pub chats: Vec<OpenChat>,
+    /// `OpenChat::tab_id` of the tab on screen
+    pub active_tab: u64,
     pub history: Vec<ChatHistory>,
+    /// Unsent input per conversation, keyed by history id ("" for a new chat).
+    pub drafts: HashMap<String, String>,
+    pub bookmarks: Vec<Bookmark>,
+    /// Benchmark results, oldest first
+    pub benchmarks: Vec<BenchmarkRun>,
+    /// Arena votes, oldest first
+    pub arena_votes: Vec<ArenaVote>,
+    /// Deleted conversations, oldest first
+    pub trash: Vec<TrashedConversation>,
     pub settings: Settings,
     pub config_path: PathBuf,
-    pub history_path: PathBuf,
+    pub history_store: HistoryStore,
     pub memory_path: PathBuf,
-    pub current_task: Option<tokio::task::AbortHandle>,
+    pub drafts_path: PathBuf,
+    pub bookmarks_path: PathBuf,
+    pub benchmarks_path: PathBuf,
+    pub arena_path: PathBuf,
+    pub trash_path: PathBuf,
+    pub recovery_path: PathBuf,
     pub available_models: Vec<String>,
+    /// Context windows of Ollama models looked up so far (`provider::context_length`),
+    /// by endpoint and model (`context_key`); `None` while a lookup is in flight
+    pub context_lengths: HashMap<(String, String), Option<u64>>,
+    /// Capabilities of Ollama models looked up so far (`provider::model_capabilities`)
+    pub model_capabilities: HashMap<String, Vec<Capability>>,
+    pub server_version: Option<String>,
+    pub storage: Storage,
+}
+
+impl AppState {
+    /// The conversation in the tab on screen.
+    pub fn chat(&self) -> &OpenChat {
+        self.chats.iter().find(|c| c.tab_id == self.active_tab).unwrap_or(&self.chats[0])
+    }
+
+    pub fn chat_mut(&mut self) -> &mut OpenChat {
+        let idx = self.chats.iter().position(|c| c.tab_id == self.active_tab).unwrap_or(0);
+        &mut self.chats[idx]
+    }
+
+    /// The conversation in tab `tab_id`, or `None` once the tab is closed.
+    pub fn chat_in_tab(&mut self, tab_id: u64) -> Option<&mut OpenChat> {
+        self.chats.iter_mut().find(|c| c.tab_id == tab_id)
+    }
+
+    /// Resolves the backend an agent should talk to, falling back to the
+    /// Ollama endpoint when the agent has no (or an unknown) provider.
+    pub fn backend_for(&self, agent: &Agent) -> Backend {
+        agent.provider_id.as_ref()
+            .and_then(|id| self.settings.providers.iter().find(|p| &p.id == id))
+            .map(|config| Backend::from_config(config, &self.settings.timeouts))
+            .unwrap_or_else(|| Backend::Ollama(self.ollama_server()))
+    }
+
+    /// The Ollama endpoint in the settings, the one `ollama` talks to.
+    pub fn ollama_server(&self) -> OllamaServer {
+        OllamaServer::new(&self.settings.ollama_endpoint, &self.settings.ollama_headers, &self.settings.ollama_tls, &self.settings.timeouts)
+    }
+
+    /// The server `agent`'s requests go to.
+    pub fn endpoint_for(&self, agent: &Agent) -> String {
+        agent.provider_id.as_ref()
+            .and_then(|id| self.settings.providers.iter().find(|p| &p.id == id))
+            .map(|config| config.base_url.clone())
+            .unwrap_or_else(|| self.settings.ollama_endpoint.clone())
+    }
+
+    /// The context window of `agent`'s model, if it was looked up already.
+    pub fn known_context_length(&self, agent: &Agent) -> Option<u64> {
+        self.context_lengths.get(&self.context_key(agent)?).copied().flatten()
+    }
+
+    /// Where `agent`'s context window is kept in `context_lengths`, or `None`
+    /// when it isn't served by Ollama and has none to look up.
+    pub fn context_key(&self, agent: &Agent) -> Option<(String, String)> {
+        let provider = agent.provider_id.as_ref().and_then(|id| self.settings.providers.iter().find(|p| &p.id == id));
+        if provider.is_some_and(|p| p.kind == ProviderKind::OpenAi) {
+            return None;
+        }
+        Some((self.endpoint_for(agent), agent.model.clone()))
+    }
+
+    /// The agent of the tab on screen, or the first one if the selection is
+    /// stale, with the chat's model override applied.
+    pub fn current_agent(&self) -> AppResult<Agent> {
+        self.agent_of(self.chat())
+    }
+
+    /// The agent of the chat in tab `tab_id`, like `current_agent`.
+    pub fn agent_in_tab(&self, tab_id: u64) -> AppResult<Agent> {
+        let chat = self.chats.iter().find(|c| c.tab_id == tab_id)
+            .ok_or_else(|| AppError::new("The chat was closed", format!("No open chat has tab id {}", tab_id)))?;
+        self.agent_of(chat)
+    }
+
+    fn agent_of(&self, chat: &OpenChat) -> AppResult<Agent> {
+        let mut agent = self.agent_at(chat.agent_idx)?;
+        if let Some(model) = &chat.model_override {
+            agent.model = model.clone();
+        }
+        Ok(agent)
+    }
+
+    /// Agent `idx`, or the first one if there is no such agent any more.
+    pub fn agent_at(&self, idx: usize) -> AppResult<Agent> {
+        self.settings.agents.get(idx)
+            .or_else(|| self.settings.agents.first())
+            .cloned()
+            .ok_or_else(|| AppError::new("No agent is configured", "Add one in Settings → Agents"))
+    }
+
+    pub fn save_settings(&self) {
+        self.storage.save_json(&self.config_path, &self.settings);
+    }
+
+    pub fn save_history(&self) {
+        self.history_store.save(&self.storage, &self.history);
+    }
+
+    pub fn save_drafts(&self) {
+        self.storage.save_private_json(&self.drafts_path, &self.drafts);
+    }
+
+    pub fn save_bookmarks(&self) {
+        self.storage.save_private_json(&self.bookmarks_path, &self.bookmarks);
+    }
+
+    pub fn save_benchmarks(&self) {
+        self.storage.save_json(&self.benchmarks_path, &self.benchmarks);
+    }
+
+    pub fn save_arena(&self) {
+        self.storage.save_json(&self.arena_path, &self.arena_votes);
+    }
+
+    pub fn save_trash(&self) {
+        self.storage.save_private_json(&self.trash_path, &self.trash);
+    }
+
+    /// Turns encryption of private files on or off and rewrites them all, so
+    /// none is left in the other form; their backups follow as they are
+    /// written (`Storage::flush`), and what remains of the old `history.json`
+    /// is deleted. `cipher` comes from the keyring, which callers read off
+    /// the main thread.
+    pub fn set_encryption(&mut self, cipher: Option<Cipher>, enabled: bool) {
+        // Memories are read with the old setting, before it changes
+        let memories: Vec<(PathBuf, String)> = fs::read_dir(&self.memory_path)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .filter_map(|path| self.storage.read_private_string(&path).map(|text| (path, text)))
+            .collect();
+        self.storage.set_encryption(cipher, enabled);
+        self.settings.encrypt_data = enabled;
+        self.history_store.rewrite_all(&self.storage, &self.history);
+        self.history_store.remove_legacy(&self.storage);
+        self.save_trash();
+        self.save_drafts();
+        self.save_bookmarks();
+        for (path, text) in memories {
+            self.storage.save_private(&path, text);
+        }
+    }
+
+    /// Drops conversations that have been in the Trash longer than
+    /// `trash_days`. Returns whether any were removed.
+    pub fn purge_trash(&mut self) -> bool {
+        let days = self.settings.trash_days;
+        if days == 0 {
+            return false;
+        }
+        let cutoff = MessageMeta::now().timestamp - i64::from(days) * 24 * 60 * 60;
+        let count = self.trash.len();
+        self.trash.retain(|t| t.deleted_at > cutoff);
+        self.trash.len() != count
+    }
+
+    /// Backend and model that name new conversations, or `None` when titles
+    /// aren't generated.
+    pub fn title_backend(&self, agent: &Agent) -> Option<(Backend, String)> {
+        let titles = &self.settings.titles;
+        if !titles.enabled {
+            return None;
+        }
+        let model = titles.model.trim();
+        if model.is_empty() {
+            Some(self.utility_backend(agent))
+        } else {
+            Some((Backend::Ollama(self.ollama_server()), model.to_string()))
+        }
+    }
+
+    /// Backend and model for background tasks on behalf of `agent`: the
+    /// utility model on the Ollama endpoint when one is set, else the agent's own.
+    pub fn utility_backend(&self, agent: &Agent) -> (Backend, String) {
+        let model = self.settings.utility_model.trim();
+        if model.is_empty() {
+            (self.backend_for(agent), agent.model.clone())
+        } else {
+            (Backend::Ollama(self.ollama_server()), model.to_string())
+        }
+    }
+
+    /// Key of the conversation on screen in `drafts`.
+    pub fn draft_key(&self) -> String {
+        self.chat().history_id.clone().unwrap_or_default()
+    }
+
+    /// Models offered for an agent using the given provider.
+    pub fn models_for(&self, provider_id: Option<&str>) -> Vec<String> {
+        match provider_id.and_then(|id| self.settings.providers.iter().find(|p| p.id == id)) {
+            Some(provider) => provider.models.clone(),
+            None => self.available_models.clone(),
+        }
+    }
 }
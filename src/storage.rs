@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::crypto::{is_encrypted, Cipher};
+use crate::error::{AppError, LockExt};
+use crate::utils::{backup_path, write_atomic};
+
+/// How long saves are collected before being written; repeated saves of the
+/// same file within this window only write the latest contents.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+struct Pending {
+    /// `None` removes the file
+    files: Mutex<HashMap<PathBuf, Option<Vec<u8>>>>,
+    /// Held while writing so a flush can't race an in-flight batch
+    writing: Mutex<()>,
+    wake: Notify,
+    encryption: Mutex<Encryption>,
+}
+
+/// How private files (conversations, memories) are stored.
+#[derive(Default)]
+struct Encryption {
+    /// Key for reading encrypted files, and for writing them when enabled
+    cipher: Option<Cipher>,
+    enabled: bool,
+}
+
+/// Owns writing the app's data files. Callers hand over the serialized
+/// contents; the files are written atomically on a blocking task, off the
+/// GTK main loop. Failures are reported through the channel returned by `new`.
+#[derive(Clone)]
+pub struct Storage {
+    pending: Arc<Pending>,
+    errors: async_channel::Sender<AppError>,
+}
+
+impl Storage {
+    pub fn new() -> (Self, async_channel::Receiver<AppError>) {
+        let (errors, errors_rx) = async_channel::unbounded();
+        let storage = Self {
+            pending: Arc::new(Pending {
+                files: Mutex::new(HashMap::new()),
+                writing: Mutex::new(()),
+                wake: Notify::new(),
+                encryption: Mutex::new(Encryption::default()),
+            }),
+            errors,
+        };
+
+        let this = storage.clone();
+        tokio::spawn(async move {
+            loop {
+                this.pending.wake.notified().await;
+                tokio::time::sleep(DEBOUNCE).await;
+                let this = this.clone();
+                let _ = tokio::task::spawn_blocking(move || this.flush()).await;
+            }
+        });
+
+        (storage, errors_rx)
+    }
+
+    /// Queues `contents` to replace the file at `path`.
+    pub fn save(&self, path: &Path, contents: impl Into<Vec<u8>>) {
+        self.pending.files.locked().insert(path.to_path_buf(), Some(contents.into()));
+        self.pending.wake.notify_one();
+    }
+
+    /// Queues deleting the file at `path`, along with its backup.
+    pub fn remove(&self, path: &Path) {
+        self.pending.files.locked().insert(path.to_path_buf(), None);
+        self.pending.wake.notify_one();
+    }
+
+    /// Queues `value` serialized as JSON.
+    pub fn save_json(&self, path: &Path, value: &impl serde::Serialize) {
+        match serde_json::to_string(value) {
+            Ok(json) => self.save(path, json),
+            Err(e) => {
+                let _ = self.errors.try_send(AppError::new(format!("Failed to save {}", file_name(path)), e));
+            }
+        }
+    }
+
+    /// Sets the key for private files and whether they are written encrypted.
+    /// The key is kept for reading even when writing plain files.
+    pub fn set_encryption(&self, cipher: Option<Cipher>, enabled: bool) {
+        *self.pending.encryption.locked() = Encryption { cipher, enabled };
+    }
+
+    /// Queues `contents` like `save`, encrypted when encryption is on.
+    pub fn save_private(&self, path: &Path, contents: impl Into<Vec<u8>>) {
+        let contents = contents.into();
+        let contents = match &*self.pending.encryption.locked() {
+            Encryption { cipher: Some(cipher), enabled: true } => cipher.encrypt(&contents),
+            Encryption { enabled: true, .. } => {
+                tracing::warn!("No encryption key, saving {} unencrypted", path.display());
+                contents
+            }
+            _ => contents,
+        };
+        self.save(path, contents);
+    }
+
+    /// Queues `value` serialized as JSON through `save_private`.
+    pub fn save_private_json(&self, path: &Path, value: &impl serde::Serialize) {
+        match serde_json::to_string(value) {
+            Ok(json) => self.save_private(path, json),
+            Err(e) => {
+                let _ = self.errors.try_send(AppError::new(format!("Failed to save {}", file_name(path)), e));
+            }
+        }
+    }
+
+    /// `contents` encrypted with the data key, for private data leaving the
+    /// app's files, e.g. backups. `None` when the key isn't in the keyring.
+    pub fn encrypt(&self, contents: &[u8]) -> Option<Vec<u8>> {
+        self.pending.encryption.locked().cipher.as_ref().map(|cipher| cipher.encrypt(contents))
+    }
+
+    /// Reverses `encrypt`; data that isn't encrypted is returned as is.
+    pub fn decrypt(&self, data: Vec<u8>) -> Result<Vec<u8>, String> {
+        if !is_encrypted(&data) {
+            return Ok(data);
+        }
+        match &self.pending.encryption.locked().cipher {
+            Some(cipher) => cipher.decrypt(&data),
+            None => Err("encrypted, but the key isn't in the keyring".to_string()),
+        }
+    }
+
+    /// Reads a file written by `save_private`, decrypting it if needed.
+    pub fn read_private(&self, path: &Path) -> Option<Vec<u8>> {
+        read_private(path, self.pending.encryption.locked().cipher.as_ref())
+    }
+
+    pub fn read_private_string(&self, path: &Path) -> Option<String> {
+        self.read_private(path).map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+
+    /// Reads JSON saved with `save_private_json`, falling back to its backup
+    /// like `utils::read_json`.
+    pub fn read_private_json<T: serde::de::DeserializeOwned>(&self, path: &Path) -> Option<T> {
+        let parse = |path: &Path| self.read_private(path).and_then(|data| serde_json::from_slice(&data).ok());
+        parse(path).or_else(|| {
+            let restored = parse(&backup_path(path));
+            if restored.is_some() {
+                tracing::warn!("{} is unreadable, using its backup", path.display());
+            }
+            restored
+        })
+    }
+
+    /// Writes everything queued so far on the calling thread, e.g. at shutdown.
+    pub fn flush(&self) {
+        let _writing = self.pending.writing.locked();
+        let files = std::mem::take(&mut *self.pending.files.locked());
+        for (path, contents) in files {
+            let Some(contents) = contents else {
+                let _ = fs::remove_file(&path);
+                let _ = fs::remove_file(backup_path(&path));
+                continue;
+            };
+            if let Err(e) = write_atomic(&path, &contents) {
+                let _ = self.errors.try_send(AppError::new(format!("Failed to save {}", file_name(&path)), format!("{}: {}", path.display(), e)));
+                continue;
+            }
+            // The previous version kept as the backup is from before encryption
+            // was turned on or off; don't leave a copy in the other form
+            if backup_is_encrypted(&path).is_some_and(|encrypted| encrypted != is_encrypted(&contents)) {
+                let _ = fs::write(backup_path(&path), &contents);
+            }
+        }
+    }
+}
+
+/// Reads a file written by `Storage::save_private` with `cipher` as the key,
+/// for readers without the app's `Storage`.
+pub fn read_private(path: &Path, cipher: Option<&Cipher>) -> Option<Vec<u8>> {
+    let data = fs::read(path).ok()?;
+    if !is_encrypted(&data) {
+        return Some(data);
+    }
+    let Some(cipher) = cipher else {
+        tracing::warn!("{} is encrypted, but the key isn't in the keyring", path.display());
+        return None;
+    };
+    cipher.decrypt(&data)
+        .inspect_err(|e| tracing::warn!("Could not decrypt {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Whether the backup of `path` is encrypted, or `None` if there is none.
+fn backup_is_encrypted(path: &Path) -> Option<bool> {
+    let mut start = Vec::new();
+    fs::File::open(backup_path(path)).ok()?.take(64).read_to_end(&mut start).ok()?;
+    Some(is_encrypted(&start))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
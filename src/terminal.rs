@@ -0,0 +1,55 @@
+use std::process::Command;
+
+use crate::utils::find_program;
+
+/// Terminals tried in order when `$TERMINAL` is unset, with the arguments
+/// that come before the command to run.
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("xdg-terminal-exec", &[]),
+    ("kgx", &["--"]),
+    ("ptyxis", &["--"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("xterm", &["-e"]),
+];
+
+/// Puts `$1` at a bash prompt through readline, so nothing runs until the
+/// user presses Enter; the shell stays open afterwards.
+const PREFILL_SCRIPT: &str = r#"read -r -e -p '$ ' -i "$1" line && history -s "$line" && eval "$line"; exec bash"#;
+
+/// Whether a code block in `lang` holds commands for a shell.
+pub fn is_shell_language(lang: &str) -> bool {
+    matches!(lang.trim().to_lowercase().as_str(), "sh" | "bash" | "shell" | "zsh" | "console" | "shell-session")
+}
+
+/// Opens the user's terminal with `command` typed at the prompt but not run.
+/// `$ ` prompts copied along with console examples are dropped.
+pub fn open_with_command(command: &str) -> Result<(), String> {
+    let command = command.trim()
+        .lines()
+        .map(|line| line.strip_prefix("$ ").unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let shell = ["bash", "-c", PREFILL_SCRIPT, "bash", command.as_str()];
+
+    let (program, flags): (String, &[&str]) = match std::env::var("TERMINAL").ok().filter(|t| !t.trim().is_empty()) {
+        Some(terminal) => (terminal, &["-e"]),
+        None => TERMINALS.iter()
+            .find(|(name, _)| find_program(name).is_some())
+            .map(|(name, flags)| (name.to_string(), *flags))
+            .ok_or("No terminal emulator found; set $TERMINAL to choose one")?,
+    };
+    let mut child = Command::new(&program)
+        .args(flags)
+        .args(shell)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+    // Reap it once the window is closed
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
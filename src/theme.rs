@@ -0,0 +1,256 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+
+use crate::portal::{PORTAL_BUS, PORTAL_PATH};
+use crate::state::Appearance;
+
+const PORTAL_SETTINGS: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+
+/// Overrides for the built-in (dark) stylesheet, loaded on top of it when the
+/// light appearance is active.
+const LIGHT_CSS: &str = r#"
+    window { background-color: #ffffff; color: #1f1f1f; }
+    headerbar {
+        background-color: #f0f4f9;
+        color: #1f1f1f;
+        border-bottom: 1px solid #dde3ea;
+    }
+    .sidebar, .inspector { background-color: #f0f4f9; }
+    .sidebar button { color: #1f1f1f; }
+    .sidebar button:hover { background-color: #dde3ea; }
+
+    .msg-header, .message-meta { color: #5f6368; }
+    .code-frame {
+        background-color: #f6f8fa;
+        border-color: #d0d7de;
+    }
+    .headers-view {
+        background-color: #f6f8fa;
+        border-color: #d0d7de;
+    }
+    .status-text { color: #5f6368; }
+    .tool-chip, .execution-result, .notice-card {
+        background-color: #f6f8fa;
+        border-color: #d0d7de;
+        color: #5f6368;
+    }
+    .speech-bar { background-color: #f0f4f9; }
+    .jump-pill { background-color: #ffffff; color: #1f1f1f; }
+    .attachment-thumb { border-color: #d0d7de; }
+    .translation { color: #3c4043; }
+    .attachment-pill { background-color: #f6f8fa; border-color: #d0d7de; color: #1f1f1f; }
+    .connection-banner {
+        background-color: #fff4e5;
+        color: #8a5300;
+    }
+    .destructive-action { color: #c62828; }
+    .destructive-action:hover { background-color: rgba(198, 40, 40, 0.08); }
+
+    textview.chat-input, .input-preview {
+        background-color: #f0f4f9;
+        color: #1f1f1f;
+    }
+    entry {
+        background-color: #f0f4f9;
+        color: #1f1f1f;
+        border-color: #c4c7c5;
+    }
+    dropdown { color: #1f1f1f; }
+
+    .bot-message { color: #1f1f1f; }
+    .settings-label { color: #5f6368; }
+    .profile-circle {
+        background-color: #e8eaed;
+        border-color: #c4c7c5;
+    }
+    .profile-circle:hover { background-color: #dde3ea; }
+    .profile-circle-label { color: #1f1f1f; }
+    .profile-mini-name { color: #5f6368; }
+    tt { background-color: #eef1f4; }
+    .welcome-text { color: #5f6368; }
+    .suggestion-chip {
+        background-color: #f0f4f9;
+        border-color: #c4c7c5;
+        color: #1f1f1f;
+    }
+    .suggestion-chip:hover { background-color: #dde3ea; }
+"#;
+
+/// Switches the application between the dark and light palettes, following
+/// the desktop's color-scheme preference when set to `Appearance::System`,
+/// and layers the user's `style.css` over both.
+pub struct ThemeManager {
+    light_provider: gtk::CssProvider,
+    font_provider: gtk::CssProvider,
+    user_provider: gtk::CssProvider,
+    user_css_path: PathBuf,
+    user_css_errors: Rc<RefCell<Vec<String>>>,
+    appearance: Cell<Appearance>,
+    /// Last `color-scheme` reported by the settings portal (1 = dark, 2 = light).
+    color_scheme: Cell<Option<u32>>,
+    /// Whether the GTK theme preferred dark before we started overriding it.
+    gtk_prefers_dark: bool,
+    _subscription: RefCell<Option<gio::SignalSubscription>>,
+}
+
+impl ThemeManager {
+    pub fn new(appearance: Appearance, user_css_path: PathBuf) -> Rc<Self> {
+        let light_provider = gtk::CssProvider::new();
+        light_provider.load_from_data(LIGHT_CSS);
+
+        let font_provider = gtk::CssProvider::new();
+        let user_provider = gtk::CssProvider::new();
+        let user_css_errors = Rc::new(RefCell::new(Vec::new()));
+        let errors = user_css_errors.clone();
+        user_provider.connect_parsing_error(move |_, section, error| {
+            errors.borrow_mut().push(format!("line {}: {}", section.start_location().lines() + 1, error.message()));
+        });
+        if let Some(display) = gtk::gdk::Display::default() {
+            gtk::style_context_add_provider_for_display(
+                &display,
+                &font_provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+            );
+            gtk::style_context_add_provider_for_display(
+                &display,
+                &user_provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 2,
+            );
+        }
+
+        let gtk_prefers_dark = gtk::Settings::default()
+            .map(|s| s.is_gtk_application_prefer_dark_theme() || s.gtk_theme_name().is_some_and(|n| n.to_lowercase().contains("dark")))
+            .unwrap_or(true);
+
+        let bus = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok();
+        let manager = Rc::new(Self {
+            light_provider,
+            font_provider,
+            user_provider,
+            user_css_path,
+            user_css_errors,
+            appearance: Cell::new(appearance),
+            color_scheme: Cell::new(bus.as_ref().and_then(read_color_scheme)),
+            gtk_prefers_dark,
+            _subscription: RefCell::new(None),
+        });
+
+        if let Some(bus) = bus {
+            let weak: Weak<Self> = Rc::downgrade(&manager);
+            let subscription = bus.subscribe_to_signal(
+                Some(PORTAL_BUS),
+                Some(PORTAL_SETTINGS),
+                Some("SettingChanged"),
+                Some(PORTAL_PATH),
+                Some(APPEARANCE_NAMESPACE),
+                gio::DBusSignalFlags::NONE,
+                move |signal| {
+                    let Some(manager) = weak.upgrade() else { return };
+                    let Some((namespace, key, value)) = signal.parameters.get::<(String, String, glib::Variant)>() else { return };
+                    if namespace == APPEARANCE_NAMESPACE && key == "color-scheme" {
+                        manager.color_scheme.set(variant_u32(&value));
+                        manager.apply();
+                    }
+                },
+            );
+            *manager._subscription.borrow_mut() = Some(subscription);
+        }
+
+        manager.apply();
+        manager
+    }
+
+    pub fn set_appearance(&self, appearance: Appearance) {
+        self.appearance.set(appearance);
+        self.apply();
+    }
+
+    /// Applies the chat text size (in px) and the font used for code blocks.
+    pub fn set_fonts(&self, chat_font_size: u32, monospace_font: &str) {
+        let family = monospace_font.replace(['"', '\\'], "");
+        let family = if family.trim().is_empty() { "monospace" } else { family.trim() };
+        self.font_provider.load_from_data(&format!(
+            ".user-message, .bot-message, textview.chat-input, .input-preview {{ font-size: {}px; }}\n\
+             .code-view {{ font-family: \"{}\", monospace; font-size: {}px; }}",
+            chat_font_size,
+            family,
+            chat_font_size.saturating_sub(2),
+        ));
+    }
+
+    pub fn user_css_path(&self) -> &PathBuf {
+        &self.user_css_path
+    }
+
+    /// (Re)loads the user stylesheet. Returns `Ok(false)` when no `style.css`
+    /// exists, and the parse errors when it does not load cleanly.
+    pub fn reload_user_css(&self) -> Result<bool, String> {
+        let css = match fs::read_to_string(&self.user_css_path) {
+            Ok(css) => css,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        self.user_css_errors.borrow_mut().clear();
+        self.user_provider.load_from_data(&css);
+        let errors = self.user_css_errors.borrow();
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        Ok(!css.is_empty())
+    }
+
+    fn is_dark(&self) -> bool {
+        match self.appearance.get() {
+            Appearance::Dark => true,
+            Appearance::Light => false,
+            Appearance::System => match self.color_scheme.get() {
+                Some(1) => true,
+                Some(2) => false,
+                _ => self.gtk_prefers_dark,
+            },
+        }
+    }
+
+    fn apply(&self) {
+        let dark = self.is_dark();
+        // libadwaita ignores gtk-application-prefer-dark-theme
+        adw::StyleManager::default().set_color_scheme(if dark { adw::ColorScheme::ForceDark } else { adw::ColorScheme::ForceLight });
+        let Some(display) = gtk::gdk::Display::default() else { return };
+        gtk::style_context_remove_provider_for_display(&display, &self.light_provider);
+        if !dark {
+            gtk::style_context_add_provider_for_display(
+                &display,
+                &self.light_provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+            );
+        }
+    }
+}
+
+fn read_color_scheme(bus: &gio::DBusConnection) -> Option<u32> {
+    let reply = bus.call_sync(
+        Some(PORTAL_BUS),
+        PORTAL_PATH,
+        PORTAL_SETTINGS,
+        "Read",
+        Some(&(APPEARANCE_NAMESPACE, "color-scheme").to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        500,
+        gio::Cancellable::NONE,
+    ).ok()?;
+    variant_u32(&reply.child_value(0))
+}
+
+/// The portal wraps values in one or two layers of `v` depending on its version.
+fn variant_u32(value: &glib::Variant) -> Option<u32> {
+    value.get::<u32>().or_else(|| value.as_variant().and_then(|inner| variant_u32(&inner)))
+}
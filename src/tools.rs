@@ -0,0 +1,127 @@
+use gtk4 as gtk;
+use gtk::glib;
+use ollama_rs::generation::tools::{ToolCall, ToolFunctionInfo, ToolInfo, ToolType};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::state::Settings;
+
+mod pacman;
+mod sandbox;
+mod sysinfo;
+
+pub use sandbox::NAME as RUN_CODE_TOOL;
+pub use sysinfo::{format_size, gpu_memory, GpuMemory};
+
+/// Upper bound on model → tool → model round trips for a single message.
+pub const MAX_TOOL_ROUNDS: usize = 8;
+
+/// Longest tool output passed back to the model, in characters.
+const MAX_OUTPUT_CHARS: usize = 8000;
+
+pub type ToolFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+
+/// A function the model can call. `parameters` is the JSON schema of the
+/// arguments object the model has to produce.
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn parameters(&self) -> Value;
+    fn call(&self, arguments: Value) -> ToolFuture;
+}
+
+/// The set of tools offered to a model for one request.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// Registry with the built-in tools the settings allow.
+    pub fn builtin(settings: &Settings) -> Self {
+        let mut registry = Self::default();
+        registry.register(CurrentTime);
+        registry.register(pacman::PackageInfo);
+        if settings.share_system_info {
+            registry.register(sysinfo::SystemInfo);
+        }
+        if settings.code_execution_enabled {
+            registry.register(sandbox::RunCode);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.push(Arc::new(tool));
+    }
+
+    /// Tool descriptions in the form `ChatMessageRequest::tools` expects.
+    pub fn infos(&self) -> Vec<ToolInfo> {
+        self.tools
+            .iter()
+            .filter_map(|tool| {
+                let function: ToolFunctionInfo = serde_json::from_value(json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "parameters": tool.parameters(),
+                }))
+                .map_err(|e| tracing::error!("Invalid schema for tool {}: {}", tool.name(), e))
+                .ok()?;
+                Some(ToolInfo { tool_type: ToolType::Function, function })
+            })
+            .collect()
+    }
+
+    /// Runs a tool call. Failures are returned as text so the model can react to them.
+    pub async fn call(&self, call: &ToolCall) -> String {
+        let Some(tool) = self.tools.iter().find(|t| t.name() == call.function.name) else {
+            return format!("Error: unknown tool `{}`", call.function.name);
+        };
+        // Some models send the arguments as a JSON-encoded string
+        let arguments = match &call.function.arguments {
+            Value::String(s) => serde_json::from_str(s).unwrap_or(Value::Null),
+            other => other.clone(),
+        };
+        match tool.call(arguments).await {
+            Ok(result) => result,
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+}
+
+/// Keeps tool output within what is useful to put into the context.
+fn truncate_output(output: &str) -> String {
+    if output.chars().count() <= MAX_OUTPUT_CHARS {
+        return output.to_string();
+    }
+    let mut truncated: String = output.chars().take(MAX_OUTPUT_CHARS).collect();
+    truncated.push_str("\n[output truncated]");
+    truncated
+}
+
+/// Reports the local date, time and timezone.
+struct CurrentTime;
+
+impl Tool for CurrentTime {
+    fn name(&self) -> &'static str {
+        "get_current_time"
+    }
+
+    fn description(&self) -> &'static str {
+        "Returns the current local date, time and timezone of the user's computer."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn call(&self, _arguments: Value) -> ToolFuture {
+        let now = glib::DateTime::now_local()
+            .and_then(|now| now.format("%A, %d %B %Y %H:%M:%S %Z (UTC%:z)"))
+            .map(|s| s.to_string())
+            .map_err(|e| e.to_string());
+        Box::pin(async move { now })
+    }
+}
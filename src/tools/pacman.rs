@@ -0,0 +1,153 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::process::Command;
+
+use super::{truncate_output, Tool, ToolFuture};
+
+const AUR_RPC: &str = "https://aur.archlinux.org/rpc/v5";
+
+/// Looks up Arch packages in the sync repositories, the local database and
+/// the AUR, so install instructions use real package names and versions.
+pub struct PackageInfo;
+
+impl Tool for PackageInfo {
+    fn name(&self) -> &'static str {
+        "arch_package"
+    }
+
+    fn description(&self) -> &'static str {
+        "Looks up Arch Linux packages. action=info returns details (version, description, dependencies, \
+         whether it is installed) for an exact package name from the official repositories or the AUR; \
+         action=search lists packages matching a search term."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["info", "search"],
+                    "description": "info for an exact package name, search for a keyword"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Package name or search term"
+                }
+            },
+            "required": ["action", "name"]
+        })
+    }
+
+    fn call(&self, arguments: Value) -> ToolFuture {
+        Box::pin(async move {
+            let action = arguments["action"].as_str().unwrap_or("info").to_string();
+            let name = arguments["name"].as_str().unwrap_or_default().trim().to_string();
+            if !is_valid_query(&name) {
+                return Err(format!("`{}` is not a valid package name", name));
+            }
+            let output = match action.as_str() {
+                "search" => search(&name).await,
+                _ => info(&name).await,
+            };
+            Ok(truncate_output(&output))
+        })
+    }
+}
+
+/// Package names are `[a-z0-9@._+-]`; anything else (and a leading dash,
+/// which pacman would read as an option) is rejected.
+fn is_valid_query(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "@._+-".contains(c))
+}
+
+async fn info(name: &str) -> String {
+    let mut sections = Vec::new();
+    match pacman(&["-Si", name]).await {
+        Some(repo) => sections.push(format!("Official repositories:\n{}", repo)),
+        None => sections.push("Official repositories: not found".to_string()),
+    }
+    match pacman(&["-Qi", name]).await {
+        Some(local) => sections.push(format!("Installed locally:\n{}", local)),
+        None => sections.push("Installed locally: no".to_string()),
+    }
+    match aur(&format!("{}/info?arg[]={}", AUR_RPC, name)).await {
+        Ok(results) if !results.is_empty() => {
+            let pkg = &results[0];
+            sections.push(format!(
+                "AUR:\nName: {}\nVersion: {}\nDescription: {}\nVotes: {}\nMaintainer: {}\nOut of date: {}\nDepends: {}\nMake depends: {}\nURL: https://aur.archlinux.org/packages/{}",
+                pkg["Name"].as_str().unwrap_or_default(),
+                pkg["Version"].as_str().unwrap_or_default(),
+                pkg["Description"].as_str().unwrap_or_default(),
+                pkg["NumVotes"],
+                pkg["Maintainer"].as_str().unwrap_or("orphaned"),
+                if pkg["OutOfDate"].is_null() { "no" } else { "yes" },
+                join_strings(&pkg["Depends"]),
+                join_strings(&pkg["MakeDepends"]),
+                pkg["Name"].as_str().unwrap_or_default(),
+            ));
+        }
+        Ok(_) => sections.push("AUR: not found".to_string()),
+        Err(e) => sections.push(format!("AUR: lookup failed ({})", e)),
+    }
+    sections.join("\n\n")
+}
+
+async fn search(term: &str) -> String {
+    let mut sections = Vec::new();
+    sections.push(match pacman(&["-Ss", term]).await {
+        Some(repo) => format!("Official repositories:\n{}", repo),
+        None => "Official repositories: no matches".to_string(),
+    });
+    sections.push(match pacman(&["-Qs", term]).await {
+        Some(local) => format!("Installed locally:\n{}", local),
+        None => "Installed locally: no matches".to_string(),
+    });
+    match aur(&format!("{}/search/{}", AUR_RPC, term)).await {
+        Ok(mut results) if !results.is_empty() => {
+            // Most popular first; the RPC returns them unordered
+            results.sort_by(|a, b| b["NumVotes"].as_u64().cmp(&a["NumVotes"].as_u64()));
+            let lines: Vec<String> = results.iter().take(15).map(|pkg| format!(
+                "aur/{} {} ({} votes)\n    {}",
+                pkg["Name"].as_str().unwrap_or_default(),
+                pkg["Version"].as_str().unwrap_or_default(),
+                pkg["NumVotes"],
+                pkg["Description"].as_str().unwrap_or_default(),
+            )).collect();
+            sections.push(format!("AUR ({} matches, top {}):\n{}", results.len(), lines.len(), lines.join("\n")));
+        }
+        Ok(_) => sections.push("AUR: no matches".to_string()),
+        Err(e) => sections.push(format!("AUR: search failed ({})", e)),
+    }
+    sections.join("\n\n")
+}
+
+/// Runs pacman and returns its stdout, or `None` when it fails or prints nothing.
+async fn pacman(args: &[&str]) -> Option<String> {
+    let output = Command::new("pacman").args(args).env("LC_ALL", "C").output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && !stdout.is_empty() { Some(stdout) } else { None }
+}
+
+async fn aur(url: &str) -> Result<Vec<Value>, String> {
+    let res = reqwest::Client::new()
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let value: Value = res.json().await.map_err(|e| e.to_string())?;
+    if let Some(error) = value["error"].as_str() {
+        return Err(error.to_string());
+    }
+    Ok(value["results"].as_array().cloned().unwrap_or_default())
+}
+
+fn join_strings(value: &Value) -> String {
+    value.as_array()
+        .map(|items| items.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" "))
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "none".to_string())
+}
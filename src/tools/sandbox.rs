@@ -0,0 +1,178 @@
+use serde_json::{json, Value};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+
+use super::{truncate_output, Tool, ToolFuture};
+use crate::utils::find_program;
+
+pub const NAME: &str = "run_code";
+
+/// Wall-clock limit for one snippet.
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Output kept from stdout and stderr together; the snippet is killed once
+/// it writes more, so something like `yes` can't fill the app's memory.
+const MAX_CAPTURE_BYTES: usize = 256 * 1024;
+/// Size of the sandbox's /tmp, which lives in RAM.
+const TMP_SIZE: &str = "67108864";
+
+/// Runs Python or Bash snippets inside bubblewrap (or firejail as a
+/// fallback) with no network, a read-only /usr and a small throwaway /tmp. Only
+/// registered when code execution is enabled in General settings.
+pub struct RunCode;
+
+impl Tool for RunCode {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn description(&self) -> &'static str {
+        "Executes a Python 3 or Bash snippet in an isolated sandbox without network access or access to \
+         the user's files, and returns the exit code, stdout and stderr. Use it to compute, verify or \
+         demonstrate things instead of guessing. Runs are limited to 15 seconds."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "language": {
+                    "type": "string",
+                    "enum": ["python", "bash"]
+                },
+                "code": {
+                    "type": "string",
+                    "description": "The complete program to run"
+                }
+            },
+            "required": ["language", "code"]
+        })
+    }
+
+    fn call(&self, arguments: Value) -> ToolFuture {
+        Box::pin(async move {
+            let language = arguments["language"].as_str().unwrap_or("python").to_string();
+            let code = arguments["code"].as_str().unwrap_or_default().to_string();
+            if code.trim().is_empty() {
+                return Err("no code given".to_string());
+            }
+            let interpreter: &[&str] = match language.as_str() {
+                "python" => &["/usr/bin/python3", "-"],
+                "bash" => &["/usr/bin/bash", "-s"],
+                other => return Err(format!("unsupported language `{}`", other)),
+            };
+            let mut command = sandboxed(interpreter)?;
+            let mut child = command
+                .env_clear()
+                .env("PATH", "/usr/bin")
+                .env("HOME", "/tmp")
+                .env("LANG", "C.UTF-8")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| format!("failed to start the sandbox: {}", e))?;
+
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(code.as_bytes()).await.map_err(|e| e.to_string())?;
+            }
+            let output = match tokio::time::timeout(TIMEOUT, capture(&mut child)).await {
+                Ok(output) => output.map_err(|e| e.to_string())?,
+                Err(_) => return Err(format!("timed out after {} seconds", TIMEOUT.as_secs())),
+            };
+
+            let exit = if output.overflowed {
+                format!("killed after writing more than {} KB of output", MAX_CAPTURE_BYTES / 1024)
+            } else {
+                output.status.code().map(|c| c.to_string()).unwrap_or_else(|| "killed".to_string())
+            };
+            let mut result = format!("Exit code: {}", exit);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !stdout.trim().is_empty() {
+                result.push_str(&format!("\n\nstdout:\n{}", stdout.trim_end()));
+            }
+            if !stderr.trim().is_empty() {
+                result.push_str(&format!("\n\nstderr:\n{}", stderr.trim_end()));
+            }
+            Ok(truncate_output(&result))
+        })
+    }
+}
+
+struct Output {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// Killed for writing more than `MAX_CAPTURE_BYTES`
+    overflowed: bool,
+}
+
+/// Reads the child's stdout and stderr as they come and waits for it to
+/// exit, killing it once the two add up to more than `MAX_CAPTURE_BYTES`.
+async fn capture(child: &mut Child) -> std::io::Result<Output> {
+    let (Some(mut stdout_pipe), Some(mut stderr_pipe)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(std::io::Error::other("the sandbox's output isn't piped"));
+    };
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    let (mut stdout_chunk, mut stderr_chunk) = ([0u8; 8192], [0u8; 8192]);
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    let mut overflowed = false;
+    while stdout_open || stderr_open {
+        tokio::select! {
+            read = stdout_pipe.read(&mut stdout_chunk), if stdout_open => match read? {
+                0 => stdout_open = false,
+                n => stdout.extend_from_slice(&stdout_chunk[..n]),
+            },
+            read = stderr_pipe.read(&mut stderr_chunk), if stderr_open => match read? {
+                0 => stderr_open = false,
+                n => stderr.extend_from_slice(&stderr_chunk[..n]),
+            },
+        }
+        if stdout.len() + stderr.len() > MAX_CAPTURE_BYTES {
+            overflowed = true;
+            child.start_kill()?;
+            break;
+        }
+    }
+    let status = child.wait().await?;
+    Ok(Output { status, stdout, stderr, overflowed })
+}
+
+/// Wraps the interpreter in bubblewrap, or firejail when bwrap is missing.
+fn sandboxed(interpreter: &[&str]) -> Result<Command, String> {
+    if let Some(bwrap) = find_program("bwrap") {
+        let mut command = Command::new(bwrap);
+        command.args([
+            "--unshare-all", "--die-with-parent", "--new-session",
+            "--ro-bind", "/usr", "/usr",
+            "--symlink", "usr/bin", "/bin",
+            "--symlink", "usr/bin", "/sbin",
+            "--symlink", "usr/lib", "/lib",
+            "--symlink", "usr/lib", "/lib64",
+            "--ro-bind-try", "/etc/ld.so.cache", "/etc/ld.so.cache",
+            "--ro-bind-try", "/etc/localtime", "/etc/localtime",
+            "--proc", "/proc",
+            "--dev", "/dev",
+            "--size", TMP_SIZE, "--tmpfs", "/tmp",
+            "--chdir", "/tmp",
+            "--",
+        ]);
+        command.args(interpreter);
+        return Ok(command);
+    }
+    if let Some(firejail) = find_program("firejail") {
+        let mut command = Command::new(firejail);
+        command.args([
+            "--quiet", "--net=none", "--private", "--private-tmp", "--noroot",
+            "--caps.drop=all", "--seccomp", "--nosound", "--no3d",
+        ]);
+        // firejail's private /tmp has no size of its own; cap what one file can grow to
+        command.arg(format!("--rlimit-fsize={}", TMP_SIZE)).arg("--");
+        command.args(interpreter);
+        return Ok(command);
+    }
+    Err("no sandbox available; install bubblewrap (`pacman -S bubblewrap`) or firejail".to_string())
+}
@@ -0,0 +1,187 @@
+use serde_json::{json, Value};
+use std::fs;
+use tokio::process::Command;
+
+use super::{Tool, ToolFuture};
+
+/// Read-only summary of the user's hardware and OS. Only registered when
+/// "Share system information with tools" is enabled in General settings.
+pub struct SystemInfo;
+
+impl Tool for SystemInfo {
+    fn name(&self) -> &'static str {
+        "get_system_info"
+    }
+
+    fn description(&self) -> &'static str {
+        "Returns the user's hardware and operating system: distribution, kernel, CPU, memory, GPUs \
+         (with VRAM where available) and disk usage. Use it to tailor advice, e.g. which model size fits."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn call(&self, _arguments: Value) -> ToolFuture {
+        Box::pin(async move {
+            let sections = [
+                format!("OS: {}", os_name().unwrap_or_else(|| "unknown".to_string())),
+                format!("Kernel: {}", read_trimmed("/proc/sys/kernel/osrelease").unwrap_or_else(|| "unknown".to_string())),
+                format!("CPU: {}", cpu().unwrap_or_else(|| "unknown".to_string())),
+                format!("Memory: {}", memory().unwrap_or_else(|| "unknown".to_string())),
+                format!("GPU:\n{}", gpus().await),
+                format!("Disks:\n{}", disks().await),
+            ];
+            Ok(sections.join("\n"))
+        })
+    }
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn os_name() -> Option<String> {
+    let release = fs::read_to_string("/etc/os-release").ok()?;
+    release.lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim_matches('"').to_string())
+}
+
+fn cpu() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    let model = cpuinfo.lines()
+        .find_map(|line| line.strip_prefix("model name").and_then(|rest| rest.split_once(':')))
+        .map(|(_, name)| name.trim().to_string())?;
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    Some(format!("{} ({} threads)", model, threads))
+}
+
+fn memory() -> Option<String> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| -> Option<u64> {
+        meminfo.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.trim_start_matches(':').split_whitespace().next())
+            .and_then(|kb| kb.parse().ok())
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable").unwrap_or(0);
+    Some(format!("{} total, {} available", format_size(total * 1024), format_size(available * 1024)))
+}
+
+async fn gpus() -> String {
+    let mut lines = Vec::new();
+    // NVIDIA reports VRAM through nvidia-smi only
+    if let Some(out) = run("nvidia-smi", &["--query-gpu=name,memory.total,memory.used,driver_version", "--format=csv,noheader"]).await {
+        for line in out.lines() {
+            lines.push(format!("  NVIDIA {} (total, used, driver)", line.trim()));
+        }
+    }
+    // amdgpu and some Intel drivers expose VRAM in sysfs
+    if let Ok(entries) = fs::read_dir("/sys/class/drm") {
+        let mut cards: Vec<_> = entries.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with("card") && !n.contains('-')).unwrap_or(false))
+            .collect();
+        cards.sort();
+        for card in cards {
+            let device = card.join("device");
+            let Some(total) = fs::read_to_string(device.join("mem_info_vram_total")).ok().and_then(|s| s.trim().parse::<u64>().ok()) else {
+                continue;
+            };
+            let used = fs::read_to_string(device.join("mem_info_vram_used")).ok().and_then(|s| s.trim().parse::<u64>().ok());
+            let vendor = fs::read_to_string(device.join("vendor")).map(|v| v.trim().to_string()).unwrap_or_default();
+            lines.push(format!(
+                "  {} ({}): {} VRAM{}",
+                card.file_name().and_then(|n| n.to_str()).unwrap_or("card"),
+                match vendor.as_str() { "0x1002" => "AMD", "0x8086" => "Intel", "0x10de" => "NVIDIA", _ => "unknown vendor" },
+                format_size(total),
+                used.map(|u| format!(", {} used", format_size(u))).unwrap_or_default(),
+            ));
+        }
+    }
+    if let Some(out) = run("lspci", &[]).await {
+        for line in out.lines().filter(|l| l.contains("VGA compatible") || l.contains("3D controller") || l.contains("Display controller")) {
+            if let Some((_, name)) = line.split_once(": ") {
+                lines.push(format!("  {}", name.trim()));
+            }
+        }
+    }
+    if lines.is_empty() { "  unknown".to_string() } else { lines.join("\n") }
+}
+
+/// Memory of one GPU on this machine, in bytes.
+pub struct GpuMemory {
+    pub name: String,
+    pub total: u64,
+    pub used: u64,
+}
+
+/// VRAM use of the local GPUs that report it: NVIDIA through `nvidia-smi`,
+/// amdgpu and some Intel drivers through sysfs.
+pub async fn gpu_memory() -> Vec<GpuMemory> {
+    let mut gpus = Vec::new();
+    if let Some(out) = run("nvidia-smi", &["--query-gpu=name,memory.total,memory.used", "--format=csv,noheader,nounits"]).await {
+        for line in out.lines() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let mib = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).map(|v| v * 1024 * 1024);
+            if let (Some(name), Some(total), Some(used)) = (fields.first(), mib(1), mib(2)) {
+                gpus.push(GpuMemory { name: name.to_string(), total, used });
+            }
+        }
+    }
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else { return gpus };
+    let mut cards: Vec<_> = entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with("card") && !n.contains('-')).unwrap_or(false))
+        .collect();
+    cards.sort();
+    for card in cards {
+        let device = card.join("device");
+        let read = |name: &str| fs::read_to_string(device.join(name)).ok().and_then(|s| s.trim().parse::<u64>().ok());
+        let (Some(total), Some(used)) = (read("mem_info_vram_total"), read("mem_info_vram_used")) else { continue };
+        let vendor = fs::read_to_string(device.join("vendor")).map(|v| v.trim().to_string()).unwrap_or_default();
+        // NVIDIA cards were already listed by nvidia-smi
+        if vendor == "0x10de" {
+            continue;
+        }
+        let vendor = match vendor.as_str() { "0x1002" => "AMD", "0x8086" => "Intel", _ => "GPU" };
+        gpus.push(GpuMemory {
+            name: format!("{} ({})", vendor, card.file_name().and_then(|n| n.to_str()).unwrap_or("card")),
+            total,
+            used,
+        });
+    }
+    gpus
+}
+
+async fn disks() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    match run("df", &["-h", "--output=target,size,used,avail", "/", &home]).await {
+        Some(out) => {
+            let mut seen = Vec::new();
+            out.lines()
+                .filter(|line| {
+                    let target = line.split_whitespace().next().unwrap_or_default().to_string();
+                    !seen.contains(&target) && { seen.push(target); true }
+                })
+                .map(|line| format!("  {}", line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        None => "  unknown".to_string(),
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).env("LC_ALL", "C").output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && !stdout.is_empty() { Some(stdout) } else { None }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    let gib = bytes as f64 / GIB;
+    if gib >= 1.0 { format!("{:.1} GiB", gib) } else { format!("{} MiB", bytes / (1024 * 1024)) }
+}
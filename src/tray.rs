@@ -0,0 +1,132 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+
+const ITEM_XML: &str = r#"
+<node>
+  <interface name="org.kde.StatusNotifierItem">
+    <property name="Category" type="s" access="read"/>
+    <property name="Id" type="s" access="read"/>
+    <property name="Title" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconName" type="s" access="read"/>
+    <property name="ToolTip" type="(sa(iiay)ss)" access="read"/>
+    <property name="ItemIsMenu" type="b" access="read"/>
+    <method name="Activate">
+      <arg name="x" type="i" direction="in"/>
+      <arg name="y" type="i" direction="in"/>
+    </method>
+    <method name="SecondaryActivate">
+      <arg name="x" type="i" direction="in"/>
+      <arg name="y" type="i" direction="in"/>
+    </method>
+    <method name="ContextMenu">
+      <arg name="x" type="i" direction="in"/>
+      <arg name="y" type="i" direction="in"/>
+    </method>
+    <method name="Scroll">
+      <arg name="delta" type="i" direction="in"/>
+      <arg name="orientation" type="s" direction="in"/>
+    </method>
+    <signal name="NewStatus">
+      <arg name="status" type="s"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+/// A StatusNotifierItem exported on the application's session bus
+/// connection, shown by panels that host a tray (KDE, XFCE, waybar, GNOME
+/// with the AppIndicator extension). Clicking it calls `on_activate`.
+pub struct StatusIcon {
+    connection: gio::DBusConnection,
+    visible: Rc<Cell<bool>>,
+}
+
+impl StatusIcon {
+    pub fn new(connection: &gio::DBusConnection, visible: bool, on_activate: impl Fn() + 'static) -> Result<Self, glib::Error> {
+        let node = gio::DBusNodeInfo::for_xml(ITEM_XML)?;
+        let interface = node.lookup_interface(ITEM_INTERFACE).expect("StatusNotifierItem interface in XML");
+        let visible = Rc::new(Cell::new(visible));
+
+        let visible_prop = visible.clone();
+        connection
+            .register_object(ITEM_PATH, &interface)
+            .method_call(move |_, _, _, _, method, _, invocation| {
+                if method == "Activate" || method == "SecondaryActivate" {
+                    on_activate();
+                }
+                invocation.return_value(None);
+            })
+            .property(move |_, _, _, _, property| match property {
+                "Category" => "ApplicationStatus".to_variant(),
+                "Id" => "arch-llm".to_variant(),
+                "Title" => "Arch LLM".to_variant(),
+                "ToolTip" => ("", Vec::<(i32, i32, Vec<u8>)>::new(), "Arch LLM", "Running in the background").to_variant(),
+                "Status" => status_name(visible_prop.get()).to_variant(),
+                "IconName" => "internet-chat".to_variant(),
+                "ItemIsMenu" => false.to_variant(),
+                _ => "".to_variant(),
+            })
+            .build()?;
+
+        // Register now and again whenever the tray host (re)starts
+        gio::bus_watch_name_on_connection(
+            connection,
+            WATCHER_NAME,
+            gio::BusNameWatcherFlags::NONE,
+            |connection, _, _| {
+                let Some(service) = connection.unique_name() else { return };
+                connection.call(
+                    Some(WATCHER_NAME),
+                    "/StatusNotifierWatcher",
+                    WATCHER_NAME,
+                    "RegisterStatusNotifierItem",
+                    Some(&(service.as_str(),).to_variant()),
+                    None,
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                    gio::Cancellable::NONE,
+                    |result| {
+                        if let Err(e) = result {
+                            tracing::error!("Failed to register status icon: {}", e);
+                        }
+                    },
+                );
+            },
+            |_, _| {},
+        );
+
+        Ok(Self {
+            connection: connection.clone(),
+            visible,
+        })
+    }
+
+    /// Shows or hides the icon; hidden icons stay registered as `Passive`.
+    pub fn set_visible(&self, visible: bool) {
+        if self.visible.replace(visible) == visible {
+            return;
+        }
+        if let Err(e) = self.connection.emit_signal(
+            None,
+            ITEM_PATH,
+            ITEM_INTERFACE,
+            "NewStatus",
+            Some(&(status_name(visible),).to_variant()),
+        ) {
+            tracing::warn!("Failed to update status icon: {}", e);
+        }
+    }
+}
+
+fn status_name(visible: bool) -> &'static str {
+    if visible { "Active" } else { "Passive" }
+}
@@ -0,0 +1,25 @@
+//! Widgets making up the main window. Each part is built by a constructor
+//! that takes the shared state it needs; `build_ui` wires them together.
+
+pub mod agent_picker;
+pub mod arena;
+pub mod badges;
+pub mod chat;
+pub mod composer;
+pub mod context_prompt;
+pub mod diagnose;
+pub mod inspector;
+pub mod monitor;
+pub mod notes;
+pub mod outbox;
+pub mod saved;
+pub mod secrets_prompt;
+pub mod send;
+pub mod server_log;
+pub mod settings;
+pub mod sidebar;
+pub mod split;
+pub mod tabs;
+pub mod templates;
+pub mod toasts;
+pub mod vision_offer;
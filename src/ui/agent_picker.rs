@@ -0,0 +1,78 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, DropDown, Label, Orientation, StringList};
+use std::sync::{Arc, Mutex};
+
+use crate::error::LockExt;
+use crate::state::{Agent, AppState};
+
+/// Longest agent subtitle in the list, in characters, before it is cut off.
+const SUBTITLE_CHARS: i32 = 48;
+
+/// Agent dropdown whose list shows each agent's model and description under
+/// its name, so similarly named agents can be told apart. Item `i` is agent
+/// `i` of the settings; the button itself shows the name only.
+#[derive(Clone)]
+pub struct AgentPicker {
+    pub dropdown: DropDown,
+    names: StringList,
+    state: Arc<Mutex<AppState>>,
+}
+
+impl AgentPicker {
+    pub fn new(state: &Arc<Mutex<AppState>>) -> Self {
+        let names = StringList::new(&[]);
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(|_, item| {
+            let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+            let row = Box::builder().orientation(Orientation::Vertical).spacing(2).build();
+            row.append(&Label::builder().xalign(0.0).build());
+            row.append(&Label::builder()
+                .xalign(0.0)
+                .max_width_chars(SUBTITLE_CHARS)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .css_classes(["dim-label", "agent-subtitle"])
+                .build());
+            item.set_child(Some(&row));
+        });
+        let state_bind = state.clone();
+        factory.connect_bind(move |_, item| {
+            let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+            let Some(row) = item.child() else { return };
+            let (Some(name), Some(subtitle)) = (row.first_child().and_downcast::<Label>(), row.last_child().and_downcast::<Label>()) else { return };
+            let Some(agent) = state_bind.locked().settings.agents.get(item.position() as usize).cloned() else { return };
+            name.set_label(&agent.name);
+            let details = agent_details(&agent);
+            subtitle.set_label(&details);
+            subtitle.set_tooltip_text(Some(&details));
+        });
+        let dropdown = DropDown::builder()
+            .model(&names)
+            .list_factory(&factory)
+            .build();
+        let state_selected = state.clone();
+        dropdown.connect_selected_notify(move |dd| {
+            let agent = state_selected.locked().settings.agents.get(dd.selected() as usize).cloned();
+            dd.set_tooltip_text(agent.as_ref().map(agent_details).as_deref());
+        });
+
+        let picker = Self { dropdown, names, state: state.clone() };
+        picker.refresh();
+        picker
+    }
+
+    /// Refills the list after the agents were edited.
+    pub fn refresh(&self) {
+        let names: Vec<String> = self.state.locked().settings.agents.iter().map(|a| a.name.clone()).collect();
+        let name_refs: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        self.names.splice(0, self.names.n_items(), &name_refs);
+    }
+}
+
+/// e.g. "llama3.2 · Answers questions about Arch Linux".
+fn agent_details(agent: &Agent) -> String {
+    match agent.description.trim() {
+        "" => agent.model.clone(),
+        description => format!("{} · {}", agent.model, description),
+    }
+}
@@ -0,0 +1,243 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, ListBox, Orientation, ScrolledWindow, TextView};
+use adw::ToastOverlay;
+use ollama_rs::generation::chat::ChatMessage;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::arena::{leaderboard, pick_pair};
+use crate::error::{AppError, LockExt};
+use crate::provider::{stream_reply, Backend, GenerationOptions};
+use crate::state::{AppState, ArenaOutcome, ArenaVote, ChatEvent};
+use crate::ui::toasts::Report;
+
+const SIDES: [&str; 2] = ["Model A", "Model B"];
+
+/// Blind comparison of two random installed models: the prompt goes to both,
+/// the answers are shown as "Model A" and "Model B", and the names are only
+/// revealed once the user has voted. Votes feed a local leaderboard.
+#[derive(Clone)]
+pub struct ArenaView {
+    pub widget: Box,
+    pub back_btn: Button,
+    prompt_view: TextView,
+    ask_btn: Button,
+    titles: [Label; 2],
+    answers: [Label; 2],
+    vote_box: Box,
+    leaderboard_list: ListBox,
+    /// The models of the current round, in display order
+    round: Rc<RefCell<Option<(String, String)>>>,
+    /// Bumped per round, so late chunks of an abandoned round are dropped
+    generation: Rc<Cell<u32>>,
+    tasks: Rc<RefCell<Vec<tokio::task::AbortHandle>>>,
+    state: Arc<Mutex<AppState>>,
+    toasts: ToastOverlay,
+}
+
+impl ArenaView {
+    pub fn new(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> Self {
+        let widget = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(10)
+            .margin_start(20)
+            .margin_end(20)
+            .margin_top(20)
+            .margin_bottom(20)
+            .build();
+
+        let header = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        let back_btn = Button::with_label("← Back to Chat");
+        header.append(&back_btn);
+        header.append(&Label::builder().label("Arena").css_classes(["title-4"]).hexpand(true).xalign(0.0).build());
+        widget.append(&header);
+
+        let prompt_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        let prompt_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .hexpand(true)
+            .css_classes(["chat-input"])
+            .build();
+        prompt_row.append(&ScrolledWindow::builder().child(&prompt_view).min_content_height(60).hexpand(true).build());
+        let ask_btn = Button::builder()
+            .label("Ask Both")
+            .tooltip_text("Send the prompt to two random installed models")
+            .valign(gtk::Align::Start)
+            .css_classes(["suggested-action"])
+            .build();
+        prompt_row.append(&ask_btn);
+        widget.append(&prompt_row);
+
+        let columns = Box::builder().orientation(Orientation::Horizontal).spacing(10).homogeneous(true).vexpand(true).build();
+        let make_column = |side: &str| {
+            let column = Box::builder().orientation(Orientation::Vertical).spacing(6).build();
+            let title = Label::builder().label(side).xalign(0.0).css_classes(["settings-label"]).build();
+            let answer = Label::builder().xalign(0.0).yalign(0.0).wrap(true).selectable(true).build();
+            column.append(&title);
+            column.append(&ScrolledWindow::builder()
+                .child(&answer)
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .build());
+            columns.append(&column);
+            (title, answer)
+        };
+        let (title_a, answer_a) = make_column(SIDES[0]);
+        let (title_b, answer_b) = make_column(SIDES[1]);
+        widget.append(&columns);
+
+        let vote_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).halign(gtk::Align::Center).sensitive(false).build();
+        widget.append(&vote_box);
+
+        widget.append(&Label::builder().label("Leaderboard").xalign(0.0).css_classes(["settings-label"]).build());
+        let leaderboard_list = ListBox::builder().selection_mode(gtk::SelectionMode::None).build();
+        widget.append(&ScrolledWindow::builder().child(&leaderboard_list).min_content_height(140).build());
+
+        let view = Self {
+            widget,
+            back_btn,
+            prompt_view,
+            ask_btn,
+            titles: [title_a, title_b],
+            answers: [answer_a, answer_b],
+            vote_box,
+            leaderboard_list,
+            round: Rc::new(RefCell::new(None)),
+            generation: Rc::new(Cell::new(0)),
+            tasks: Rc::new(RefCell::new(Vec::new())),
+            state: state.clone(),
+            toasts: toasts.clone(),
+        };
+
+        for (label, outcome) in [
+            ("A is better", ArenaOutcome::AWins),
+            ("Tie", ArenaOutcome::Tie),
+            ("Both are bad", ArenaOutcome::BothBad),
+            ("B is better", ArenaOutcome::BWins),
+        ] {
+            let button = Button::with_label(label);
+            let view_c = view.clone();
+            button.connect_clicked(move |_| view_c.vote(outcome));
+            view.vote_box.append(&button);
+        }
+        view.ask_btn.connect_clicked({
+            let view = view.clone();
+            move |_| view.ask()
+        });
+        view.refresh();
+        view
+    }
+
+    /// Rebuilds the leaderboard from the stored votes.
+    pub fn refresh(&self) {
+        while let Some(child) = self.leaderboard_list.first_child() {
+            self.leaderboard_list.remove(&child);
+        }
+        let standings = leaderboard(&self.state.locked().arena_votes);
+        if standings.is_empty() {
+            self.leaderboard_list.append(&Label::builder().label("No votes yet").xalign(0.0).margin_start(10).margin_top(5).margin_bottom(5).css_classes(["dim-label"]).build());
+        }
+        for (place, standing) in standings.iter().enumerate() {
+            let row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_start(10).margin_end(10).margin_top(5).margin_bottom(5).build();
+            row.append(&Label::new(Some(&format!("{}.", place + 1))));
+            row.append(&Label::builder().label(&standing.model).xalign(0.0).hexpand(true).build());
+            row.append(&Label::builder()
+                .label(format!("{} wins · {} losses · {} ties", standing.wins, standing.losses, standing.ties))
+                .css_classes(["dim-label"])
+                .build());
+            row.append(&Label::new(Some(&format!("{:.0}", standing.rating))));
+            self.leaderboard_list.append(&row);
+        }
+    }
+
+    /// Starts a round with two new models, abandoning an unfinished one.
+    fn ask(&self) {
+        let buffer = self.prompt_view.buffer();
+        let (start, end) = buffer.bounds();
+        let prompt = buffer.text(&start, &end, false).trim().to_string();
+        if prompt.is_empty() {
+            return;
+        }
+        let (server, models) = {
+            let s = self.state.locked();
+            (s.ollama_server(), s.available_models.clone())
+        };
+        let Some((model_a, model_b)) = pick_pair(&models) else {
+            self.toasts.report(&AppError::new("The arena needs two models", "Install at least two models in Settings → Models"));
+            return;
+        };
+
+        for task in self.tasks.borrow_mut().drain(..) {
+            task.abort();
+        }
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+        *self.round.borrow_mut() = Some((model_a.clone(), model_b.clone()));
+        self.vote_box.set_sensitive(false);
+        let finished = Rc::new(Cell::new(0));
+
+        for (i, model) in [model_a, model_b].into_iter().enumerate() {
+            self.titles[i].set_label(SIDES[i]);
+            self.answers[i].set_label("Thinking…");
+            let (sender, receiver) = async_channel::unbounded();
+            let backend = Backend::Ollama(server.clone());
+            let messages = vec![ChatMessage::user(prompt.clone())];
+            let task = tokio::spawn(async move {
+                stream_reply(&backend, &model, &messages, &[], None, &GenerationOptions::default(), &sender).await
+            });
+            self.tasks.borrow_mut().push(task.abort_handle());
+
+            let view = self.clone();
+            let finished = finished.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let answer = &view.answers[i];
+                let mut text = String::new();
+                while let Ok(event) = receiver.recv().await {
+                    if let ChatEvent::Chunk(chunk) = event {
+                        if view.generation.get() != generation {
+                            return;
+                        }
+                        text.push_str(&chunk);
+                        answer.set_label(&text);
+                    }
+                }
+                let result = task.await;
+                if view.generation.get() != generation {
+                    return;
+                }
+                match result {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => answer.set_label(&format!("{}\n\nError: {}", text, e)),
+                    Err(_) => return,
+                }
+                finished.set(finished.get() + 1);
+                if finished.get() == SIDES.len() {
+                    view.vote_box.set_sensitive(true);
+                }
+            });
+        }
+    }
+
+    /// Records the vote and reveals which model was which.
+    fn vote(&self, outcome: ArenaOutcome) {
+        let Some((model_a, model_b)) = self.round.borrow_mut().take() else { return };
+        self.vote_box.set_sensitive(false);
+        self.titles[0].set_label(&format!("{}: {}", SIDES[0], model_a));
+        self.titles[1].set_label(&format!("{}: {}", SIDES[1], model_b));
+        {
+            let mut s = self.state.locked();
+            s.arena_votes.push(ArenaVote {
+                model_a,
+                model_b,
+                outcome,
+                voted_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default(),
+            });
+            s.save_arena();
+        }
+        self.refresh();
+    }
+}
@@ -0,0 +1,63 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Label, Orientation};
+use std::sync::{Arc, Mutex};
+
+use crate::error::LockExt;
+use crate::provider::{model_capabilities, Capability};
+use crate::state::AppState;
+
+/// Small labels for what a model can do, e.g. "Vision" and "Tools".
+pub fn build_badges(capabilities: &[Capability]) -> Box {
+    let badges = Box::builder().orientation(Orientation::Horizontal).spacing(4).valign(gtk::Align::Center).build();
+    fill_badges(&badges, capabilities);
+    badges
+}
+
+fn fill_badges(badges: &Box, capabilities: &[Capability]) {
+    while let Some(child) = badges.first_child() {
+        badges.remove(&child);
+    }
+    for capability in capabilities {
+        badges.append(&Label::builder().label(capability.label()).css_classes(["capability-badge"]).build());
+    }
+}
+
+/// Asks the Ollama server what each of `models` can do, skipping models
+/// already known, and keeps the answers in `AppState::model_capabilities`.
+pub async fn look_up_capabilities(state: &Arc<Mutex<AppState>>, models: &[String]) {
+    let (ollama, missing) = {
+        let s = state.locked();
+        let missing: Vec<String> = models.iter().filter(|m| !s.model_capabilities.contains_key(*m)).cloned().collect();
+        (s.ollama.clone(), missing)
+    };
+    for model in missing {
+        if let Some(capabilities) = model_capabilities(&ollama, &model).await {
+            state.locked().model_capabilities.insert(model, capabilities);
+        }
+    }
+}
+
+/// List rows for a model dropdown over a `StringList` of model names: the
+/// name with the model's badges, as far as they are known.
+pub fn model_list_factory(state: &Arc<Mutex<AppState>>) -> gtk::SignalListItemFactory {
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+        let row = Box::builder().orientation(Orientation::Horizontal).spacing(8).build();
+        row.append(&Label::builder().xalign(0.0).hexpand(true).build());
+        row.append(&build_badges(&[]));
+        item.set_child(Some(&row));
+    });
+    let state = state.clone();
+    factory.connect_bind(move |_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+        let Some(row) = item.child() else { return };
+        let (Some(name), Some(badges)) = (row.first_child().and_downcast::<Label>(), row.last_child().and_downcast::<Box>()) else { return };
+        let Some(model) = item.item().and_downcast::<gtk::StringObject>().map(|o| o.string().to_string()) else { return };
+        let capabilities = state.locked().model_capabilities.get(&model).cloned().unwrap_or_default();
+        name.set_label(&model);
+        fill_badges(&badges, &capabilities);
+    });
+    factory
+}
@@ -0,0 +1,1093 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, GestureClick, Label, Orientation, ScrolledWindow};
+use adw::ToastOverlay;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::controller::{Command, Commands};
+use crate::error::LockExt;
+use crate::provider::Provider;
+use crate::speech::Speaker;
+use crate::state::{AppState, Bookmark, MessageMeta, StoredMessage};
+use crate::terminal;
+use crate::tools::RUN_CODE_TOOL;
+use crate::ui::toasts::{toast, Report};
+use crate::utils::{find_code_fence, format_timestamp, image_data_uri, is_openable_link, markdown_to_pango, pretty_json, relative_time, MarkdownBlock};
+
+/// Smallest horizontal margin around the chat column.
+pub const CHAT_MIN_MARGIN: i32 = 16;
+/// How close to the end (in pixels) still counts as reading the latest message.
+const FOLLOW_THRESHOLD: f64 = 40.0;
+/// Largest image downloaded for a reply.
+const MAX_REMOTE_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+/// How long an image download may take.
+const REMOTE_IMAGE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The scrolling message list of a conversation, one per tab. New content keeps
+/// the view at the bottom only while the user is there; otherwise a
+/// "Jump to latest" pill appears.
+#[derive(Clone)]
+pub struct ChatView {
+    pub widget: gtk::Overlay,
+    pub scrolled_window: ScrolledWindow,
+    pub chat_box: Box,
+    following: Rc<Cell<bool>>,
+    jump_btn: Button,
+    /// Relative-time captions of the shown messages, refreshed every minute
+    time_labels: Rc<RefCell<Vec<(glib::WeakRef<Label>, i64)>>>,
+    /// Bookmark stars of the shown replies, with the text they save
+    bookmark_buttons: Rc<RefCell<Vec<(glib::WeakRef<Button>, String)>>>,
+    /// Remote images the user chose to load, by URL, so re-rendering shows
+    /// them without downloading again
+    remote_images: Rc<RefCell<HashMap<String, gtk::gdk::Texture>>>,
+    suggestion_handler: Rc<RefCell<Option<Rc<dyn Fn(String)>>>>,
+    /// `OpenChat::tab_id` of the conversation shown, which bookmarks and edits apply to
+    tab_id: u64,
+    state: Arc<Mutex<AppState>>,
+    speaker: Rc<Speaker>,
+    toasts: ToastOverlay,
+    commands: Commands,
+}
+
+impl ChatView {
+    pub fn new(state: &Arc<Mutex<AppState>>, speaker: &Rc<Speaker>, toasts: &ToastOverlay, commands: &Commands, tab_id: u64) -> Self {
+        let scrolled_window = ScrolledWindow::builder()
+            .vexpand(true)
+            .build();
+        let chat_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(10)
+            .margin_start(CHAT_MIN_MARGIN)
+            .margin_end(CHAT_MIN_MARGIN)
+            .margin_top(20)
+            .margin_bottom(20)
+            .build();
+        scrolled_window.set_child(Some(&chat_box));
+
+        let jump_btn = Button::builder()
+            .label("Jump to latest ↓")
+            .halign(gtk::Align::Center)
+            .valign(gtk::Align::End)
+            .margin_bottom(16)
+            .css_classes(["jump-pill"])
+            .visible(false)
+            .build();
+        let widget = gtk::Overlay::builder().child(&scrolled_window).build();
+        widget.add_overlay(&jump_btn);
+
+        let following = Rc::new(Cell::new(true));
+        let vadj = scrolled_window.vadjustment();
+        vadj.connect_value_changed({
+            let following = following.clone();
+            let jump_btn = jump_btn.clone();
+            move |vadj| {
+                let at_bottom = vadj.value() + vadj.page_size() >= vadj.upper() - FOLLOW_THRESHOLD;
+                following.set(at_bottom);
+                if at_bottom {
+                    jump_btn.set_visible(false);
+                }
+            }
+        });
+        // Content grew, e.g. a streamed chunk: stay at the bottom or offer to jump there
+        vadj.connect_upper_notify({
+            let following = following.clone();
+            let jump_btn = jump_btn.clone();
+            move |vadj| {
+                if following.get() {
+                    vadj.set_value(vadj.upper() - vadj.page_size());
+                } else {
+                    jump_btn.set_visible(true);
+                }
+            }
+        });
+
+        let time_labels: Rc<RefCell<Vec<(glib::WeakRef<Label>, i64)>>> = Rc::new(RefCell::new(Vec::new()));
+        glib::timeout_add_seconds_local(60, {
+            let time_labels = time_labels.clone();
+            move || {
+                time_labels.borrow_mut().retain(|(label, timestamp)| match label.upgrade() {
+                    Some(label) => {
+                        label.set_label(&relative_time(*timestamp));
+                        true
+                    }
+                    None => false,
+                });
+                glib::ControlFlow::Continue
+            }
+        });
+
+        let view = Self {
+            widget,
+            scrolled_window,
+            chat_box,
+            following,
+            jump_btn,
+            time_labels,
+            bookmark_buttons: Rc::new(RefCell::new(Vec::new())),
+            remote_images: Rc::new(RefCell::new(HashMap::new())),
+            suggestion_handler: Rc::new(RefCell::new(None)),
+            tab_id,
+            state: state.clone(),
+            speaker: speaker.clone(),
+            toasts: toasts.clone(),
+            commands: commands.clone(),
+        };
+        view.jump_btn.connect_clicked({
+            let view = view.clone();
+            move |_| view.scroll_to_bottom()
+        });
+        view
+    }
+
+    /// Scrolls to the latest message and keeps following new content.
+    pub fn scroll_to_bottom(&self) {
+        self.following.set(true);
+        self.jump_btn.set_visible(false);
+        let vadj = self.scrolled_window.vadjustment();
+        vadj.set_value(vadj.upper() - vadj.page_size());
+    }
+
+    /// Caption with how long ago a message was written; the tooltip has the
+    /// exact time and, for replies, the model and how long it took.
+    pub fn build_meta_label(&self, meta: &MessageMeta, halign: gtk::Align) -> Label {
+        let mut details = vec![format_timestamp(meta.timestamp)];
+        if let Some(model) = &meta.model {
+            details.push(format!("Model: {}", model));
+        }
+        if let Some(latency) = meta.latency_ms {
+            details.push(format!("Took {:.1} s", latency as f64 / 1000.0));
+        }
+        let label = Label::builder()
+            .label(relative_time(meta.timestamp))
+            .tooltip_text(details.join("\n"))
+            .halign(halign)
+            .css_classes(["message-meta"])
+            .build();
+        self.time_labels.borrow_mut().push((label.downgrade(), meta.timestamp));
+        label
+    }
+
+    /// "Translate to…" in a message's right-click menu; the translation by the
+    /// current model is shown beneath the message.
+    pub fn connect_translate_menu(&self, msg_container: &Box, text: String) {
+        let popover = gtk::Popover::builder().has_arrow(true).build();
+        popover.set_parent(msg_container);
+        let menu = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .margin_end(6)
+            .build();
+        let language_entry = gtk::Entry::builder().placeholder_text("Language").width_chars(14).build();
+        let translate_btn = Button::builder().label("Translate").css_classes(["suggested-action"]).build();
+        menu.append(&Label::new(Some("Translate to")));
+        menu.append(&language_entry);
+        menu.append(&translate_btn);
+        popover.set_child(Some(&menu));
+
+        let open_menu = {
+            let popover = popover.clone();
+            let language_entry = language_entry.clone();
+            let state = self.state.clone();
+            move |point: Option<(f64, f64)>| {
+                language_entry.set_text(&state.locked().settings.translate_language);
+                popover.set_pointing_to(point.map(|(x, y)| gtk::gdk::Rectangle::new(x as i32, y as i32, 1, 1)).as_ref());
+                popover.popup();
+                language_entry.grab_focus();
+            }
+        };
+        // Text labels have their own context menu, which gets the item; the rest
+        // of the bubble opens the menu directly
+        let action = gtk::gio::SimpleAction::new("translate", None);
+        action.connect_activate({
+            let open_menu = open_menu.clone();
+            move |_, _| open_menu(None)
+        });
+        let actions = gtk::gio::SimpleActionGroup::new();
+        actions.add_action(&action);
+        msg_container.insert_action_group("message", Some(&actions));
+        let extra_menu = gtk::gio::Menu::new();
+        extra_menu.append(Some("Translate to…"), Some("message.translate"));
+        let mut child = msg_container.first_child();
+        while let Some(widget) = child {
+            if let Some(label) = widget.downcast_ref::<Label>().filter(|l| l.is_selectable()) {
+                label.set_extra_menu(Some(&extra_menu));
+            }
+            child = widget.next_sibling();
+        }
+        let click = GestureClick::builder().button(gtk::gdk::BUTTON_SECONDARY).build();
+        click.connect_pressed(move |_, _, x, y| open_menu(Some((x, y))));
+        msg_container.add_controller(click);
+
+        let translate = {
+            let msg_container = msg_container.clone();
+            let state = self.state.clone();
+            let toasts = self.toasts.clone();
+            let language_entry = language_entry.clone();
+            move || {
+                let language = language_entry.text().trim().to_string();
+                if language.is_empty() {
+                    return;
+                }
+                popover.popdown();
+                let (backend, model) = {
+                    let mut s = state.locked();
+                    if s.settings.translate_language != language {
+                        s.settings.translate_language = language.clone();
+                        s.save_settings();
+                    }
+                    match s.current_agent() {
+                        Ok(agent) => (s.backend_for(&agent), agent.model),
+                        Err(e) => {
+                            toasts.report(&e);
+                            return;
+                        }
+                    }
+                };
+                let translation = Label::builder()
+                    .label(format!("Translating to {}…", language))
+                    .xalign(0.0)
+                    .wrap(true)
+                    .selectable(true)
+                    .halign(msg_container.halign())
+                    .css_classes(["translation"])
+                    .build();
+                msg_container.append(&translation);
+                let prompt = format!(
+                    "Translate the following text to {}. Reply with only the translation and keep its markdown formatting.\n\n{}",
+                    language, text
+                );
+                glib::MainContext::default().spawn_local(async move {
+                    match backend.chat(model, vec![ChatMessage::user(prompt)]).await {
+                        Ok(result) => translation.set_markup(&format!("<small><b>{}</b></small>\n{}", glib::markup_escape_text(&language), markdown_to_pango(result.trim()))),
+                        Err(e) => translation.set_label(&format!("Translation failed: {}", e)),
+                    }
+                });
+            }
+        };
+        let translate = Rc::new(translate);
+        translate_btn.connect_clicked({
+            let translate = translate.clone();
+            move |_| translate()
+        });
+        language_entry.connect_activate(move |_| translate());
+    }
+
+    /// Star that saves a reply to the bookmarks, or removes it again.
+    fn build_bookmark_button(&self, stored: &StoredMessage) -> Button {
+        let content = stored.message.content.clone();
+        let saved = self.state.locked().bookmarks.iter().any(|b| b.content == content);
+        let button = Button::builder()
+            .css_classes(["flat"])
+            .valign(gtk::Align::Center)
+            .build();
+        show_bookmarked(&button, saved);
+        self.bookmark_buttons.borrow_mut().push((button.downgrade(), content.clone()));
+        let state = self.state.clone();
+        let commands = self.commands.clone();
+        let meta = stored.meta.clone();
+        let tab_id = self.tab_id;
+        button.connect_clicked(move |button| {
+            let (existing, conversation_id, conversation) = {
+                let s = state.locked();
+                let existing = s.bookmarks.iter().find(|b| b.content == content).map(|b| b.id.clone());
+                let history_id = s.chats.iter().find(|c| c.tab_id == tab_id).and_then(|c| c.history_id.clone());
+                let conversation = history_id.as_ref()
+                    .and_then(|id| s.history.iter().find(|h| &h.id == id))
+                    .map(|h| h.title.clone())
+                    .unwrap_or_default();
+                (existing, history_id, conversation)
+            };
+            match existing {
+                Some(id) => {
+                    commands.send(Command::RemoveBookmark(id));
+                    show_bookmarked(button, false);
+                }
+                None => {
+                    commands.send(Command::AddBookmark(Bookmark {
+                        id: glib::uuid_string_random().to_string(),
+                        content: content.clone(),
+                        conversation,
+                        conversation_id,
+                        meta: meta.clone(),
+                        saved_at: MessageMeta::now().timestamp,
+                    }));
+                    show_bookmarked(button, true);
+                }
+            }
+        });
+        button
+    }
+
+    /// An http(s) image from a reply. It isn't fetched until the user clicks
+    /// "Load image", since the URL may carry chat contents to a third party;
+    /// once loaded it's kept for re-renders and clicking opens it externally.
+    fn build_remote_image(&self, url: &str, alt: &str) -> gtk::Widget {
+        let picture = image_picture(alt);
+        let click = GestureClick::new();
+        let src = url.to_string();
+        click.connect_released(move |gesture, _, _, _| {
+            let parent = gesture.widget().and_then(|w| w.root()).and_downcast::<gtk::Window>();
+            gtk::UriLauncher::new(&src).launch(parent.as_ref(), gtk::gio::Cancellable::NONE, |_| {});
+        });
+        picture.add_controller(click);
+        picture.set_cursor_from_name(Some("pointer"));
+        if let Some(texture) = self.remote_images.borrow().get(url) {
+            picture.set_paintable(Some(texture));
+            return picture.upcast();
+        }
+
+        let stack = gtk::Stack::new();
+        let host = glib::Uri::parse(url, glib::UriFlags::NONE).ok()
+            .and_then(|uri| uri.host())
+            .map(|host| host.to_string())
+            .unwrap_or_else(|| url.to_string());
+        let load_btn = Button::builder()
+            .label(format!("Load image from {}", host))
+            .tooltip_text(url)
+            .halign(gtk::Align::Start)
+            .build();
+        stack.add_child(&load_btn);
+        stack.add_child(&picture);
+        let remote_images = self.remote_images.clone();
+        let url = url.to_string();
+        let picture_c = picture.clone();
+        let stack_c = stack.clone();
+        load_btn.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            button.set_label("Loading image…");
+            let remote_images = remote_images.clone();
+            let url = url.clone();
+            let picture = picture_c.clone();
+            let stack = stack_c.clone();
+            let button = button.clone();
+            glib::MainContext::default().spawn_local(async move {
+                match fetch_image(&url).await {
+                    Ok(texture) => {
+                        picture.set_paintable(Some(&texture));
+                        stack.set_visible_child(&picture);
+                        remote_images.borrow_mut().insert(url, texture);
+                    }
+                    Err(e) => {
+                        button.set_label("Load image again");
+                        button.set_tooltip_text(Some(&format!("Failed to load image: {}", e)));
+                        button.set_sensitive(true);
+                    }
+                }
+            });
+        });
+        stack.upcast()
+    }
+
+    /// Called with the prompt of a suggestion chip clicked on the welcome screen.
+    pub fn connect_suggestion(&self, f: impl Fn(String) + 'static) {
+        *self.suggestion_handler.borrow_mut() = Some(Rc::new(f));
+    }
+
+    /// The conversation's agent's greeting and suggestion chips.
+    fn build_welcome(&self) -> Box {
+        let agent = {
+            let s = self.state.locked();
+            s.chats.iter().find(|c| c.tab_id == self.tab_id).and_then(|c| s.agent_at(c.agent_idx).ok())
+        };
+        let welcome = Box::builder()
+            .orientation(Orientation::Vertical)
+            .valign(gtk::Align::Center)
+            .halign(gtk::Align::Center)
+            .spacing(20)
+            .margin_top(50)
+            .build();
+        let icon = Label::builder().label("🤖").css_classes(["welcome-icon"]).build();
+        let greeting = agent.as_ref()
+            .map(|a| a.greeting.trim())
+            .filter(|g| !g.is_empty())
+            .unwrap_or("Select an agent or start typing...");
+        let text = Label::builder()
+            .label(greeting)
+            .wrap(true)
+            .justify(gtk::Justification::Center)
+            .css_classes(["welcome-text"])
+            .build();
+        welcome.append(&icon);
+        welcome.append(&text);
+
+        let suggestions = agent.map(|a| a.suggestions).unwrap_or_default();
+        if !suggestions.is_empty() {
+            let chips = gtk::FlowBox::builder()
+                .selection_mode(gtk::SelectionMode::None)
+                .homogeneous(true)
+                .max_children_per_line(2)
+                .column_spacing(10)
+                .row_spacing(10)
+                .build();
+            for suggestion in suggestions {
+                let chip = Button::builder()
+                    .css_classes(["suggestion-chip"])
+                    .child(&Label::builder().label(&suggestion).wrap(true).max_width_chars(30).build())
+                    .build();
+                let handler = self.suggestion_handler.clone();
+                chip.connect_clicked(move |_| {
+                    let handler = handler.borrow().clone();
+                    if let Some(handler) = handler {
+                        handler(suggestion.clone());
+                    }
+                });
+                chips.insert(&chip, -1);
+            }
+            welcome.append(&chips);
+        }
+        welcome
+    }
+
+    /// Updates the stars after bookmarks changed elsewhere, e.g. in the Saved view.
+    pub fn refresh_bookmarks(&self) {
+        let s = self.state.locked();
+        self.bookmark_buttons.borrow_mut().retain(|(button, content)| match button.upgrade() {
+            Some(button) => {
+                show_bookmarked(&button, s.bookmarks.iter().any(|b| &b.content == content));
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Replaces the shown messages, or shows the welcome screen for an empty chat.
+    /// Locks the state, so it must not be held by the caller.
+    pub fn render(&self, messages: &[StoredMessage]) {
+        self.rebuild(messages);
+        if !messages.is_empty() {
+            self.scroll_to_bottom();
+        }
+    }
+
+    /// `render` without scrolling to the end.
+    fn rebuild(&self, messages: &[StoredMessage]) {
+        while let Some(child) = self.chat_box.first_child() {
+            self.chat_box.remove(&child);
+        }
+        self.time_labels.borrow_mut().clear();
+        self.bookmark_buttons.borrow_mut().clear();
+        
+        if messages.is_empty() {
+            self.chat_box.append(&self.build_welcome());
+        } else {
+            // Tool results follow the assistant message that made the calls, in order
+            let mut pending_tool_names: std::collections::VecDeque<String> = std::collections::VecDeque::new();
+            for (index, stored) in messages.iter().enumerate() {
+                let msg = &stored.message;
+                if msg.role == MessageRole::System { continue; }
+                if msg.role == MessageRole::Tool {
+                    let name = pending_tool_names.pop_front().unwrap_or_default();
+                    self.chat_box.append(&build_tool_result_chip(&name, &msg.content));
+                    continue;
+                }
+                pending_tool_names = msg.tool_calls.iter().map(|call| call.function.name.clone()).collect();
+                for call in &msg.tool_calls {
+                    self.chat_box.append(&build_tool_call_chip(&call.function.name, &call.function.arguments.to_string()));
+                }
+                if !msg.tool_calls.is_empty() && msg.content.trim().is_empty() { continue; }
+                let is_user = msg.role == MessageRole::User;
+                
+                let msg_container = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(5)
+                    .margin_bottom(10)
+                    .build();
+                // The text, swapped for an editor while a reply is edited
+                let body_box = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(5)
+                    .build();
+                
+                if is_user {
+                    msg_container.set_halign(gtk::Align::End);
+                } else {
+                    msg_container.set_halign(gtk::Align::Start);
+                    let header_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+                    let header = Label::builder()
+                        .label("Ollama")
+                        .css_classes(["msg-header"])
+                        .halign(gtk::Align::Start)
+                        .hexpand(true)
+                        .build();
+                    if let Some(edited_at) = stored.meta.as_ref().and_then(|m| m.edited_at) {
+                        header.set_label("Ollama (edited)");
+                        header.set_tooltip_text(Some(&format!("Edited {}", format_timestamp(edited_at))));
+                    }
+                    header_box.append(&header);
+                    header_box.append(&self.build_bookmark_button(stored));
+                    
+                    let copy_btn = Button::builder()
+                        .icon_name("edit-copy-symbolic")
+                        .css_classes(["flat"])
+                        .valign(gtk::Align::Center)
+                        .tooltip_text("Copy Response")
+                        .build();
+                    
+                    let content = msg.content.clone();
+                    copy_btn.connect_clicked(move |_| {
+                        if let Some(display) = gtk::gdk::Display::default() {
+                            display.clipboard().set(&content);
+                        }
+                    });
+                    header_box.append(&copy_btn);
+
+                    let speak_btn = Button::builder()
+                        .icon_name("audio-speakers-symbolic")
+                        .css_classes(["flat"])
+                        .valign(gtk::Align::Center)
+                        .tooltip_text("Read Aloud")
+                        .build();
+                    let content = msg.content.clone();
+                    let state = self.state.clone();
+                    let speaker = self.speaker.clone();
+                    let toasts = self.toasts.clone();
+                    speak_btn.connect_clicked(move |_| {
+                        let speech = state.locked().settings.speech.clone();
+                        if let Err(e) = speaker.speak(&speech, &content) {
+                            toasts.add_toast(toast(&e));
+                        }
+                    });
+                    header_box.append(&speak_btn);
+
+                    let edit_btn = Button::builder()
+                        .icon_name("document-edit-symbolic")
+                        .css_classes(["flat"])
+                        .valign(gtk::Align::Center)
+                        .tooltip_text("Edit Response")
+                        .build();
+                    header_box.append(&edit_btn);
+                    
+                    msg_container.append(&header_box);
+                    let view = self.clone();
+                    let msg_container = msg_container.clone();
+                    let body_box = body_box.clone();
+                    let text = msg.content.clone();
+                    edit_btn.connect_clicked(move |_| view.start_edit(&msg_container, &body_box, index, &text));
+                }
+
+                for image in msg.images.iter().flatten() {
+                    let picture = build_image_block(&image_data_uri(image.to_base64()), "Attached image");
+                    picture.set_halign(if is_user { gtk::Align::End } else { gtk::Align::Start });
+                    msg_container.append(&picture);
+                }
+
+                // Attached files show collapsed instead of as part of the message
+                let (body, attached) = stored.display_parts();
+                for (name, content) in &attached {
+                    msg_container.append(&build_sent_attachment_chip(name, content));
+                }
+
+                msg_container.append(&body_box);
+                for block in stored.blocks().iter() {
+                    match block {
+                        MarkdownBlock::Text(text) => {
+                            let label = Label::builder()
+                                .xalign(0.0)
+                                .wrap(true)
+                                .selectable(true)
+                                .css_classes([if is_user { "user-message" } else { "bot-message" }])
+                                .build();
+                            label.set_markup(text);
+                            connect_link_handler(&label);
+                            if is_user {
+                                label.set_halign(gtk::Align::End);
+                            } else {
+                                label.set_halign(gtk::Align::Start);
+                            }
+                            body_box.append(&label);
+                        }
+                        MarkdownBlock::Code(lang, code) => {
+                            let (frame, _) = build_code_frame(code);
+                            if terminal::is_shell_language(lang) {
+                                let run_btn = Button::builder()
+                                    .icon_name("utilities-terminal-symbolic")
+                                    .css_classes(["flat"])
+                                    .halign(gtk::Align::End)
+                                    .tooltip_text("Run in Terminal (opens it with the command typed, not run)")
+                                    .build();
+                                let toasts = self.toasts.clone();
+                                let code = code.clone();
+                                run_btn.connect_clicked(move |_| {
+                                    if let Err(e) = terminal::open_with_command(&code) {
+                                        toasts.add_toast(toast(&e));
+                                    }
+                                });
+                                body_box.append(&run_btn);
+                            }
+                            body_box.append(&frame);
+                        }
+                        MarkdownBlock::Image(src, alt) if is_web_image(src) => {
+                            body_box.append(&self.build_remote_image(src, alt));
+                        }
+                        MarkdownBlock::Image(src, alt) => {
+                            body_box.append(&build_image_block(src, alt));
+                        }
+                    }
+                }
+                if let Some(meta) = &stored.meta {
+                    msg_container.append(&self.build_meta_label(meta, if is_user { gtk::Align::End } else { gtk::Align::Start }));
+                }
+                self.connect_translate_menu(&msg_container, body);
+                self.chat_box.append(&msg_container);
+            }
+        }
+    }
+
+    /// Swaps a reply's text for an editor. Saving stores the new text in the
+    /// conversation, so later turns see the corrected context.
+    fn start_edit(&self, msg_container: &Box, body_box: &Box, index: usize, text: &str) {
+        if !body_box.is_visible() {
+            return;
+        }
+        let buffer = gtk::TextBuffer::builder().text(text).build();
+        let text_view = gtk::TextView::builder()
+            .buffer(&buffer)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .top_margin(10)
+            .bottom_margin(10)
+            .left_margin(10)
+            .right_margin(10)
+            .css_classes(["chat-input"])
+            .build();
+        let buttons = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let cancel_btn = Button::with_label("Cancel");
+        let save_btn = Button::builder()
+            .label("Save")
+            .css_classes(["suggested-action"])
+            .build();
+        buttons.append(&cancel_btn);
+        buttons.append(&save_btn);
+        let editor = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(6)
+            .build();
+        editor.append(&text_view);
+        editor.append(&buttons);
+        msg_container.insert_child_after(&editor, Some(body_box));
+        body_box.set_visible(false);
+        text_view.grab_focus();
+
+        let close = {
+            let msg_container = msg_container.clone();
+            let editor = editor.clone();
+            let body_box = body_box.clone();
+            move || {
+                msg_container.remove(&editor);
+                body_box.set_visible(true);
+            }
+        };
+        cancel_btn.connect_clicked({
+            let close = close.clone();
+            move |_| close()
+        });
+        let view = self.clone();
+        let original = text.to_string();
+        save_btn.connect_clicked(move |_| {
+            let content = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string();
+            if content.trim().is_empty() {
+                view.toasts.add_toast(toast("A reply can't be empty"));
+                return;
+            }
+            if content == original {
+                close();
+                return;
+            }
+            let (history_id, mut messages) = {
+                let s = view.state.locked();
+                let Some(chat) = s.chats.iter().find(|c| c.tab_id == view.tab_id) else { return };
+                // Rebuilding the list would drop the reply being streamed
+                if chat.task.is_some() {
+                    drop(s);
+                    view.toasts.add_toast(toast("Wait for the reply to finish before saving the edit"));
+                    return;
+                }
+                (chat.history_id.clone(), chat.messages.clone())
+            };
+            let Some(message) = messages.get_mut(index) else { return };
+            message.set_content(content.clone());
+            view.commands.send(Command::EditMessage { tab_id: view.tab_id, history_id, index, content });
+            // Rebuild with the new text, staying where the user was reading
+            let vadj = view.scrolled_window.vadjustment();
+            let position = vadj.value();
+            view.rebuild(&messages);
+            glib::idle_add_local_once(move || vadj.set_value(position));
+        });
+    }
+}
+
+fn show_bookmarked(button: &Button, saved: bool) {
+    button.set_icon_name(if saved { "starred-symbolic" } else { "non-starred-symbolic" });
+    button.set_tooltip_text(Some(if saved { "Remove from Saved" } else { "Save Response" }));
+}
+
+/// Opens links in message labels with the default browser, ignoring
+/// anything that is not a web or mail link.
+pub fn connect_link_handler(label: &Label) {
+    label.connect_activate_link(|label, uri| {
+        if is_openable_link(uri) {
+            let parent = label.root().and_downcast::<gtk::Window>();
+            gtk::UriLauncher::new(uri).launch(parent.as_ref(), gtk::gio::Cancellable::NONE, |res| {
+                if let Err(e) = res {
+                    tracing::warn!("Failed to open link: {}", e);
+                }
+            });
+        }
+        glib::Propagation::Stop
+    });
+}
+
+/// Builds a picture for a markdown image. Data URIs are decoded in place and
+/// clicking opens them externally; anything else is shown as its alt text.
+/// Remote images go through `ChatView::build_remote_image`.
+pub fn build_image_block(src: &str, alt: &str) -> gtk::Widget {
+    let Some(data) = src.strip_prefix("data:")
+        .and_then(|uri| uri.split_once(";base64,"))
+        .map(|(_, payload)| glib::base64_decode(payload)) else {
+        return Label::builder()
+            .label(format!("[image: {}]", if alt.is_empty() { src } else { alt }))
+            .xalign(0.0)
+            .build()
+            .upcast();
+    };
+
+    let picture = image_picture(alt);
+    match gtk::gdk::Texture::from_bytes(&glib::Bytes::from(&data)) {
+        Ok(texture) => picture.set_paintable(Some(&texture)),
+        Err(e) => picture.set_tooltip_text(Some(&format!("Failed to decode image: {}", e))),
+    }
+
+    let click = GestureClick::new();
+    let src = src.to_string();
+    click.connect_released(move |gesture, _, _, _| {
+        let parent = gesture.widget().and_then(|w| w.root()).and_downcast::<gtk::Window>();
+        // Data URIs have no location of their own; hand a temporary copy to the viewer
+        let extension = src.strip_prefix("data:image/")
+            .and_then(|rest| rest.split([';', '+']).next())
+            .unwrap_or("png");
+        let path = glib::tmp_dir().join(format!("arch-llm-{}.{}", glib::uuid_string_random(), extension));
+        if let Err(e) = fs::write(&path, &data) {
+            tracing::warn!("Failed to write image: {}", e);
+            return;
+        }
+        gtk::FileLauncher::new(Some(&gtk::gio::File::for_path(&path))).launch(parent.as_ref(), gtk::gio::Cancellable::NONE, |_| {});
+    });
+    picture.add_controller(click);
+    picture.set_cursor_from_name(Some("pointer"));
+    picture.upcast()
+}
+
+fn is_web_image(src: &str) -> bool {
+    src.starts_with("http://") || src.starts_with("https://")
+}
+
+fn image_picture(alt: &str) -> gtk::Picture {
+    gtk::Picture::builder()
+        .can_shrink(true)
+        .content_fit(gtk::ContentFit::ScaleDown)
+        .halign(gtk::Align::Start)
+        .height_request(240)
+        .alternative_text(alt)
+        .tooltip_text(if alt.is_empty() { "Open image" } else { alt })
+        .build()
+}
+
+/// Downloads an image for a reply, giving up past `MAX_REMOTE_IMAGE_BYTES`
+/// or `REMOTE_IMAGE_TIMEOUT`.
+async fn fetch_image(url: &str) -> Result<gtk::gdk::Texture, String> {
+    let client = reqwest::Client::builder().timeout(REMOTE_IMAGE_TIMEOUT).build().map_err(|e| e.to_string())?;
+    let mut res = client.get(url).send().await.and_then(|r| r.error_for_status()).map_err(|e| e.to_string())?;
+    let too_large = || format!("larger than {} MB", MAX_REMOTE_IMAGE_BYTES / (1024 * 1024));
+    if res.content_length().is_some_and(|len| len as usize > MAX_REMOTE_IMAGE_BYTES) {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(|e| e.to_string())? {
+        if bytes.len() + chunk.len() > MAX_REMOTE_IMAGE_BYTES {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    gtk::gdk::Texture::from_bytes(&glib::Bytes::from_owned(bytes)).map_err(|e| e.to_string())
+}
+
+/// Read-only code block; the buffer is returned so it can be filled while streaming.
+fn build_code_frame(code: &str) -> (gtk::Frame, gtk::TextBuffer) {
+    let buffer = gtk::TextBuffer::builder().text(code).build();
+    let view = gtk::TextView::builder()
+        .buffer(&buffer)
+        .editable(false)
+        .monospace(true)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .bottom_margin(10)
+        .top_margin(10)
+        .left_margin(10)
+        .right_margin(10)
+        .css_classes(["code-view"])
+        .build();
+    let frame = gtk::Frame::builder()
+        .child(&view)
+        .css_classes(["code-frame"])
+        .build();
+    (frame, buffer)
+}
+
+fn build_reply_label() -> Label {
+    let label = Label::builder()
+        .xalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .css_classes(["bot-message"])
+        .hexpand(true)
+        .build();
+    connect_link_handler(&label);
+    label
+}
+
+/// A reply as it streams in: text so far in a label, and a code frame that
+/// grows as soon as a fence opens instead of showing the code as text until
+/// it closes. Finished parts are left alone; only the tail is re-rendered.
+pub struct StreamingReply {
+    pub widget: Box,
+    /// The first text part, which also shows status like "Thinking..."
+    pub status_label: Label,
+    label: RefCell<Label>,
+    code: RefCell<Option<gtk::TextBuffer>>,
+    /// Byte offset of the text not yet frozen into finished parts
+    done: Cell<usize>,
+}
+
+impl StreamingReply {
+    pub fn new() -> Self {
+        let widget = Box::builder().orientation(Orientation::Vertical).spacing(5).hexpand(true).build();
+        let label = build_reply_label();
+        widget.append(&label);
+        Self {
+            widget,
+            status_label: label.clone(),
+            label: RefCell::new(label),
+            code: RefCell::new(None),
+            done: Cell::new(0),
+        }
+    }
+
+    /// Shows `markdown`, the whole reply so far.
+    pub fn update(&self, markdown: &str) {
+        loop {
+            let tail = &markdown[self.done.get().min(markdown.len())..];
+            let Some(fence) = find_code_fence(tail) else {
+                self.set_text(tail);
+                return;
+            };
+            self.set_text(&tail[..fence.start]);
+            let end = fence.close.map_or(tail.len(), |(close_start, _)| close_start);
+            self.set_code(tail[fence.code_start..end].trim_end_matches('\n'));
+            let Some((_, close_end)) = fence.close else { return };
+            // The block is finished: freeze it and continue in a new label
+            self.code.replace(None);
+            let label = build_reply_label();
+            self.widget.append(&label);
+            self.label.replace(label);
+            self.done.set(self.done.get() + close_end);
+        }
+    }
+
+    /// Shows the finished reply as a code block when it is all JSON.
+    pub fn finish(&self, text: &str) {
+        let Some(json) = pretty_json(text) else { return };
+        while let Some(child) = self.widget.first_child() {
+            self.widget.remove(&child);
+        }
+        self.code.replace(None);
+        self.widget.append(&build_code_frame(&json).0);
+    }
+
+    fn set_text(&self, markdown: &str) {
+        let label = self.label.borrow();
+        let markup = markdown_to_pango(markdown);
+        label.set_visible(!markup.trim().is_empty() || *label == self.status_label);
+        label.set_markup(&markup);
+    }
+
+    fn set_code(&self, code: &str) {
+        let mut current = self.code.borrow_mut();
+        let buffer = current.get_or_insert_with(|| {
+            let (frame, buffer) = build_code_frame("");
+            self.widget.append(&frame);
+            buffer
+        });
+        let (start, end) = buffer.bounds();
+        let shown = buffer.text(&start, &end, false);
+        // Chunks only append, so only the new part is inserted
+        match code.strip_prefix(shown.as_str()) {
+            Some(new) => buffer.insert(&mut buffer.end_iter(), new),
+            None => buffer.set_text(code),
+        }
+    }
+}
+
+/// Thumbnail of an image waiting to be sent, with a button to drop it.
+pub fn build_attachment_chip(base64: &str, on_remove: impl Fn() + 'static) -> gtk::Widget {
+    let picture = gtk::Picture::builder()
+        .content_fit(gtk::ContentFit::Cover)
+        .width_request(56)
+        .height_request(56)
+        .css_classes(["attachment-thumb"])
+        .build();
+    match gtk::gdk::Texture::from_bytes(&glib::Bytes::from_owned(glib::base64_decode(base64))) {
+        Ok(texture) => picture.set_paintable(Some(&texture)),
+        Err(e) => picture.set_tooltip_text(Some(&format!("Failed to decode image: {}", e))),
+    }
+    let remove_btn = Button::builder()
+        .icon_name("window-close-symbolic")
+        .css_classes(["circular", "attachment-remove"])
+        .tooltip_text("Remove Attachment")
+        .halign(gtk::Align::End)
+        .valign(gtk::Align::Start)
+        .build();
+    remove_btn.connect_clicked(move |_| on_remove());
+    let overlay = gtk::Overlay::builder().child(&picture).css_classes(["attachment-chip"]).build();
+    overlay.add_overlay(&remove_btn);
+    overlay.upcast()
+}
+
+/// Pill for a file or page waiting to be sent, with a button to drop it.
+pub fn build_text_attachment_chip(icon_name: &str, label: &str, tooltip: &str, on_remove: impl Fn() + 'static) -> gtk::Widget {
+    let chip = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(6)
+        .valign(gtk::Align::Center)
+        .css_classes(["attachment-pill"])
+        .tooltip_text(tooltip)
+        .build();
+    chip.append(&gtk::Image::from_icon_name(icon_name));
+    chip.append(&Label::builder().label(label).ellipsize(gtk::pango::EllipsizeMode::Middle).max_width_chars(32).build());
+    let remove_btn = Button::builder()
+        .icon_name("window-close-symbolic")
+        .css_classes(["flat", "circular"])
+        .tooltip_text("Remove Attachment")
+        .build();
+    remove_btn.connect_clicked(move |_| on_remove());
+    chip.append(&remove_btn);
+    chip.upcast()
+}
+
+/// Collapsed file or page that was sent with a message; expanding it shows
+/// the text the model got.
+pub fn build_sent_attachment_chip(name: &str, content: &str) -> gtk::Widget {
+    const MAX_CHARS: usize = 4000;
+    let mut shown: String = content.chars().take(MAX_CHARS).collect();
+    if content.chars().count() > MAX_CHARS {
+        shown.push_str("\n…");
+    }
+    let details = Label::builder()
+        .label(&shown)
+        .xalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .css_classes(["tool-chip-details"])
+        .build();
+    gtk::Expander::builder()
+        .label(format!("📎 {}", crate::attachments::describe(name, content)))
+        .child(&details)
+        .halign(gtk::Align::End)
+        .css_classes(["tool-chip"])
+        .build()
+        .upcast()
+}
+
+/// Inline card for a problem the user can fix from the chat, e.g. a model
+/// that isn't installed. Returns the card, its message and its action button.
+pub fn build_notice_card(message: &str, action: &str) -> (gtk::Widget, Label, Button) {
+    let card = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .halign(gtk::Align::Start)
+        .css_classes(["notice-card"])
+        .build();
+    let label = Label::builder().label(message).xalign(0.0).wrap(true).hexpand(true).build();
+    let button = Button::builder()
+        .label(action)
+        .valign(gtk::Align::Center)
+        .css_classes(["suggested-action"])
+        .build();
+    card.append(&label);
+    card.append(&button);
+    (card.upcast(), label, button)
+}
+
+/// Chip shown in the chat for a tool the model called; expands to the arguments.
+pub fn build_tool_call_chip(name: &str, arguments: &str) -> gtk::Widget {
+    let pretty = serde_json::from_str::<serde_json::Value>(arguments)
+        .and_then(|v| serde_json::to_string_pretty(&v))
+        .unwrap_or_else(|_| arguments.to_string());
+    let details = Label::builder()
+        .label(&pretty)
+        .xalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .css_classes(["tool-chip-details"])
+        .build();
+    gtk::Expander::builder()
+        .label(&format!("🔧 {}", name))
+        .child(&details)
+        .halign(gtk::Align::Start)
+        .css_classes(["tool-chip"])
+        .build()
+        .upcast()
+}
+
+/// Chip for a tool's output, collapsed by default and truncated for display.
+/// Code runs get an always-visible "Execution result" block instead.
+pub fn build_tool_result_chip(name: &str, output: &str) -> gtk::Widget {
+    const MAX_CHARS: usize = 4000;
+    let mut shown: String = output.chars().take(MAX_CHARS).collect();
+    if output.chars().count() > MAX_CHARS {
+        shown.push_str("\n…");
+    }
+    if name == RUN_CODE_TOOL {
+        let block = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(4)
+            .halign(gtk::Align::Start)
+            .css_classes(["execution-result"])
+            .build();
+        block.append(&Label::builder().label("▶ Execution result").xalign(0.0).css_classes(["execution-result-title"]).build());
+        block.append(&Label::builder()
+            .label(&shown)
+            .xalign(0.0)
+            .wrap(true)
+            .wrap_mode(gtk::pango::WrapMode::WordChar)
+            .selectable(true)
+            .css_classes(["tool-chip-details"])
+            .build());
+        return block.upcast();
+    }
+    let details = Label::builder()
+        .label(&shown)
+        .xalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .css_classes(["tool-chip-details"])
+        .build();
+    gtk::Expander::builder()
+        .label("↳ Tool result")
+        .child(&details)
+        .halign(gtk::Align::Start)
+        .css_classes(["tool-chip"])
+        .build()
+        .upcast()
+}
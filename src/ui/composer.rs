@@ -0,0 +1,291 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Label, Orientation, Stack, TextView, ToggleButton};
+use adw::ToastOverlay;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::attachments::{self, Attachment};
+use crate::context;
+use crate::error::{AppError, LockExt};
+use crate::provider::{context_length, Backend};
+use crate::state::AppState;
+use crate::ui::chat::{build_attachment_chip, build_text_attachment_chip};
+use crate::ui::toasts::Report;
+use crate::ui::vision_offer::VisionOffer;
+use crate::utils::markdown_to_pango;
+
+/// The estimated size of the message being composed, with its attachments,
+/// next to the input; it warns when that won't fit the current agent's
+/// context window.
+#[derive(Clone)]
+pub struct TokenCounter {
+    pub label: Label,
+    text_view: TextView,
+    attachments: Rc<RefCell<Vec<Attachment>>>,
+    state: Arc<Mutex<AppState>>,
+}
+
+impl TokenCounter {
+    /// Counts what is typed in `text_view` and attached in `attachments`,
+    /// updating as the text changes; call `update` when the attachments or
+    /// the agent change.
+    pub fn new(state: &Arc<Mutex<AppState>>, text_view: &TextView, attachments: &Rc<RefCell<Vec<Attachment>>>) -> Self {
+        let label = Label::builder()
+            .halign(gtk::Align::End)
+            .margin_top(4)
+            .css_classes(["token-count"])
+            .build();
+        let counter = Self {
+            label,
+            text_view: text_view.clone(),
+            attachments: attachments.clone(),
+            state: state.clone(),
+        };
+        text_view.buffer().connect_changed({
+            let counter = counter.clone();
+            move |_| counter.update()
+        });
+        counter.update();
+        counter
+    }
+
+    pub fn update(&self) {
+        let buffer = self.text_view.buffer();
+        let (start, end) = buffer.bounds();
+        let text = buffer.text(&start, &end, false);
+        let (tokens, images) = {
+            let attachments = self.attachments.borrow();
+            (context::draft_tokens(&text, &attachments), attachments.iter().filter(|a| a.text().is_none()).count())
+        };
+        self.label.set_visible(tokens > 0);
+
+        let mut s = self.state.locked();
+        let Ok(agent) = s.current_agent() else { return };
+        let context = match s.context_key(&agent) {
+            // Not served by Ollama: no window to check against
+            None => None,
+            Some(key) => match s.context_lengths.get(&key) {
+                Some(context) => *context,
+                None => {
+                    // Look it up once, then show the count again; failures are retried on the next edit
+                    s.context_lengths.insert(key.clone(), None);
+                    if let Backend::Ollama(server) = s.backend_for(&agent) {
+                        let this = self.clone();
+                        glib::MainContext::default().spawn_local(async move {
+                            let Some(context) = context_length(&server.client, &key.1).await else {
+                                this.state.locked().context_lengths.remove(&key);
+                                return;
+                            };
+                            this.state.locked().context_lengths.insert(key, Some(context));
+                            this.update();
+                        });
+                    }
+                    None
+                }
+            },
+        };
+        drop(s);
+
+        let mut label = format!("~{} tokens", tokens);
+        if images > 0 {
+            label.push_str(&format!(" (incl. {} image{})", images, if images == 1 { "" } else { "s" }));
+        }
+        match context {
+            Some(context) if tokens as u64 > context => {
+                label.push_str(&format!(" · longer than {}'s {}-token context", agent.model, context));
+                self.label.add_css_class("token-warning");
+            }
+            _ => self.label.remove_css_class("token-warning"),
+        }
+        self.label.set_label(&label);
+    }
+}
+
+/// The eye toggle that renders the input's markdown in place of the input
+/// until it is toggled off.
+#[derive(Clone)]
+pub struct MarkdownPreview {
+    /// Holds the input, or its rendered markdown while previewing; put it
+    /// where the input would go.
+    pub stack: Stack,
+    pub button: ToggleButton,
+    label: Label,
+    text_view: TextView,
+}
+
+impl MarkdownPreview {
+    pub fn new(text_view: &TextView) -> Self {
+        let label = Label::builder()
+            .xalign(0.0)
+            .yalign(0.0)
+            .wrap(true)
+            .selectable(true)
+            .css_classes(["input-preview"])
+            .build();
+        let stack = Stack::builder().vhomogeneous(false).build();
+        stack.add_named(text_view, Some("edit"));
+        stack.add_named(&label, Some("preview"));
+        let button = ToggleButton::builder()
+            .icon_name("view-reveal-symbolic")
+            .tooltip_text("Preview markdown")
+            .valign(gtk::Align::End)
+            .build();
+        let preview = Self {
+            stack,
+            button,
+            label,
+            text_view: text_view.clone(),
+        };
+        preview.button.connect_toggled({
+            let preview = preview.clone();
+            move |btn| {
+                if btn.is_active() {
+                    preview.update();
+                    preview.stack.set_visible_child_name("preview");
+                } else {
+                    preview.stack.set_visible_child_name("edit");
+                    preview.text_view.grab_focus();
+                }
+            }
+        });
+        text_view.buffer().connect_changed({
+            let preview = preview.clone();
+            move |_| {
+                if preview.button.is_active() {
+                    preview.update();
+                }
+            }
+        });
+        preview
+    }
+
+    fn update(&self) {
+        let buffer = self.text_view.buffer();
+        let (start, end) = buffer.bounds();
+        let text = buffer.text(&start, &end, false);
+        if text.trim().is_empty() {
+            self.label.set_markup("<i>Nothing to preview</i>");
+        } else {
+            self.label.set_markup(&markdown_to_pango(&text));
+        }
+    }
+}
+
+/// The images, files and pages to send with the next message, shown as
+/// removable chips above the input.
+pub struct AttachmentBar {
+    pub widget: Box,
+    attachments: Rc<RefCell<Vec<Attachment>>>,
+    token_counter: TokenCounter,
+    vision_offer: VisionOffer,
+    text_view: TextView,
+}
+
+impl AttachmentBar {
+    /// Shows `attachments`; images added for a model that can't see them
+    /// get a vision model offered next to `text_view`.
+    pub fn new(attachments: &Rc<RefCell<Vec<Attachment>>>, token_counter: &TokenCounter, vision_offer: VisionOffer, text_view: &TextView) -> Rc<Self> {
+        let widget = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_bottom(8)
+            .visible(false)
+            .build();
+        Rc::new(Self {
+            widget,
+            attachments: attachments.clone(),
+            token_counter: token_counter.clone(),
+            vision_offer,
+            text_view: text_view.clone(),
+        })
+    }
+
+    pub fn add(self: &Rc<Self>, attachment: Attachment) {
+        let is_image = matches!(attachment, Attachment::Image(_));
+        self.attachments.borrow_mut().push(attachment);
+        self.refresh();
+        if is_image {
+            self.vision_offer.offer(&self.text_view);
+        }
+    }
+
+    /// Makes Ctrl+V in the input attach an image on the clipboard instead of
+    /// pasting, and a huge text (`attachments::is_huge_paste`), which would
+    /// bury the rest of the prompt. Other text is left to GTK's own paste.
+    pub fn intercept_pastes(self: &Rc<Self>, toasts: &ToastOverlay) {
+        // Set while the clipboard's text turned out small and GTK pastes it after all
+        let pasting_text = Rc::new(Cell::new(false));
+        let this = Rc::downgrade(self);
+        let toasts = toasts.clone();
+        self.text_view.connect_paste_clipboard(move |text_view| {
+            if pasting_text.get() {
+                return;
+            }
+            let Some(this) = this.upgrade() else { return };
+            let clipboard = text_view.clipboard();
+            let formats = clipboard.formats();
+            if formats.contain_mime_type("text/plain") {
+                text_view.stop_signal_emission_by_name("paste-clipboard");
+                let text_view = text_view.clone();
+                let pasting_text = pasting_text.clone();
+                let toasts = toasts.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    match clipboard.read_text_future().await {
+                        Ok(Some(text)) if attachments::is_huge_paste(&text) => this.add(Attachment::Pasted(text.to_string())),
+                        Ok(Some(_)) => {
+                            pasting_text.set(true);
+                            text_view.emit_paste_clipboard();
+                            pasting_text.set(false);
+                        }
+                        Ok(None) => {}
+                        Err(e) => toasts.report(&AppError::new("Failed to paste", e)),
+                    }
+                });
+                return;
+            }
+            if !formats.contains_type(gtk::gdk::Texture::static_type()) {
+                return;
+            }
+            text_view.stop_signal_emission_by_name("paste-clipboard");
+            let toasts = toasts.clone();
+            glib::MainContext::default().spawn_local(async move {
+                match clipboard.read_texture_future().await {
+                    Ok(Some(texture)) => this.add(Attachment::Image(glib::base64_encode(&texture.save_to_png_bytes()).to_string())),
+                    Ok(None) => {}
+                    Err(e) => toasts.report(&AppError::new("Failed to paste image", e)),
+                }
+            });
+        });
+    }
+
+    /// Rebuilds the chips; call it after changing the attachments directly.
+    pub fn refresh(self: &Rc<Self>) {
+        while let Some(child) = self.widget.first_child() {
+            self.widget.remove(&child);
+        }
+        for (i, attachment) in self.attachments.borrow().iter().enumerate() {
+            let remove = {
+                let this = Rc::downgrade(self);
+                move || {
+                    let Some(this) = this.upgrade() else { return };
+                    this.attachments.borrow_mut().remove(i);
+                    this.refresh();
+                }
+            };
+            let chip = match attachment {
+                Attachment::Image(image) => build_attachment_chip(image, remove),
+                _ => {
+                    let text = attachment.text().unwrap_or_default();
+                    let preview: String = text.chars().take(400).collect();
+                    build_text_attachment_chip(attachment.icon_name(), &attachments::describe(attachment.name(), text), &preview, remove)
+                }
+            };
+            self.widget.append(&chip);
+        }
+        self.widget.set_visible(!self.attachments.borrow().is_empty());
+        self.token_counter.update();
+    }
+}
@@ -0,0 +1,91 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use adw::ToastOverlay;
+use std::sync::{Arc, Mutex};
+
+use crate::attachments::Attachment;
+use crate::context;
+use crate::error::{AppError, LockExt};
+use crate::state::{Agent, AppState};
+use crate::ui::chat::ChatView;
+use crate::ui::toasts::{toast, Report};
+use crate::utils::estimate_tokens;
+
+/// Ollama silently cuts a prompt longer than the context window from the
+/// start, so the system prompt and the oldest messages would be lost without
+/// a word. When the message would overflow `agent`'s model in the chat of
+/// tab `tab_id`, asks over `view` whether to send anyway, drop the oldest
+/// messages or summarize them, and does so. Returns whether to go on sending.
+pub async fn check(view: &ChatView, state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay, tab_id: u64, agent: &Agent, text: &str, attachments: &[Attachment]) -> bool {
+    let new_tokens = context::draft_tokens(text, attachments);
+    let over = {
+        let s = state.locked();
+        s.chats.iter().find(|c| c.tab_id == tab_id).and_then(|chat| {
+            let context = s.known_context_length(agent)?;
+            let messages = &chat.messages;
+            let system_tokens = if messages.is_empty() { estimate_tokens(&agent.system_prompt) } else { 0 };
+            let tokens = context::prompt_tokens(messages) + system_tokens + new_tokens;
+            (tokens as u64 > context).then(|| (tokens, context, context::history_len(messages)))
+        })
+    };
+    let Some((tokens, context, history_len)) = over else { return true };
+
+    let mut buttons = vec!["Cancel", "Send Anyway"];
+    let mut detail = format!(
+        "With this message the chat is about {} tokens, more than {}'s {}-token context. The model would not see its start, including the agent's instructions.",
+        tokens, agent.model, context,
+    );
+    if history_len > 0 {
+        buttons.extend(["Drop Oldest Messages", "Summarize and Send"]);
+        detail.push_str(" Dropping removes the oldest messages from this chat; summarizing replaces them with a short summary.");
+    }
+    let dialog = gtk::AlertDialog::builder()
+        .modal(true)
+        .message("This chat is too long for the model")
+        .detail(detail)
+        .buttons(buttons)
+        .cancel_button(0)
+        .default_button(if history_len > 0 { 3 } else { 1 })
+        .build();
+    let window = view.widget.root().and_downcast::<gtk::Window>();
+    match dialog.choose_future(window.as_ref()).await {
+        Ok(1) => true,
+        Ok(2) => {
+            let (dropped, messages) = {
+                let mut s = state.locked();
+                let Some(chat) = s.chat_in_tab(tab_id) else { return false };
+                let dropped = context::truncate(&mut chat.messages, new_tokens, context);
+                (dropped, chat.messages.clone())
+            };
+            view.render(&messages);
+            toasts.add_toast(toast(&format!("Dropped the {} oldest message{}", dropped, if dropped == 1 { "" } else { "s" })));
+            true
+        }
+        Ok(3) => {
+            let (backend, model, messages) = {
+                let mut s = state.locked();
+                let (backend, model) = s.utility_backend(agent);
+                let Some(chat) = s.chat_in_tab(tab_id) else { return false };
+                (backend, model, chat.messages.clone())
+            };
+            toasts.add_toast(toast("Summarizing the earlier conversation…"));
+            let summarized = match context::summarize(&backend, model, &messages).await {
+                Ok(summarized) => summarized,
+                Err(e) => {
+                    toasts.report(&AppError::new("Failed to summarize the conversation", e));
+                    return false;
+                }
+            };
+            // Unless the chat was closed or changed meanwhile
+            let summarized = {
+                let mut s = state.locked();
+                let Some(chat) = s.chat_in_tab(tab_id).filter(|c| c.task.is_none() && c.messages.len() == messages.len()) else { return false };
+                chat.messages = summarized;
+                chat.messages.clone()
+            };
+            view.render(&summarized);
+            true
+        }
+        _ => false,
+    }
+}
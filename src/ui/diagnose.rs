@@ -0,0 +1,158 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Entry, Label, Orientation, ScrolledWindow, TextView};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::AppError;
+use crate::ui::toasts::Report;
+use crate::utils::{filter_log, journal_args};
+
+/// Opens the Diagnose dialog over `parent`'s window: recent journal entries,
+/// filtered by period, priority, unit and text, shown for the user to review
+/// and trim. "Ask Agent" hands `ask` a prompt with what is left.
+pub fn open(parent: &impl IsA<gtk::Widget>, toasts: &ToastOverlay, ask: impl Fn(String) + 'static) {
+    let dialog = gtk::Window::builder()
+        .title("Diagnose")
+        .modal(true)
+        .default_width(720)
+        .default_height(560)
+        .build();
+    if let Some(parent) = parent.root().and_downcast::<gtk::Window>() {
+        dialog.set_transient_for(Some(&parent));
+    }
+    let form = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .build();
+
+    let filters = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+    let period_dropdown = DropDown::from_strings(&["Current boot", "Last hour", "Last 24 hours"]);
+    let priority_dropdown = DropDown::from_strings(&["Errors", "Warnings and errors", "Everything"]);
+    let unit_entry = Entry::builder().placeholder_text("Unit (optional, e.g. NetworkManager)").hexpand(true).build();
+    let fetch_btn = Button::with_label("Fetch Logs");
+    filters.append(&period_dropdown);
+    filters.append(&priority_dropdown);
+    filters.append(&unit_entry);
+    filters.append(&fetch_btn);
+    form.append(&filters);
+
+    let filter_entry = Entry::builder().placeholder_text("Only keep lines containing…").build();
+    form.append(&filter_entry);
+
+    form.append(&Label::builder()
+        .label("Review the log below and remove anything you don't want to share before sending it.")
+        .xalign(0.0)
+        .wrap(true)
+        .css_classes(["settings-label"])
+        .build());
+    let log_view = TextView::builder()
+        .monospace(true)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .css_classes(["headers-view"])
+        .build();
+    let log_scroll = ScrolledWindow::builder().vexpand(true).child(&log_view).build();
+    form.append(&log_scroll);
+
+    let status = Label::builder().xalign(0.0).css_classes(["dim-label"]).build();
+    let actions = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(10).build();
+    status.set_hexpand(true);
+    let cancel_btn = Button::with_label("Cancel");
+    let ask_btn = Button::with_label("Ask Agent");
+    ask_btn.add_css_class("suggested-action");
+    ask_btn.set_sensitive(false);
+    actions.append(&status);
+    actions.append(&cancel_btn);
+    actions.append(&ask_btn);
+    form.append(&actions);
+    dialog.set_child(Some(&form));
+
+    // The unfiltered output of the last fetch; the text filter is applied on top of it
+    let fetched = Rc::new(RefCell::new(String::new()));
+    let apply_filter = {
+        let fetched = fetched.clone();
+        let filter_entry = filter_entry.clone();
+        let log_view = log_view.clone();
+        let status = status.clone();
+        let ask_btn = ask_btn.clone();
+        move || {
+            let log = filter_log(&fetched.borrow(), &filter_entry.text());
+            status.set_label(&format!("{} lines", log.lines().count()));
+            ask_btn.set_sensitive(!log.trim().is_empty());
+            log_view.buffer().set_text(&log);
+        }
+    };
+    filter_entry.connect_changed({
+        let apply_filter = apply_filter.clone();
+        move |_| apply_filter()
+    });
+
+    let args = Rc::new(RefCell::new(Vec::new()));
+    fetch_btn.connect_clicked({
+        let args = args.clone();
+        let status = status.clone();
+        let toasts = toasts.clone();
+        move |fetch_btn| {
+            let argv = journal_args(period_dropdown.selected(), priority_dropdown.selected(), &unit_entry.text());
+            *args.borrow_mut() = argv.clone();
+            let os_argv: Vec<&std::ffi::OsStr> = argv.iter().map(std::ffi::OsStr::new).collect();
+            let process = match gtk::gio::Subprocess::newv(&os_argv, gtk::gio::SubprocessFlags::STDOUT_PIPE | gtk::gio::SubprocessFlags::STDERR_PIPE) {
+                Ok(process) => process,
+                Err(e) => {
+                    toasts.report(&AppError::new("Failed to run journalctl", e));
+                    return;
+                }
+            };
+            fetch_btn.set_sensitive(false);
+            status.set_label("Reading journal…");
+            let fetch_btn = fetch_btn.clone();
+            let fetched = fetched.clone();
+            let status = status.clone();
+            let apply_filter = apply_filter.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let result = process.communicate_utf8_future(None).await;
+                fetch_btn.set_sensitive(true);
+                match result {
+                    Ok((stdout, stderr)) => {
+                        let stdout = stdout.map(|s| s.to_string()).unwrap_or_default();
+                        if stdout.trim().is_empty() || stdout.trim() == "-- No entries --" {
+                            *fetched.borrow_mut() = String::new();
+                            apply_filter();
+                            let stderr = stderr.map(|s| s.trim().to_string()).unwrap_or_default();
+                            status.set_label(if stderr.is_empty() { "No matching entries" } else { &stderr });
+                        } else {
+                            *fetched.borrow_mut() = stdout;
+                            apply_filter();
+                        }
+                    }
+                    Err(e) => status.set_label(&format!("Failed to read the journal: {}", e)),
+                }
+            });
+        }
+    });
+
+    let dialog_c = dialog.clone();
+    cancel_btn.connect_clicked(move |_| dialog_c.close());
+    let dialog_c = dialog.clone();
+    ask_btn.connect_clicked(move |_| {
+        let buffer = log_view.buffer();
+        let (start, end) = buffer.bounds();
+        let log = buffer.text(&start, &end, false).to_string();
+        if log.trim().is_empty() {
+            return;
+        }
+        dialog_c.close();
+        ask(format!(
+            "Here is recent output of `{}` from my Arch Linux system. Explain the errors and warnings in it, their likely causes, and how to fix them.\n\n```\n{}\n```",
+            args.borrow().join(" "),
+            log.trim_end(),
+        ));
+    });
+    dialog.present();
+}
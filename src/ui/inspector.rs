@@ -0,0 +1,129 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, Orientation, ScrolledWindow, TextBuffer, TextView};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Debug drawer showing what was sent to the model for the last message,
+/// one request per tool round, and the raw chunks that came back.
+#[derive(Clone)]
+pub struct Inspector {
+    pub widget: gtk::Revealer,
+    request_buffer: TextBuffer,
+    stream_buffer: TextBuffer,
+    requests: Rc<RefCell<Vec<serde_json::Value>>>,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        let panel = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(8)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(20)
+            .margin_bottom(20)
+            .width_request(380)
+            .css_classes(["inspector"])
+            .build();
+
+        let header = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        header.append(&Label::builder()
+            .label("Last request")
+            .css_classes(["title-4"])
+            .hexpand(true)
+            .xalign(0.0)
+            .build());
+        let copy_btn = Button::builder()
+            .icon_name("edit-copy-symbolic")
+            .css_classes(["flat"])
+            .tooltip_text("Copy request JSON")
+            .build();
+        header.append(&copy_btn);
+        panel.append(&header);
+
+        let (request_view, request_buffer) = build_log_view("Send a message to see the request.");
+        let (stream_view, stream_buffer) = build_log_view("");
+        let notebook = gtk::Notebook::builder().vexpand(true).build();
+        notebook.append_page(&request_view, Some(&Label::new(Some("Request"))));
+        notebook.append_page(&stream_view, Some(&Label::new(Some("Stream"))));
+        panel.append(&notebook);
+
+        let widget = gtk::Revealer::builder()
+            .transition_type(gtk::RevealerTransitionType::SlideLeft)
+            .child(&panel)
+            .build();
+
+        let request_buffer_c = request_buffer.clone();
+        copy_btn.connect_clicked(move |_| {
+            let text = request_buffer_c.text(&request_buffer_c.start_iter(), &request_buffer_c.end_iter(), false);
+            if let Some(display) = gtk::gdk::Display::default() {
+                display.clipboard().set(&text.as_str());
+            }
+        });
+
+        Self { widget, request_buffer, stream_buffer, requests: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    /// Clears the drawer for a new message.
+    pub fn start_turn(&self) {
+        self.requests.borrow_mut().clear();
+        self.request_buffer.set_text("");
+        self.stream_buffer.set_text("");
+    }
+
+    /// Records a request as it is sent; tool rounds add one each.
+    pub fn add_request(&self, request: serde_json::Value) {
+        let mut requests = self.requests.borrow_mut();
+        requests.push(request);
+        let shown = match requests.as_slice() {
+            [request] => request.clone(),
+            rounds => serde_json::Value::Array(rounds.to_vec()),
+        };
+        self.request_buffer.set_text(&serde_json::to_string_pretty(&shown).unwrap_or_default());
+        if requests.len() > 1 {
+            self.push_line(&format!("── round {} ──", requests.len()));
+        }
+    }
+
+    /// Appends one streamed chunk, quoted so whitespace and newlines show.
+    pub fn push_chunk(&self, chunk: &str) {
+        self.push_line(&serde_json::Value::String(chunk.to_string()).to_string());
+    }
+
+    /// Appends a line that isn't text from the model, e.g. a tool call.
+    pub fn push_line(&self, line: &str) {
+        let mut end = self.stream_buffer.end_iter();
+        self.stream_buffer.insert(&mut end, &format!("{}\n", line));
+    }
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn build_log_view(placeholder: &str) -> (ScrolledWindow, TextBuffer) {
+    let buffer = TextBuffer::builder().text(placeholder).build();
+    let view = TextView::builder()
+        .buffer(&buffer)
+        .editable(false)
+        .monospace(true)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .top_margin(8)
+        .bottom_margin(8)
+        .left_margin(8)
+        .right_margin(8)
+        .css_classes(["code-view"])
+        .build();
+    let scrolled = ScrolledWindow::builder()
+        .child(&view)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vexpand(true)
+        .build();
+    (scrolled, buffer)
+}
@@ -0,0 +1,186 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, Orientation};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, LockExt};
+use crate::provider::{loaded_models, unload_model, LoadedModel};
+use crate::state::AppState;
+use crate::tools::{format_size, gpu_memory, GpuMemory};
+use crate::ui::toasts::{toast, Report};
+use crate::utils::is_local_url;
+
+/// Seconds between refreshes while the popover is open.
+const REFRESH_SECONDS: u32 = 3;
+
+/// Header bar button whose popover shows the models the Ollama server has
+/// loaded, how each one's memory splits between VRAM and system RAM, and, if
+/// the server runs on this machine, the GPUs' VRAM use. Each model can be
+/// unloaded from there.
+#[derive(Clone)]
+pub struct ResourceMonitor {
+    pub button: gtk::MenuButton,
+    content: Box,
+    state: Arc<Mutex<AppState>>,
+    toasts: ToastOverlay,
+}
+
+impl ResourceMonitor {
+    pub fn new(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> Self {
+        let panel = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(8)
+            .margin_start(6)
+            .margin_end(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .width_request(320)
+            .build();
+        panel.append(&Label::builder().label("Memory").css_classes(["title-4"]).xalign(0.0).build());
+        let content = Box::builder().orientation(Orientation::Vertical).spacing(8).build();
+        panel.append(&content);
+        let popover = gtk::Popover::builder().child(&panel).build();
+        let button = gtk::MenuButton::builder()
+            .icon_name("utilities-system-monitor-symbolic")
+            .tooltip_text("Loaded models and VRAM")
+            .popover(&popover)
+            .build();
+
+        let monitor = Self { button, content, state: state.clone(), toasts: toasts.clone() };
+        // Refreshes only while the popover is open
+        let timer: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+        let monitor_show = monitor.clone();
+        let timer_show = timer.clone();
+        popover.connect_show(move |_| {
+            monitor_show.refresh();
+            let monitor = monitor_show.clone();
+            let source = glib::timeout_add_seconds_local(REFRESH_SECONDS, move || {
+                monitor.refresh();
+                glib::ControlFlow::Continue
+            });
+            if let Some(previous) = timer_show.replace(Some(source)) {
+                previous.remove();
+            }
+        });
+        popover.connect_closed(move |_| {
+            if let Some(source) = timer.take() {
+                source.remove();
+            }
+        });
+        monitor
+    }
+
+    /// Queries the server (and the local GPUs) again and rebuilds the popover.
+    pub fn refresh(&self) {
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let (endpoint, headers, tls) = {
+                let s = this.state.locked();
+                (s.settings.ollama_endpoint.clone(), s.settings.ollama_headers.clone(), s.settings.ollama_tls.clone())
+            };
+            let models = loaded_models(&endpoint, &headers, &tls).await;
+            // GPUs of another machine can't be queried from here
+            let gpus = if is_local_url(&endpoint) { gpu_memory().await } else { Vec::new() };
+            this.show(models, &gpus);
+        });
+    }
+
+    fn show(&self, models: Result<Vec<LoadedModel>, String>, gpus: &[GpuMemory]) {
+        while let Some(child) = self.content.first_child() {
+            self.content.remove(&child);
+        }
+
+        for gpu in gpus {
+            let row = Box::builder().orientation(Orientation::Vertical).spacing(2).build();
+            row.append(&Label::builder()
+                .label(format!("{}: {} of {} VRAM used", gpu.name, format_size(gpu.used), format_size(gpu.total)))
+                .xalign(0.0)
+                .wrap(true)
+                .build());
+            let bar = gtk::LevelBar::builder().min_value(0.0).max_value(1.0).build();
+            bar.set_value(if gpu.total > 0 { gpu.used as f64 / gpu.total as f64 } else { 0.0 });
+            row.append(&bar);
+            self.content.append(&row);
+        }
+
+        let models = match models {
+            Ok(models) => models,
+            Err(e) => {
+                self.content.append(&Label::builder().label(format!("Could not ask the server: {}", e)).xalign(0.0).wrap(true).css_classes(["dim-label"]).build());
+                self.button.set_tooltip_text(Some("Loaded models and VRAM"));
+                return;
+            }
+        };
+        if models.is_empty() {
+            self.content.append(&Label::builder().label("No models loaded").xalign(0.0).css_classes(["dim-label"]).build());
+        }
+        for model in &models {
+            let row = Box::builder().orientation(Orientation::Horizontal).spacing(8).build();
+            let text = Box::builder().orientation(Orientation::Vertical).spacing(2).hexpand(true).build();
+            text.append(&Label::builder().label(&model.name).xalign(0.0).ellipsize(gtk::pango::EllipsizeMode::End).build());
+            let details = Label::builder()
+                .label(memory_split(model))
+                .xalign(0.0)
+                .wrap(true)
+                .css_classes(["dim-label"])
+                .build();
+            if !model.expires_at.is_empty() {
+                details.set_tooltip_text(Some(&format!("Unloaded if unused at {}", model.expires_at)));
+            }
+            text.append(&details);
+            row.append(&text);
+            let unload_btn = Button::builder()
+                .icon_name("media-eject-symbolic")
+                .tooltip_text("Unload from memory")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            let this = self.clone();
+            let name = model.name.clone();
+            unload_btn.connect_clicked(move |btn| {
+                btn.set_sensitive(false);
+                let this = this.clone();
+                let name = name.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    let (endpoint, headers, tls) = {
+                        let s = this.state.locked();
+                        (s.settings.ollama_endpoint.clone(), s.settings.ollama_headers.clone(), s.settings.ollama_tls.clone())
+                    };
+                    match unload_model(&endpoint, &headers, &tls, &name).await {
+                        Ok(()) => this.toasts.add_toast(toast(&format!("Unloaded {}", name))),
+                        Err(e) => this.toasts.report(&AppError::new(format!("Failed to unload {}", name), e)),
+                    }
+                    this.refresh();
+                });
+            });
+            row.append(&unload_btn);
+            self.content.append(&row);
+        }
+
+        let vram: u64 = models.iter().map(|m| m.size_vram).sum();
+        let total: u64 = models.iter().map(|m| m.size).sum();
+        let tooltip = match models.len() {
+            0 => "No models loaded".to_string(),
+            n => format!("{} loaded, {} in VRAM, {} in RAM", if n == 1 { "1 model".to_string() } else { format!("{} models", n) }, format_size(vram), format_size(total.saturating_sub(vram))),
+        };
+        self.button.set_tooltip_text(Some(&tooltip));
+    }
+}
+
+/// e.g. "4.2 GiB · 75% GPU / 25% CPU".
+fn memory_split(model: &LoadedModel) -> String {
+    if model.size == 0 {
+        return "Size unknown".to_string();
+    }
+    let gpu = ((model.size_vram as f64 / model.size as f64) * 100.0).round() as u64;
+    let split = match gpu {
+        100.. => "100% GPU".to_string(),
+        0 => "100% CPU".to_string(),
+        gpu => format!("{}% GPU / {}% CPU", gpu, 100 - gpu),
+    };
+    format!("{} · {}", format_size(model.size), split)
+}
@@ -0,0 +1,151 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Label, Orientation, ScrolledWindow, TextBuffer, TextView};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::controller::{Command, Commands};
+use crate::error::LockExt;
+use crate::state::AppState;
+
+/// How long typing pauses before the notes are saved.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Drawer with the user's own notes on the conversation on screen. They are
+/// stored with its history entry, never sent to the model, and found by the
+/// desktop search. A new chat can have notes once its first reply is saved.
+#[derive(Clone)]
+pub struct NotesPanel {
+    pub widget: gtk::Revealer,
+    stack: gtk::Stack,
+    buffer: TextBuffer,
+    /// History id of the conversation whose notes the buffer holds
+    shown: Rc<RefCell<Option<String>>>,
+    /// Set while the buffer is filled from the state, so it isn't saved back
+    loading: Rc<Cell<bool>>,
+    /// Pending save of an edit, replaced while typing continues
+    save_timer: Rc<RefCell<Option<glib::SourceId>>>,
+    state: Arc<Mutex<AppState>>,
+    commands: Commands,
+}
+
+impl NotesPanel {
+    pub fn new(state: &Arc<Mutex<AppState>>, commands: &Commands) -> Self {
+        let panel = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(8)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(20)
+            .margin_bottom(20)
+            .width_request(300)
+            .css_classes(["inspector"])
+            .build();
+        panel.append(&Label::builder().label("Notes").css_classes(["title-4"]).xalign(0.0).build());
+        panel.append(&Label::builder()
+            .label("Only for you: notes are never sent to the model.")
+            .xalign(0.0)
+            .wrap(true)
+            .css_classes(["dim-label"])
+            .build());
+
+        let buffer = TextBuffer::new(None);
+        let text_view = TextView::builder()
+            .buffer(&buffer)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .top_margin(8)
+            .bottom_margin(8)
+            .left_margin(8)
+            .right_margin(8)
+            .css_classes(["code-view"])
+            .build();
+        let stack = gtk::Stack::builder().vexpand(true).build();
+        stack.add_named(&ScrolledWindow::builder()
+            .child(&text_view)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build(), Some("notes"));
+        stack.add_named(&Label::builder()
+            .label("Notes can be added once the chat has a reply.")
+            .wrap(true)
+            .valign(gtk::Align::Start)
+            .css_classes(["dim-label"])
+            .build(), Some("unsaved"));
+        stack.set_visible_child_name("unsaved");
+        panel.append(&stack);
+
+        let widget = gtk::Revealer::builder()
+            .transition_type(gtk::RevealerTransitionType::SlideLeft)
+            .child(&panel)
+            .build();
+
+        let notes = Self {
+            widget,
+            stack,
+            buffer,
+            shown: Rc::new(RefCell::new(None)),
+            loading: Rc::new(Cell::new(false)),
+            save_timer: Rc::new(RefCell::new(None)),
+            state: state.clone(),
+            commands: commands.clone(),
+        };
+        notes.buffer.connect_changed({
+            let notes = notes.clone();
+            move |_| {
+                if notes.loading.get() {
+                    return;
+                }
+                let notes_c = notes.clone();
+                let source = glib::timeout_add_local_once(SAVE_DELAY, move || {
+                    notes_c.save_timer.borrow_mut().take();
+                    notes_c.save();
+                });
+                if let Some(previous) = notes.save_timer.replace(Some(source)) {
+                    previous.remove();
+                }
+            }
+        });
+        notes
+    }
+
+    /// Shows the notes of the conversation in the tab on screen, saving an
+    /// unsaved edit to the one shown before.
+    pub fn sync(&self) {
+        let (history_id, text) = {
+            let s = self.state.locked();
+            let history_id = s.chat().history_id.clone();
+            let text = history_id.as_ref()
+                .and_then(|id| s.history.iter().find(|h| &h.id == id))
+                .map(|h| h.notes.clone())
+                .unwrap_or_default();
+            (history_id, text)
+        };
+        if *self.shown.borrow() == history_id {
+            return;
+        }
+        self.flush();
+        self.loading.set(true);
+        self.buffer.set_text(&text);
+        self.loading.set(false);
+        self.stack.set_visible_child_name(if history_id.is_some() { "notes" } else { "unsaved" });
+        *self.shown.borrow_mut() = history_id;
+    }
+
+    /// Saves an edit still waiting for typing to pause.
+    pub fn flush(&self) {
+        let pending = self.save_timer.borrow_mut().take();
+        if let Some(source) = pending {
+            source.remove();
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(id) = self.shown.borrow().clone() else { return };
+        let notes = self.buffer.text(&self.buffer.start_iter(), &self.buffer.end_iter(), false).to_string();
+        self.commands.send(Command::SetNotes { id, notes });
+    }
+}
@@ -0,0 +1,125 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, Orientation};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::attachments::Attachment;
+use crate::ui::chat::ChatView;
+
+struct QueuedMessage {
+    tab_id: u64,
+    /// The tab's chat view, which the message is sent into
+    view: ChatView,
+    text: String,
+    attachments: Vec<Attachment>,
+    bubble: Box,
+}
+
+/// Messages written while Ollama is unreachable. Each shows as a faded
+/// "Queued" bubble at the end of its tab until it is sent, in order, once
+/// the connection is back, or cancelled.
+#[derive(Clone, Default)]
+pub struct Outbox {
+    queued: Rc<RefCell<Vec<QueuedMessage>>>,
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a message for the chat in tab `tab_id`, shown in `view`.
+    pub fn push(&self, view: &ChatView, tab_id: u64, text: String, attachments: Vec<Attachment>) {
+        let bubble = Box::builder()
+            .orientation(Orientation::Vertical)
+            .halign(gtk::Align::End)
+            .css_classes(["queued-message"])
+            .build();
+        if !text.trim().is_empty() {
+            let label = Label::builder()
+                .label(&text)
+                .xalign(0.0)
+                .wrap(true)
+                .selectable(true)
+                .css_classes(["user-message"])
+                .build();
+            bubble.append(&label);
+        }
+        let status = Box::builder().orientation(Orientation::Horizontal).spacing(6).halign(gtk::Align::End).build();
+        let mut caption = "Queued · sends when Ollama is back".to_string();
+        if !attachments.is_empty() {
+            let names: Vec<&str> = attachments.iter().map(|a| a.name()).collect();
+            caption.push_str(&format!(" · with {}", names.join(", ")));
+        }
+        status.append(&Label::builder().label(&caption).css_classes(["message-meta"]).build());
+        let cancel_btn = Button::builder()
+            .icon_name("edit-delete-symbolic")
+            .tooltip_text("Don't send")
+            .css_classes(["flat", "circular"])
+            .build();
+        status.append(&cancel_btn);
+        bubble.append(&status);
+
+        let queued = self.queued.clone();
+        let bubble_weak = bubble.downgrade();
+        cancel_btn.connect_clicked(move |_| {
+            let Some(bubble) = bubble_weak.upgrade() else { return };
+            queued.borrow_mut().retain(|m| m.bubble != bubble);
+            remove_bubble(&bubble);
+        });
+
+        view.chat_box.append(&bubble);
+        view.scroll_to_bottom();
+        self.queued.borrow_mut().push(QueuedMessage { tab_id, view: view.clone(), text, attachments, bubble });
+    }
+
+    /// Whether messages for tab `tab_id` are waiting, so a new one has to
+    /// queue behind them.
+    pub fn has(&self, tab_id: u64) -> bool {
+        self.queued.borrow().iter().any(|m| m.tab_id == tab_id)
+    }
+
+    /// The tabs with messages waiting, in the order they were first queued.
+    pub fn tabs(&self) -> Vec<u64> {
+        let mut tabs = Vec::new();
+        for message in self.queued.borrow().iter() {
+            if !tabs.contains(&message.tab_id) {
+                tabs.push(message.tab_id);
+            }
+        }
+        tabs
+    }
+
+    /// Takes the oldest message queued for tab `tab_id` off the queue and
+    /// its bubble out of the chat, with the view it was queued in.
+    pub fn take_next(&self, tab_id: u64) -> Option<(ChatView, String, Vec<Attachment>)> {
+        let mut queued = self.queued.borrow_mut();
+        let idx = queued.iter().position(|m| m.tab_id == tab_id)?;
+        let message = queued.remove(idx);
+        drop(queued);
+        remove_bubble(&message.bubble);
+        Some((message.view, message.text, message.attachments))
+    }
+
+    /// Drops the messages queued for tab `tab_id`, e.g. when the tab is
+    /// closed or shows another conversation. Returns how many there were.
+    pub fn discard(&self, tab_id: u64) -> usize {
+        let mut queued = self.queued.borrow_mut();
+        let count = queued.len();
+        queued.retain(|m| {
+            let keep = m.tab_id != tab_id;
+            if !keep {
+                remove_bubble(&m.bubble);
+            }
+            keep
+        });
+        count - queued.len()
+    }
+}
+
+fn remove_bubble(bubble: &Box) {
+    if let Some(parent) = bubble.parent().and_downcast::<Box>() {
+        parent.remove(bubble);
+    }
+}
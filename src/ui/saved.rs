@@ -0,0 +1,187 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, ListBox, Orientation, ScrolledWindow, SearchEntry};
+use adw::ToastOverlay;
+use std::sync::{Arc, Mutex};
+
+use crate::controller::{Command, Commands};
+use crate::error::LockExt;
+use crate::portal::write_chosen_file;
+use crate::state::{AppState, Bookmark};
+use crate::ui::toasts::{toast, Report};
+use crate::utils::{bookmarks_to_markdown, markdown_to_pango, relative_time};
+
+/// Starred responses, newest first, with search and markdown export.
+#[derive(Clone)]
+pub struct SavedView {
+    pub widget: Box,
+    pub back_btn: Button,
+    list: ListBox,
+    search_entry: SearchEntry,
+    state: Arc<Mutex<AppState>>,
+    commands: Commands,
+}
+
+impl SavedView {
+    pub fn new(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay, commands: &Commands) -> Self {
+        let widget = Box::builder()
+            .orientation(Orientation::Vertical)
+            .build();
+
+        let header = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .margin_start(20)
+            .margin_end(20)
+            .margin_top(20)
+            .margin_bottom(10)
+            .build();
+        let back_btn = Button::with_label("← Back to Chat");
+        header.append(&back_btn);
+        let search_entry = SearchEntry::builder()
+            .placeholder_text("Search saved responses")
+            .hexpand(true)
+            .build();
+        header.append(&search_entry);
+        let export_btn = Button::builder()
+            .label("Export…")
+            .tooltip_text("Save the shown responses as a markdown file")
+            .build();
+        header.append(&export_btn);
+        widget.append(&header);
+
+        let list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["saved-list"])
+            .margin_start(20)
+            .margin_end(20)
+            .build();
+        widget.append(&ScrolledWindow::builder()
+            .child(&list)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build());
+
+        let view = Self {
+            widget,
+            back_btn,
+            list,
+            search_entry,
+            state: state.clone(),
+            commands: commands.clone(),
+        };
+
+        view.search_entry.connect_search_changed({
+            let view = view.clone();
+            move |_| view.refresh()
+        });
+        export_btn.connect_clicked({
+            let view = view.clone();
+            let toasts = toasts.clone();
+            move |btn| {
+                let bookmarks = view.matching();
+                if bookmarks.is_empty() {
+                    toasts.add_toast(toast("No saved responses to export"));
+                    return;
+                }
+                let dialog = gtk::FileDialog::builder()
+                    .title("Export Saved Responses")
+                    .initial_name("saved-responses.md")
+                    .build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let toasts = toasts.clone();
+                dialog.save(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    match write_chosen_file(&file, bookmarks_to_markdown(&bookmarks), "Failed to export saved responses") {
+                        Ok(()) => toasts.add_toast(toast(&format!("Exported {} responses", bookmarks.len()))),
+                        Err(e) => toasts.report(&e),
+                    }
+                });
+            }
+        });
+        view
+    }
+
+    /// Bookmarks matching the search, newest first.
+    fn matching(&self) -> Vec<Bookmark> {
+        let query = self.search_entry.text().to_lowercase();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        self.state.locked().bookmarks.iter()
+            .rev()
+            .filter(|b| {
+                let text = format!("{}\n{}", b.conversation, b.content).to_lowercase();
+                terms.iter().all(|term| text.contains(term))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Rebuilds the list from `state.bookmarks`.
+    pub fn refresh(&self) {
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+        let bookmarks = self.matching();
+        if bookmarks.is_empty() {
+            let empty = if self.state.locked().bookmarks.is_empty() {
+                "Star a response to save it here"
+            } else {
+                "No saved responses match"
+            };
+            self.list.append(&Label::builder().label(empty).css_classes(["welcome-text"]).margin_top(40).build());
+            return;
+        }
+        for bookmark in bookmarks {
+            let card = Box::builder()
+                .orientation(Orientation::Vertical)
+                .spacing(6)
+                .margin_top(10)
+                .margin_bottom(10)
+                .build();
+
+            let header = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+            let title = if bookmark.conversation.is_empty() { "Untitled chat" } else { &bookmark.conversation };
+            header.append(&Label::builder()
+                .label(format!("{} · {}", title, relative_time(bookmark.saved_at)))
+                .xalign(0.0)
+                .hexpand(true)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .css_classes(["msg-header"])
+                .build());
+
+            let copy_btn = Button::builder()
+                .icon_name("edit-copy-symbolic")
+                .css_classes(["flat"])
+                .tooltip_text("Copy Response")
+                .build();
+            let content = bookmark.content.clone();
+            copy_btn.connect_clicked(move |_| {
+                if let Some(display) = gtk::gdk::Display::default() {
+                    display.clipboard().set(&content);
+                }
+            });
+            header.append(&copy_btn);
+
+            let remove_btn = Button::builder()
+                .icon_name("starred-symbolic")
+                .css_classes(["flat"])
+                .tooltip_text("Remove from Saved")
+                .build();
+            let commands = self.commands.clone();
+            let id = bookmark.id.clone();
+            remove_btn.connect_clicked(move |_| commands.send(Command::RemoveBookmark(id.clone())));
+            header.append(&remove_btn);
+            card.append(&header);
+
+            let body = Label::builder()
+                .xalign(0.0)
+                .wrap(true)
+                .selectable(true)
+                .css_classes(["bot-message"])
+                .build();
+            body.set_markup(&markdown_to_pango(&bookmark.content));
+            card.append(&body);
+            self.list.append(&card);
+        }
+    }
+}
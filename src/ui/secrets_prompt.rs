@@ -0,0 +1,47 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use crate::attachments::Attachment;
+use crate::error::LockExt;
+use crate::secrets;
+use crate::state::{Agent, AppState};
+use crate::utils::is_local_url;
+
+/// Before a message leaves the machine for `agent`'s server, offers to take
+/// out what looks like credentials (`secrets::find`), asking over `parent`'s
+/// window. Servers on this computer and a disabled check skip it. Returns
+/// the message to send, redacted if the user chose so, or `None` if they
+/// cancelled.
+pub async fn check(parent: &impl IsA<gtk::Widget>, state: &Arc<Mutex<AppState>>, agent: &Agent, text: &str, attachments: &[Attachment]) -> Option<(String, Vec<Attachment>)> {
+    let remote = {
+        let s = state.locked();
+        s.settings.check_secrets && !is_local_url(&s.endpoint_for(agent))
+    };
+    let mut found = if remote { secrets::find(text) } else { Vec::new() };
+    if remote {
+        for attachment in attachments {
+            found.extend(attachment.text().map(secrets::find).unwrap_or_default());
+        }
+    }
+    if found.is_empty() {
+        return Some((text.to_string(), attachments.to_vec()));
+    }
+    let dialog = gtk::AlertDialog::builder()
+        .modal(true)
+        .message("This message may contain secrets")
+        .detail(format!(
+            "It looks like it includes: {}. The agent's server is not on this computer.",
+            secrets::describe(&found),
+        ))
+        .buttons(["Cancel", "Send Anyway", "Redact and Send"])
+        .cancel_button(0)
+        .default_button(2)
+        .build();
+    let window = parent.root().and_downcast::<gtk::Window>();
+    match dialog.choose_future(window.as_ref()).await {
+        Ok(1) => Some((text.to_string(), attachments.to_vec())),
+        Ok(2) => Some((secrets::redact(text), attachments.iter().map(Attachment::redacted).collect())),
+        _ => None,
+    }
+}
@@ -0,0 +1,605 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Label, Orientation, Spinner};
+use adw::ToastOverlay;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use ollama_rs::generation::images::Image;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::attachments::{self, Attachment};
+use crate::context;
+use crate::controller::{Command, Commands};
+use crate::error::LockExt;
+use crate::provider::{describe_request, model_missing, pull_model, stream_reply, Backend, Provider, Reply};
+use crate::recovery;
+use crate::speech::Speaker;
+use crate::state::{chat_messages, Agent, AppState, ChatEvent, ChatHistory, MessageMeta, StoredMessage};
+use crate::storage::Storage;
+use crate::tools::{ToolRegistry, MAX_TOOL_ROUNDS};
+use crate::ui::chat::{build_image_block, build_notice_card, build_sent_attachment_chip, build_tool_call_chip, build_tool_result_chip, ChatView, StreamingReply};
+use crate::ui::inspector::Inspector;
+use crate::ui::context_prompt;
+use crate::ui::outbox::Outbox;
+use crate::ui::secrets_prompt;
+use crate::ui::toasts::toast;
+use crate::utils::{check_json_reply, fill_template, image_data_uri};
+
+/// Sends the messages written in the tabs and the split pane and streams the
+/// replies into their chat views. Before a message leaves it is checked for
+/// secrets and against the model's context window; while Ollama is
+/// unreachable it waits in the outbox. Tool calls are run until the model
+/// answers, and the finished turn is saved with its title and memory.
+#[derive(Clone)]
+pub struct ChatSender {
+    outbox: Outbox,
+    /// Tabs whose last message is still being checked, e.g. summarized
+    checking: Rc<RefCell<HashSet<u64>>>,
+    busy_handlers: Rc<RefCell<Vec<Rc<dyn Fn(u64, bool)>>>>,
+    /// Set while Ollama is unreachable, so messages to it are queued
+    reconnecting: Rc<Cell<bool>>,
+    start_reconnect: Rc<dyn Fn()>,
+    state: Arc<Mutex<AppState>>,
+    commands: Commands,
+    toasts: ToastOverlay,
+    speaker: Rc<Speaker>,
+    inspector: Inspector,
+}
+
+impl ChatSender {
+    pub fn new(
+        state: &Arc<Mutex<AppState>>,
+        commands: &Commands,
+        toasts: &ToastOverlay,
+        speaker: &Rc<Speaker>,
+        inspector: &Inspector,
+        reconnecting: &Rc<Cell<bool>>,
+        start_reconnect: &Rc<dyn Fn()>,
+    ) -> Self {
+        Self {
+            outbox: Outbox::new(),
+            checking: Rc::new(RefCell::new(HashSet::new())),
+            busy_handlers: Rc::new(RefCell::new(Vec::new())),
+            reconnecting: reconnecting.clone(),
+            start_reconnect: start_reconnect.clone(),
+            state: state.clone(),
+            commands: commands.clone(),
+            toasts: toasts.clone(),
+            speaker: speaker.clone(),
+            inspector: inspector.clone(),
+        }
+    }
+
+    /// Called with a chat's tab id and whether it is generating whenever a
+    /// reply starts or ends.
+    pub fn connect_busy(&self, f: impl Fn(u64, bool) + 'static) {
+        self.busy_handlers.borrow_mut().push(Rc::new(f));
+    }
+
+    fn notify_busy(&self, tab_id: u64) {
+        let busy = self.state.locked().chats.iter().any(|c| c.tab_id == tab_id && c.task.is_some());
+        let handlers: Vec<_> = self.busy_handlers.borrow().clone();
+        for handler in handlers {
+            handler(tab_id, busy);
+        }
+    }
+
+    /// Stops the reply streaming in tab `tab_id`.
+    pub fn stop(&self, tab_id: u64) {
+        if let Some(chat) = self.state.locked().chat_in_tab(tab_id) {
+            chat.stop_tasks();
+        }
+        self.notify_busy(tab_id);
+    }
+
+    /// Drops the messages queued for tab `tab_id`, e.g. when the tab is
+    /// closed or shows another conversation. Returns how many there were.
+    pub fn discard_queued(&self, tab_id: u64) -> usize {
+        self.outbox.discard(tab_id)
+    }
+
+    /// Starts sending what was queued while Ollama was unreachable, in every
+    /// tab, whether on screen or not.
+    pub fn send_all_queued(&self) {
+        for tab_id in self.outbox.tabs() {
+            self.send_queued(tab_id);
+        }
+    }
+
+    /// Sends the next message queued for tab `tab_id`, once connected and idle.
+    fn send_queued(&self, tab_id: u64) {
+        let busy = self.state.locked().chats.iter().any(|c| c.tab_id == tab_id && c.task.is_some());
+        if self.reconnecting.get() || busy {
+            return;
+        }
+        if let Some((view, text, attachments)) = self.outbox.take_next(tab_id) {
+            self.start_turn(tab_id, &view, text, attachments);
+        }
+    }
+
+    /// Checks a message for the chat in tab `tab_id`, shown in `view`, and
+    /// sends or queues it. Gives the message back if the user cancelled at a
+    /// check, so it can go back into the input.
+    pub async fn submit(&self, tab_id: u64, view: ChatView, text: String, attachments: Vec<Attachment>) -> Option<(String, Vec<Attachment>)> {
+        // Still asking about, or summarizing for, the previous message
+        if !self.checking.borrow_mut().insert(tab_id) {
+            return Some((text, attachments));
+        }
+        let agent = self.state.locked().agent_in_tab(tab_id);
+        let mut checked = match &agent {
+            Ok(agent) => secrets_prompt::check(&view.widget, &self.state, agent, &text, &attachments).await,
+            // Sending reports the error
+            Err(_) => Some((text.clone(), attachments.clone())),
+        };
+        if let (Some((text, attachments)), Ok(agent)) = (&checked, &agent) {
+            if !context_prompt::check(&view, &self.state, &self.toasts, tab_id, agent, text, attachments).await {
+                checked = None;
+            }
+        }
+        self.checking.borrow_mut().remove(&tab_id);
+        let Some((checked_text, checked_attachments)) = checked else {
+            return Some((text, attachments));
+        };
+
+        // While Ollama is unreachable, messages to it wait in the outbox, and so do
+        // the ones written after them until they are sent
+        let to_ollama = self.state.locked().agent_in_tab(tab_id).is_ok_and(|agent| agent.provider_id.is_none());
+        if to_ollama && (self.reconnecting.get() || self.outbox.has(tab_id)) {
+            self.outbox.push(&view, tab_id, checked_text, checked_attachments);
+        } else {
+            self.start_turn(tab_id, &view, checked_text, checked_attachments);
+        }
+        None
+    }
+
+    /// Shows the message in `view`, sends it with the conversation so far and
+    /// streams the reply below it.
+    fn start_turn(&self, tab_id: u64, view: &ChatView, text: String, attached: Vec<Attachment>) {
+        let agent = self.state.locked().agent_in_tab(tab_id);
+        let (content, images) = attachments::compose(&text, &attached);
+        self.inspector.start_turn();
+
+        let user_label = Label::builder()
+            .xalign(0.0)
+            .wrap(true)
+            .selectable(true)
+            .css_classes(["user-message"])
+            .halign(gtk::Align::End)
+            .build();
+        user_label.set_markup(&glib::markup_escape_text(&text));
+        for image in &images {
+            let picture = build_image_block(&image_data_uri(image), "Attached image");
+            picture.set_halign(gtk::Align::End);
+            view.chat_box.append(&picture);
+        }
+        for attachment in &attached {
+            if let Some(body) = attachment.text() {
+                view.chat_box.append(&build_sent_attachment_chip(attachment.name(), body));
+            }
+        }
+        if !text.trim().is_empty() {
+            view.chat_box.append(&user_label);
+        }
+        view.chat_box.append(&view.build_meta_label(&MessageMeta::now(), gtk::Align::End));
+        view.scroll_to_bottom();
+        let sent_at = glib::monotonic_time();
+
+        // Response container
+        let bot_msg_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        let bot_spinner = Spinner::builder().spinning(true).build();
+        let reply_view = Rc::new(StreamingReply::new());
+        let bot_label = reply_view.status_label.clone();
+        bot_label.set_label("Thinking...");
+        bot_msg_box.append(&bot_spinner);
+        bot_msg_box.append(&reply_view.widget);
+        view.chat_box.append(&bot_msg_box);
+        view.scroll_to_bottom();
+
+        let mut turn = agent.as_ref().ok().and_then(|agent| self.prepare_turn(tab_id, agent, content.clone(), images));
+        let memory = turn.as_mut().and_then(|turn| turn.memory.take());
+        let (sender, receiver) = async_channel::unbounded();
+
+        // Receiver (Main Thread)
+        let this = self.clone();
+        let view_c = view.clone();
+        let content_c = content.clone();
+        // A message that is only attachments is titled after them
+        let title = if text.trim().is_empty() { attached.iter().map(|a| a.name()).collect::<Vec<_>>().join(", ") } else { text.clone() };
+        let agent_c = agent.as_ref().ok().cloned();
+        glib::MainContext::default().spawn_local(async move {
+            let view = view_c;
+            let mut full_response_acc = String::new();
+            let mut last_recovery_save = glib::monotonic_time();
+            let end_turn = || {
+                if let Some(chat) = this.state.locked().chat_in_tab(tab_id) {
+                    chat.task = None;
+                }
+                this.notify_busy(tab_id);
+            };
+            while let Ok(event) = receiver.recv().await {
+                let interrupted = matches!(event, ChatEvent::Interrupted(_));
+                match event {
+                    ChatEvent::ToolCall(name, arguments) => {
+                        this.inspector.push_line(&format!("tool call: {} {}", name, arguments));
+                        // Chips go above the streaming response
+                        let chip = build_tool_call_chip(&name, &arguments);
+                        view.chat_box.insert_child_after(&chip, bot_msg_box.prev_sibling().as_ref());
+                        bot_label.set_label("Running tool…");
+                    }
+                    ChatEvent::ToolResult(name, output) => {
+                        let chip = build_tool_result_chip(&name, &output);
+                        view.chat_box.insert_child_after(&chip, bot_msg_box.prev_sibling().as_ref());
+                        if full_response_acc.is_empty() {
+                            bot_label.set_label("Thinking...");
+                        }
+                    }
+                    ChatEvent::Retrying(attempt, attempts) => {
+                        bot_label.set_label(&format!("Connection lost, retrying ({}/{})…", attempt, attempts));
+                    }
+                    ChatEvent::ModelMissing(ollama, model) => {
+                        end_turn();
+
+                        let (card, card_label, pull_btn) = build_notice_card(&format!("Model {} not found — pull it now?", model), "Pull now");
+                        view.chat_box.insert_child_after(&card, Some(&bot_msg_box));
+                        view.chat_box.remove(&bot_msg_box);
+
+                        let this = this.clone();
+                        let view = view.clone();
+                        let text = text.clone();
+                        let content = content_c.clone();
+                        let attached = attached.clone();
+                        pull_btn.connect_clicked(move |btn| {
+                            btn.set_visible(false);
+                            card_label.set_label(&format!("Pulling {}…", model));
+                            let btn = btn.clone();
+                            let card_label = card_label.clone();
+                            let model = model.clone();
+                            let ollama = ollama.clone();
+                            let this = this.clone();
+                            let view = view.clone();
+                            let text = text.clone();
+                            let content = content.clone();
+                            let attached = attached.clone();
+                            glib::MainContext::default().spawn_local(async move {
+                                let result = pull_model(&ollama, &model, |status| {
+                                    card_label.set_label(&format!("Pulling {}: {}", model, status));
+                                }).await;
+                                if let Err(e) = result {
+                                    card_label.set_label(&format!("Failed to pull {}: {}", model, e));
+                                    btn.set_visible(true);
+                                    return;
+                                }
+
+                                // Drop the failed turn and send it again, unless the chat moved on meanwhile
+                                let messages = {
+                                    let mut s = this.state.locked();
+                                    if !s.available_models.contains(&model) {
+                                        s.available_models.push(model.clone());
+                                    }
+                                    match s.chat_in_tab(tab_id) {
+                                        Some(chat) if chat.task.is_none() && chat.messages.last().is_some_and(|m| m.message.role == MessageRole::User && m.message.content == content) => {
+                                            chat.messages.pop();
+                                            Some(chat.messages.clone())
+                                        }
+                                        _ => None,
+                                    }
+                                };
+                                let Some(messages) = messages else {
+                                    card_label.set_label(&format!("Pulled {}. Send your message again to use it.", model));
+                                    return;
+                                };
+                                view.render(&messages);
+                                this.start_turn(tab_id, &view, text, attached);
+                            });
+                        });
+                        break;
+                    }
+                    ChatEvent::Request(request) => {
+                        this.inspector.add_request(request);
+                    }
+                    ChatEvent::ToolRound(messages) => {
+                        if let Some(chat) = this.state.locked().chat_in_tab(tab_id) {
+                            chat.messages.extend(messages.into_iter().map(StoredMessage::new));
+                        }
+                    }
+                    ChatEvent::Chunk(chunk) => {
+                        bot_spinner.set_spinning(false);
+                        bot_spinner.set_visible(false);
+                        this.inspector.push_chunk(&chunk);
+                        full_response_acc.push_str(&chunk);
+                        reply_view.update(&full_response_acc);
+                        view.scroll_to_bottom();
+                        if glib::monotonic_time() - last_recovery_save > recovery::SAVE_INTERVAL {
+                            last_recovery_save = glib::monotonic_time();
+                            recovery::save(&this.state.locked(), tab_id, &full_response_acc);
+                        }
+                    }
+                    ChatEvent::Error(err) => {
+                        bot_label.set_label(&format!("Error: {}", err));
+                        end_turn();
+                        break;
+                    }
+                    ChatEvent::ConnectionLost(err) => {
+                        bot_label.set_label(&format!("Error: {}", err));
+                        end_turn();
+                        (this.start_reconnect)();
+                        break;
+                    }
+                    ChatEvent::Done(full_text) | ChatEvent::Interrupted(full_text) => {
+                        if interrupted {
+                            this.toasts.add_toast(toast("The reply was cut off before it finished"));
+                        }
+                        view.connect_translate_menu(&reply_view.widget, full_text.clone());
+                        // Save the turn into the open conversation, or start one
+                        let is_new;
+                        let history_id;
+                        let speech = this.state.locked().settings.speech.clone();
+                        if speech.auto_read {
+                            if let Err(e) = this.speaker.speak(&speech, &full_text) {
+                                this.toasts.add_toast(toast(&e));
+                            }
+                        }
+                        let (title_backend, memory_update) = {
+                            let mut s = this.state.locked();
+                            let agent = agent_c.clone();
+                            // Structured replies show as JSON and are checked against what was asked for
+                            reply_view.finish(&full_text);
+                            if let Some(format) = agent.as_ref().and_then(|a| a.json_format()).filter(|_| !interrupted) {
+                                if let Err(e) = check_json_reply(&full_text, &format) {
+                                    this.toasts.add_toast(toast(&format!("The reply doesn't match the requested format: {}", e)));
+                                }
+                            }
+                            let latency_ms = (glib::monotonic_time() - sent_at) as u64 / 1000;
+                            let reply = StoredMessage::new(ChatMessage::assistant(full_text))
+                                .with_generation(agent.as_ref().map(|a| a.model.as_str()).unwrap_or_default(), latency_ms);
+                            if let Some(meta) = &reply.meta {
+                                view.chat_box.insert_child_after(&view.build_meta_label(meta, gtk::Align::Start), Some(&bot_msg_box));
+                            }
+                            let on_screen = s.active_tab == tab_id;
+                            // The tab was closed as the reply finished
+                            let Some(chat) = s.chat_in_tab(tab_id) else { break };
+                            chat.messages.push(reply);
+                            chat.task = None;
+                            let conversation = chat_messages(&chat.messages);
+                            is_new = chat.history_id.is_none();
+                            history_id = chat.history_id.get_or_insert_with(|| glib::uuid_string_random().to_string()).clone();
+
+                            let now = Some(MessageMeta::now().timestamp);
+                            this.commands.send(Command::SaveConversation(ChatHistory {
+                                id: history_id.clone(),
+                                title: title.chars().take(20).collect(),
+                                messages: chat.messages.clone(),
+                                agent: agent.as_ref().map(|a| a.name.clone()),
+                                model: agent.as_ref().map(|a| a.model.clone()),
+                                tags: Vec::new(),
+                                notes: String::new(),
+                                created_at: now,
+                                updated_at: now,
+                            }));
+                            // Anything typed during generation belongs to the saved conversation
+                            if is_new && on_screen {
+                                if let Some(draft) = s.drafts.remove("") {
+                                    s.drafts.insert(history_id.clone(), draft);
+                                }
+                            }
+                            recovery::clear(&s);
+
+                            let title_backend = agent.as_ref()
+                                .and_then(|agent| s.title_backend(agent))
+                                .map(|(backend, model)| (backend, model, s.settings.titles.prompt.clone()));
+                            // A cut-off reply doesn't update the memory
+                            let memory_update = memory.clone().filter(|_| !interrupted).map(|(profile_id, backend, model)| {
+                                (s.memory_path.join(format!("{}.txt", profile_id)), s.storage.clone(), backend, model, conversation)
+                            });
+                            (title_backend, memory_update)
+                        };
+
+                        end_turn();
+
+                        if let (true, Some((backend, model, title_prompt))) = (is_new && !interrupted, title_backend) {
+                            let commands = this.commands.clone();
+                            let title = title.clone();
+                            let task = tokio::spawn(async move {
+                                let title_prompt = fill_template(&title_prompt, &[("message".to_string(), title)]);
+                                match backend.chat(model, vec![ChatMessage::user(title_prompt)]).await {
+                                    Ok(res) => {
+                                        let new_title = res.trim().trim_matches('"').trim_matches('.').to_string();
+                                        if !new_title.is_empty() {
+                                            commands.send(Command::RenameConversation { id: history_id, title: new_title });
+                                        }
+                                    }
+                                    Err(e) => tracing::warn!("Title generation failed: {}", e),
+                                }
+                            });
+                            if let Some(chat) = this.state.locked().chat_in_tab(tab_id) {
+                                chat.track_background(task.abort_handle());
+                            }
+                        }
+                        if let Some((mem_file, storage, backend, model, conversation)) = memory_update {
+                            let task = tokio::spawn(update_memory(mem_file, storage, backend, model, conversation));
+                            if let Some(chat) = this.state.locked().chat_in_tab(tab_id) {
+                                chat.track_background(task.abort_handle());
+                            }
+                        }
+                        this.send_queued(tab_id);
+                        break;
+                    }
+                }
+            }
+        });
+
+        // Task (Tokio Thread), which never locks the state: the turn was read
+        // from it above, and what changes goes back as events
+        let commands = self.commands.clone();
+        let task = tokio::spawn(async move {
+            let agent = match agent {
+                Ok(agent) => agent,
+                Err(e) => {
+                    let _ = sender.send(ChatEvent::Error(e.to_string())).await;
+                    return;
+                }
+            };
+            // The tab was closed before the request went out
+            let Some(Turn { backend, messages, tools, known_context, .. }) = turn else { return };
+            let model = agent.model.clone();
+
+            let tool_infos = tools.infos();
+            let options = context::generation_options(&agent, &backend, known_context, &commands).await;
+            let mut messages = messages;
+            let mut rounds = 0;
+            let result = loop {
+                tracing::debug!("Chat request to {} with {} messages", model, messages.len());
+                let _ = sender.send(ChatEvent::Request(describe_request(&backend, &model, &messages, &tool_infos, agent.raw_template(), &options))).await;
+                let Reply { text: full_response, tool_calls, interrupted } = match stream_reply(&backend, &model, &messages, &tool_infos, agent.raw_template(), &options, &sender).await {
+                    Ok(reply) => reply,
+                    Err(e) => break Err(e),
+                };
+
+                rounds += 1;
+                // Calls from a cut-off reply may be incomplete, so they aren't run
+                if interrupted || tool_calls.is_empty() || rounds > MAX_TOOL_ROUNDS {
+                    break Ok((full_response, interrupted));
+                }
+
+                // Record the calls and their results, then let the model continue
+                let mut call_message = ChatMessage::assistant(full_response);
+                call_message.tool_calls = tool_calls.clone();
+                let mut round_messages = vec![call_message];
+                for call in &tool_calls {
+                    let _ = sender.send(ChatEvent::ToolCall(call.function.name.clone(), call.function.arguments.to_string())).await;
+                    tracing::debug!("Tool call {} {}", call.function.name, call.function.arguments);
+                    let output = tools.call(call).await;
+                    let _ = sender.send(ChatEvent::ToolResult(call.function.name.clone(), output.clone())).await;
+                    round_messages.push(ChatMessage::tool(output));
+                }
+                messages.extend(round_messages.iter().cloned());
+                let _ = sender.send(ChatEvent::ToolRound(round_messages)).await;
+            };
+
+            match result {
+                Ok((full_response, true)) => {
+                    let _ = sender.send(ChatEvent::Interrupted(full_response)).await;
+                }
+                Ok((full_response, false)) => {
+                    let _ = sender.send(ChatEvent::Done(full_response)).await;
+                }
+                Err(e) => {
+                    if let Backend::Ollama(server) = &backend {
+                        if model_missing(&server.client, &model).await {
+                            let _ = sender.send(ChatEvent::ModelMissing(server.client.clone(), model)).await;
+                            return;
+                        }
+                    }
+                    // Only Ollama has a reconnect loop; other providers' and answered errors just show
+                    let lost = e.connection && matches!(backend, Backend::Ollama(_));
+                    let _ = sender.send(if lost { ChatEvent::ConnectionLost(e.message) } else { ChatEvent::Error(e.message) }).await;
+                }
+            }
+        });
+
+        if let Some(chat) = self.state.locked().chat_in_tab(tab_id) {
+            chat.task = Some(task.abort_handle());
+        }
+        self.notify_busy(tab_id);
+    }
+
+    /// Adds the message to the chat in tab `tab_id`, after the agent's system
+    /// prompt with the active profile and its memory if the chat is new, and
+    /// reads what the request needs. `None` if the tab was closed.
+    fn prepare_turn(&self, tab_id: u64, agent: &Agent, content: String, images: Vec<String>) -> Option<Turn> {
+        let mut s = self.state.locked();
+        let profile = s.settings.active_profile.as_ref()
+            .and_then(|name| s.settings.profiles.iter().find(|p| &p.name == name))
+            .cloned();
+        let memory_enabled = s.settings.memory_enabled && profile.as_ref().is_some_and(|p| p.memory_enabled);
+
+        if s.chat_in_tab(tab_id)?.messages.is_empty() {
+            let mut system_prompt = agent.system_prompt.clone();
+            if let Some(profile) = &profile {
+                system_prompt.push_str("\n\n---\nUser Profile:\n");
+                if !profile.first_name.is_empty() || !profile.last_name.is_empty() {
+                    system_prompt.push_str(&format!("Name: {} {}\n", profile.first_name, profile.last_name));
+                }
+                if !profile.location.is_empty() {
+                    system_prompt.push_str(&format!("Location: {}\n", profile.location));
+                }
+                if !profile.bio.is_empty() {
+                    system_prompt.push_str(&format!("Bio: {}\n", profile.bio));
+                }
+
+                // Load Long-term Memory
+                let mem_file = s.memory_path.join(format!("{}.txt", profile.id));
+                if memory_enabled {
+                    if let Some(memory) = s.storage.read_private_string(&mem_file) {
+                        if !memory.trim().is_empty() {
+                            system_prompt.push_str("\nLong-term Memory of User:\n");
+                            system_prompt.push_str(&memory);
+                        }
+                    }
+                }
+            }
+            s.chat_in_tab(tab_id)?.messages.push(StoredMessage::new(ChatMessage::system(system_prompt)));
+        }
+
+        let mut user_message = ChatMessage::user(content);
+        if !images.is_empty() {
+            user_message = user_message.with_images(images.into_iter().map(Image::from_base64).collect());
+        }
+        let chat = s.chat_in_tab(tab_id)?;
+        chat.messages.push(StoredMessage::new(user_message));
+        let messages = chat_messages(&chat.messages);
+        recovery::save(&s, tab_id, "");
+        let memory = profile.filter(|_| memory_enabled).map(|profile| {
+            let (backend, model) = s.utility_backend(agent);
+            (profile.id, backend, model)
+        });
+        Some(Turn {
+            backend: s.backend_for(agent),
+            messages,
+            // Raw completions have no tool calling
+            tools: if agent.tools_enabled && !agent.raw_mode { ToolRegistry::builtin(&s.settings) } else { ToolRegistry::default() },
+            known_context: s.known_context_length(agent),
+            memory,
+        })
+    }
+}
+
+/// What a turn's request is made from, read from the state before it starts.
+struct Turn {
+    backend: Backend,
+    messages: Vec<ChatMessage>,
+    tools: ToolRegistry,
+    /// The model's context window, if looked up already
+    known_context: Option<u64>,
+    /// Profile id and utility model whose memory the finished turn updates
+    memory: Option<(String, Backend, String)>,
+}
+
+/// Rewrites the profile's long-term memory in `mem_file` from `conversation`,
+/// which ends with the reply.
+async fn update_memory(mem_file: PathBuf, storage: Storage, backend: Backend, model: String, mut conversation: Vec<ChatMessage>) {
+    let existing_memory = storage.read_private_string(&mem_file).unwrap_or_default();
+
+    let memory_prompt = format!(
+        "You are a memory module. Based on the recent conversation above and the existing knowledge about the user, update the Long-term Memory. \
+        Existing Knowledge:\n{}\n\n\
+        Requirements:\n\
+        1. Output a concise, bulleted list of facts, preferences, and important context about the user.\n\
+        2. Include new info from this chat.\n\
+        3. Keep it brief and relevant for future assistance.\n\
+        4. Output ONLY the list, no headers or conversational text.",
+        existing_memory
+    );
+
+    conversation.push(ChatMessage::user(memory_prompt));
+    if let Ok(res) = backend.chat(model, conversation).await {
+        let new_memory = res.trim().to_string();
+        if !new_memory.is_empty() {
+            storage.save_private(&mem_file, new_memory);
+        }
+    }
+}
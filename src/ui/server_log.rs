@@ -0,0 +1,291 @@
+use gtk4 as gtk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Label, Orientation, ScrolledWindow, TextBuffer, TextView};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::service;
+
+/// Lines kept in the drawer; the oldest go as new ones arrive.
+const MAX_LINES: i32 = 2000;
+/// Journal lines shown from before the drawer was opened.
+const BACKLOG_LINES: &str = "200";
+/// How often the output of an `ollama serve` started by the app is read.
+const SERVE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the server's log is read from, in the order of the source dropdown.
+#[derive(Clone, Copy, PartialEq)]
+enum LogSource {
+    SystemUnit,
+    UserUnit,
+    Serve,
+}
+
+const SOURCES: [LogSource; 3] = [LogSource::SystemUnit, LogSource::UserUnit, LogSource::Serve];
+
+impl LogSource {
+    fn label(self) -> &'static str {
+        match self {
+            LogSource::SystemUnit => "System service",
+            LogSource::UserUnit => "User service",
+            LogSource::Serve => "Started by this app",
+        }
+    }
+
+    /// `journalctl` following the unit, or `None` for the app's own server.
+    fn journal_argv(self) -> Option<Vec<&'static str>> {
+        let mut argv = vec!["journalctl", "-u", "ollama", "-f", "-n", BACKLOG_LINES, "-o", "short-iso", "--no-pager"];
+        match self {
+            LogSource::SystemUnit => {}
+            LogSource::UserUnit => argv.insert(1, "--user"),
+            LogSource::Serve => return None,
+        }
+        Some(argv)
+    }
+}
+
+/// Drawer following the local Ollama server's log while it is open: the
+/// journal of its systemd unit, or the output of the `ollama serve` the app
+/// started, so failed generations can be looked into without a terminal.
+#[derive(Clone)]
+pub struct ServerLog {
+    pub widget: gtk::Revealer,
+    source_dropdown: DropDown,
+    buffer: TextBuffer,
+    scrolled: ScrolledWindow,
+    view: TextView,
+    /// Follows the end of the buffer, for scrolling along with new lines
+    end_mark: gtk::TextMark,
+    /// The `journalctl -f` being read
+    tail: Rc<RefCell<Option<gio::Subprocess>>>,
+    /// Reads the app's `ollama serve` output while it is shown
+    poll: Rc<RefCell<Option<glib::SourceId>>>,
+    /// Set once the source was picked for the user on first opening
+    detected: Rc<Cell<bool>>,
+}
+
+impl ServerLog {
+    pub fn new() -> Self {
+        let panel = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(8)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(20)
+            .margin_bottom(20)
+            .width_request(460)
+            .css_classes(["inspector"])
+            .build();
+
+        let header = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        header.append(&Label::builder()
+            .label("Ollama log")
+            .css_classes(["title-4"])
+            .hexpand(true)
+            .xalign(0.0)
+            .build());
+        let labels: Vec<&str> = SOURCES.iter().map(|s| s.label()).collect();
+        let source_dropdown = DropDown::from_strings(&labels);
+        source_dropdown.set_tooltip_text(Some("Where the server's log is read from"));
+        header.append(&source_dropdown);
+        let copy_btn = Button::builder()
+            .icon_name("edit-copy-symbolic")
+            .css_classes(["flat"])
+            .tooltip_text("Copy log")
+            .build();
+        header.append(&copy_btn);
+        panel.append(&header);
+
+        let buffer = TextBuffer::new(None);
+        let end_mark = buffer.create_mark(None, &buffer.end_iter(), false);
+        let view = TextView::builder()
+            .buffer(&buffer)
+            .editable(false)
+            .monospace(true)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .top_margin(8)
+            .bottom_margin(8)
+            .left_margin(8)
+            .right_margin(8)
+            .css_classes(["code-view"])
+            .build();
+        let scrolled = ScrolledWindow::builder()
+            .child(&view)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build();
+        panel.append(&scrolled);
+
+        let widget = gtk::Revealer::builder()
+            .transition_type(gtk::RevealerTransitionType::SlideLeft)
+            .child(&panel)
+            .build();
+
+        let buffer_c = buffer.clone();
+        copy_btn.connect_clicked(move |_| {
+            let text = buffer_c.text(&buffer_c.start_iter(), &buffer_c.end_iter(), false);
+            if let Some(display) = gtk::gdk::Display::default() {
+                display.clipboard().set(&text.as_str());
+            }
+        });
+
+        let log = Self {
+            widget,
+            source_dropdown,
+            buffer,
+            scrolled,
+            view,
+            end_mark,
+            tail: Rc::new(RefCell::new(None)),
+            poll: Rc::new(RefCell::new(None)),
+            detected: Rc::new(Cell::new(false)),
+        };
+        log.widget.connect_reveal_child_notify({
+            let log = log.clone();
+            move |revealer| {
+                if !revealer.reveals_child() {
+                    log.stop();
+                } else if log.detected.replace(true) {
+                    log.start();
+                } else {
+                    log.detect_source();
+                }
+            }
+        });
+        log.source_dropdown.connect_selected_notify({
+            let log = log.clone();
+            move |_| {
+                if log.widget.reveals_child() {
+                    log.start();
+                }
+            }
+        });
+        log
+    }
+
+    /// Picks the app's own server if it started one, else the user unit if
+    /// it is running, else the system unit, and starts following it.
+    fn detect_source(&self) {
+        let log = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let source = if service::serve_log().is_some() {
+                LogSource::Serve
+            } else {
+                let argv = ["systemctl", "--user", "is-active", "--quiet", "ollama"].map(std::ffi::OsStr::new);
+                let user_unit = gio::Subprocess::newv(&argv, gio::SubprocessFlags::NONE);
+                match user_unit {
+                    Ok(process) if process.wait_check_future().await.is_ok() => LogSource::UserUnit,
+                    _ => LogSource::SystemUnit,
+                }
+            };
+            let idx = SOURCES.iter().position(|s| *s == source).unwrap_or_default() as u32;
+            if log.source_dropdown.selected() == idx {
+                log.start();
+            } else {
+                log.source_dropdown.set_selected(idx);
+            }
+        });
+    }
+
+    fn start(&self) {
+        self.stop();
+        self.buffer.set_text("");
+        let source = SOURCES.get(self.source_dropdown.selected() as usize).copied().unwrap_or(LogSource::SystemUnit);
+        match source.journal_argv() {
+            Some(argv) => self.follow_journal(&argv),
+            None => self.follow_serve_log(),
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(process) = self.tail.borrow_mut().take() {
+            process.force_exit();
+        }
+        if let Some(source) = self.poll.borrow_mut().take() {
+            source.remove();
+        }
+    }
+
+    fn follow_journal(&self, argv: &[&str]) {
+        let os_argv: Vec<&std::ffi::OsStr> = argv.iter().map(std::ffi::OsStr::new).collect();
+        // Errors such as missing journal permissions are shown along with the lines
+        let process = match gio::Subprocess::newv(&os_argv, gio::SubprocessFlags::STDOUT_PIPE | gio::SubprocessFlags::STDERR_MERGE) {
+            Ok(process) => process,
+            Err(e) => {
+                self.append(&format!("Failed to run journalctl: {}", e));
+                return;
+            }
+        };
+        let Some(stdout) = process.stdout_pipe() else { return };
+        let lines = gio::DataInputStream::new(&stdout);
+        *self.tail.borrow_mut() = Some(process.clone());
+        let log = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            while let Ok(Some(line)) = lines.read_line_future(glib::Priority::DEFAULT).await {
+                // Lines still buffered from a journalctl that was replaced or stopped
+                if log.tail.borrow().as_ref() != Some(&process) {
+                    break;
+                }
+                log.append(&String::from_utf8_lossy(&line));
+            }
+        });
+    }
+
+    fn follow_serve_log(&self) {
+        if service::serve_log().is_none() {
+            self.buffer.set_text("Ollama wasn't started by this app. Its log is in the journal of its service.");
+            return;
+        }
+        // Lines written so far that are already in the buffer
+        let shown = Rc::new(Cell::new(0u64));
+        let log = self.clone();
+        let read = move || {
+            let Some((lines, total)) = service::serve_log() else { return };
+            // Restarted from the app: the count starts over with the new process
+            if total < shown.get() {
+                shown.set(0);
+            }
+            let new = total.saturating_sub(shown.get()).min(lines.len() as u64) as usize;
+            for line in &lines[lines.len() - new..] {
+                log.append(line);
+            }
+            shown.set(total);
+        };
+        read();
+        let source = glib::timeout_add_local(SERVE_POLL_INTERVAL, move || {
+            read();
+            glib::ControlFlow::Continue
+        });
+        *self.poll.borrow_mut() = Some(source);
+    }
+
+    /// Adds a line at the end, dropping the oldest past `MAX_LINES`, and
+    /// scrolls along unless the user scrolled up to read.
+    fn append(&self, line: &str) {
+        let adjustment = self.scrolled.vadjustment();
+        let at_end = adjustment.value() + adjustment.page_size() >= adjustment.upper() - 20.0;
+        let mut end = self.buffer.end_iter();
+        self.buffer.insert(&mut end, &format!("{}\n", line.trim_end()));
+        let excess = self.buffer.line_count() - MAX_LINES;
+        if excess > 0 {
+            if let Some(mut cut) = self.buffer.iter_at_line(excess) {
+                self.buffer.delete(&mut self.buffer.start_iter(), &mut cut);
+            }
+        }
+        if at_end {
+            self.view.scroll_to_mark(&self.end_mark, 0.0, false, 0.0, 1.0);
+        }
+    }
+}
+
+impl Default for ServerLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,115 @@
+use gtk4 as gtk;
+use adw::prelude::*;
+use gtk::{Box, Button, Entry, Label, Orientation};
+use adw::ToastOverlay;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::controller::Commands;
+use crate::state::{AppState, TlsOptions};
+use crate::theme::ThemeManager;
+
+mod agents;
+mod general;
+mod models;
+mod personalization;
+mod providers;
+mod templates;
+
+pub use general::GeneralPage;
+
+/// The settings window, with a page per section. It is hidden rather than
+/// destroyed on close, so the pages keep their state between visits.
+pub struct SettingsWindow {
+    pub window: adw::PreferencesWindow,
+    pub general: GeneralPage,
+}
+
+/// What the pages need from the rest of the window.
+pub struct SettingsCallbacks {
+    /// Agents were added, renamed or removed
+    pub agents_changed: Rc<dyn Fn()>,
+    /// Ollama couldn't be reached
+    pub start_reconnect: Rc<dyn Fn()>,
+    /// A backup was restored into the state
+    pub restored: Rc<dyn Fn()>,
+}
+
+impl SettingsWindow {
+    pub fn new(state: &Arc<Mutex<AppState>>, theme: &Rc<ThemeManager>, toasts: &ToastOverlay, commands: &Commands, callbacks: SettingsCallbacks) -> Self {
+        // The pages are built from plain widgets rather than preference rows, which search can't look into
+        let window = adw::PreferencesWindow::builder()
+            .title("Settings")
+            .default_width(900)
+            .default_height(700)
+            .search_enabled(false)
+            .hide_on_close(true)
+            .build();
+
+        let general = GeneralPage::new(state, theme, toasts, commands, callbacks.restored);
+        window.add(&page("General", "preferences-system-symbolic", &general.widget));
+        window.add(&page("Agents", "system-users-symbolic", &agents::build(state, toasts, callbacks.agents_changed)));
+        window.add(&page("Providers", "network-server-symbolic", &providers::build(state, toasts)));
+        window.add(&page("Templates", "document-edit-symbolic", &templates::build(state, toasts)));
+        window.add(&page("Models", "drive-harddisk-symbolic", &models::build(state, callbacks.start_reconnect)));
+        window.add(&page("Personalization", "avatar-default-symbolic", &personalization::build(state, toasts)));
+
+        Self { window, general }
+    }
+}
+
+/// A preferences page holding `content` in a single group.
+fn page(title: &str, icon_name: &str, content: &impl IsA<gtk::Widget>) -> adw::PreferencesPage {
+    let group = adw::PreferencesGroup::new();
+    group.add(content);
+    let page = adw::PreferencesPage::builder().title(title).icon_name(icon_name).build();
+    page.add(&group);
+    page
+}
+
+/// Builds the TLS options editor shared by the default endpoint and named
+/// connections: an "accept invalid certificates" switch and a CA bundle path.
+fn build_tls_editor(tls: &TlsOptions) -> (Box, gtk::Switch, Entry) {
+    let container = Box::builder().orientation(Orientation::Vertical).spacing(5).build();
+
+    let invalid_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+    invalid_row.append(&Label::builder().label("Accept invalid TLS certificates").xalign(0.0).hexpand(true).build());
+    let invalid_switch = gtk::Switch::builder()
+        .active(tls.accept_invalid_certs)
+        .valign(gtk::Align::Center)
+        .build();
+    invalid_row.append(&invalid_switch);
+    container.append(&invalid_row);
+
+    let ca_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+    let ca_entry = Entry::builder()
+        .text(tls.ca_cert_path.as_deref().unwrap_or(""))
+        .placeholder_text("Custom CA certificate (PEM)")
+        .hexpand(true)
+        .build();
+    let browse_btn = Button::with_label("Browse…");
+    let ca_entry_c = ca_entry.clone();
+    browse_btn.connect_clicked(move |btn| {
+        let dialog = gtk::FileDialog::builder().title("Select CA Certificate").build();
+        let parent = btn.root().and_downcast::<gtk::Window>();
+        let ca_entry = ca_entry_c.clone();
+        dialog.open(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+            if let Some(path) = res.ok().and_then(|file| file.path()) {
+                ca_entry.set_text(&path.to_string_lossy());
+            }
+        });
+    });
+    ca_row.append(&ca_entry);
+    ca_row.append(&browse_btn);
+    container.append(&ca_row);
+
+    (container, invalid_switch, ca_entry)
+}
+
+fn read_tls_editor(invalid_switch: &gtk::Switch, ca_entry: &Entry) -> TlsOptions {
+    let ca_path = ca_entry.text().trim().to_string();
+    TlsOptions {
+        ca_cert_path: if ca_path.is_empty() { None } else { Some(ca_path) },
+        accept_invalid_certs: invalid_switch.is_active(),
+    }
+}
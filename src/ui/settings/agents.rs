@@ -0,0 +1,472 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Entry, Label, ListBox, Orientation, ScrolledWindow, StringList};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+
+use crate::confirm::{confirm, undo_toast};
+use crate::error::LockExt;
+use crate::state::{default_raw_template, Agent, AppState, ResponseFormat, MAX_SUGGESTIONS};
+use crate::ui::badges::model_list_factory;
+use crate::ui::toasts::toast;
+
+/// Fills `model_list` with `models`, appending `current` when it is missing so
+/// the user can still see and save it. Returns the index to select.
+fn populate_model_list(model_list: &StringList, models: &[String], current: &str) -> u32 {
+    model_list.splice(0, model_list.n_items(), &models.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+    match models.iter().position(|m| m == current) {
+        Some(idx) => idx as u32,
+        None if !current.is_empty() => {
+            model_list.append(current);
+            models.len() as u32
+        }
+        None => 0,
+    }
+}
+
+/// Reply formats in the order of the format dropdown.
+const FORMATS: [ResponseFormat; 3] = [ResponseFormat::Text, ResponseFormat::Json, ResponseFormat::Schema];
+
+/// Reads an optional number field; empty means unset.
+fn parse_optional<T: std::str::FromStr>(text: &str) -> Result<Option<T>, T::Err> {
+    let text = text.trim();
+    if text.is_empty() {
+        Ok(None)
+    } else {
+        text.parse().map(Some)
+    }
+}
+
+/// One editable row per agent. `on_changed` runs whenever agents are saved or
+/// deleted, so the agent picker can follow.
+pub fn build(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay, on_changed: Rc<dyn Fn()>) -> gtk::Widget {
+    let agents_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .build();
+    
+    let agents_list = ListBox::builder().build();
+    let scrolled_agents = ScrolledWindow::builder()
+        .child(&agents_list)
+        .propagate_natural_height(true)
+        .build();
+    agents_box.append(&scrolled_agents);
+
+    // Lets rows rebuild the list they are part of, e.g. when a deletion is undone
+    let refresh_self: Rc<RefCell<Option<Weak<dyn Fn()>>>> = Rc::new(RefCell::new(None));
+    let refresh_agents_list_func: Rc<dyn Fn()> = {
+        let refresh_self = refresh_self.clone();
+        let state = state.clone();
+        let toasts = toasts.clone();
+        let agents_list = agents_list.clone();
+        let on_changed = on_changed.clone();
+        
+        Rc::new(move || {
+            while let Some(child) = agents_list.first_child() {
+                agents_list.remove(&child);
+            }
+            on_changed();
+            let (agents, providers, default_idx) = {
+                let s = state.locked();
+                (s.settings.agents.clone(), s.settings.providers.clone(), s.settings.default_agent_index())
+            };
+            let mut default_group: Option<gtk::CheckButton> = None;
+            for (idx, agent) in agents.into_iter().enumerate() {
+                let row = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(5)
+                    .margin_top(10)
+                    .margin_bottom(10)
+                    .build();
+
+                row.append(&Label::builder().label("Agent Name").xalign(0.0).css_classes(["settings-label"]).build());
+                let name_entry = Entry::builder().text(&agent.name).placeholder_text("Name").build();
+                row.append(&name_entry);
+
+                row.append(&Label::builder().label("Description").xalign(0.0).css_classes(["settings-label"]).build());
+                let desc_entry = Entry::builder().text(&agent.description).placeholder_text("Description").build();
+                row.append(&desc_entry);
+
+                row.append(&Label::builder().label("Provider").xalign(0.0).css_classes(["settings-label"]).build());
+                let provider_names = StringList::new(&["Ollama (default endpoint)"]);
+                for provider in &providers {
+                    provider_names.append(&provider.name);
+                }
+                let provider_selected = agent.provider_id.as_ref()
+                    .and_then(|id| providers.iter().position(|p| &p.id == id))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                let provider_dropdown = DropDown::builder()
+                    .model(&provider_names)
+                    .selected(provider_selected as u32)
+                    .build();
+                row.append(&provider_dropdown);
+
+                row.append(&Label::builder().label("Model").xalign(0.0).css_classes(["settings-label"]).build());
+                
+                let model_list = StringList::new(&[]);
+                let models = state.locked().models_for(agent.provider_id.as_deref());
+                let selected_idx = populate_model_list(&model_list, &models, &agent.model);
+
+                let model_dropdown = DropDown::builder()
+                    .model(&model_list)
+                    .list_factory(&model_list_factory(&state))
+                    .selected(selected_idx)
+                    .build();
+                row.append(&model_dropdown);
+
+                let state_p = state.clone();
+                let providers_p = providers.clone();
+                let model_list_p = model_list.clone();
+                let model_dropdown_p = model_dropdown.clone();
+                let agent_model = agent.model.clone();
+                provider_dropdown.connect_selected_notify(move |dd| {
+                    let provider_id = (dd.selected() as usize).checked_sub(1)
+                        .and_then(|i| providers_p.get(i))
+                        .map(|p| p.id.clone());
+                    let models = state_p.locked().models_for(provider_id.as_deref());
+                    let selected = populate_model_list(&model_list_p, &models, &agent_model);
+                    model_dropdown_p.set_selected(selected);
+                });
+
+                row.append(&Label::builder().label("System Prompt").xalign(0.0).css_classes(["settings-label"]).build());
+                let prompt_entry = Entry::builder().text(&agent.system_prompt).placeholder_text("System Prompt").build();
+                row.append(&prompt_entry);
+
+                row.append(&Label::builder().label("Greeting").xalign(0.0).css_classes(["settings-label"]).build());
+                let greeting_entry = Entry::builder().text(&agent.greeting).placeholder_text("Shown when a new chat starts").build();
+                row.append(&greeting_entry);
+
+                row.append(&Label::builder().label(format!("Suggested Prompts (one per line, up to {})", MAX_SUGGESTIONS)).xalign(0.0).css_classes(["settings-label"]).build());
+                let suggestions_view = gtk::TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .height_request(60)
+                    .css_classes(["headers-view"])
+                    .build();
+                suggestions_view.buffer().set_text(&agent.suggestions.join("\n"));
+                row.append(&suggestions_view);
+
+                let tools_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                tools_row.append(&Label::builder()
+                    .label("Enable tools (requires a model with tool calling support)")
+                    .xalign(0.0)
+                    .hexpand(true)
+                    .wrap(true)
+                    .build());
+                let tools_switch = gtk::Switch::builder().active(agent.tools_enabled).valign(gtk::Align::Center).build();
+                tools_row.append(&tools_switch);
+                row.append(&tools_row);
+
+                let raw_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                raw_row.append(&Label::builder()
+                    .label("Raw completion mode (sends the template below to /api/generate; Ollama only, no tools)")
+                    .xalign(0.0)
+                    .hexpand(true)
+                    .wrap(true)
+                    .build());
+                let raw_switch = gtk::Switch::builder().active(agent.raw_mode).valign(gtk::Align::Center).build();
+                raw_row.append(&raw_switch);
+                row.append(&raw_row);
+                let raw_template_view = gtk::TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .monospace(true)
+                    .height_request(80)
+                    .tooltip_text("{{system}}: system prompt, {{history}}: earlier turns, {{prompt}}: the new message")
+                    .css_classes(["headers-view"])
+                    .visible(agent.raw_mode)
+                    .build();
+                raw_template_view.buffer().set_text(&agent.raw_template);
+                row.append(&raw_template_view);
+                raw_switch.bind_property("active", &raw_template_view, "visible").build();
+
+                row.append(&Label::builder().label("Stop Sequences (one per line)").xalign(0.0).css_classes(["settings-label"]).build());
+                let stop_view = gtk::TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .monospace(true)
+                    .height_request(40)
+                    .css_classes(["headers-view"])
+                    .build();
+                stop_view.buffer().set_text(&agent.stop.join("\n"));
+                row.append(&stop_view);
+
+                let limits_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                limits_row.append(&Label::builder().label("Max tokens").xalign(0.0).build());
+                let num_predict_entry = Entry::builder()
+                    .text(agent.num_predict.map(|n| n.to_string()).unwrap_or_default())
+                    .placeholder_text("Default")
+                    .width_chars(8)
+                    .input_purpose(gtk::InputPurpose::Number)
+                    .build();
+                limits_row.append(&num_predict_entry);
+                limits_row.append(&Label::builder().label("Seed").xalign(0.0).build());
+                let seed_entry = Entry::builder()
+                    .text(agent.seed.map(|n| n.to_string()).unwrap_or_default())
+                    .placeholder_text("Random")
+                    .width_chars(8)
+                    .input_purpose(gtk::InputPurpose::Number)
+                    .build();
+                limits_row.append(&seed_entry);
+                limits_row.append(&Label::builder().label("GPU layers").xalign(0.0).build());
+                let num_gpu_entry = Entry::builder()
+                    .text(agent.num_gpu.map(|n| n.to_string()).unwrap_or_default())
+                    .placeholder_text("Auto")
+                    .tooltip_text("Layers to offload to the GPU; fewer moves the rest to the CPU, 0 runs on the CPU only")
+                    .width_chars(8)
+                    .input_purpose(gtk::InputPurpose::Digits)
+                    .build();
+                limits_row.append(&num_gpu_entry);
+                limits_row.append(&Label::builder().label("Main GPU").xalign(0.0).build());
+                let main_gpu_entry = Entry::builder()
+                    .text(agent.main_gpu.map(|n| n.to_string()).unwrap_or_default())
+                    .placeholder_text("Auto")
+                    .tooltip_text("Index of the GPU that keeps what isn't split when the model spans several GPUs")
+                    .width_chars(8)
+                    .input_purpose(gtk::InputPurpose::Digits)
+                    .build();
+                limits_row.append(&main_gpu_entry);
+                row.append(&limits_row);
+
+                let format_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                format_row.append(&Label::builder().label("Reply format").xalign(0.0).hexpand(true).build());
+                let format_dropdown = DropDown::from_strings(&["Text", "JSON", "JSON schema"]);
+                format_dropdown.set_selected(FORMATS.iter().position(|f| *f == agent.response_format).unwrap_or(0) as u32);
+                format_row.append(&format_dropdown);
+                row.append(&format_row);
+                let schema_view = gtk::TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .monospace(true)
+                    .height_request(80)
+                    .tooltip_text("A JSON schema, e.g. {\"type\": \"object\", \"properties\": {…}, \"required\": […]}")
+                    .css_classes(["headers-view"])
+                    .visible(agent.response_format == ResponseFormat::Schema)
+                    .build();
+                schema_view.buffer().set_text(&agent.json_schema);
+                row.append(&schema_view);
+                format_dropdown.connect_selected_notify({
+                    let schema_view = schema_view.clone();
+                    move |dd| schema_view.set_visible(FORMATS.get(dd.selected() as usize) == Some(&ResponseFormat::Schema))
+                });
+
+                let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                let save_btn = Button::with_label("Save");
+                let delete_btn = Button::with_label("Delete");
+                let default_check = gtk::CheckButton::builder()
+                    .label("Default for new chats")
+                    .active(idx == default_idx)
+                    .hexpand(true)
+                    .halign(gtk::Align::End)
+                    .build();
+                default_check.set_group(default_group.as_ref());
+                default_group.get_or_insert_with(|| default_check.clone());
+                actions_box.append(&save_btn);
+                actions_box.append(&delete_btn);
+                actions_box.append(&default_check);
+                row.append(&actions_box);
+                row.append(&gtk::Separator::new(Orientation::Horizontal));
+
+                let state_c = state.clone();
+                let name_c = name_entry.clone();
+                let desc_c = desc_entry.clone();
+                let model_c = model_dropdown.clone();
+                let provider_c = provider_dropdown.clone();
+                let providers_c = providers.clone();
+                let prompt_c = prompt_entry.clone();
+                let tools_c = tools_switch.clone();
+                let greeting_c = greeting_entry.clone();
+                let raw_switch_c = raw_switch.clone();
+                let raw_template_c = raw_template_view.clone();
+                let suggestions_c = suggestions_view.clone();
+                let stop_c = stop_view.clone();
+                let num_predict_c = num_predict_entry.clone();
+                let seed_c = seed_entry.clone();
+                let num_gpu_c = num_gpu_entry.clone();
+                let main_gpu_c = main_gpu_entry.clone();
+                let format_c = format_dropdown.clone();
+                let schema_c = schema_view.clone();
+                let on_changed_c = on_changed.clone();
+                let toasts_c = toasts.clone();
+                save_btn.connect_clicked(move |_| {
+                    let name = name_c.text().to_string();
+                    let desc = desc_c.text().to_string();
+                    let model = if let Some(item) = model_c.selected_item() {
+                        item.downcast::<gtk::StringObject>().map(|s| s.string().to_string()).unwrap_or_default()
+                    } else {
+                        "".to_string()
+                    };
+                    let prompt = prompt_c.text().to_string();
+                    let buffer = raw_template_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    let raw_template = match buffer.text(&start, &end, false).to_string() {
+                        template if template.trim().is_empty() => default_raw_template(),
+                        template => template,
+                    };
+                    let buffer = suggestions_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    let suggestions = buffer.text(&start, &end, false).lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .take(MAX_SUGGESTIONS)
+                        .map(String::from)
+                        .collect();
+                    let buffer = stop_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    // Only line breaks separate sequences; spaces may be part of one
+                    let stop = buffer.text(&start, &end, false).lines()
+                        .filter(|line| !line.is_empty())
+                        .map(String::from)
+                        .collect();
+                    let (num_predict, seed) = match (parse_optional(&num_predict_c.text()), parse_optional(&seed_c.text())) {
+                        (Ok(num_predict), Ok(seed)) => (num_predict, seed),
+                        _ => {
+                            toasts_c.add_toast(toast("Max tokens and seed must be whole numbers"));
+                            return;
+                        }
+                    };
+                    let Ok(num_gpu) = parse_optional(&num_gpu_c.text()) else {
+                        toasts_c.add_toast(toast("GPU layers must be a whole number of 0 or more"));
+                        return;
+                    };
+                    let Ok(main_gpu) = parse_optional(&main_gpu_c.text()) else {
+                        toasts_c.add_toast(toast("Main GPU must be a GPU index of 0 or more"));
+                        return;
+                    };
+                    let response_format = FORMATS.get(format_c.selected() as usize).copied().unwrap_or_default();
+                    let buffer = schema_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    let json_schema = buffer.text(&start, &end, false).trim().to_string();
+                    if response_format == ResponseFormat::Schema {
+                        if let Err(e) = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&json_schema) {
+                            toasts_c.add_toast(toast(&format!("The JSON schema isn't a valid JSON object: {}", e)));
+                            return;
+                        }
+                    }
+                    let provider_id = (provider_c.selected() as usize).checked_sub(1)
+                        .and_then(|i| providers_c.get(i))
+                        .map(|p| p.id.clone());
+                    
+                    {
+                        let mut s = state_c.locked();
+                        // Keep the default pointing at the agent when it is renamed
+                        let old_name = s.settings.agents.get(idx).map(|a| a.name.clone());
+                        if old_name.is_some() && s.settings.default_agent == old_name {
+                            s.settings.default_agent = Some(name.clone());
+                        }
+                        if let Some(a) = s.settings.agents.get_mut(idx) {
+                            a.name = name;
+                            a.description = desc;
+                            a.model = model;
+                            a.system_prompt = prompt;
+                            a.provider_id = provider_id;
+                            a.tools_enabled = tools_c.is_active();
+                            a.greeting = greeting_c.text().trim().to_string();
+                            a.suggestions = suggestions;
+                            a.raw_mode = raw_switch_c.is_active();
+                            a.raw_template = raw_template;
+                            a.stop = stop;
+                            a.num_predict = num_predict;
+                            a.seed = seed;
+                            a.num_gpu = num_gpu;
+                            a.main_gpu = main_gpu;
+                            a.response_format = response_format;
+                            a.json_schema = json_schema;
+                            s.save_settings();
+                            toasts_c.add_toast(toast("Agent saved"));
+                        }
+                    }
+                    on_changed_c();
+                });
+
+                let state_def = state.clone();
+                default_check.connect_toggled(move |check| {
+                    if !check.is_active() {
+                        return;
+                    }
+                    let mut s = state_def.locked();
+                    let Some(name) = s.settings.agents.get(idx).map(|a| a.name.clone()) else { return };
+                    if s.settings.default_agent.as_ref() != Some(&name) {
+                        s.settings.default_agent = Some(name);
+                        s.save_settings();
+                    }
+                });
+
+                let state_d = state.clone();
+                let agent_d = agent.clone();
+                let agents_list_clone = agents_list.clone();
+                let row_clone = row.clone();
+                let on_changed_d = on_changed.clone();
+                let toasts_d = toasts.clone();
+                let refresh_d = refresh_self.clone();
+                delete_btn.connect_clicked(move |btn| {
+                    let state = state_d.clone();
+                    let agent = agent_d.clone();
+                    let agents_list = agents_list_clone.clone();
+                    let row = row_clone.clone();
+                    let on_changed = on_changed_d.clone();
+                    let toasts = toasts_d.clone();
+                    let refresh = refresh_d.clone();
+                    confirm(btn, &format!("Delete “{}”?", agent.name), "Its system prompt and settings will be removed.", "Delete", move || {
+                        let mut s = state.locked();
+                        let Some(idx) = s.settings.agents.iter().position(|a| a.name == agent.name) else { return };
+                        s.settings.agents.remove(idx);
+                        s.save_settings();
+                        drop(s);
+                        agents_list.remove(&row);
+                        on_changed();
+                        undo_toast(&toasts, "Agent deleted", move || {
+                            let mut s = state.locked();
+                            let idx = idx.min(s.settings.agents.len());
+                            s.settings.agents.insert(idx, agent.clone());
+                            s.save_settings();
+                            drop(s);
+                            if let Some(refresh) = refresh.borrow().as_ref().and_then(Weak::upgrade) {
+                                refresh();
+                            }
+                        });
+                    });
+                });
+                agents_list.append(&row);
+            }
+        })
+    };
+
+    *refresh_self.borrow_mut() = Some(Rc::downgrade(&refresh_agents_list_func));
+    refresh_agents_list_func();
+
+    // Models and providers may have changed since the rows were built
+    let refresh_agents = refresh_agents_list_func.clone();
+    agents_box.connect_map(move |_| refresh_agents());
+
+    let add_agent_btn = Button::with_label("Add Agent");
+    let state_add = state.clone();
+    let refresh_agents_add = refresh_agents_list_func.clone();
+    add_agent_btn.connect_clicked(move |_| {
+        let mut s = state_add.locked();
+        s.settings.agents.push(Agent {
+            name: "New Agent".to_string(),
+            model: "llama3".to_string(),
+            system_prompt: "You are a helpful assistant.".to_string(),
+            description: "Personal Assistant".to_string(),
+            provider_id: None,
+            tools_enabled: false,
+            greeting: String::new(),
+            suggestions: Vec::new(),
+            raw_mode: false,
+            raw_template: default_raw_template(),
+            stop: Vec::new(),
+            num_predict: None,
+            seed: None,
+            num_gpu: None,
+            main_gpu: None,
+            response_format: ResponseFormat::Text,
+            json_schema: String::new(),
+        });
+        s.save_settings();
+        drop(s);
+        refresh_agents_add();
+    });
+
+    agents_box.append(&add_agent_btn);
+    agents_box.upcast()
+}
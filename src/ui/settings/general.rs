@@ -0,0 +1,826 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Entry, Label, Orientation, TextView};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::api_server::new_api_key;
+use crate::autostart;
+use crate::backup::{self, RestoreMode};
+use crate::confirm::confirm;
+use crate::crypto::Cipher;
+use crate::controller::{Command, Commands};
+use crate::error::{AppError, Context, LockExt};
+use crate::import;
+use crate::logging;
+use crate::portal::{read_chosen_file, write_chosen_file};
+use crate::provider::{missing_features, ollama_client};
+use crate::state::{default_title_prompt, AppState, Appearance, LogLevel, ShareService, Timeouts, MAX_CHAT_FONT_SIZE, MIN_CHAT_FONT_SIZE};
+use crate::theme::ThemeManager;
+use crate::ui::toasts::{toast, Report};
+use crate::utils::{format_headers, parse_headers};
+
+use super::{build_tls_editor, read_tls_editor};
+
+/// Endpoint, integration, appearance and data settings. The widgets other
+/// parts of the window react to are exposed; everything else is saved by
+/// `save_btn`.
+pub struct GeneralPage {
+    pub widget: Box,
+    pub save_btn: Button,
+    pub endpoint_entry: Entry,
+    pub background_switch: gtk::Switch,
+    pub font_size_spin: gtk::SpinButton,
+    pub chat_width_spin: gtk::SpinButton,
+}
+
+impl GeneralPage {
+    /// `on_restore` runs after a backup has been restored into `state`.
+    pub fn new(state: &Arc<Mutex<AppState>>, theme: &Rc<ThemeManager>, toasts: &ToastOverlay, commands: &Commands, on_restore: Rc<dyn Fn()>) -> Self {
+        let general_box = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(10)
+            .build();
+
+        general_box.append(&Label::new(Some("Ollama Endpoint")));
+        let endpoint_entry = Entry::builder()
+            .text(&state.locked().settings.ollama_endpoint)
+            .build();
+        general_box.append(&endpoint_entry);
+        let server_version_label = Label::builder().xalign(0.0).wrap(true).css_classes(["dim-label"]).build();
+        general_box.append(&server_version_label);
+        general_box.connect_map({
+            let state = state.clone();
+            move |_| {
+                let text = match state.locked().server_version.clone() {
+                    Some(version) => {
+                        let missing = missing_features(&version);
+                        if missing.is_empty() {
+                            format!("Server version: Ollama {}", version)
+                        } else {
+                            format!("Server version: Ollama {} ⚠ too old for {}", version, missing.join(", "))
+                        }
+                    }
+                    None => "Server version: not connected".to_string(),
+                };
+                server_version_label.set_label(&text);
+            }
+        });
+
+        general_box.append(&Label::builder().label("Custom Headers").xalign(0.0).css_classes(["settings-label"]).build());
+        general_box.append(&Label::builder()
+            .label("One header per line, e.g. Authorization: Bearer <token>. Sent with every request.")
+            .xalign(0.0)
+            .wrap(true)
+            .build());
+        let headers_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .monospace(true)
+            .height_request(60)
+            .css_classes(["headers-view"])
+            .build();
+        headers_view.buffer().set_text(&format_headers(&state.locked().settings.ollama_headers));
+        general_box.append(&headers_view);
+
+        general_box.append(&Label::builder().label("TLS").xalign(0.0).css_classes(["settings-label"]).build());
+        let (tls_editor, tls_invalid_switch, tls_ca_entry) = build_tls_editor(&state.locked().settings.ollama_tls);
+        general_box.append(&tls_editor);
+
+        general_box.append(&Label::builder().label("Timeouts").xalign(0.0).css_classes(["settings-label"]).build());
+        let timeouts = state.locked().settings.timeouts;
+        let connect_timeout_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        connect_timeout_row.append(&Label::builder().label("Connect timeout (seconds)").xalign(0.0).hexpand(true).build());
+        let connect_timeout_spin = gtk::SpinButton::with_range(0.0, 300.0, 1.0);
+        connect_timeout_spin.set_value(timeouts.connect_secs as f64);
+        connect_timeout_spin.set_tooltip_text(Some("0 waits forever"));
+        connect_timeout_row.append(&connect_timeout_spin);
+        general_box.append(&connect_timeout_row);
+
+        let response_timeout_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        response_timeout_row.append(&Label::builder().label("Response timeout (seconds)").xalign(0.0).hexpand(true).build());
+        let response_timeout_spin = gtk::SpinButton::with_range(0.0, 3600.0, 10.0);
+        response_timeout_spin.set_value(timeouts.response_secs as f64);
+        response_timeout_spin.set_tooltip_text(Some("How long the server may go silent, e.g. while loading a model, before the request fails. 0 waits forever"));
+        response_timeout_row.append(&response_timeout_spin);
+        general_box.append(&response_timeout_row);
+
+        let memory_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        memory_row.append(&Label::builder().label("Enable long-term memory").xalign(0.0).hexpand(true).build());
+        let memory_switch = gtk::Switch::builder()
+            .active(state.locked().settings.memory_enabled)
+            .valign(gtk::Align::Center)
+            .build();
+        memory_row.append(&memory_switch);
+        general_box.append(&memory_row);
+
+        let quick_ask_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        quick_ask_row.append(&Label::builder()
+            .label("Global Quick Ask shortcut (Ctrl+Alt+Space)")
+            .tooltip_text("Asks the desktop to bind a system-wide shortcut through the global shortcuts portal; you can change the keys there")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let quick_ask_switch = gtk::Switch::builder()
+            .active(state.locked().settings.quick_ask_shortcut)
+            .valign(gtk::Align::Center)
+            .build();
+        quick_ask_row.append(&quick_ask_switch);
+
+        let background_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        background_row.append(&Label::builder()
+            .label("Keep running in the background when the window is closed")
+            .tooltip_text("Reopen it from the status icon or by launching the app again")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let background_switch = gtk::Switch::builder()
+            .active(state.locked().settings.run_in_background)
+            .valign(gtk::Align::Center)
+            .build();
+        background_row.append(&background_switch);
+        general_box.append(&background_row);
+
+        let autostart_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        autostart_row.append(&Label::builder()
+            .label("Start automatically after login")
+            .tooltip_text("Adds an entry to ~/.config/autostart")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let autostart_switch = gtk::Switch::builder()
+            .active(autostart::is_enabled())
+            .valign(gtk::Align::Center)
+            .build();
+        autostart_row.append(&autostart_switch);
+        general_box.append(&autostart_row);
+
+        let start_hidden_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        start_hidden_row.append(&Label::builder()
+            .label("Start hidden in the background")
+            .tooltip_text("When started at login, only the status icon appears until you open the window or use Quick Ask. Needs \"Keep running in the background\"")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let start_hidden_switch = gtk::Switch::builder()
+            .active(state.locked().settings.start_hidden)
+            .valign(gtk::Align::Center)
+            .build();
+        start_hidden_row.append(&start_hidden_switch);
+        background_switch.bind_property("active", &start_hidden_row, "sensitive").sync_create().build();
+        general_box.append(&start_hidden_row);
+        general_box.append(&quick_ask_row);
+
+        let system_info_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        system_info_row.append(&Label::builder()
+            .label("Share system information with tools")
+            .tooltip_text("Lets agents with tools enabled read your CPU, memory, GPU, kernel and disk details")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let system_info_switch = gtk::Switch::builder()
+            .active(state.locked().settings.share_system_info)
+            .valign(gtk::Align::Center)
+            .build();
+        system_info_row.append(&system_info_switch);
+        general_box.append(&system_info_row);
+
+        let code_execution_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        code_execution_row.append(&Label::builder()
+            .label("Let tools run Python and Bash code in a sandbox")
+            .tooltip_text("Snippets run without network or access to your files. Requires bubblewrap or firejail")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let code_execution_switch = gtk::Switch::builder()
+            .active(state.locked().settings.code_execution_enabled)
+            .valign(gtk::Align::Center)
+            .build();
+        code_execution_row.append(&code_execution_switch);
+        general_box.append(&code_execution_row);
+
+        let check_secrets_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        check_secrets_row.append(&Label::builder()
+            .label("Check messages for secrets before sending to remote servers")
+            .tooltip_text("Asks before API keys, private keys or passwords in a message or attachment go to a server that isn't on this computer")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let check_secrets_switch = gtk::Switch::builder()
+            .active(state.locked().settings.check_secrets)
+            .valign(gtk::Align::Center)
+            .build();
+        check_secrets_row.append(&check_secrets_switch);
+        general_box.append(&check_secrets_row);
+
+        general_box.append(&Label::builder().label("API Server").xalign(0.0).css_classes(["settings-label"]).build());
+        let api_server_settings = state.locked().settings.api_server.clone();
+        let api_server_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        api_server_row.append(&Label::builder()
+            .label("Serve agents on a local OpenAI-compatible API")
+            .tooltip_text("Editors and other tools can use each agent as a model at http://127.0.0.1:<port>/v1 (chat completions and model list)")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let api_server_switch = gtk::Switch::builder()
+            .active(api_server_settings.enabled)
+            .valign(gtk::Align::Center)
+            .build();
+        api_server_row.append(&api_server_switch);
+        general_box.append(&api_server_row);
+        let api_port_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        api_port_row.append(&Label::builder().label("Port").xalign(0.0).hexpand(true).build());
+        let api_port_spin = gtk::SpinButton::with_range(1024.0, 65535.0, 1.0);
+        api_port_spin.set_value(api_server_settings.port as f64);
+        api_port_row.append(&api_port_spin);
+        general_box.append(&api_port_row);
+        let api_key_entry = gtk::PasswordEntry::builder()
+            .text(&api_server_settings.api_key)
+            .placeholder_text("API key clients must send")
+            .show_peek_icon(true)
+            .build();
+        general_box.append(&api_key_entry);
+        let api_key_entry_c = api_key_entry.clone();
+        api_server_switch.connect_active_notify(move |switch| {
+            if switch.is_active() && api_key_entry_c.text().trim().is_empty() {
+                api_key_entry_c.set_text(&new_api_key());
+            }
+        });
+
+        general_box.append(&Label::builder().label("Speech").xalign(0.0).css_classes(["settings-label"]).build());
+        let speech_settings = state.locked().settings.speech.clone();
+        let piper_command_entry = Entry::builder()
+            .text(&speech_settings.command)
+            .placeholder_text("piper")
+            .tooltip_text("The piper executable, e.g. piper or piper-tts")
+            .build();
+        general_box.append(&piper_command_entry);
+        let voice_model_entry = Entry::builder()
+            .text(&speech_settings.voice_model)
+            .placeholder_text("Path to a piper voice model (.onnx)")
+            .build();
+        general_box.append(&voice_model_entry);
+        let auto_read_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        auto_read_row.append(&Label::builder().label("Read replies aloud automatically").xalign(0.0).hexpand(true).build());
+        let auto_read_switch = gtk::Switch::builder()
+            .active(speech_settings.auto_read)
+            .valign(gtk::Align::Center)
+            .build();
+        auto_read_row.append(&auto_read_switch);
+        general_box.append(&auto_read_row);
+
+        let utility_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        utility_row.append(&Label::builder()
+            .label("Utility model")
+            .tooltip_text("Used for titles and memory updates so the conversation model isn't evicted from VRAM")
+            .xalign(0.0)
+            .hexpand(true)
+            .build());
+        let utility_model_entry = Entry::builder()
+            .text(&state.locked().settings.utility_model)
+            .placeholder_text("e.g. qwen2.5:0.5b (empty uses the agent's model)")
+            .width_chars(34)
+            .build();
+        utility_row.append(&utility_model_entry);
+        general_box.append(&utility_row);
+
+        general_box.append(&Label::builder().label("Conversation Titles").xalign(0.0).css_classes(["settings-label"]).build());
+        let title_settings = state.locked().settings.titles.clone();
+        let titles_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        titles_row.append(&Label::builder().label("Name new conversations automatically").xalign(0.0).hexpand(true).build());
+        let titles_switch = gtk::Switch::builder()
+            .active(title_settings.enabled)
+            .valign(gtk::Align::Center)
+            .build();
+        titles_row.append(&titles_switch);
+        general_box.append(&titles_row);
+        let title_model_entry = Entry::builder()
+            .text(&title_settings.model)
+            .placeholder_text("Model for titles (empty uses the utility model)")
+            .tooltip_text("A small local model, e.g. qwen2.5:0.5b, keeps the conversation model loaded")
+            .build();
+        general_box.append(&title_model_entry);
+        let title_prompt_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .height_request(60)
+            .tooltip_text("{{message}} is replaced with the first message of the conversation")
+            .css_classes(["headers-view"])
+            .build();
+        title_prompt_view.buffer().set_text(&title_settings.prompt);
+        general_box.append(&title_prompt_view);
+        let update_title_fields = {
+            let title_model_entry = title_model_entry.clone();
+            let title_prompt_view = title_prompt_view.clone();
+            move |switch: &gtk::Switch| {
+                title_model_entry.set_sensitive(switch.is_active());
+                title_prompt_view.set_sensitive(switch.is_active());
+            }
+        };
+        update_title_fields(&titles_switch);
+        titles_switch.connect_active_notify(update_title_fields);
+
+        general_box.append(&Label::builder().label("Sharing").xalign(0.0).css_classes(["settings-label"]).build());
+        let share_settings = state.locked().settings.share.clone();
+        let share_service_dropdown = DropDown::from_strings(&["Paste service (0x0.st)", "GitHub Gist"]);
+        share_service_dropdown.set_selected(match share_settings.service {
+            ShareService::Paste => 0,
+            ShareService::Gist => 1,
+        });
+        general_box.append(&share_service_dropdown);
+        let paste_url_entry = Entry::builder()
+            .text(&share_settings.paste_url)
+            .placeholder_text("https://0x0.st")
+            .tooltip_text("Any service that accepts a multipart `file` upload and answers with the URL")
+            .build();
+        general_box.append(&paste_url_entry);
+        let github_token_entry = gtk::PasswordEntry::builder()
+            .text(&share_settings.github_token)
+            .placeholder_text("GitHub token with the gist scope")
+            .show_peek_icon(true)
+            .build();
+        general_box.append(&github_token_entry);
+        let update_share_fields = {
+            let paste_url_entry = paste_url_entry.clone();
+            let github_token_entry = github_token_entry.clone();
+            move |dd: &DropDown| {
+                paste_url_entry.set_visible(dd.selected() == 0);
+                github_token_entry.set_visible(dd.selected() == 1);
+            }
+        };
+        update_share_fields(&share_service_dropdown);
+        share_service_dropdown.connect_selected_notify(update_share_fields);
+
+        let appearance_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        appearance_row.append(&Label::builder().label("Appearance").xalign(0.0).hexpand(true).build());
+        let appearance_dropdown = DropDown::from_strings(&["System", "Light", "Dark"]);
+        appearance_dropdown.set_selected(match state.locked().settings.appearance {
+            Appearance::System => 0,
+            Appearance::Light => 1,
+            Appearance::Dark => 2,
+        });
+        appearance_row.append(&appearance_dropdown);
+        general_box.append(&appearance_row);
+
+        let font_size_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        font_size_row.append(&Label::builder().label("Chat font size").xalign(0.0).hexpand(true).build());
+        let font_size_spin = gtk::SpinButton::with_range(MIN_CHAT_FONT_SIZE as f64, MAX_CHAT_FONT_SIZE as f64, 1.0);
+        font_size_spin.set_value(state.locked().settings.chat_font_size as f64);
+        font_size_spin.set_tooltip_text(Some("Ctrl+= / Ctrl+- to zoom, Ctrl+0 to reset"));
+        font_size_row.append(&font_size_spin);
+        general_box.append(&font_size_row);
+
+        let mono_font_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        mono_font_row.append(&Label::builder().label("Monospace font").xalign(0.0).hexpand(true).build());
+        let mono_font_btn = gtk::FontDialogButton::new(Some(gtk::FontDialog::builder().title("Monospace Font").build()));
+        mono_font_btn.set_level(gtk::FontLevel::Family);
+        mono_font_btn.set_font_desc(&gtk::pango::FontDescription::from_string(&state.locked().settings.monospace_font));
+        mono_font_row.append(&mono_font_btn);
+        general_box.append(&mono_font_row);
+
+        let chat_width_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        chat_width_row.append(&Label::builder().label("Maximum chat width").xalign(0.0).hexpand(true).build());
+        let chat_width_spin = gtk::SpinButton::with_range(500.0, 2400.0, 50.0);
+        chat_width_spin.set_value(state.locked().settings.chat_max_width as f64);
+        chat_width_row.append(&chat_width_spin);
+        general_box.append(&chat_width_row);
+
+        let selected_mono_font = |button: &gtk::FontDialogButton| {
+            button.font_desc()
+                .and_then(|desc| desc.family())
+                .map(|family| family.to_string())
+                .unwrap_or_else(|| "monospace".to_string())
+        };
+        let apply_fonts = {
+            let theme = theme.clone();
+            let state = state.clone();
+            let font_size_spin = font_size_spin.clone();
+            let mono_font_btn = mono_font_btn.clone();
+            move || {
+                let size = font_size_spin.value_as_int() as u32;
+                let family = selected_mono_font(&mono_font_btn);
+                theme.set_fonts(size, &family);
+                let mut s = state.locked();
+                s.settings.chat_font_size = size;
+                s.settings.monospace_font = family;
+            }
+        };
+        let apply_fonts_size = apply_fonts.clone();
+        font_size_spin.connect_value_changed(move |_| apply_fonts_size());
+        mono_font_btn.connect_font_desc_notify(move |_| apply_fonts());
+
+        general_box.append(&Label::builder().label("Custom Stylesheet").xalign(0.0).css_classes(["settings-label"]).build());
+        let user_css_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        user_css_row.append(&Label::builder()
+            .label(&format!("Loaded from {}", theme.user_css_path().display()))
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .selectable(true)
+            .build());
+        let reload_css_btn = Button::with_label("Reload Style");
+        user_css_row.append(&reload_css_btn);
+        general_box.append(&user_css_row);
+
+        general_box.append(&Label::builder().label("Data").xalign(0.0).css_classes(["settings-label"]).build());
+        let data_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        let backup_btn = Button::with_label("Backup All Data…");
+        let restore_btn = Button::with_label("Restore from Backup…");
+        data_row.append(&backup_btn);
+        data_row.append(&restore_btn);
+        let import_btn = Button::builder()
+            .label("Import Chats…")
+            .tooltip_text("Add conversations from a ChatGPT data export (ZIP) or an Open WebUI export (JSON)")
+            .build();
+        data_row.append(&import_btn);
+        general_box.append(&data_row);
+        let encrypt_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        encrypt_row.append(&Label::builder()
+            .label("Encrypt chats and memories on disk")
+            .tooltip_text("Conversations, the Trash, crash recovery snapshots and memories are encrypted with a key kept in your login keyring (needs libsecret's secret-tool). Settings, drafts and saved responses stay readable")
+            .xalign(0.0)
+            .hexpand(true)
+            .wrap(true)
+            .build());
+        let encrypt_switch = gtk::Switch::builder()
+            .active(state.locked().settings.encrypt_data)
+            .valign(gtk::Align::Center)
+            .build();
+        encrypt_row.append(&encrypt_switch);
+        general_box.append(&encrypt_row);
+
+        general_box.append(&Label::builder().label("Logs").xalign(0.0).css_classes(["settings-label"]).build());
+        let log_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        log_row.append(&Label::builder().label("Log verbosity").xalign(0.0).hexpand(true).build());
+        let log_level_dropdown = DropDown::from_strings(&["Errors", "Warnings", "Info", "Debug", "Trace"]);
+        log_level_dropdown.set_selected(state.locked().settings.log_level as u32);
+        log_row.append(&log_level_dropdown);
+        let open_logs_btn = Button::with_label("Open Logs");
+        log_row.append(&open_logs_btn);
+        general_box.append(&log_row);
+        open_logs_btn.connect_clicked(|btn| {
+            let Some(dir) = logging::log_dir() else { return };
+            let parent = btn.root().and_downcast::<gtk::Window>();
+            gtk::FileLauncher::new(Some(&gtk::gio::File::for_path(dir))).launch(parent.as_ref(), gtk::gio::Cancellable::NONE, |res| {
+                if let Err(e) = res {
+                    tracing::warn!("Failed to open log directory: {}", e);
+                }
+            });
+        });
+
+        let theme_reload = theme.clone();
+        let toasts_reload_css = toasts.clone();
+        reload_css_btn.connect_clicked(move |_| {
+            let message = match theme_reload.reload_user_css() {
+                Ok(true) => "Custom stylesheet reloaded".to_string(),
+                Ok(false) => "No style.css found".to_string(),
+                Err(e) => format!("Custom stylesheet has errors: {}", e),
+            };
+            toasts_reload_css.add_toast(toast(&message));
+        });
+
+        let selected_appearance = |dropdown: &DropDown| match dropdown.selected() {
+            1 => Appearance::Light,
+            2 => Appearance::Dark,
+            _ => Appearance::System,
+        };
+        let theme_preview = theme.clone();
+        appearance_dropdown.connect_selected_notify(move |dd| {
+            theme_preview.set_appearance(selected_appearance(dd));
+        });
+
+        let trash_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+        trash_row.append(&Label::builder().label("Keep deleted chats in Trash (days)").xalign(0.0).hexpand(true).build());
+        let trash_days_spin = gtk::SpinButton::with_range(0.0, 365.0, 1.0);
+        trash_days_spin.set_value(state.locked().settings.trash_days as f64);
+        trash_days_spin.set_tooltip_text(Some("0 keeps them until the Trash is emptied"));
+        trash_row.append(&trash_days_spin);
+        general_box.append(&trash_row);
+
+        let delete_chat_history_btn = Button::with_label("Delete Chat History");
+        let commands_delete_history = commands.clone();
+        let toasts_delete_history = toasts.clone();
+        delete_chat_history_btn.connect_clicked(move |btn| {
+            let commands = commands_delete_history.clone();
+            let toasts = toasts_delete_history.clone();
+            confirm(btn, "Delete all chat history?", "Every conversation, including the Trash, is deleted for good. This can't be undone.", "Delete All", move || {
+                commands.send(Command::DeleteAllConversations);
+                toasts.add_toast(toast("Chat history deleted"));
+            });
+        });
+        general_box.append(&delete_chat_history_btn);
+
+        let save_btn = Button::with_label("Save Settings");
+        let state_save = state.clone();
+        let endpoint_entry_clone = endpoint_entry.clone();
+        let memory_switch_clone = memory_switch.clone();
+        let background_switch_clone = background_switch.clone();
+        let autostart_switch_clone = autostart_switch.clone();
+        let start_hidden_switch_clone = start_hidden_switch.clone();
+        let system_info_switch_clone = system_info_switch.clone();
+        let code_execution_switch_clone = code_execution_switch.clone();
+        let check_secrets_switch_clone = check_secrets_switch.clone();
+        let quick_ask_switch_clone = quick_ask_switch.clone();
+        let api_server_switch_clone = api_server_switch.clone();
+        let encrypt_switch_clone = encrypt_switch.clone();
+        let api_port_spin_clone = api_port_spin.clone();
+        let api_key_entry_clone = api_key_entry.clone();
+        let share_service_dropdown_clone = share_service_dropdown.clone();
+        let paste_url_entry_clone = paste_url_entry.clone();
+        let github_token_entry_clone = github_token_entry.clone();
+        let piper_command_entry_clone = piper_command_entry.clone();
+        let voice_model_entry_clone = voice_model_entry.clone();
+        let auto_read_switch_clone = auto_read_switch.clone();
+        let titles_switch_clone = titles_switch.clone();
+        let utility_model_entry_clone = utility_model_entry.clone();
+        let title_model_entry_clone = title_model_entry.clone();
+        let title_prompt_view_clone = title_prompt_view.clone();
+        let appearance_dropdown_clone = appearance_dropdown.clone();
+        let log_level_dropdown_clone = log_level_dropdown.clone();
+        let headers_view_clone = headers_view.clone();
+        let tls_invalid_switch_clone = tls_invalid_switch.clone();
+        let tls_ca_entry_clone = tls_ca_entry.clone();
+        let connect_timeout_spin_clone = connect_timeout_spin.clone();
+        let response_timeout_spin_clone = response_timeout_spin.clone();
+        let trash_days_spin_clone = trash_days_spin.clone();
+        let toasts_save = toasts.clone();
+        save_btn.connect_clicked(move |_| {
+            let endpoint = endpoint_entry_clone.text().to_string();
+            let buffer = headers_view_clone.buffer();
+            let (start, end) = buffer.bounds();
+            let headers = parse_headers(&buffer.text(&start, &end, false));
+            let mut s = state_save.locked();
+            // Another server may have other context windows under the same model names
+            if s.settings.ollama_endpoint != endpoint {
+                s.context_lengths.clear();
+            }
+            s.settings.ollama_endpoint = endpoint.clone();
+            s.settings.ollama_headers = headers;
+            s.settings.ollama_tls = read_tls_editor(&tls_invalid_switch_clone, &tls_ca_entry_clone);
+            s.settings.timeouts = Timeouts {
+                connect_secs: connect_timeout_spin_clone.value_as_int() as u64,
+                response_secs: response_timeout_spin_clone.value_as_int() as u64,
+            };
+            s.settings.trash_days = trash_days_spin_clone.value_as_int() as u32;
+            s.settings.memory_enabled = memory_switch_clone.is_active();
+            s.settings.run_in_background = background_switch_clone.is_active();
+            s.settings.start_hidden = start_hidden_switch_clone.is_active();
+            if autostart_switch_clone.is_active() != autostart::is_enabled() {
+                if let Err(e) = autostart::set_enabled(autostart_switch_clone.is_active()) {
+                    toasts_save.report(&e);
+                    autostart_switch_clone.set_active(autostart::is_enabled());
+                }
+            }
+            s.settings.share_system_info = system_info_switch_clone.is_active();
+            s.settings.code_execution_enabled = code_execution_switch_clone.is_active();
+            s.settings.check_secrets = check_secrets_switch_clone.is_active();
+            s.settings.quick_ask_shortcut = quick_ask_switch_clone.is_active();
+            s.settings.api_server.enabled = api_server_switch_clone.is_active();
+            s.settings.api_server.port = api_port_spin_clone.value_as_int() as u16;
+            let api_key = api_key_entry_clone.text().trim().to_string();
+            s.settings.api_server.api_key = if api_key.is_empty() && s.settings.api_server.enabled { new_api_key() } else { api_key };
+            api_key_entry_clone.set_text(&s.settings.api_server.api_key);
+            let encrypt = encrypt_switch_clone.is_active();
+            let encryption_changed = encrypt != s.settings.encrypt_data;
+            s.settings.share.service = if share_service_dropdown_clone.selected() == 1 { ShareService::Gist } else { ShareService::Paste };
+            let paste_url = paste_url_entry_clone.text().trim().to_string();
+            s.settings.share.paste_url = if paste_url.is_empty() { "https://0x0.st".to_string() } else { paste_url };
+            s.settings.share.github_token = github_token_entry_clone.text().trim().to_string();
+            let piper_command = piper_command_entry_clone.text().trim().to_string();
+            s.settings.speech.command = if piper_command.is_empty() { "piper".to_string() } else { piper_command };
+            s.settings.speech.voice_model = voice_model_entry_clone.text().trim().to_string();
+            s.settings.speech.auto_read = auto_read_switch_clone.is_active();
+            s.settings.utility_model = utility_model_entry_clone.text().trim().to_string();
+            s.settings.titles.enabled = titles_switch_clone.is_active();
+            s.settings.titles.model = title_model_entry_clone.text().trim().to_string();
+            let buffer = title_prompt_view_clone.buffer();
+            let (start, end) = buffer.bounds();
+            let title_prompt = buffer.text(&start, &end, false).trim().to_string();
+            s.settings.titles.prompt = if title_prompt.is_empty() { default_title_prompt() } else { title_prompt };
+            s.settings.appearance = selected_appearance(&appearance_dropdown_clone);
+            s.settings.log_level = match log_level_dropdown_clone.selected() {
+                0 => LogLevel::Error,
+                1 => LogLevel::Warn,
+                3 => LogLevel::Debug,
+                4 => LogLevel::Trace,
+                _ => LogLevel::Info,
+            };
+            logging::set_level(s.settings.log_level);
+
+            if let Some(ollama) = ollama_client(&endpoint, &s.settings.ollama_headers, &s.settings.ollama_tls, &s.settings.timeouts) {
+                s.ollama = ollama;
+            }
+            s.save_settings();
+            drop(s);
+            toasts_save.add_toast(toast("Settings saved"));
+
+            if encryption_changed {
+                let state = state_save.clone();
+                let toasts = toasts_save.clone();
+                let encrypt_switch = encrypt_switch_clone.clone();
+                glib::MainContext::default().spawn_local(async move {
+                    // secret-tool may wait for the keyring to be unlocked
+                    let cipher = tokio::task::spawn_blocking(move || {
+                        if encrypt { Cipher::from_keyring_or_create().map(Some) } else { Ok(Cipher::from_keyring()) }
+                    }).await.unwrap_or_else(|e| Err(AppError::new("Could not read the encryption key", e)));
+                    match cipher {
+                        Ok(cipher) => {
+                            let mut s = state.locked();
+                            s.set_encryption(cipher, encrypt);
+                            s.save_settings();
+                        }
+                        Err(e) => {
+                            toasts.report(&e);
+                            encrypt_switch.set_active(state.locked().settings.encrypt_data);
+                        }
+                    }
+                });
+            }
+        });
+        general_box.append(&save_btn);
+
+        // Backup: settings, history, drafts, memories and style.css in one JSON file,
+        // encrypted with the data key while encryption is on, as it holds every chat and memory
+        backup_btn.connect_clicked({
+            let state = state.clone();
+            let theme = theme.clone();
+            let toasts = toasts.clone();
+            move |btn| {
+                let date = glib::DateTime::now_local().and_then(|now| now.format("%Y-%m-%d")).map(|s| s.to_string()).unwrap_or_default();
+                let dialog = gtk::FileDialog::builder()
+                    .title("Backup All Data")
+                    .initial_name(format!("arch-llm-backup-{}.json", date))
+                    .build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let state = state.clone();
+                let theme = theme.clone();
+                let toasts = toasts.clone();
+                dialog.save(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    let (backup, encrypt, storage) = {
+                        let s = state.locked();
+                        (backup::create(&s, theme.user_css_path()), s.settings.encrypt_data, s.storage.clone())
+                    };
+                    let written = serde_json::to_string_pretty(&backup).context("Failed to write backup")
+                        .and_then(|json| match encrypt {
+                            true => storage.encrypt(json.as_bytes())
+                                .ok_or_else(|| AppError::new("Failed to write backup", "Encryption is on, but the key isn't in the keyring")),
+                            false => Ok(json.into_bytes()),
+                        })
+                        .and_then(|data| write_chosen_file(&file, data, "Failed to write backup"));
+                    match written {
+                        Ok(()) if encrypt => toasts.add_toast(toast(&format!(
+                            "Backed up {} conversations, encrypted with the key in your keyring. Restoring needs the same key.",
+                            backup.history.len(),
+                        ))),
+                        Ok(()) => toasts.add_toast(toast(&format!("Backed up {} conversations", backup.history.len()))),
+                        Err(e) => toasts.report(&e),
+                    }
+                });
+            }
+        });
+
+        restore_btn.connect_clicked({
+            let state = state.clone();
+            let theme = theme.clone();
+            let toasts = toasts.clone();
+            let on_restore = on_restore.clone();
+            move |btn| {
+                let dialog = gtk::FileDialog::builder().title("Restore from Backup").build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let state = state.clone();
+                let theme = theme.clone();
+                let toasts = toasts.clone();
+                let on_restore = on_restore.clone();
+                dialog.open(parent.clone().as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    let storage = state.locked().storage.clone();
+                    let backup = match read_chosen_file(&file).and_then(|(_, data)| {
+                        let data = storage.decrypt(data).map_err(|e| AppError::new("Failed to read backup", e))?;
+                        backup::parse(&String::from_utf8_lossy(&data)).map_err(|e| AppError::new("Failed to read backup", e))
+                    }) {
+                        Ok(backup) => backup,
+                        Err(e) => {
+                            toasts.report(&e);
+                            return;
+                        }
+                    };
+
+                    let confirm = gtk::Window::builder()
+                        .title("Restore from Backup")
+                        .modal(true)
+                        .default_width(440)
+                        .build();
+                    confirm.set_transient_for(parent.as_ref());
+                    let form = Box::builder()
+                        .orientation(Orientation::Vertical)
+                        .spacing(8)
+                        .margin_top(20)
+                        .margin_bottom(20)
+                        .margin_start(20)
+                        .margin_end(20)
+                        .build();
+                    form.append(&Label::builder()
+                        .label(format!("This backup from {} has {} conversations and {} memories.", backup.created, backup.history.len(), backup.memories.len()))
+                        .xalign(0.0)
+                        .wrap(true)
+                        .build());
+                    let keep_radio = gtk::CheckButton::with_label("Merge, keeping my current version of conflicting items");
+                    let prefer_radio = gtk::CheckButton::with_label("Merge, taking the backup's version of conflicting items");
+                    let replace_radio = gtk::CheckButton::with_label("Replace all current data with the backup");
+                    prefer_radio.set_group(Some(&keep_radio));
+                    replace_radio.set_group(Some(&keep_radio));
+                    keep_radio.set_active(true);
+                    form.append(&keep_radio);
+                    form.append(&prefer_radio);
+                    form.append(&replace_radio);
+                    let actions = Box::builder().orientation(Orientation::Horizontal).spacing(10).halign(gtk::Align::End).margin_top(10).build();
+                    let cancel_btn = Button::with_label("Cancel");
+                    let restore_btn = Button::with_label("Restore");
+                    restore_btn.add_css_class("suggested-action");
+                    actions.append(&cancel_btn);
+                    actions.append(&restore_btn);
+                    form.append(&actions);
+                    confirm.set_child(Some(&form));
+
+                    let confirm_c = confirm.clone();
+                    cancel_btn.connect_clicked(move |_| confirm_c.close());
+                    let confirm_c = confirm.clone();
+                    let state = state.clone();
+                    let theme = theme.clone();
+                    let toasts = toasts.clone();
+                    let on_restore = on_restore.clone();
+                    let backup = RefCell::new(Some(backup));
+                    restore_btn.connect_clicked(move |_| {
+                        let Some(backup) = backup.borrow_mut().take() else { return };
+                        let mode = if replace_radio.is_active() {
+                            RestoreMode::Replace
+                        } else if prefer_radio.is_active() {
+                            RestoreMode::PreferBackup
+                        } else {
+                            RestoreMode::KeepCurrent
+                        };
+                        confirm_c.close();
+                        let count = backup::restore(&mut state.locked(), backup, mode, theme.user_css_path());
+                        on_restore();
+                        let _ = theme.reload_user_css();
+                        toasts.add_toast(toast(&format!("Restored {} conversations. Restart Arch LLM to apply the restored settings.", count)));
+                    });
+                    confirm.present();
+                });
+            }
+        });
+
+        import_btn.connect_clicked({
+            let state = state.clone();
+            let toasts = toasts.clone();
+            let commands = commands.clone();
+            move |btn| {
+                let dialog = gtk::FileDialog::builder().title("Import Chats").build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let state = state.clone();
+                let toasts = toasts.clone();
+                let commands = commands.clone();
+                dialog.open(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    let conversations = match import::read_file(&file) {
+                        Ok(conversations) => conversations,
+                        Err(e) => {
+                            toasts.report(&e);
+                            return;
+                        }
+                    };
+                    let new = {
+                        let s = state.locked();
+                        conversations.iter().filter(|c| !s.history.iter().any(|h| h.id == c.id)).count()
+                    };
+                    let skipped = conversations.len() - new;
+                    commands.send(Command::ImportConversations(conversations));
+                    let message = match skipped {
+                        0 => format!("Imported {} conversations", new),
+                        _ => format!("Imported {} conversations, {} were already imported", new, skipped),
+                    };
+                    toasts.add_toast(toast(&message));
+                });
+            }
+        });
+
+        Self {
+            widget: general_box,
+            save_btn,
+            endpoint_entry,
+            background_switch,
+            font_size_spin,
+            chat_width_spin,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, Entry, Label, ListBox, Orientation, ScrolledWindow};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::benchmark;
+use crate::error::LockExt;
+use crate::provider::pull_model;
+use crate::state::AppState;
+use crate::ui::badges::{build_badges, look_up_capabilities};
+use crate::utils::format_timestamp;
+
+/// Installed models and pulling new ones. `start_reconnect` is called when
+/// Ollama can't be reached to list them.
+pub fn build(state: &Arc<Mutex<AppState>>, start_reconnect: Rc<dyn Fn()>) -> gtk::Widget {
+    let models_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .build();
+
+    models_box.append(&Label::builder().label("Pull Model").xalign(0.0).css_classes(["settings-title"]).build());
+    
+    let pull_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+    let pull_entry = Entry::builder().placeholder_text("Model name (e.g. llama3)").hexpand(true).build();
+    let pull_btn = Button::with_label("Pull");
+    pull_box.append(&pull_entry);
+    pull_box.append(&pull_btn);
+    models_box.append(&pull_box);
+
+    let progress_label = Label::new(None);
+    progress_label.set_visible(false);
+    models_box.append(&progress_label);
+
+    models_box.append(&gtk::Separator::new(Orientation::Horizontal));
+    models_box.append(&Label::builder().label("Installed Models").xalign(0.0).css_classes(["settings-title"]).build());
+
+    let models_list = ListBox::builder().build();
+    let models_scrolled = ScrolledWindow::builder().child(&models_list).propagate_natural_height(true).build();
+    models_box.append(&models_scrolled);
+
+    models_box.append(&Label::builder().label("Benchmarks").xalign(0.0).css_classes(["settings-title"]).build());
+    models_box.append(&Label::builder()
+        .label("Runs the same prompts on a model and measures its load time and speed on this machine.")
+        .xalign(0.0)
+        .wrap(true)
+        .css_classes(["dim-label"])
+        .build());
+    let benchmarks_list = ListBox::builder().build();
+    let benchmarks_scrolled = ScrolledWindow::builder().child(&benchmarks_list).min_content_height(160).build();
+    models_box.append(&benchmarks_scrolled);
+    let refresh_benchmarks: Rc<dyn Fn()> = {
+        let benchmarks_list = benchmarks_list.clone();
+        let state = state.clone();
+        Rc::new(move || {
+            while let Some(child) = benchmarks_list.first_child() {
+                benchmarks_list.remove(&child);
+            }
+            let runs = state.locked().benchmarks.clone();
+            if runs.is_empty() {
+                benchmarks_list.append(&Label::builder().label("No benchmarks yet").xalign(0.0).margin_start(10).margin_top(5).margin_bottom(5).css_classes(["dim-label"]).build());
+            }
+            for run in runs.iter().rev() {
+                let row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_start(10).margin_end(10).margin_top(5).margin_bottom(5).build();
+                row.append(&Label::builder().label(&run.model).xalign(0.0).hexpand(true).ellipsize(gtk::pango::EllipsizeMode::End).build());
+                row.append(&Label::new(Some(&format!(
+                    "load {:.1} s · prompt {:.0} tok/s · generation {:.1} tok/s",
+                    run.load_secs, run.prompt_tokens_per_sec, run.generation_tokens_per_sec,
+                ))));
+                row.append(&Label::builder().label(format_timestamp(run.run_at)).css_classes(["dim-label"]).build());
+                benchmarks_list.append(&row);
+            }
+        })
+    };
+    refresh_benchmarks();
+
+    // Only one benchmark at a time, they would skew each other
+    let benchmarking = Rc::new(Cell::new(false));
+    let run_benchmark: Rc<dyn Fn(String)> = {
+        let state = state.clone();
+        let progress_label = progress_label.clone();
+        let refresh_benchmarks = refresh_benchmarks.clone();
+        Rc::new(move |model: String| {
+            if benchmarking.replace(true) {
+                return;
+            }
+            progress_label.set_visible(true);
+            let state = state.clone();
+            let progress_label = progress_label.clone();
+            let refresh_benchmarks = refresh_benchmarks.clone();
+            let benchmarking = benchmarking.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let ollama = state.locked().ollama.clone();
+                let result = benchmark::run(&ollama, &model, |status| {
+                    progress_label.set_label(&format!("Benchmarking {}: {}…", model, status));
+                }).await;
+                benchmarking.set(false);
+                match result {
+                    Ok(run) => {
+                        progress_label.set_label(&format!(
+                            "{}: loaded in {:.1} s, prompt {:.0} tok/s, generation {:.1} tok/s",
+                            model, run.load_secs, run.prompt_tokens_per_sec, run.generation_tokens_per_sec,
+                        ));
+                        let mut s = state.locked();
+                        s.benchmarks.push(run);
+                        s.save_benchmarks();
+                        drop(s);
+                        refresh_benchmarks();
+                    }
+                    Err(e) => progress_label.set_label(&format!("Benchmark of {} failed: {}", model, e)),
+                }
+            });
+        })
+    };
+
+    let refresh_models_list = {
+        let models_list = models_list.clone();
+        let state = state.clone();
+        let start_reconnect = start_reconnect.clone();
+        Rc::new(move || {
+            let models_list = models_list.clone();
+            let state = state.clone();
+            let start_reconnect = start_reconnect.clone();
+            let run_benchmark = run_benchmark.clone();
+            glib::MainContext::default().spawn_local(async move {
+                let ollama = state.locked().ollama.clone();
+                if let Ok(models) = ollama.list_local_models().await {
+                    {
+                        let mut s = state.locked();
+                        s.available_models = models.iter().map(|m| m.name.clone()).collect();
+                    }
+                    while let Some(child) = models_list.first_child() {
+                        models_list.remove(&child);
+                    }
+                    // Badges are added once the server has said what each model can do
+                    let mut badge_slots = Vec::new();
+                    for model in &models {
+                        let row = Box::builder().orientation(Orientation::Horizontal).spacing(10).build();
+                        let label = Label::builder().label(&model.name).xalign(0.0).hexpand(true).margin_start(10).margin_top(5).margin_bottom(5).build();
+                        row.append(&label);
+                        let badge_slot = Box::builder().orientation(Orientation::Horizontal).build();
+                        row.append(&badge_slot);
+                        badge_slots.push((model.name.clone(), badge_slot));
+                        
+                        let size_gb = model.size as f64 / 1024.0 / 1024.0 / 1024.0;
+                        let size_label = Label::new(Some(&format!("{:.1} GB", size_gb)));
+                        row.append(&size_label);
+
+                        let benchmark_btn = Button::builder()
+                            .label("Benchmark")
+                            .tooltip_text("Measure load time and tokens per second")
+                            .css_classes(["flat"])
+                            .build();
+                        let run_benchmark = run_benchmark.clone();
+                        let name = model.name.clone();
+                        benchmark_btn.connect_clicked(move |_| run_benchmark(name.clone()));
+                        row.append(&benchmark_btn);
+
+                        models_list.append(&row);
+                    }
+                    let names: Vec<String> = models.iter().map(|m| m.name.clone()).collect();
+                    look_up_capabilities(&state, &names).await;
+                    for (name, badge_slot) in badge_slots {
+                        let capabilities = state.locked().model_capabilities.get(&name).cloned().unwrap_or_default();
+                        badge_slot.append(&build_badges(&capabilities));
+                    }
+                } else {
+                    start_reconnect();
+                }
+            });
+        })
+    };
+    refresh_models_list();
+
+    let state_pull = state.clone();
+    let pull_entry_c = pull_entry.clone();
+    let progress_label_c = progress_label.clone();
+    let refresh_models_c = refresh_models_list.clone();
+    pull_btn.connect_clicked(move |btn| {
+        let model_name = pull_entry_c.text().to_string();
+        if model_name.is_empty() { return; }
+        
+        btn.set_sensitive(false);
+        progress_label_c.set_visible(true);
+        progress_label_c.set_label(&format!("Pulling {}... this may take a while.", model_name));
+        
+        let state = state_pull.clone();
+        let btn = btn.clone();
+        let progress_label = progress_label_c.clone();
+        let refresh = refresh_models_c.clone();
+        
+        glib::MainContext::default().spawn_local(async move {
+            let ollama = state.locked().ollama.clone();
+            let res = pull_model(&ollama, &model_name, |status| {
+                progress_label.set_label(&format!("Pulling {}: {}", model_name, status));
+            }).await;
+
+            btn.set_sensitive(true);
+            match res {
+                Ok(_) => {
+                    progress_label.set_label(&format!("Successfully pulled {}", model_name));
+                    refresh();
+                }
+                Err(e) => {
+                    progress_label.set_label(&format!("Error: {}", e));
+                }
+            }
+        });
+    });
+    models_box.upcast()
+}
@@ -0,0 +1,382 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, Entry, Label, Orientation, ScrolledWindow, Stack, TextView};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::confirm::{confirm, undo_toast};
+use crate::error::LockExt;
+use crate::state::{AppState, Profile};
+
+/// Profiles with the personal details shared with agents, and their memories.
+pub fn build(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> gtk::Widget {
+    let personalization_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .build();
+    
+    personalization_box.append(&Label::builder()
+        .label("Personalization")
+        .xalign(0.0)
+        .css_classes(["settings-title"])
+        .build());
+
+    personalization_box.append(&Label::builder()
+        .label("Profiles")
+        .xalign(0.0)
+        .css_classes(["settings-title"])
+        .build());
+
+    let profiles_scrolled_content = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(15)
+        .build();
+    let scrolled_profiles = ScrolledWindow::builder()
+        .child(&profiles_scrolled_content)
+        .vexpand(false)
+        .min_content_height(120)
+        .build();
+    scrolled_profiles.add_css_class("profile-scrolled-window");
+    personalization_box.append(&scrolled_profiles);
+
+    personalization_box.append(&gtk::Separator::new(Orientation::Horizontal));
+
+    // Editor Stack
+    let editor_stack = Stack::builder()
+        .transition_type(gtk::StackTransitionType::Crossfade)
+        .vexpand(true)
+        .build();
+    
+    let empty_page = Box::builder()
+        .orientation(Orientation::Vertical)
+        .valign(gtk::Align::Center)
+        .halign(gtk::Align::Center)
+        .spacing(10)
+        .build();
+    empty_page.append(&Label::new(Some("Select a profile above to edit or activate.")));
+    editor_stack.add_named(&empty_page, Some("empty"));
+
+    let editor_page = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .margin_top(10)
+        .build();
+    
+    let edit_name = Entry::builder().placeholder_text("Profile Name").build();
+    editor_page.append(&Label::builder().label("Profile Name").xalign(0.0).css_classes(["settings-label"]).build());
+    editor_page.append(&edit_name);
+
+    let edit_grid = gtk::Grid::builder().column_spacing(10).row_spacing(5).build();
+    let edit_fname = Entry::builder().placeholder_text("First Name").hexpand(true).build();
+    let edit_lname = Entry::builder().placeholder_text("Last Name").hexpand(true).build();
+    let edit_email = Entry::builder().placeholder_text("Email").hexpand(true).build();
+    let edit_phone = Entry::builder().placeholder_text("Phone").hexpand(true).build();
+    
+    edit_grid.attach(&edit_fname, 0, 0, 1, 1);
+    edit_grid.attach(&edit_lname, 1, 0, 1, 1);
+    edit_grid.attach(&edit_email, 0, 1, 1, 1);
+    edit_grid.attach(&edit_phone, 1, 1, 1, 1);
+    editor_page.append(&edit_grid);
+
+    editor_page.append(&Label::builder().label("Location").xalign(0.0).css_classes(["settings-label"]).build());
+    let edit_location = Entry::builder().placeholder_text("City, Country").build();
+    editor_page.append(&edit_location);
+
+    editor_page.append(&Label::builder().label("Bio / Context").xalign(0.0).css_classes(["settings-label"]).build());
+    let edit_bio = Entry::builder().placeholder_text("Short bio").build();
+    editor_page.append(&edit_bio);
+
+    let edit_memory_row = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(10).build();
+    edit_memory_row.append(&Label::builder().label("Enable long-term memory").xalign(0.0).hexpand(true).build());
+    let edit_memory = gtk::Switch::builder().valign(gtk::Align::Center).build();
+    edit_memory_row.append(&edit_memory);
+    editor_page.append(&edit_memory_row);
+
+    let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(10).build();
+    let activate_btn = Button::with_label("Use This Profile");
+    let save_btn = Button::with_label("Save Changes");
+    let delete_btn = Button::with_label("Delete Profile");
+    let clear_mem_btn = Button::with_label("Clear Memory");
+    
+    delete_btn.add_css_class("destructive-action");
+    clear_mem_btn.add_css_class("destructive-action");
+    
+    actions_box.append(&activate_btn);
+    actions_box.append(&save_btn);
+    actions_box.append(&delete_btn);
+    actions_box.append(&clear_mem_btn);
+    editor_page.append(&actions_box);
+
+    editor_page.append(&Label::builder().label("Long-term Memory").xalign(0.0).css_classes(["settings-label"]).build());
+    let memory_view = TextView::builder()
+        .editable(false)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .height_request(150)
+        .build();
+    let memory_scroll = ScrolledWindow::builder().child(&memory_view).min_content_height(160).build();
+    editor_page.append(&memory_scroll);
+
+    editor_stack.add_named(&editor_page, Some("editor"));
+    personalization_box.append(&editor_stack);
+
+    let selected_profile_idx: Rc<RefCell<Option<usize>>> = Rc::new(RefCell::new(None));
+
+    let refresh_profiles_ui = {
+        let state = state.clone();
+        let profiles_list = profiles_scrolled_content.clone();
+        let selected_idx = selected_profile_idx.clone();
+        let editor_stack = editor_stack.clone();
+        
+        let edit_name = edit_name.clone();
+        let edit_fname = edit_fname.clone();
+        let edit_lname = edit_lname.clone();
+        let edit_email = edit_email.clone();
+        let edit_phone = edit_phone.clone();
+        let edit_location = edit_location.clone();
+        let edit_bio = edit_bio.clone();
+        let edit_memory = edit_memory.clone();
+        let activate_btn = activate_btn.clone();
+        let memory_view = memory_view.clone();
+
+        let refresh_ref: Rc<RefCell<Option<std::boxed::Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
+        let refresh_ref_weak = refresh_ref.clone();
+
+        let logic = move || {
+            while let Some(child) = profiles_list.first_child() {
+                profiles_list.remove(&child);
+            }
+            
+            let (profiles, active_profile, memory_path, storage) = {
+                let s = state.locked();
+                (s.settings.profiles.clone(), s.settings.active_profile.clone(), s.memory_path.clone(), s.storage.clone())
+            };
+
+            let current_sel = *selected_idx.borrow();
+
+            for (idx, profile) in profiles.iter().enumerate() {
+                let circle_btn = Button::builder()
+                    .css_classes(["profile-circle"])
+                    .width_request(80)
+                    .height_request(80)
+                    .build();
+                
+                if let Some(active) = &active_profile {
+                    if active == &profile.name {
+                        circle_btn.add_css_class("active-profile");
+                    }
+                }
+                
+                if Some(idx) == current_sel {
+                     circle_btn.add_css_class("selected-editing");
+                }
+
+                let icon_label = Label::new(Some(&profile.name.chars().next().unwrap_or('?').to_string().to_uppercase()));
+                circle_btn.set_child(Some(&icon_label));
+                
+                let container = Box::builder().orientation(Orientation::Vertical).spacing(5).build();
+                container.append(&circle_btn);
+                container.append(&Label::builder().label(&profile.name).css_classes(["profile-mini-name"]).build());
+                profiles_list.append(&container);
+
+                let sel_idx = selected_idx.clone();
+                let refresh = refresh_ref_weak.clone();
+                circle_btn.connect_clicked(move |_| {
+                    *sel_idx.borrow_mut() = Some(idx);
+                    if let Some(f) = &*refresh.borrow() { f(); }
+                });
+            }
+
+            // Add Profile Button
+            let add_btn = Button::builder().label("+").css_classes(["profile-circle"]).width_request(80).height_request(80).build();
+            let state_add = state.clone();
+            let refresh_add = refresh_ref_weak.clone();
+            let sel_add = selected_idx.clone();
+            add_btn.connect_clicked(move |_| {
+                {
+                    let mut s = state_add.locked();
+                    s.settings.profiles.push(Profile {
+                        id: glib::uuid_string_random().to_string(),
+                        name: "New Profile".to_string(),
+                        first_name: "".to_string(),
+                        last_name: "".to_string(),
+                        email: "".to_string(),
+                        phone: "".to_string(),
+                        location: "".to_string(),
+                        bio: "".to_string(),
+                        image_path: None,
+                        memory_enabled: true,
+                    });
+                    s.save_settings();
+                    *sel_add.borrow_mut() = Some(s.settings.profiles.len() - 1);
+                }
+                if let Some(f) = &*refresh_add.borrow() { f(); }
+            });
+            let container = Box::builder().orientation(Orientation::Vertical).spacing(5).build();
+            container.append(&add_btn);
+            container.append(&Label::new(Some("Add")));
+            profiles_list.append(&container);
+
+            if let Some(idx) = current_sel {
+                if let Some(profile) = profiles.get(idx) {
+                    editor_stack.set_visible_child_name("editor");
+                    edit_name.set_text(&profile.name);
+                    edit_fname.set_text(&profile.first_name);
+                    edit_lname.set_text(&profile.last_name);
+                    edit_email.set_text(&profile.email);
+                    edit_phone.set_text(&profile.phone);
+                    edit_location.set_text(&profile.location);
+                    edit_bio.set_text(&profile.bio);
+                    edit_memory.set_active(profile.memory_enabled);
+
+                    // Load Memory
+                    let mem_file = memory_path.join(format!("{}.txt", profile.id));
+                    let memory = storage.read_private_string(&mem_file).unwrap_or_default();
+                    memory_view.buffer().set_text(&memory);
+                    
+                    if let Some(active) = &active_profile {
+                        if active == &profile.name {
+                            activate_btn.set_label("Current Profile");
+                            activate_btn.set_sensitive(false);
+                        } else {
+                            activate_btn.set_label("Use This Profile");
+                            activate_btn.set_sensitive(true);
+                        }
+                    } else {
+                        activate_btn.set_label("Use This Profile");
+                        activate_btn.set_sensitive(true);
+                    }
+                } else {
+                    *selected_idx.borrow_mut() = None;
+                    editor_stack.set_visible_child_name("empty");
+                }
+            } else {
+                editor_stack.set_visible_child_name("empty");
+            }
+        };
+        
+        *refresh_ref.borrow_mut() = Some(std::boxed::Box::new(logic));
+        refresh_ref
+    };
+
+    let call_refresh = {
+        let refresh = refresh_profiles_ui.clone();
+        move || { if let Some(f) = &*refresh.borrow() { f(); } }
+    };
+    call_refresh();
+
+    let state_save = state.clone();
+    let sel_save = selected_profile_idx.clone();
+    let refresh_save = call_refresh.clone();
+    let name_s = edit_name.clone();
+    let fname_s = edit_fname.clone();
+    let lname_s = edit_lname.clone();
+    let email_s = edit_email.clone();
+    let phone_s = edit_phone.clone();
+    let loc_s = edit_location.clone();
+    let bio_s = edit_bio.clone();
+    let memory_s = edit_memory.clone();
+
+    save_btn.connect_clicked(move |_| {
+        if let Some(idx) = *sel_save.borrow() {
+            let mut s = state_save.locked();
+            if let Some(p) = s.settings.profiles.get_mut(idx) {
+                p.name = name_s.text().to_string();
+                p.first_name = fname_s.text().to_string();
+                p.last_name = lname_s.text().to_string();
+                p.email = email_s.text().to_string();
+                p.phone = phone_s.text().to_string();
+                p.location = loc_s.text().to_string();
+                p.bio = bio_s.text().to_string();
+                p.memory_enabled = memory_s.is_active();
+                s.save_settings();
+            }
+        }
+        refresh_save();
+    });
+
+    let state_act = state.clone();
+    let sel_act = selected_profile_idx.clone();
+    let refresh_act = call_refresh.clone();
+    activate_btn.connect_clicked(move |_| {
+        if let Some(idx) = *sel_act.borrow() {
+            let mut s = state_act.locked();
+            if let Some(p) = s.settings.profiles.get(idx) {
+                s.settings.active_profile = Some(p.name.clone());
+                s.save_settings();
+            }
+        }
+        refresh_act();
+    });
+
+    let state_del = state.clone();
+    let sel_del = selected_profile_idx.clone();
+    let refresh_del = call_refresh.clone();
+    let toasts_del = toasts.clone();
+    delete_btn.connect_clicked(move |btn| {
+        let Some(idx) = *sel_del.borrow() else { return };
+        let Some(profile) = state_del.locked().settings.profiles.get(idx).cloned() else { return };
+        let state = state_del.clone();
+        let sel = sel_del.clone();
+        let refresh = refresh_del.clone();
+        let toasts = toasts_del.clone();
+        confirm(btn, &format!("Delete “{}”?", profile.name), "Its personal details will be removed. Its memory is kept until cleared.", "Delete", move || {
+            let was_active = {
+                let mut s = state.locked();
+                let Some(idx) = s.settings.profiles.iter().position(|p| p.id == profile.id) else { return };
+                s.settings.profiles.remove(idx);
+                let was_active = s.settings.active_profile.as_ref() == Some(&profile.name);
+                if was_active {
+                    s.settings.active_profile = None;
+                }
+                s.save_settings();
+                was_active
+            };
+            *sel.borrow_mut() = None;
+            refresh();
+            undo_toast(&toasts, "Profile deleted", move || {
+                {
+                    let mut s = state.locked();
+                    let idx = idx.min(s.settings.profiles.len());
+                    s.settings.profiles.insert(idx, profile.clone());
+                    if was_active {
+                        s.settings.active_profile = Some(profile.name.clone());
+                    }
+                    s.save_settings();
+                }
+                refresh();
+            });
+        });
+    });
+
+    let state_clr = state.clone();
+    let sel_clr = selected_profile_idx.clone();
+    let toasts_clr = toasts.clone();
+    let memory_view_clr = memory_view.clone();
+    clear_mem_btn.connect_clicked(move |btn| {
+        let Some(idx) = *sel_clr.borrow() else { return };
+        let Some(profile) = state_clr.locked().settings.profiles.get(idx).cloned() else { return };
+        let state = state_clr.clone();
+        let sel = sel_clr.clone();
+        let toasts = toasts_clr.clone();
+        let memory_view = memory_view_clr.clone();
+        confirm(btn, &format!("Clear the memory of “{}”?", profile.name), "Everything agents have remembered about this profile will be forgotten.", "Clear", move || {
+            let mem_file = state.locked().memory_path.join(format!("{}.txt", profile.id));
+            let memory = state.locked().storage.read_private_string(&mem_file).unwrap_or_default();
+            state.locked().storage.remove(&mem_file);
+            // The file is removed after the storage debounce, so don't reload it yet
+            memory_view.buffer().set_text("");
+            undo_toast(&toasts, "Memory cleared", move || {
+                state.locked().storage.save_private(&mem_file, memory.clone());
+                let selected = (*sel.borrow()).and_then(|idx| state.locked().settings.profiles.get(idx).map(|p| p.id.clone()));
+                if selected.as_ref() == Some(&profile.id) {
+                    memory_view.buffer().set_text(&memory);
+                }
+            });
+        });
+    });
+
+    personalization_box.upcast()
+}
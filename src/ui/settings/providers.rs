@@ -0,0 +1,249 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Entry, Label, ListBox, Orientation, ScrolledWindow, StringList, TextView};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::error::LockExt;
+use crate::provider::{Backend, Provider};
+use crate::state::{AppState, ProviderConfig, ProviderKind, TlsOptions};
+use crate::ui::toasts::toast;
+use crate::utils::{format_headers, parse_headers};
+
+use super::{build_tls_editor, read_tls_editor};
+
+/// Named Ollama connections and OpenAI-compatible servers agents can use.
+pub fn build(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> gtk::Widget {
+    let providers_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .build();
+
+    providers_box.append(&Label::builder().label("Providers & Connections").xalign(0.0).css_classes(["settings-title"]).build());
+    providers_box.append(&Label::builder()
+        .label("Named Ollama endpoints and OpenAI-compatible servers that agents can use instead of the default endpoint.")
+        .xalign(0.0)
+        .wrap(true)
+        .build());
+
+    let providers_list = ListBox::builder().build();
+    let scrolled_providers = ScrolledWindow::builder()
+        .child(&providers_list)
+        .propagate_natural_height(true)
+        .build();
+    providers_box.append(&scrolled_providers);
+
+    let refresh_providers_list: Rc<RefCell<Option<std::boxed::Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
+    let refresh_providers_impl = {
+        let state = state.clone();
+        let toasts = toasts.clone();
+        let providers_list = providers_list.clone();
+        let refresh_ref = refresh_providers_list.clone();
+        move || {
+            while let Some(child) = providers_list.first_child() {
+                providers_list.remove(&child);
+            }
+            let providers = state.locked().settings.providers.clone();
+            for provider in providers {
+                let row = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(5)
+                    .margin_top(10)
+                    .margin_bottom(10)
+                    .build();
+
+                row.append(&Label::builder().label("Name").xalign(0.0).css_classes(["settings-label"]).build());
+                let name_entry = Entry::builder().text(&provider.name).placeholder_text("GPU Desktop").build();
+                row.append(&name_entry);
+
+                row.append(&Label::builder().label("Type").xalign(0.0).css_classes(["settings-label"]).build());
+                let kind_dropdown = DropDown::builder()
+                    .model(&StringList::new(&["OpenAI-compatible", "Ollama"]))
+                    .selected(if provider.kind == ProviderKind::Ollama { 1 } else { 0 })
+                    .build();
+                row.append(&kind_dropdown);
+
+                row.append(&Label::builder().label("Base URL").xalign(0.0).css_classes(["settings-label"]).build());
+                let url_entry = Entry::builder().text(&provider.base_url).placeholder_text("http://localhost:1234/v1").build();
+                row.append(&url_entry);
+
+                let key_label = Label::builder().label("API Key").xalign(0.0).css_classes(["settings-label"]).build();
+                row.append(&key_label);
+                let key_entry = gtk::PasswordEntry::builder().text(&provider.api_key).show_peek_icon(true).build();
+                row.append(&key_entry);
+
+                let update_kind_fields = {
+                    let url_entry = url_entry.clone();
+                    let key_label = key_label.clone();
+                    let key_entry = key_entry.clone();
+                    move |kind: ProviderKind| {
+                        let is_ollama = kind == ProviderKind::Ollama;
+                        url_entry.set_placeholder_text(Some(if is_ollama { "http://gpu-box:11434" } else { "http://localhost:1234/v1" }));
+                        key_label.set_visible(!is_ollama);
+                        key_entry.set_visible(!is_ollama);
+                    }
+                };
+                update_kind_fields(provider.kind);
+                kind_dropdown.connect_selected_notify(move |dd| {
+                    update_kind_fields(if dd.selected() == 1 { ProviderKind::Ollama } else { ProviderKind::OpenAi });
+                });
+
+                row.append(&Label::builder().label("Models (comma separated)").xalign(0.0).css_classes(["settings-label"]).build());
+                let models_entry = Entry::builder().text(provider.models.join(", ")).placeholder_text("gpt-4o-mini, llama-3.1-8b").build();
+                row.append(&models_entry);
+
+                row.append(&Label::builder().label("Custom Headers (one Name: value per line)").xalign(0.0).css_classes(["settings-label"]).build());
+                let headers_view = TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .monospace(true)
+                    .height_request(40)
+                    .css_classes(["headers-view"])
+                    .build();
+                headers_view.buffer().set_text(&format_headers(&provider.headers));
+                row.append(&headers_view);
+
+                row.append(&Label::builder().label("TLS").xalign(0.0).css_classes(["settings-label"]).build());
+                let (tls_editor, tls_invalid_switch, tls_ca_entry) = build_tls_editor(&provider.tls);
+                row.append(&tls_editor);
+
+                let status_label = Label::builder().xalign(0.0).css_classes(["settings-label"]).visible(false).build();
+                row.append(&status_label);
+
+                let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                let save_btn = Button::with_label("Save");
+                let fetch_btn = Button::with_label("Fetch Models");
+                let delete_btn = Button::with_label("Delete");
+                delete_btn.add_css_class("destructive-action");
+                actions_box.append(&save_btn);
+                actions_box.append(&fetch_btn);
+                actions_box.append(&delete_btn);
+                row.append(&actions_box);
+                row.append(&gtk::Separator::new(Orientation::Horizontal));
+
+                let state_s = state.clone();
+                let provider_id = provider.id.clone();
+                let name_c = name_entry.clone();
+                let kind_c = kind_dropdown.clone();
+                let url_c = url_entry.clone();
+                let key_c = key_entry.clone();
+                let models_c = models_entry.clone();
+                let headers_c = headers_view.clone();
+                let tls_invalid_c = tls_invalid_switch.clone();
+                let tls_ca_c = tls_ca_entry.clone();
+                let toasts_s = toasts.clone();
+                save_btn.connect_clicked(move |_| {
+                    let buffer = headers_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    let headers = parse_headers(&buffer.text(&start, &end, false));
+                    let mut s = state_s.locked();
+                    let base_url = url_c.text().to_string();
+                    if s.settings.providers.iter().any(|p| p.id == provider_id && p.base_url != base_url) {
+                        s.context_lengths.clear();
+                    }
+                    if let Some(p) = s.settings.providers.iter_mut().find(|p| p.id == provider_id) {
+                        p.name = name_c.text().to_string();
+                        p.kind = if kind_c.selected() == 1 { ProviderKind::Ollama } else { ProviderKind::OpenAi };
+                        p.base_url = base_url;
+                        p.api_key = key_c.text().to_string();
+                        p.models = models_c.text()
+                            .split(',')
+                            .map(|m| m.trim().to_string())
+                            .filter(|m| !m.is_empty())
+                            .collect();
+                        p.headers = headers;
+                        p.tls = read_tls_editor(&tls_invalid_c, &tls_ca_c);
+                        s.save_settings();
+                        toasts_s.add_toast(toast("Provider saved"));
+                    }
+                });
+
+                let kind_f = kind_dropdown.clone();
+                let url_f = url_entry.clone();
+                let key_f = key_entry.clone();
+                let models_f = models_entry.clone();
+                let headers_f = headers_view.clone();
+                let tls_invalid_f = tls_invalid_switch.clone();
+                let tls_ca_f = tls_ca_entry.clone();
+                let status_f = status_label.clone();
+                let state_f = state.clone();
+                fetch_btn.connect_clicked(move |btn| {
+                    let buffer = headers_f.buffer();
+                    let (start, end) = buffer.bounds();
+                    let client = Backend::from_config(&ProviderConfig {
+                        id: String::new(),
+                        name: String::new(),
+                        kind: if kind_f.selected() == 1 { ProviderKind::Ollama } else { ProviderKind::OpenAi },
+                        base_url: url_f.text().to_string(),
+                        api_key: key_f.text().to_string(),
+                        models: Vec::new(),
+                        headers: parse_headers(&buffer.text(&start, &end, false)),
+                        tls: read_tls_editor(&tls_invalid_f, &tls_ca_f),
+                    }, &state_f.locked().settings.timeouts);
+                    btn.set_sensitive(false);
+                    let btn = btn.clone();
+                    let models_entry = models_f.clone();
+                    let status_label = status_f.clone();
+                    glib::MainContext::default().spawn_local(async move {
+                        let res = client.list_models().await;
+                        btn.set_sensitive(true);
+                        status_label.set_visible(true);
+                        match res {
+                            Ok(models) => {
+                                status_label.set_label(&format!("Found {} models — press Save to keep them", models.len()));
+                                models_entry.set_text(&models.join(", "));
+                            }
+                            Err(e) => status_label.set_label(&format!("Error: {}", e)),
+                        }
+                    });
+                });
+
+                let state_d = state.clone();
+                let provider_id_d = provider.id.clone();
+                let refresh_d = refresh_ref.clone();
+                delete_btn.connect_clicked(move |_| {
+                    {
+                        let mut s = state_d.locked();
+                        s.settings.providers.retain(|p| p.id != provider_id_d);
+                        for agent in &mut s.settings.agents {
+                            if agent.provider_id.as_ref() == Some(&provider_id_d) {
+                                agent.provider_id = None;
+                            }
+                        }
+                        s.save_settings();
+                    }
+                    if let Some(f) = &*refresh_d.borrow() { f(); }
+                });
+
+                providers_list.append(&row);
+            }
+        }
+    };
+    *refresh_providers_list.borrow_mut() = Some(std::boxed::Box::new(refresh_providers_impl));
+    if let Some(f) = &*refresh_providers_list.borrow() { f(); }
+
+    let add_provider_btn = Button::with_label("Add Provider");
+    let state_add_provider = state.clone();
+    let refresh_providers_add = refresh_providers_list.clone();
+    add_provider_btn.connect_clicked(move |_| {
+        {
+            let mut s = state_add_provider.locked();
+            s.settings.providers.push(ProviderConfig {
+                id: glib::uuid_string_random().to_string(),
+                name: "New Provider".to_string(),
+                kind: ProviderKind::OpenAi,
+                base_url: "http://localhost:8080/v1".to_string(),
+                api_key: String::new(),
+                models: Vec::new(),
+                headers: Vec::new(),
+                tls: TlsOptions::default(),
+            });
+            s.save_settings();
+        }
+        if let Some(f) = &*refresh_providers_add.borrow() { f(); }
+    });
+    providers_box.append(&add_provider_btn);
+    providers_box.upcast()
+}
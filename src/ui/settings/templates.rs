@@ -0,0 +1,129 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, Entry, Label, ListBox, Orientation, ScrolledWindow, TextView};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::error::LockExt;
+use crate::state::{AppState, PromptTemplate};
+use crate::ui::toasts::toast;
+
+/// Editor for the prompt templates offered in the input bar.
+pub fn build(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> gtk::Widget {
+    let templates_box = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(10)
+        .build();
+
+    templates_box.append(&Label::builder().label("Prompt Templates").xalign(0.0).css_classes(["settings-title"]).build());
+    templates_box.append(&Label::builder()
+        .label("Reusable prompts. Placeholders like {{language}} or {{text}} are asked for before sending.")
+        .xalign(0.0)
+        .wrap(true)
+        .build());
+
+    let templates_settings_list = ListBox::builder().build();
+    let scrolled_templates = ScrolledWindow::builder()
+        .child(&templates_settings_list)
+        .propagate_natural_height(true)
+        .build();
+    templates_box.append(&scrolled_templates);
+
+    let refresh_templates_list: Rc<RefCell<Option<std::boxed::Box<dyn Fn()>>>> = Rc::new(RefCell::new(None));
+    let refresh_templates_impl = {
+        let state = state.clone();
+        let toasts = toasts.clone();
+        let templates_settings_list = templates_settings_list.clone();
+        let refresh_ref = refresh_templates_list.clone();
+        move || {
+            while let Some(child) = templates_settings_list.first_child() {
+                templates_settings_list.remove(&child);
+            }
+            let templates = state.locked().settings.templates.clone();
+            for template in templates {
+                let row = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(5)
+                    .margin_top(10)
+                    .margin_bottom(10)
+                    .build();
+
+                row.append(&Label::builder().label("Name").xalign(0.0).css_classes(["settings-label"]).build());
+                let name_entry = Entry::builder().text(&template.name).placeholder_text("Translate").build();
+                row.append(&name_entry);
+
+                row.append(&Label::builder().label("Prompt").xalign(0.0).css_classes(["settings-label"]).build());
+                let body_view = TextView::builder()
+                    .wrap_mode(gtk::WrapMode::WordChar)
+                    .height_request(80)
+                    .css_classes(["headers-view"])
+                    .build();
+                body_view.buffer().set_text(&template.body);
+                row.append(&body_view);
+
+                let actions_box = Box::builder().orientation(Orientation::Horizontal).spacing(10).margin_top(5).build();
+                let save_btn = Button::with_label("Save");
+                let delete_btn = Button::with_label("Delete");
+                delete_btn.add_css_class("destructive-action");
+                actions_box.append(&save_btn);
+                actions_box.append(&delete_btn);
+                row.append(&actions_box);
+                row.append(&gtk::Separator::new(Orientation::Horizontal));
+
+                let state_s = state.clone();
+                let template_id = template.id.clone();
+                let name_c = name_entry.clone();
+                let body_c = body_view.clone();
+                let toasts_s = toasts.clone();
+                save_btn.connect_clicked(move |_| {
+                    let buffer = body_c.buffer();
+                    let (start, end) = buffer.bounds();
+                    let mut s = state_s.locked();
+                    if let Some(t) = s.settings.templates.iter_mut().find(|t| t.id == template_id) {
+                        t.name = name_c.text().to_string();
+                        t.body = buffer.text(&start, &end, false).to_string();
+                        s.save_settings();
+                        toasts_s.add_toast(toast("Template saved"));
+                    }
+                });
+
+                let state_d = state.clone();
+                let template_id_d = template.id.clone();
+                let refresh_d = refresh_ref.clone();
+                delete_btn.connect_clicked(move |_| {
+                    {
+                        let mut s = state_d.locked();
+                        s.settings.templates.retain(|t| t.id != template_id_d);
+                        s.save_settings();
+                    }
+                    if let Some(f) = &*refresh_d.borrow() { f(); }
+                });
+
+                templates_settings_list.append(&row);
+            }
+        }
+    };
+    *refresh_templates_list.borrow_mut() = Some(std::boxed::Box::new(refresh_templates_impl));
+    if let Some(f) = &*refresh_templates_list.borrow() { f(); }
+
+    let add_template_btn = Button::with_label("Add Template");
+    let state_add_template = state.clone();
+    let refresh_templates_add = refresh_templates_list.clone();
+    add_template_btn.connect_clicked(move |_| {
+        {
+            let mut s = state_add_template.locked();
+            s.settings.templates.push(PromptTemplate {
+                id: glib::uuid_string_random().to_string(),
+                name: "New Template".to_string(),
+                body: "Translate the following text into {{language}}:\n\n{{text}}".to_string(),
+            });
+            s.save_settings();
+        }
+        if let Some(f) = &*refresh_templates_add.borrow() { f(); }
+    });
+    templates_box.append(&add_template_btn);
+    templates_box.upcast()
+}
@@ -0,0 +1,633 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, CheckButton, Entry, Expander, GestureClick, Label, ListBox, Orientation, Popover, ScrolledWindow, ToggleButton};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::confirm::undo_toast;
+use crate::controller::{Command, Commands};
+use crate::error::{AppError, LockExt};
+use crate::export::{file_stem, history_to_html, history_to_training_example};
+use crate::portal::write_chosen_file;
+use crate::share;
+use crate::state::{AppState, ShareService, StoredMessage};
+use crate::ui::toasts::{toast, Report};
+use crate::utils::{date_group, history_to_markdown, relative_time};
+
+type OpenConversation = Rc<dyn Fn(Option<String>, Vec<StoredMessage>, bool)>;
+
+/// New chat button, saved conversations and the connection status.
+#[derive(Clone)]
+pub struct Sidebar {
+    pub widget: Box,
+    pub new_chat_btn: Button,
+    pub saved_btn: Button,
+    pub arena_btn: Button,
+    pub settings_btn: Button,
+    history_list: ListBox,
+    select_btn: ToggleButton,
+    /// Ids of the conversations checked in selection mode
+    selection: Rc<RefCell<HashSet<String>>>,
+    selection_bar: Box,
+    selection_label: Label,
+    bulk_buttons: Vec<gtk::Widget>,
+    trash_expander: Expander,
+    trash_list: ListBox,
+    status_box: Box,
+    status_dot: Label,
+    status_label: Label,
+    state: Arc<Mutex<AppState>>,
+    toasts: ToastOverlay,
+    commands: Commands,
+    open_conversation: Rc<RefCell<Option<OpenConversation>>>,
+}
+
+impl Sidebar {
+    pub fn new(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay, commands: &Commands) -> Self {
+        let sidebar = Box::builder()
+            .orientation(Orientation::Vertical)
+            .css_name("sidebar")
+            .build();
+
+        let sidebar_top = Box::builder()
+            .orientation(Orientation::Vertical)
+            .vexpand(true)
+            .build();
+        sidebar.append(&sidebar_top);
+
+        let top_row = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(4)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(10)
+            .margin_bottom(10)
+            .build();
+        let new_chat_btn = Button::builder().label("New chat").hexpand(true).build();
+        top_row.append(&new_chat_btn);
+        let select_btn = ToggleButton::builder()
+            .icon_name("object-select-symbolic")
+            .tooltip_text("Select Chats")
+            .build();
+        top_row.append(&select_btn);
+        sidebar_top.append(&top_row);
+
+        let history_list = ListBox::builder()
+            .margin_top(20)
+            .css_classes(["history-list"])
+            .build();
+        let history_scrolled = ScrolledWindow::builder()
+            .child(&history_list)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .build();
+        sidebar_top.append(&history_scrolled);
+
+        let selection_bar = Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(4)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_bottom(10)
+            .visible(false)
+            .build();
+        let selection_label = Label::builder().xalign(0.0).css_classes(["dim-label"]).build();
+        selection_bar.append(&selection_label);
+        let bulk_row = Box::builder().orientation(Orientation::Horizontal).spacing(4).homogeneous(true).build();
+        let bulk_delete_btn = Button::builder().label("Delete").css_classes(["destructive-action"]).tooltip_text("Move the selected chats to the Trash").build();
+        let bulk_export_btn = gtk::MenuButton::builder().label("Export…").tooltip_text("Save the selected chats to a file").build();
+        let bulk_tag_btn = gtk::MenuButton::builder().label("Tag…").tooltip_text("Add or remove a tag on the selected chats").build();
+        bulk_row.append(&bulk_delete_btn);
+        bulk_row.append(&bulk_export_btn);
+        bulk_row.append(&bulk_tag_btn);
+        selection_bar.append(&bulk_row);
+        sidebar_top.append(&selection_bar);
+
+        let tag_box = Box::builder().orientation(Orientation::Vertical).spacing(5).margin_top(10).margin_bottom(10).margin_start(10).margin_end(10).build();
+        let tag_entry = Entry::builder().placeholder_text("Tag").build();
+        tag_box.append(&tag_entry);
+        let tag_actions = Box::builder().orientation(Orientation::Horizontal).spacing(5).homogeneous(true).build();
+        let add_tag_btn = Button::with_label("Add");
+        let remove_tag_btn = Button::with_label("Remove");
+        tag_actions.append(&add_tag_btn);
+        tag_actions.append(&remove_tag_btn);
+        tag_box.append(&tag_actions);
+        let tag_popover = Popover::builder().child(&tag_box).build();
+        bulk_tag_btn.set_popover(Some(&tag_popover));
+
+        let export_box = Box::builder().orientation(Orientation::Vertical).spacing(5).margin_top(10).margin_bottom(10).margin_start(10).margin_end(10).build();
+        let export_markdown_btn = Button::builder().label("Markdown…").tooltip_text("One markdown file with all selected chats").build();
+        let export_jsonl_btn = Button::builder().label("Fine-tuning JSONL…").tooltip_text("One training example per chat, with its system, user and assistant messages").build();
+        export_box.append(&export_markdown_btn);
+        export_box.append(&export_jsonl_btn);
+        let export_popover = Popover::builder().child(&export_box).build();
+        bulk_export_btn.set_popover(Some(&export_popover));
+
+        let trash_list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["history-list"])
+            .build();
+        let empty_trash_btn = Button::builder()
+            .label("Empty Trash")
+            .css_classes(["flat", "destructive-action"])
+            .margin_top(4)
+            .build();
+        let trash_box = Box::builder().orientation(Orientation::Vertical).build();
+        trash_box.append(&ScrolledWindow::builder()
+            .child(&trash_list)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .max_content_height(200)
+            .propagate_natural_height(true)
+            .build());
+        trash_box.append(&empty_trash_btn);
+        let trash_expander = Expander::builder()
+            .child(&trash_box)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_bottom(10)
+            .visible(false)
+            .build();
+        sidebar_top.append(&trash_expander);
+        let commands_empty = commands.clone();
+        empty_trash_btn.connect_clicked(move |_| commands_empty.send(Command::EmptyTrash));
+
+        let status_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(8)
+            .margin_start(20)
+            .margin_end(20)
+            .margin_bottom(10)
+            .build();
+        let status_dot = Label::builder().label("●").css_classes(["status-dot"]).build();
+        let status_label = Label::builder().label("Connecting…").xalign(0.0).css_classes(["status-text"]).build();
+        status_box.append(&status_dot);
+        status_box.append(&status_label);
+        sidebar.append(&status_box);
+
+        let saved_btn = Button::with_label("Saved");
+        saved_btn.set_margin_start(10);
+        saved_btn.set_margin_end(10);
+        saved_btn.set_margin_bottom(6);
+        sidebar.append(&saved_btn);
+
+        let arena_btn = Button::builder()
+            .label("Arena")
+            .tooltip_text("Compare two random models blindly and vote")
+            .build();
+        arena_btn.set_margin_start(10);
+        arena_btn.set_margin_end(10);
+        arena_btn.set_margin_bottom(6);
+        sidebar.append(&arena_btn);
+
+        let settings_btn = Button::with_label("Settings");
+        settings_btn.set_margin_start(10);
+        settings_btn.set_margin_end(10);
+        settings_btn.set_margin_bottom(20);
+        sidebar.append(&settings_btn);
+
+        let this = Self {
+            widget: sidebar,
+            new_chat_btn,
+            saved_btn,
+            arena_btn,
+            settings_btn,
+            history_list,
+            select_btn,
+            selection: Rc::new(RefCell::new(HashSet::new())),
+            selection_bar,
+            selection_label,
+            bulk_buttons: vec![bulk_delete_btn.clone().upcast(), bulk_export_btn.clone().upcast(), bulk_tag_btn.upcast()],
+            trash_expander,
+            trash_list,
+            status_box,
+            status_dot,
+            status_label,
+            state: state.clone(),
+            toasts: toasts.clone(),
+            commands: commands.clone(),
+            open_conversation: Rc::new(RefCell::new(None)),
+        };
+
+        this.select_btn.connect_toggled({
+            let this = this.clone();
+            move |btn| {
+                this.selection.borrow_mut().clear();
+                this.selection_bar.set_visible(btn.is_active());
+                this.refresh_history();
+            }
+        });
+        bulk_delete_btn.connect_clicked({
+            let this = this.clone();
+            move |_| {
+                let ids: Vec<String> = this.selection.borrow().iter().cloned().collect();
+                this.commands.send(Command::DeleteConversations(ids.clone()));
+                this.select_btn.set_active(false);
+                let commands = this.commands.clone();
+                let title = if ids.len() == 1 { "Chat moved to Trash".to_string() } else { format!("{} chats moved to Trash", ids.len()) };
+                undo_toast(&this.toasts, &title, move || commands.send(Command::RestoreConversations(ids.clone())));
+            }
+        });
+        export_markdown_btn.connect_clicked({
+            let this = this.clone();
+            let export_popover = export_popover.clone();
+            move |btn| {
+                export_popover.popdown();
+                let chats = {
+                    let selection = this.selection.borrow();
+                    let s = this.state.locked();
+                    s.history.iter().rev().filter(|h| selection.contains(&h.id)).map(history_to_markdown).collect::<Vec<_>>()
+                };
+                let dialog = gtk::FileDialog::builder()
+                    .title("Export Chats")
+                    .initial_name("chats.md")
+                    .build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let toasts = this.toasts.clone();
+                dialog.save(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    match write_chosen_file(&file, chats.join("\n---\n\n"), "Failed to export chats") {
+                        Ok(()) => toasts.add_toast(toast(&format!("Exported {} chats", chats.len()))),
+                        Err(e) => toasts.report(&e),
+                    }
+                });
+            }
+        });
+        export_jsonl_btn.connect_clicked({
+            let this = this.clone();
+            move |btn| {
+                export_popover.popdown();
+                let examples = {
+                    let selection = this.selection.borrow();
+                    let s = this.state.locked();
+                    s.history.iter().rev().filter(|h| selection.contains(&h.id)).filter_map(history_to_training_example).collect::<Vec<_>>()
+                };
+                if examples.is_empty() {
+                    this.toasts.add_toast(toast("None of the selected chats has a reply to export"));
+                    return;
+                }
+                let dialog = gtk::FileDialog::builder()
+                    .title("Export Chats for Fine-Tuning")
+                    .initial_name("chats.jsonl")
+                    .build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let toasts = this.toasts.clone();
+                dialog.save(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    match write_chosen_file(&file, examples.join("\n") + "\n", "Failed to export chats") {
+                        Ok(()) => toasts.add_toast(toast(&format!("Exported {} training examples", examples.len()))),
+                        Err(e) => toasts.report(&e),
+                    }
+                });
+            }
+        });
+        for (button, tagged) in [(add_tag_btn, true), (remove_tag_btn, false)] {
+            let this = this.clone();
+            let tag_entry = tag_entry.clone();
+            let tag_popover = tag_popover.clone();
+            button.connect_clicked(move |_| {
+                let tag = tag_entry.text().trim().to_string();
+                if tag.is_empty() {
+                    return;
+                }
+                let ids = this.selection.borrow().iter().cloned().collect();
+                this.commands.send(Command::SetTag { ids, tag, tagged });
+                tag_entry.set_text("");
+                tag_popover.popdown();
+            });
+        }
+        this
+    }
+
+    /// Shows how many chats are checked; the bulk actions need at least one.
+    fn update_selection(&self) {
+        let count = self.selection.borrow().len();
+        self.selection_label.set_label(&format!("{} selected", count));
+        for button in &self.bulk_buttons {
+            button.set_sensitive(count > 0);
+        }
+    }
+
+    /// Called with the id and messages of a conversation picked from the list,
+    /// and whether it should open in a new tab.
+    pub fn connect_open_conversation(&self, f: impl Fn(Option<String>, Vec<StoredMessage>, bool) + 'static) {
+        *self.open_conversation.borrow_mut() = Some(Rc::new(f));
+    }
+
+    pub fn set_connection_status(&self, connected: bool, text: &str, tooltip: &str) {
+        if connected {
+            self.status_dot.remove_css_class("status-disconnected");
+            self.status_dot.add_css_class("status-connected");
+        } else {
+            self.status_dot.remove_css_class("status-connected");
+            self.status_dot.add_css_class("status-disconnected");
+        }
+        self.status_label.set_label(text);
+        self.status_box.set_tooltip_text(Some(tooltip));
+    }
+
+    /// Rebuilds the Trash section from `state.trash`, most recently deleted first.
+    fn refresh_trash(&self) {
+        while let Some(child) = self.trash_list.first_child() {
+            self.trash_list.remove(&child);
+        }
+        let trash = self.state.locked().trash.clone();
+        self.trash_expander.set_visible(!trash.is_empty());
+        self.trash_expander.set_label(Some(&format!("Trash ({})", trash.len())));
+        for trashed in trash.into_iter().rev() {
+            let row = Box::builder().orientation(Orientation::Horizontal).spacing(4).build();
+            row.append(&Label::builder()
+                .label(&trashed.conversation.title)
+                .xalign(0.0)
+                .hexpand(true)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .tooltip_text(format!("Deleted {}", relative_time(trashed.deleted_at)))
+                .build());
+
+            let restore_btn = Button::builder()
+                .icon_name("edit-undo-symbolic")
+                .css_classes(["flat"])
+                .tooltip_text("Restore")
+                .build();
+            let commands = self.commands.clone();
+            let id = trashed.conversation.id.clone();
+            restore_btn.connect_clicked(move |_| commands.send(Command::RestoreConversations(vec![id.clone()])));
+            row.append(&restore_btn);
+
+            let purge_btn = Button::builder()
+                .icon_name("edit-delete-symbolic")
+                .css_classes(["flat"])
+                .tooltip_text("Delete Forever")
+                .build();
+            let commands = self.commands.clone();
+            let id = trashed.conversation.id.clone();
+            purge_btn.connect_clicked(move |_| commands.send(Command::PurgeConversation(id.clone())));
+            row.append(&purge_btn);
+
+            self.trash_list.append(&row);
+        }
+    }
+
+    /// Rebuilds the conversation list from `state.history`, most recently
+    /// active first under date headers, and the Trash below it.
+    pub fn refresh_history(&self) {
+        self.refresh_trash();
+        while let Some(child) = self.history_list.first_child() {
+            self.history_list.remove(&child);
+        }
+        let mut history = {
+            let s = self.state.locked();
+            s.history.clone()
+        };
+        history.reverse();
+        // Stable, so entries without timestamps keep their saved order
+        history.sort_by_key(|h| std::cmp::Reverse(h.last_activity()));
+        let selecting = self.select_btn.is_active();
+        // Forget chats that were deleted meanwhile
+        self.selection.borrow_mut().retain(|id| history.iter().any(|h| &h.id == id));
+        self.update_selection();
+        let mut group = None;
+        for item in history {
+            let item_group = date_group(item.last_activity());
+            if group != Some(item_group) {
+                group = Some(item_group);
+                let header = Label::builder()
+                    .label(item_group)
+                    .xalign(0.0)
+                    .margin_start(20)
+                    .margin_top(10)
+                    .css_classes(["dim-label", "history-group"])
+                    .build();
+                self.history_list.append(&header);
+                if let Some(row) = header.parent().and_downcast::<gtk::ListBoxRow>() {
+                    row.set_activatable(false);
+                    row.set_selectable(false);
+                }
+            }
+            let row_btn = Button::builder()
+                .css_classes(["history-item"])
+                .hexpand(true)
+                .build();
+            let title_label = Label::builder()
+                .label(&item.title)
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+            if item.tags.is_empty() {
+                row_btn.set_child(Some(&title_label));
+            } else {
+                let labels = Box::builder().orientation(Orientation::Vertical).build();
+                labels.append(&title_label);
+                labels.append(&Label::builder()
+                    .label(item.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "))
+                    .xalign(0.0)
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .css_classes(["dim-label"])
+                    .build());
+                row_btn.set_child(Some(&labels));
+            }
+
+            let row = Box::builder().orientation(Orientation::Horizontal).build();
+            let check = CheckButton::builder()
+                .active(self.selection.borrow().contains(&item.id))
+                .visible(selecting)
+                .margin_start(10)
+                .build();
+            row.append(&check);
+            row.append(&row_btn);
+            let this = self.clone();
+            let item_id = item.id.clone();
+            check.connect_toggled(move |check| {
+                if check.is_active() {
+                    this.selection.borrow_mut().insert(item_id.clone());
+                } else {
+                    this.selection.borrow_mut().remove(&item_id);
+                }
+                this.update_selection();
+            });
+
+            let open_conversation = self.open_conversation.clone();
+            let item_id = item.id.clone();
+            let item_messages = item.messages.clone();
+            row_btn.connect_clicked(move |_| {
+                if selecting {
+                    check.set_active(!check.is_active());
+                } else if let Some(open) = &*open_conversation.borrow() {
+                    open(Some(item_id.clone()), item_messages.clone(), false);
+                }
+            });
+
+            // Context Menu
+            let popover = Popover::new();
+            let menu_box = Box::builder().orientation(Orientation::Vertical).spacing(5).margin_top(10).margin_bottom(10).margin_start(10).margin_end(10).build();
+            
+            let rename_box = Box::builder().orientation(Orientation::Horizontal).spacing(5).build();
+            let rename_entry = Entry::builder().text(&item.title).hexpand(true).build();
+            let rename_confirm_btn = Button::with_label("Save");
+            rename_box.append(&rename_entry);
+            rename_box.append(&rename_confirm_btn);
+            menu_box.append(&rename_box);
+
+            let new_tab_btn = Button::with_label("Open in New Tab");
+            menu_box.append(&new_tab_btn);
+
+            let share_btn = Button::with_label("Share…");
+            menu_box.append(&share_btn);
+
+            let export_html_btn = Button::with_label("Export as HTML…");
+            menu_box.append(&export_html_btn);
+
+            let delete_btn = Button::with_label("Move to Trash");
+            delete_btn.add_css_class("destructive-action"); // Will add CSS later
+            menu_box.append(&delete_btn);
+            
+            popover.set_child(Some(&menu_box));
+            popover.set_parent(&row_btn);
+            popover.set_has_arrow(false);
+
+            let gesture = GestureClick::new();
+            gesture.set_button(3); // Right click
+            gesture.connect_pressed(glib::clone!(#[weak] popover, #[weak] row_btn, move |_, _, _, _| {
+                 let allocation = row_btn.allocation();
+                 popover.set_pointing_to(Some(&allocation));
+                 popover.popup();
+            }));
+            row_btn.add_controller(gesture);
+
+            // Handlers
+            let open_conversation = self.open_conversation.clone();
+            let item_id = item.id.clone();
+            let item_messages = item.messages.clone();
+            let popover_t = popover.clone();
+            new_tab_btn.connect_clicked(move |_| {
+                popover_t.popdown();
+                if let Some(open) = &*open_conversation.borrow() {
+                    open(Some(item_id.clone()), item_messages.clone(), true);
+                }
+            });
+
+            let commands_r = self.commands.clone();
+            let item_id = item.id.clone();
+            let rename_entry_c = rename_entry.clone();
+            let popover_r = popover.clone();
+            
+            rename_confirm_btn.connect_clicked(move |_| {
+                let new_title = rename_entry_c.text().to_string();
+                if new_title.is_empty() { return; }
+                commands_r.send(Command::RenameConversation { id: item_id.clone(), title: new_title });
+                popover_r.popdown();
+            });
+
+            let item_e = item.clone();
+            let popover_e = popover.clone();
+            let toasts_e = self.toasts.clone();
+            export_html_btn.connect_clicked(move |btn| {
+                popover_e.popdown();
+                let dialog = gtk::FileDialog::builder()
+                    .title("Export Chat as HTML")
+                    .initial_name(format!("{}.html", file_stem(&item_e.title)))
+                    .build();
+                let parent = btn.root().and_downcast::<gtk::Window>();
+                let html = history_to_html(&item_e);
+                let toasts = toasts_e.clone();
+                dialog.save(parent.as_ref(), gtk::gio::Cancellable::NONE, move |res| {
+                    let Ok(file) = res else { return };
+                    match write_chosen_file(&file, &html, "Failed to export chat") {
+                        Ok(()) => toasts.add_toast(toast("Chat exported")),
+                        Err(e) => toasts.report(&e),
+                    }
+                });
+            });
+
+            let state_s = self.state.clone();
+            let item_s = item.clone();
+            let popover_s = popover.clone();
+            let toasts_s = self.toasts.clone();
+            share_btn.connect_clicked(move |btn| {
+                popover_s.popdown();
+                let share = state_s.locked().settings.share.clone();
+                let service = match share.service {
+                    ShareService::Paste => share.paste_url.clone(),
+                    ShareService::Gist => "a secret GitHub gist".to_string(),
+                };
+                let dialog = gtk::Window::builder()
+                    .title("Share Conversation")
+                    .modal(true)
+                    .default_width(420)
+                    .build();
+                if let Some(parent) = btn.root().and_downcast::<gtk::Window>() {
+                    dialog.set_transient_for(Some(&parent));
+                }
+                let form = Box::builder()
+                    .orientation(Orientation::Vertical)
+                    .spacing(10)
+                    .margin_top(20)
+                    .margin_bottom(20)
+                    .margin_start(20)
+                    .margin_end(20)
+                    .build();
+                form.append(&Label::builder()
+                    .label(format!("Upload a transcript of “{}” to {}? Anyone with the link can read it.", item_s.title, service))
+                    .xalign(0.0)
+                    .wrap(true)
+                    .build());
+                let actions = Box::builder().orientation(Orientation::Horizontal).spacing(10).halign(gtk::Align::End).margin_top(10).build();
+                let cancel_btn = Button::with_label("Cancel");
+                let upload_btn = Button::with_label("Upload");
+                upload_btn.add_css_class("suggested-action");
+                actions.append(&cancel_btn);
+                actions.append(&upload_btn);
+                form.append(&actions);
+                dialog.set_child(Some(&form));
+
+                let dialog_c = dialog.clone();
+                cancel_btn.connect_clicked(move |_| dialog_c.close());
+                let dialog_c = dialog.clone();
+                let item = item_s.clone();
+                let toasts = toasts_s.clone();
+                upload_btn.connect_clicked(move |upload_btn| {
+                    upload_btn.set_sensitive(false);
+                    upload_btn.set_label("Uploading…");
+                    let dialog = dialog_c.clone();
+                    let item = item.clone();
+                    let share = share.clone();
+                    let toasts = toasts.clone();
+                    glib::MainContext::default().spawn_local(async move {
+                        let result = share::upload(&share, &item.title, &history_to_markdown(&item)).await;
+                        dialog.close();
+                        match result {
+                            Ok(url) => {
+                                if let Some(display) = gtk::gdk::Display::default() {
+                                    display.clipboard().set(&url);
+                                }
+                                toasts.add_toast(toast(&format!("Link copied: {}", url)));
+                            }
+                            Err(e) => toasts.report(&AppError::new("Failed to share chat", e)),
+                        }
+                    });
+                });
+                dialog.present();
+            });
+
+            let commands_d = self.commands.clone();
+            let item_id_d = item.id.clone();
+            let popover_d = popover.clone();
+            let toasts_d = self.toasts.clone();
+            
+            delete_btn.connect_clicked(move |_| {
+                commands_d.send(Command::DeleteConversations(vec![item_id_d.clone()]));
+                popover_d.popdown();
+                let commands = commands_d.clone();
+                let id = item_id_d.clone();
+                undo_toast(&toasts_d, "Chat moved to Trash", move || {
+                    commands.send(Command::RestoreConversations(vec![id.clone()]));
+                });
+            });
+
+            self.history_list.append(&row);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::{Box, Button, DropDown, Orientation, ScrolledWindow, TextView};
+use adw::ToastOverlay;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::controller::Commands;
+use crate::error::LockExt;
+use crate::speech::Speaker;
+use crate::state::{AppState, OpenChat};
+use crate::ui::agent_picker::AgentPicker;
+use crate::ui::chat::{ChatView, CHAT_MIN_MARGIN};
+use crate::ui::send::ChatSender;
+
+/// A second conversation beside the tabs, with its own agent and input, e.g.
+/// a coding agent next to a documentation agent. Its messages go through the
+/// same `ChatSender` as the tabs' and stream independently of them, but are
+/// plain text only, without attachments.
+#[derive(Clone)]
+pub struct SplitChat {
+    pub widget: Box,
+    agent_picker: AgentPicker,
+    agent_dropdown: DropDown,
+    /// Holds the chat view while the pane is open
+    view_slot: Box,
+    /// `OpenChat::tab_id` and view of the pane's chat while it is open
+    open: Rc<RefCell<Option<(u64, ChatView)>>>,
+    /// Set while the dropdown is filled or synced, so it doesn't count as a pick
+    syncing: Rc<Cell<bool>>,
+    text_view: TextView,
+    send_btn: Button,
+    state: Arc<Mutex<AppState>>,
+    speaker: Rc<Speaker>,
+    toasts: ToastOverlay,
+    commands: Commands,
+    sender: ChatSender,
+}
+
+impl SplitChat {
+    pub fn new(state: &Arc<Mutex<AppState>>, speaker: &Rc<Speaker>, toasts: &ToastOverlay, commands: &Commands, sender: &ChatSender) -> Self {
+        let widget = Box::builder()
+            .orientation(Orientation::Vertical)
+            .hexpand(true)
+            .visible(false)
+            .build();
+
+        let header = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .margin_start(20)
+            .margin_end(20)
+            .margin_top(20)
+            .margin_bottom(20)
+            .build();
+        let agent_picker = AgentPicker::new(state);
+        let agent_dropdown = agent_picker.dropdown.clone();
+        header.append(&agent_dropdown);
+        let new_chat_btn = Button::builder()
+            .icon_name("document-new-symbolic")
+            .css_classes(["flat"])
+            .tooltip_text("New chat in this pane")
+            .hexpand(true)
+            .halign(gtk::Align::End)
+            .build();
+        header.append(&new_chat_btn);
+        widget.append(&header);
+
+        let view_slot = Box::builder().orientation(Orientation::Vertical).vexpand(true).build();
+        widget.append(&view_slot);
+
+        let input_box = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .margin_bottom(30)
+            .margin_start(CHAT_MIN_MARGIN)
+            .margin_end(CHAT_MIN_MARGIN)
+            .build();
+        let text_view = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .hexpand(true)
+            .css_classes(["chat-input"])
+            .build();
+        input_box.append(&ScrolledWindow::builder()
+            .child(&text_view)
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_height(50)
+            .max_content_height(150)
+            .hexpand(true)
+            .build());
+        let send_btn = Button::builder()
+            .label("Send")
+            .valign(gtk::Align::End)
+            .css_classes(["send-btn"])
+            .build();
+        input_box.append(&send_btn);
+        widget.append(&input_box);
+
+        let split = Self {
+            widget,
+            agent_picker,
+            agent_dropdown,
+            view_slot,
+            open: Rc::new(RefCell::new(None)),
+            syncing: Rc::new(Cell::new(false)),
+            text_view,
+            send_btn,
+            state: state.clone(),
+            speaker: speaker.clone(),
+            toasts: toasts.clone(),
+            commands: commands.clone(),
+            sender: sender.clone(),
+        };
+
+        split.sender.connect_busy({
+            let split = split.clone();
+            move |tab_id, _| {
+                if split.tab_id() == Some(tab_id) {
+                    split.update_send_btn();
+                }
+            }
+        });
+        split.send_btn.connect_clicked({
+            let split = split.clone();
+            move |_| split.send_or_stop()
+        });
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed({
+            let split = split.clone();
+            move |_, key, _, modifiers| {
+                if key == gtk::gdk::Key::Return && !modifiers.contains(gtk::gdk::ModifierType::SHIFT_MASK) {
+                    split.send_or_stop();
+                    return glib::Propagation::Stop;
+                }
+                glib::Propagation::Proceed
+            }
+        });
+        split.text_view.add_controller(keys);
+        new_chat_btn.connect_clicked({
+            let split = split.clone();
+            move |_| {
+                let agent_idx = split.agent_dropdown.selected() as usize;
+                split.open_chat(agent_idx);
+            }
+        });
+        // A different agent starts a new chat, unless nothing was asked yet
+        split.agent_dropdown.connect_selected_notify({
+            let split = split.clone();
+            move |dd| {
+                if split.syncing.get() || dd.selected() == gtk::INVALID_LIST_POSITION {
+                    return;
+                }
+                let agent_idx = dd.selected() as usize;
+                let Some(tab_id) = split.tab_id() else { return };
+                let started = {
+                    let mut s = split.state.locked();
+                    match s.chat_in_tab(tab_id) {
+                        Some(chat) if chat.messages.is_empty() => {
+                            chat.agent_idx = agent_idx;
+                            false
+                        }
+                        _ => true,
+                    }
+                };
+                if started {
+                    split.open_chat(agent_idx);
+                } else if let Some(view) = split.view() {
+                    view.render(&[]);
+                }
+            }
+        });
+        split.refresh_agents();
+        split
+    }
+
+    /// Shows the pane with a new chat for the default agent, or hides it,
+    /// stopping a reply that is still streaming.
+    pub fn set_open(&self, open: bool) {
+        if open == self.open.borrow().is_some() {
+            return;
+        }
+        if open {
+            let agent_idx = self.state.locked().settings.default_agent_index();
+            self.open_chat(agent_idx);
+        } else {
+            self.close_chat();
+        }
+        self.widget.set_visible(open);
+    }
+
+    /// Refills the agent dropdown after the agents were edited.
+    pub fn refresh_agents(&self) {
+        let agent_idx = {
+            let s = self.state.locked();
+            self.tab_id()
+                .and_then(|tab_id| s.chats.iter().find(|c| c.tab_id == tab_id))
+                .map(|c| c.agent_idx)
+                .unwrap_or_else(|| s.settings.default_agent_index())
+        };
+        self.syncing.set(true);
+        self.agent_picker.refresh();
+        self.agent_dropdown.set_selected(agent_idx as u32);
+        self.syncing.set(false);
+    }
+
+    /// Updates the stars of the pane's replies after the bookmarks changed.
+    pub fn refresh_bookmarks(&self) {
+        if let Some(view) = self.view() {
+            view.refresh_bookmarks();
+        }
+    }
+
+    fn tab_id(&self) -> Option<u64> {
+        self.open.borrow().as_ref().map(|(tab_id, _)| *tab_id)
+    }
+
+    fn view(&self) -> Option<ChatView> {
+        self.open.borrow().as_ref().map(|(_, view)| view.clone())
+    }
+
+    /// Replaces the pane's chat with an empty one for agent `agent_idx`. The
+    /// new chat gets a new tab id, so a reply still arriving for the old one
+    /// finds nothing to write to.
+    fn open_chat(&self, agent_idx: usize) {
+        self.close_chat();
+        let chat = OpenChat::new(agent_idx);
+        let tab_id = chat.tab_id;
+        self.state.locked().chats.push(chat);
+        let view = ChatView::new(&self.state, &self.speaker, &self.toasts, &self.commands, tab_id);
+        view.connect_suggestion({
+            let split = self.clone();
+            move |prompt| {
+                split.text_view.buffer().set_text(&prompt);
+                split.send_or_stop();
+            }
+        });
+        view.render(&[]);
+        self.view_slot.append(&view.widget);
+        *self.open.borrow_mut() = Some((tab_id, view));
+        self.syncing.set(true);
+        self.agent_dropdown.set_selected(agent_idx as u32);
+        self.syncing.set(false);
+        self.update_send_btn();
+    }
+
+    fn close_chat(&self) {
+        let Some((tab_id, view)) = self.open.borrow_mut().take() else { return };
+        self.view_slot.remove(&view.widget);
+        self.sender.discard_queued(tab_id);
+        let mut s = self.state.locked();
+        if let Some(idx) = s.chats.iter().position(|c| c.tab_id == tab_id) {
+            s.chats.remove(idx).stop_tasks();
+        }
+        drop(s);
+        self.update_send_btn();
+    }
+
+    /// Send turns into Stop while the pane's reply is streaming.
+    fn update_send_btn(&self) {
+        let busy = self.tab_id().is_some_and(|tab_id| {
+            self.state.locked().chats.iter().any(|c| c.tab_id == tab_id && c.task.is_some())
+        });
+        self.send_btn.set_label(if busy { "Stop" } else { "Send" });
+        self.send_btn.remove_css_class(if busy { "send-btn" } else { "stop-btn" });
+        self.send_btn.add_css_class(if busy { "stop-btn" } else { "send-btn" });
+    }
+
+    fn send_or_stop(&self) {
+        let Some((tab_id, view)) = self.open.borrow().clone() else { return };
+        let busy = self.state.locked().chats.iter().any(|c| c.tab_id == tab_id && c.task.is_some());
+        if busy {
+            self.sender.stop(tab_id);
+            return;
+        }
+
+        let buffer = self.text_view.buffer();
+        let (start, end) = buffer.bounds();
+        let text = buffer.text(&start, &end, false).trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        buffer.set_text("");
+        let split = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            // Cancelled at a check: back into the input, unless something else was typed meanwhile
+            if let Some((text, _)) = split.sender.submit(tab_id, view, text, Vec::new()).await {
+                if split.tab_id() == Some(tab_id) && split.text_view.buffer().char_count() == 0 {
+                    split.text_view.buffer().set_text(&text);
+                }
+            }
+        });
+    }
+}
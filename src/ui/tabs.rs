@@ -0,0 +1,198 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, Orientation, Spinner};
+use adw::ToastOverlay;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::controller::Commands;
+use crate::error::LockExt;
+use crate::speech::Speaker;
+use crate::state::AppState;
+use crate::ui::chat::ChatView;
+
+/// Longest tab title, in characters, before it is cut off.
+const TITLE_CHARS: i32 = 20;
+
+type TabHandler = Rc<dyn Fn(u64)>;
+
+struct ChatTab {
+    tab_id: u64,
+    view: ChatView,
+    title: Label,
+    spinner: Spinner,
+}
+
+/// The open conversations as notebook pages, each with its own `ChatView`,
+/// so a reply keeps streaming into its tab while another one is shown. The
+/// tab strip only shows with more than one tab open; a spinner marks tabs
+/// that are still generating.
+#[derive(Clone)]
+pub struct ChatTabs {
+    pub notebook: gtk::Notebook,
+    tabs: Rc<RefCell<Vec<ChatTab>>>,
+    suggestion_handler: Rc<RefCell<Option<Rc<dyn Fn(String)>>>>,
+    resize_handler: Rc<RefCell<Option<Rc<dyn Fn()>>>>,
+    close_handler: Rc<RefCell<Option<TabHandler>>>,
+    state: Arc<Mutex<AppState>>,
+    speaker: Rc<Speaker>,
+    toasts: ToastOverlay,
+    commands: Commands,
+}
+
+impl ChatTabs {
+    pub fn new(state: &Arc<Mutex<AppState>>, speaker: &Rc<Speaker>, toasts: &ToastOverlay, commands: &Commands) -> Self {
+        let notebook = gtk::Notebook::builder()
+            .scrollable(true)
+            .show_border(false)
+            .show_tabs(false)
+            .vexpand(true)
+            .build();
+        Self {
+            notebook,
+            tabs: Rc::new(RefCell::new(Vec::new())),
+            suggestion_handler: Rc::new(RefCell::new(None)),
+            resize_handler: Rc::new(RefCell::new(None)),
+            close_handler: Rc::new(RefCell::new(None)),
+            state: state.clone(),
+            speaker: speaker.clone(),
+            toasts: toasts.clone(),
+            commands: commands.clone(),
+        }
+    }
+
+    /// Adds a page for the chat in tab `tab_id`, after the others.
+    pub fn add(&self, tab_id: u64) -> ChatView {
+        let view = ChatView::new(&self.state, &self.speaker, &self.toasts, &self.commands, tab_id);
+        let handler = self.suggestion_handler.clone();
+        view.connect_suggestion(move |prompt| {
+            let handler = handler.borrow().clone();
+            if let Some(handler) = handler {
+                handler(prompt);
+            }
+        });
+        let handler = self.resize_handler.clone();
+        view.scrolled_window.hadjustment().connect_page_size_notify(move |_| {
+            let handler = handler.borrow().clone();
+            if let Some(handler) = handler {
+                handler();
+            }
+        });
+
+        let label_box = Box::builder().orientation(Orientation::Horizontal).spacing(6).build();
+        let spinner = Spinner::builder().visible(false).build();
+        let title = Label::builder()
+            .label("New Chat")
+            .max_width_chars(TITLE_CHARS)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        let close_btn = Button::builder()
+            .icon_name("window-close-symbolic")
+            .tooltip_text("Close tab (Ctrl+W)")
+            .css_classes(["flat", "circular"])
+            .build();
+        let handler = self.close_handler.clone();
+        close_btn.connect_clicked(move |_| {
+            let handler = handler.borrow().clone();
+            if let Some(handler) = handler {
+                handler(tab_id);
+            }
+        });
+        label_box.append(&spinner);
+        label_box.append(&title);
+        label_box.append(&close_btn);
+
+        self.tabs.borrow_mut().push(ChatTab { tab_id, view: view.clone(), title, spinner });
+        self.notebook.append_page(&view.widget, Some(&label_box));
+        self.notebook.set_tab_reorderable(&view.widget, true);
+        self.notebook.set_show_tabs(self.tabs.borrow().len() > 1);
+        view
+    }
+
+    /// Removes the page of tab `tab_id`; the notebook then shows a neighbour.
+    pub fn remove(&self, tab_id: u64) {
+        let mut tabs = self.tabs.borrow_mut();
+        let Some(idx) = tabs.iter().position(|t| t.tab_id == tab_id) else { return };
+        let tab = tabs.remove(idx);
+        let show_tabs = tabs.len() > 1;
+        drop(tabs);
+        self.notebook.remove_page(self.notebook.page_num(&tab.view.widget));
+        self.notebook.set_show_tabs(show_tabs);
+    }
+
+    pub fn select(&self, tab_id: u64) {
+        if let Some(view) = self.view(tab_id) {
+            self.notebook.set_current_page(self.notebook.page_num(&view.widget));
+        }
+    }
+
+    /// The view of the tab on screen.
+    pub fn current(&self) -> ChatView {
+        let page = self.notebook.nth_page(self.notebook.current_page());
+        let tabs = self.tabs.borrow();
+        tabs.iter()
+            .find(|t| Some(t.view.widget.upcast_ref::<gtk::Widget>()) == page.as_ref())
+            .or_else(|| tabs.first())
+            .map(|t| t.view.clone())
+            .expect("a chat tab is always open")
+    }
+
+    pub fn view(&self, tab_id: u64) -> Option<ChatView> {
+        self.tabs.borrow().iter().find(|t| t.tab_id == tab_id).map(|t| t.view.clone())
+    }
+
+    pub fn views(&self) -> Vec<ChatView> {
+        self.tabs.borrow().iter().map(|t| t.view.clone()).collect()
+    }
+
+    /// Shows or hides the spinner of a tab whose reply is streaming.
+    pub fn set_busy(&self, tab_id: u64, busy: bool) {
+        if let Some(tab) = self.tabs.borrow().iter().find(|t| t.tab_id == tab_id) {
+            tab.spinner.set_visible(busy);
+            tab.spinner.set_spinning(busy);
+        }
+    }
+
+    /// Names each tab after its conversation's history entry.
+    pub fn refresh_titles(&self) {
+        let s = self.state.locked();
+        for tab in self.tabs.borrow().iter() {
+            let title = s.chats.iter()
+                .find(|c| c.tab_id == tab.tab_id)
+                .and_then(|c| c.history_id.as_ref())
+                .and_then(|id| s.history.iter().find(|h| &h.id == id))
+                .map(|h| h.title.as_str())
+                .unwrap_or("New Chat");
+            tab.title.set_label(title);
+            tab.title.set_tooltip_text(Some(title));
+        }
+    }
+
+    /// Called with the prompt of a suggestion chip clicked in any tab.
+    pub fn connect_suggestion(&self, f: impl Fn(String) + 'static) {
+        *self.suggestion_handler.borrow_mut() = Some(Rc::new(f));
+    }
+
+    /// Called when the width of the message lists changes.
+    pub fn connect_resized(&self, f: impl Fn() + 'static) {
+        *self.resize_handler.borrow_mut() = Some(Rc::new(f));
+    }
+
+    /// Called with the tab id when a tab's close button is clicked.
+    pub fn connect_close(&self, f: impl Fn(u64) + 'static) {
+        *self.close_handler.borrow_mut() = Some(Rc::new(f));
+    }
+
+    /// Called with the tab id after another tab was brought on screen.
+    pub fn connect_switched(&self, f: impl Fn(u64) + 'static) {
+        let tabs = self.tabs.clone();
+        self.notebook.connect_page_notify(move |notebook| {
+            let Some(page) = notebook.nth_page(notebook.current_page()) else { return };
+            let tab_id = tabs.borrow().iter().find(|t| t.view.widget.upcast_ref::<gtk::Widget>() == &page).map(|t| t.tab_id);
+            if let Some(tab_id) = tab_id {
+                f(tab_id);
+            }
+        });
+    }
+}
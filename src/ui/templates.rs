@@ -0,0 +1,121 @@
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{Box, Button, Label, MenuButton, Orientation, Popover, TextView};
+use std::sync::{Arc, Mutex};
+
+use crate::error::LockExt;
+use crate::state::{AppState, PromptTemplate};
+use crate::utils::{fill_template, template_variables};
+
+/// The input's template button: a popover listing the templates from
+/// settings, read again each time it opens. Picking one fills in its
+/// `{{variables}}` (see `open_form`) and hands the prompt to `send`.
+pub fn picker(state: &Arc<Mutex<AppState>>, send: impl Fn(String) + Clone + 'static) -> MenuButton {
+    let popover = Popover::new();
+    let button = MenuButton::builder()
+        .icon_name("document-edit-symbolic")
+        .tooltip_text("Prompt templates")
+        .valign(gtk::Align::End)
+        .popover(&popover)
+        .build();
+    let list = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(6)
+        .margin_end(6)
+        .build();
+    popover.set_child(Some(&list));
+
+    popover.connect_show({
+        let state = state.clone();
+        let button = button.clone();
+        move |popover| {
+            while let Some(child) = list.first_child() {
+                list.remove(&child);
+            }
+            let templates = state.locked().settings.templates.clone();
+            if templates.is_empty() {
+                list.append(&Label::builder()
+                    .label("No templates yet — add them in Settings → Templates")
+                    .margin_top(6)
+                    .margin_bottom(6)
+                    .build());
+            }
+            for template in templates {
+                let item = Button::builder().label(&template.name).css_classes(["flat"]).build();
+                let popover = popover.clone();
+                let button = button.clone();
+                let send = send.clone();
+                item.connect_clicked(move |_| {
+                    popover.popdown();
+                    open_form(&button, template.clone(), send.clone());
+                });
+                list.append(&item);
+            }
+        }
+    });
+    button
+}
+
+/// Asks for the values of `template`'s `{{variables}}` in a dialog over
+/// `parent`'s window, then hands `send` the filled-in prompt. A template
+/// without variables is sent as is.
+pub fn open_form(parent: &impl IsA<gtk::Widget>, template: PromptTemplate, send: impl Fn(String) + 'static) {
+    let variables = template_variables(&template.body);
+    if variables.is_empty() {
+        send(template.body);
+        return;
+    }
+
+    let dialog = gtk::Window::builder()
+        .title(&template.name)
+        .modal(true)
+        .default_width(480)
+        .build();
+    if let Some(parent) = parent.root().and_downcast::<gtk::Window>() {
+        dialog.set_transient_for(Some(&parent));
+    }
+    let form = Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(20)
+        .margin_bottom(20)
+        .margin_start(20)
+        .margin_end(20)
+        .build();
+    let mut fields = Vec::new();
+    for name in &variables {
+        form.append(&Label::builder().label(name).xalign(0.0).css_classes(["settings-label"]).build());
+        let field = TextView::builder()
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .height_request(40)
+            .css_classes(["headers-view"])
+            .build();
+        form.append(&field);
+        fields.push((name.clone(), field));
+    }
+    let actions = Box::builder().orientation(Orientation::Horizontal).spacing(10).halign(gtk::Align::End).margin_top(10).build();
+    let cancel_btn = Button::with_label("Cancel");
+    let fill_btn = Button::with_label("Send");
+    fill_btn.add_css_class("suggested-action");
+    actions.append(&cancel_btn);
+    actions.append(&fill_btn);
+    form.append(&actions);
+    dialog.set_child(Some(&form));
+
+    let dialog_c = dialog.clone();
+    cancel_btn.connect_clicked(move |_| dialog_c.close());
+    let dialog_c = dialog.clone();
+    fill_btn.connect_clicked(move |_| {
+        let values: Vec<(String, String)> = fields.iter().map(|(name, field)| {
+            let buffer = field.buffer();
+            let (start, end) = buffer.bounds();
+            (name.clone(), buffer.text(&start, &end, false).to_string())
+        }).collect();
+        dialog_c.close();
+        send(fill_template(&template.body, &values));
+    });
+    dialog.present();
+}
@@ -0,0 +1,64 @@
+use gtk4 as gtk;
+use adw::prelude::*;
+
+use crate::error::AppError;
+
+/// A toast showing `title` as plain text. `adw::Toast` titles are Pango
+/// markup, and ours often quote model names, paths and server replies.
+pub fn toast(title: &str) -> adw::Toast {
+    adw::Toast::builder().title(title).use_markup(false).build()
+}
+
+/// Reporting `AppError`s in the window's toast overlay.
+pub trait Report {
+    /// Shows an error's summary, with a button that opens the full details.
+    fn report(&self, error: &AppError);
+}
+
+impl Report for adw::ToastOverlay {
+    fn report(&self, error: &AppError) {
+        tracing::error!("{}", error);
+        let toast = toast(&error.summary);
+        toast.set_timeout(8);
+        if !error.details.is_empty() {
+            toast.set_button_label(Some("Details"));
+            let overlay = self.clone();
+            let error = error.clone();
+            toast.connect_button_clicked(move |_| show_details(&overlay, &error));
+        }
+        self.add_toast(toast);
+    }
+}
+
+fn show_details(overlay: &adw::ToastOverlay, error: &AppError) {
+    let dialog = adw::MessageDialog::builder()
+        .heading(&error.summary)
+        .modal(true)
+        .default_width(480)
+        .build();
+    dialog.set_transient_for(overlay.root().and_downcast::<gtk::Window>().as_ref());
+
+    let details = gtk::Label::builder()
+        .label(&error.details)
+        .xalign(0.0)
+        .yalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .css_classes(["monospace"])
+        .build();
+    let scroll = gtk::ScrolledWindow::builder()
+        .child(&details)
+        .min_content_height(80)
+        .max_content_height(300)
+        .propagate_natural_height(true)
+        .build();
+    dialog.set_extra_child(Some(&scroll));
+    dialog.add_responses(&[("copy", "Copy"), ("close", "Close")]);
+    dialog.set_response_appearance("close", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
+
+    let text = error.to_string();
+    dialog.connect_response(Some("copy"), move |dialog, _| dialog.clipboard().set_text(&text));
+    dialog.present();
+}
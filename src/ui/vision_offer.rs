@@ -0,0 +1,113 @@
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use adw::ToastOverlay;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, LockExt};
+use crate::provider::{pull_model, Capability};
+use crate::state::AppState;
+use crate::ui::badges::look_up_capabilities;
+use crate::ui::toasts::{toast, Report};
+
+/// Vision model offered for pulling when an image is attached and none is installed.
+const VISION_MODEL_TO_PULL: &str = "llava";
+
+/// Asks, when an image is attached for a model that can't see it, whether to
+/// use an installed vision model for this chat (`OpenChat::model_override`)
+/// or to pull one. Declining isn't asked again in the tab.
+#[derive(Clone)]
+pub struct VisionOffer {
+    /// Tab in which the user last chose to keep the agent's model
+    declined_tab: Rc<Cell<Option<u64>>>,
+    state: Arc<Mutex<AppState>>,
+    toasts: ToastOverlay,
+}
+
+impl VisionOffer {
+    pub fn new(state: &Arc<Mutex<AppState>>, toasts: &ToastOverlay) -> Self {
+        Self {
+            declined_tab: Rc::new(Cell::new(None)),
+            state: state.clone(),
+            toasts: toasts.clone(),
+        }
+    }
+
+    /// Checks the model of the tab on screen and asks over `parent`'s window
+    /// if it lacks vision.
+    pub fn offer(&self, parent: &impl IsA<gtk::Widget>) {
+        let (tab_id, agent, models) = {
+            let s = self.state.locked();
+            (s.active_tab, s.current_agent(), s.available_models.clone())
+        };
+        // Capabilities are only known for models on the default Ollama server
+        let Ok(agent) = agent else { return };
+        if agent.provider_id.is_some() || self.declined_tab.get() == Some(tab_id) {
+            return;
+        }
+        let window = parent.root().and_downcast::<gtk::Window>();
+        let this = self.clone();
+        glib::MainContext::default().spawn_local(async move {
+            let mut models = models;
+            models.push(agent.model.clone());
+            look_up_capabilities(&this.state, &models).await;
+            let (lacks_vision, vision_model) = {
+                let s = this.state.locked();
+                let has = |model: &str, capability| s.model_capabilities.get(model).map(|c| c.contains(&capability));
+                (
+                    has(&agent.model, Capability::Vision) == Some(false),
+                    s.available_models.iter().find(|m| has(m, Capability::Vision) == Some(true)).cloned(),
+                )
+            };
+            if !lacks_vision {
+                return;
+            }
+            let (detail, action) = match &vision_model {
+                Some(model) => (
+                    format!("Use {} for this chat instead? The agent's other settings stay the same, and another chat goes back to {}.", model, agent.model),
+                    format!("Use {}", model),
+                ),
+                None => (
+                    format!("No installed model can. Pull {} (several GB) and use it for this chat?", VISION_MODEL_TO_PULL),
+                    format!("Pull {}", VISION_MODEL_TO_PULL),
+                ),
+            };
+            let dialog = gtk::AlertDialog::builder()
+                .modal(true)
+                .message(format!("{} can't see images", agent.model))
+                .detail(detail)
+                .buttons([format!("Keep {}", agent.model), action])
+                .cancel_button(0)
+                .default_button(1)
+                .build();
+            if !matches!(dialog.choose_future(window.as_ref()).await, Ok(1)) {
+                this.declined_tab.set(Some(tab_id));
+                return;
+            }
+
+            let model = match vision_model {
+                Some(model) => model,
+                None => {
+                    this.toasts.add_toast(toast(&format!("Pulling {}…", VISION_MODEL_TO_PULL)));
+                    let ollama = this.state.locked().ollama.clone();
+                    if let Err(e) = pull_model(&ollama, VISION_MODEL_TO_PULL, |_| {}).await {
+                        this.toasts.report(&AppError::new(format!("Failed to pull {}", VISION_MODEL_TO_PULL), e));
+                        return;
+                    }
+                    let mut s = this.state.locked();
+                    if !s.available_models.iter().any(|m| m == VISION_MODEL_TO_PULL) {
+                        s.available_models.push(VISION_MODEL_TO_PULL.to_string());
+                    }
+                    VISION_MODEL_TO_PULL.to_string()
+                }
+            };
+            // The tab may have been closed or moved on while pulling
+            if let Some(chat) = this.state.locked().chat_in_tab(tab_id) {
+                chat.model_override = Some(model.clone());
+            }
+            this.toasts.add_toast(toast(&format!("This chat now uses {}", model)));
+        });
+    }
+}
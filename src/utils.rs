@@ -1,6 +1,12 @@
 use gtk4 as gtk;
 use gtk::glib;
+use ollama_rs::generation::chat::ChatMessage;
 use pulldown_cmark::{Parser, Options, Tag, TagEnd, Event};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::state::{Bookmark, ChatHistory, CustomHeader, StoredMessage};
 
 pub fn normalize_url(s: &str) -> String {
     let mut s = s.trim().to_string();
@@ -10,9 +16,454 @@ pub fn normalize_url(s: &str) -> String {
     s
 }
 
+/// Parses one `Name: value` header per line, ignoring blank or malformed lines.
+pub fn parse_headers(text: &str) -> Vec<CustomHeader> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| CustomHeader { name: name.trim().to_string(), value: value.trim().to_string() })
+        .filter(|h| !h.name.is_empty())
+        .collect()
+}
+
+pub fn format_headers(headers: &[CustomHeader]) -> String {
+    headers.iter().map(|h| format!("{}: {}", h.name, h.value)).collect::<Vec<_>>().join("\n")
+}
+
+/// Names of the `{{placeholder}}` variables in a prompt template, in order of
+/// first appearance and without duplicates.
+pub fn template_variables(body: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + len].trim();
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    names
+}
+
+/// Replaces each `{{name}}` placeholder with its value; unknown ones are kept.
+pub fn fill_template(body: &str, values: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + 2 + len + 2];
+        let name = placeholder[2..placeholder.len() - 2].trim();
+        match values.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// How long ago a Unix timestamp was: "just now", "5 min ago", "3 h ago",
+/// "yesterday", or the date for anything older.
+pub fn relative_time(timestamp: i64) -> String {
+    let (Ok(now), Ok(then)) = (glib::DateTime::now_local(), glib::DateTime::from_unix_local(timestamp)) else {
+        return String::new();
+    };
+    let secs = now.to_unix() - timestamp;
+    let same_day = |a: &glib::DateTime, b: &glib::DateTime| a.year() == b.year() && a.day_of_year() == b.day_of_year();
+    if secs < 60 {
+        "just now".to_string()
+    } else if secs < 60 * 60 {
+        format!("{} min ago", secs / 60)
+    } else if secs < 24 * 60 * 60 && same_day(&now, &then) {
+        format!("{} h ago", secs / 3600)
+    } else if now.add_days(-1).is_ok_and(|yesterday| same_day(&yesterday, &then)) {
+        "yesterday".to_string()
+    } else {
+        let format = if then.year() == now.year() { "%b %e" } else { "%Y-%m-%d" };
+        then.format(format).map(|s| s.to_string()).unwrap_or_default()
+    }
+}
+
+/// Sidebar section for a conversation last active at `timestamp`: "Today",
+/// "Yesterday", "Previous 7 days" or "Older".
+pub fn date_group(timestamp: Option<i64>) -> &'static str {
+    let (Some(timestamp), Ok(now)) = (timestamp, glib::DateTime::now_local()) else {
+        return "Older";
+    };
+    let Ok(today) = glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0) else {
+        return "Older";
+    };
+    let days_before = |days: i32| today.add_days(-days).map(|day| day.to_unix()).unwrap_or(i64::MAX);
+    if timestamp >= today.to_unix() {
+        "Today"
+    } else if timestamp >= days_before(1) {
+        "Yesterday"
+    } else if timestamp >= days_before(7) {
+        "Previous 7 days"
+    } else {
+        "Older"
+    }
+}
+
+/// Local date and time of a Unix timestamp, e.g. "2024-05-01 14:03:22".
+pub fn format_timestamp(timestamp: i64) -> String {
+    glib::DateTime::from_unix_local(timestamp)
+        .and_then(|time| time.format("%Y-%m-%d %H:%M:%S"))
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Rough token cost of an attached image; vision encoders differ per model.
+pub const IMAGE_TOKEN_ESTIMATE: usize = 576;
+
+/// Rough token count of `text`, at about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The prompt for raw completion mode: `template` with `{{system}}`,
+/// `{{history}}` and `{{prompt}}` filled in from `messages`, whose last entry
+/// is the new user message.
+pub fn raw_prompt(template: &str, messages: &[ChatMessage]) -> String {
+    use ollama_rs::generation::chat::MessageRole;
+    let (prompt, earlier) = match messages.split_last() {
+        Some((last, earlier)) if last.role == MessageRole::User => (last.content.as_str(), earlier),
+        _ => ("", messages),
+    };
+    let mut system = String::new();
+    let mut history = String::new();
+    for msg in earlier {
+        match msg.role {
+            MessageRole::System => system.push_str(&msg.content),
+            MessageRole::User => history.push_str(&format!("User: {}\n", msg.content.trim())),
+            MessageRole::Assistant => history.push_str(&format!("Assistant: {}\n", msg.content.trim())),
+            MessageRole::Tool => {}
+        }
+    }
+    fill_template(template, &[
+        ("system".to_string(), system),
+        ("history".to_string(), history),
+        ("prompt".to_string(), prompt.to_string()),
+    ])
+}
+
+/// Markdown transcript of a saved conversation, without the system prompt.
+pub fn history_to_markdown(item: &ChatHistory) -> String {
+    transcript_to_markdown(Some(&item.title), &item.messages)
+}
+
+/// Markdown transcript of `messages` under an optional title heading,
+/// without the system prompt.
+pub fn transcript_to_markdown(title: Option<&str>, messages: &[StoredMessage]) -> String {
+    use ollama_rs::generation::chat::MessageRole;
+    let mut out = title.map(|title| format!("# {}\n", title)).unwrap_or_default();
+    for msg in messages.iter().map(|m| &m.message) {
+        match msg.role {
+            MessageRole::System => continue,
+            MessageRole::User => out.push_str(&format!("\n**You:**\n\n{}\n", msg.content.trim())),
+            MessageRole::Assistant => {
+                for call in &msg.tool_calls {
+                    out.push_str(&format!("\n> 🔧 `{}({})`\n", call.function.name, call.function.arguments));
+                }
+                if !msg.content.trim().is_empty() {
+                    out.push_str(&format!("\n**Assistant:**\n\n{}\n", msg.content.trim()));
+                }
+            }
+            MessageRole::Tool => {
+                out.push_str("\n<details><summary>Tool result</summary>\n\n```\n");
+                out.push_str(msg.content.trim());
+                out.push_str("\n```\n\n</details>\n");
+            }
+        }
+        if msg.images.as_ref().is_some_and(|images| !images.is_empty()) {
+            out.push_str("\n*(image attached)*\n");
+        }
+    }
+    out
+}
+
+/// Markdown document of saved responses, each under its conversation's title.
+pub fn bookmarks_to_markdown(bookmarks: &[Bookmark]) -> String {
+    let mut out = "# Saved Responses\n".to_string();
+    for bookmark in bookmarks {
+        let title = if bookmark.conversation.is_empty() { "Untitled chat" } else { &bookmark.conversation };
+        out.push_str(&format!("\n## {}\n\n*Saved {}*\n\n{}\n", title, format_timestamp(bookmark.saved_at), bookmark.content.trim()));
+    }
+    out
+}
+
+/// Data URI for a base64-encoded image attachment, guessing the type from
+/// its magic bytes.
+pub fn image_data_uri(base64: &str) -> String {
+    let mime = if base64.starts_with("/9j/") {
+        "image/jpeg"
+    } else if base64.starts_with("R0lGOD") {
+        "image/gif"
+    } else if base64.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    };
+    format!("data:{};base64,{}", mime, base64)
+}
+
+/// Looks `name` up in `PATH`.
+/// The rolling copy `write_atomic` keeps of the previous contents.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Replaces a file without ever leaving it half-written: the data goes to a
+/// temporary file that is synced and then renamed over the original, after
+/// the previous version has been copied to `backup_path`.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    drop(file);
+
+    if path.exists() {
+        let _ = fs::copy(path, backup_path(path));
+    }
+    fs::rename(&tmp_path, path)?;
+    // Make the rename itself durable
+    if let Some(dir) = path.parent().and_then(|dir| fs::File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Reads a JSON file written by `write_atomic`, falling back to its backup
+/// when the file is missing or doesn't parse.
+pub fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let parse = |path: &Path| fs::read_to_string(path).ok().and_then(|s| serde_json::from_str(&s).ok());
+    parse(path).or_else(|| {
+        let restored = parse(&backup_path(path));
+        if restored.is_some() {
+            tracing::warn!("{} is unreadable, using its backup", path.display());
+        }
+        restored
+    })
+}
+
+/// Whether `url` points at this machine.
+pub fn is_local_url(url: &str) -> bool {
+    url::Url::parse(&normalize_url(url)).ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .is_some_and(|host| matches!(host.as_str(), "localhost" | "127.0.0.1" | "[::1]" | "0.0.0.0"))
+}
+
+pub fn find_program(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Plain text of a reply for reading aloud: markup dropped, code blocks and images skipped.
+pub fn markdown_to_speech(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut in_code_block = false;
+    let mut in_image = false;
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::Image { .. }) => in_image = true,
+            Event::End(TagEnd::Image) => in_image = false,
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                out.push_str("\n");
+            }
+            Event::Text(text) | Event::Code(text) if !in_code_block && !in_image => out.push_str(&text),
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::TableRow) => out.push('\n'),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Most journal lines pulled into a diagnosis; older ones are cut by `journalctl -n`.
+pub const JOURNAL_MAX_LINES: u32 = 300;
+
+/// `journalctl` arguments for the Diagnose dialog. `period` and `priority`
+/// are the indices of its "Current boot / Last hour / Last 24 hours" and
+/// "Errors / Warnings / Everything" dropdowns.
+pub fn journal_args(period: u32, priority: u32, unit: &str) -> Vec<String> {
+    let mut args = vec!["journalctl".to_string(), "--no-pager".to_string(), "-o".to_string(), "short-iso".to_string()];
+    match period {
+        1 => args.extend(["--since".to_string(), "-1h".to_string()]),
+        2 => args.extend(["--since".to_string(), "-24h".to_string()]),
+        _ => args.push("-b".to_string()),
+    }
+    match priority {
+        0 => args.extend(["-p".to_string(), "err".to_string()]),
+        1 => args.extend(["-p".to_string(), "warning".to_string()]),
+        _ => {}
+    }
+    let unit = unit.trim();
+    if !unit.is_empty() {
+        args.extend(["-u".to_string(), unit.to_string()]);
+    }
+    args.extend(["-n".to_string(), JOURNAL_MAX_LINES.to_string()]);
+    args
+}
+
+/// Keeps the log lines containing `needle` (case-insensitive); an empty needle keeps everything.
+pub fn filter_log(log: &str, needle: &str) -> String {
+    let needle = needle.trim().to_lowercase();
+    if needle.is_empty() {
+        return log.to_string();
+    }
+    log.lines()
+        .filter(|line| line.to_lowercase().contains(&needle))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn link_open_tag(url: &str) -> String {
+    format!("<a href=\"{}\" title=\"{}\">", glib::markup_escape_text(url), glib::markup_escape_text(url))
+}
+
+/// Whether a link from model output may be opened: web and mail links only,
+/// never local files or custom URI handlers.
+pub fn is_openable_link(uri: &str) -> bool {
+    let lower = uri.trim().to_ascii_lowercase();
+    ["http://", "https://", "mailto:"].iter().any(|scheme| lower.starts_with(scheme))
+}
+
+/// Tracks nested lists while converting markdown: one entry per open list,
+/// holding the next number for ordered lists.
+#[derive(Default)]
+struct ListStack(Vec<Option<u64>>);
+
+impl ListStack {
+    fn start_list(&mut self, start: Option<u64>, out: &mut String) {
+        // A nested list starts inside its parent item's text
+        if !self.0.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        self.0.push(start);
+    }
+
+    fn end_list(&mut self) {
+        self.0.pop();
+    }
+
+    fn start_item(&mut self, out: &mut String) {
+        let depth = self.0.len().max(1);
+        out.push_str(&"    ".repeat(depth - 1));
+        match self.0.last_mut() {
+            Some(Some(number)) => {
+                out.push_str(&format!("  {}. ", number));
+                *number += 1;
+            }
+            _ => out.push_str(if depth % 2 == 0 { "  ◦ " } else { "  • " }),
+        }
+    }
+
+    fn end_item(&mut self, out: &mut String) {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+}
+
+/// `text` pretty-printed when it is a JSON object or array, so structured
+/// replies show as a code block.
+pub fn pretty_json(text: &str) -> Option<String> {
+    let text = text.trim();
+    if !text.starts_with('{') && !text.starts_with('[') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// Checks a structured reply against `format` (`"json"` or a schema): it
+/// must parse, and for a schema have its top-level type and required keys.
+pub fn check_json_reply(text: &str, format: &serde_json::Value) -> Result<(), String> {
+    let value: serde_json::Value = serde_json::from_str(text.trim()).map_err(|e| format!("not valid JSON ({})", e))?;
+    let type_matches = match format["type"].as_str() {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("number") => value.is_number(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("boolean") => value.is_boolean(),
+        _ => true,
+    };
+    if !type_matches {
+        return Err(format!("expected a JSON {}", format["type"].as_str().unwrap_or_default()));
+    }
+    let missing: Vec<&str> = format["required"].as_array().into_iter().flatten()
+        .filter_map(|key| key.as_str())
+        .filter(|key| value.get(key).is_none())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing {}", missing.join(", ")));
+    }
+    Ok(())
+}
+
+/// The first fenced code block in `markdown`, by byte offsets.
+pub struct CodeFence {
+    /// Start of the opening fence line
+    pub start: usize,
+    /// Start of the code, after the opening fence line
+    pub code_start: usize,
+    /// Start of the closing fence line and the end of it, if it has arrived
+    pub close: Option<(usize, usize)>,
+}
+
+/// Finds the first fenced code block, closed or not, for rendering a reply
+/// while it streams. A fence only opens on a complete line, so a half-sent
+/// one shows as text for a moment rather than swallowing the rest.
+pub fn find_code_fence(markdown: &str) -> Option<CodeFence> {
+    let mut offset = 0;
+    let mut open: Option<(usize, usize, char, usize)> = None;
+    for line in markdown.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start_matches(' ');
+        if line.len() - trimmed.len() > 3 {
+            continue;
+        }
+        let Some(fence_char) = trimmed.chars().next().filter(|c| *c == '`' || *c == '~') else { continue };
+        let fence_len = trimmed.chars().take_while(|c| *c == fence_char).count();
+        if fence_len < 3 {
+            continue;
+        }
+        match open {
+            None if line.ends_with('\n') => open = Some((line_start, offset, fence_char, fence_len)),
+            Some((start, code_start, open_char, open_len)) if fence_char == open_char && fence_len >= open_len && trimmed[fence_len..].trim().is_empty() => {
+                return Some(CodeFence { start, code_start, close: Some((line_start, offset)) });
+            }
+            _ => {}
+        }
+    }
+    open.map(|(start, code_start, _, _)| CodeFence { start, code_start, close: None })
+}
+
 pub enum MarkdownBlock {
     Text(String),
     Code(String, String), // (language, code)
+    Image(String, String), // (source URL or data URI, alt text)
+}
+
+/// Whether `text` is a standalone base64 image data URI.
+fn is_image_data_uri(text: &str) -> bool {
+    let text = text.trim();
+    text.starts_with("data:image/") && text.contains(";base64,") && !text.contains(char::is_whitespace)
 }
 
 pub fn parse_markdown(markdown: &str) -> Vec<MarkdownBlock> {
@@ -25,10 +476,20 @@ pub fn parse_markdown(markdown: &str) -> Vec<MarkdownBlock> {
     let mut in_code_block = false;
     let mut code_lang = String::new();
     let mut current_code = String::new();
+    let mut lists = ListStack::default();
+    // Source and alt text of the image being parsed
+    let mut image: Option<(String, String)> = None;
 
     for event in parser {
         match event {
             Event::Start(tag) => match tag {
+                Tag::Image { dest_url, .. } => {
+                    if !current_text.is_empty() {
+                        blocks.push(MarkdownBlock::Text(current_text.clone()));
+                        current_text.clear();
+                    }
+                    image = Some((dest_url.to_string(), String::new()));
+                }
                 Tag::CodeBlock(kind) => {
                     // Flush text
                     if !current_text.is_empty() {
@@ -53,11 +514,17 @@ pub fn parse_markdown(markdown: &str) -> Vec<MarkdownBlock> {
                     };
                     current_text.push_str(&format!("\n<span font_size=\"{}\" weight=\"bold\">", size));
                 }
-                Tag::Link { .. } => current_text.push_str("<u>"),
-                Tag::Item => current_text.push_str("  • "),
+                Tag::Link { dest_url, .. } => current_text.push_str(&link_open_tag(&dest_url)),
+                Tag::List(start) => lists.start_list(start, &mut current_text),
+                Tag::Item => lists.start_item(&mut current_text),
                 _ => {}
             },
             Event::End(tag) => match tag {
+                TagEnd::Image => {
+                    if let Some((src, alt)) = image.take() {
+                        blocks.push(MarkdownBlock::Image(src, alt));
+                    }
+                }
                 TagEnd::CodeBlock => {
                     in_code_block = false;
                     blocks.push(MarkdownBlock::Code(code_lang.clone(), current_code.trim().to_string()));
@@ -69,13 +536,22 @@ pub fn parse_markdown(markdown: &str) -> Vec<MarkdownBlock> {
                 TagEnd::Strikethrough => current_text.push_str("</s>"),
                 TagEnd::Heading(_) => current_text.push_str("</span>\n"),
                 TagEnd::BlockQuote(_) => current_text.push_str("</blockquote>\n"),
-                TagEnd::Link => current_text.push_str("</u>"),
-                TagEnd::Item => current_text.push_str("\n"),
+                TagEnd::Link => current_text.push_str("</a>"),
+                TagEnd::List(_) => lists.end_list(),
+                TagEnd::Item => lists.end_item(&mut current_text),
                 _ => {}
             },
             Event::Text(text) => {
-                if in_code_block {
+                if let Some((_, alt)) = image.as_mut() {
+                    alt.push_str(&text);
+                } else if in_code_block {
                     current_code.push_str(&text);
+                } else if is_image_data_uri(&text) {
+                    if !current_text.is_empty() {
+                        blocks.push(MarkdownBlock::Text(current_text.clone()));
+                        current_text.clear();
+                    }
+                    blocks.push(MarkdownBlock::Image(text.trim().to_string(), String::new()));
                 } else {
                     current_text.push_str(&glib::markup_escape_text(&text));
                 }
@@ -111,6 +587,7 @@ pub fn markdown_to_pango(markdown: &str) -> String {
     options.insert(Options::ENABLE_STRIKETHROUGH);
     let parser = Parser::new_ext(markdown, options);
     let mut pango_markup = String::new();
+    let mut lists = ListStack::default();
 
     for event in parser {
         match event {
@@ -128,8 +605,10 @@ pub fn markdown_to_pango(markdown: &str) -> String {
                     };
                     pango_markup.push_str(&format!("\n<span font_size=\"{}\" weight=\"bold\">", size));
                 }
-                Tag::Link { .. } => pango_markup.push_str("<u>"),
-                Tag::Item => pango_markup.push_str("  • "),
+                Tag::Link { dest_url, .. } => pango_markup.push_str(&link_open_tag(&dest_url)),
+                Tag::Image { .. } => pango_markup.push_str("<i>[image: "),
+                Tag::List(start) => lists.start_list(start, &mut pango_markup),
+                Tag::Item => lists.start_item(&mut pango_markup),
                 _ => {}
             },
             Event::End(tag) => match tag {
@@ -139,10 +618,13 @@ pub fn markdown_to_pango(markdown: &str) -> String {
                 TagEnd::CodeBlock => pango_markup.push_str("</tt>\n"),
                 TagEnd::Heading(_) => pango_markup.push_str("</span>\n"),
                 TagEnd::BlockQuote(_) => pango_markup.push_str("</blockquote>\n"),
-                TagEnd::Link => pango_markup.push_str("</u>"),
-                TagEnd::Item => pango_markup.push_str("\n"),
+                TagEnd::Link => pango_markup.push_str("</a>"),
+                TagEnd::Image => pango_markup.push_str("]</i>"),
+                TagEnd::List(_) => lists.end_list(),
+                TagEnd::Item => lists.end_item(&mut pango_markup),
                 _ => {}
             },
+            Event::Text(text) if is_image_data_uri(&text) => pango_markup.push_str("<i>[image]</i>"),
             Event::Text(text) => pango_markup.push_str(&glib::markup_escape_text(&text)),
             Event::Code(code) => pango_markup.push_str(&format!("<tt>{}</tt>", glib::markup_escape_text(&code))),
             Event::SoftBreak | Event::HardBreak => pango_markup.push('\n'),